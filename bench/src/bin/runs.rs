@@ -0,0 +1,77 @@
+//! Micro-benchmark for images dominated by runs of pixels.
+//!
+//! `cargo run --release -p bench --bin runs [iterations]`
+
+use std::time::{Duration, Instant};
+
+use rapid_qoi::{Colors, Qoi};
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 1024;
+
+/// Returns best time of `runs` calls of `f`.
+#[inline(never)]
+fn best_of(runs: u32, mut f: impl FnMut()) -> Duration {
+    f();
+
+    (0..runs)
+        .map(|_| {
+            let time_start = Instant::now();
+            f();
+            time_start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// Returns pixels of the image with runs of `run` pixels in every row.
+fn image(colors: Colors, run: u32) -> Vec<u8> {
+    let channels = colors.channels();
+    let mut pixels = Vec::with_capacity(WIDTH as usize * HEIGHT as usize * channels);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let v = (x / run) as u8 ^ (y / 8) as u8;
+            pixels.extend_from_slice(&[v, v.wrapping_mul(3), !v, 255][..channels]);
+        }
+    }
+    pixels
+}
+
+fn main() {
+    let runs = std::env::args()
+        .nth(1)
+        .map_or(25, |runs| runs.parse().unwrap())
+        .max(1);
+
+    let px = WIDTH as f64 * HEIGHT as f64;
+    println!("## Runs of pixels {}x{} -- {} runs", WIDTH, HEIGHT, runs);
+    println!("                 decode ms   encode ms   decode mpps   encode mpps");
+    for colors in [Colors::Srgb, Colors::SrgbLinA] {
+        for run in [WIDTH, 64, 16] {
+            let qoi = Qoi {
+                width: WIDTH,
+                height: HEIGHT,
+                colors,
+            };
+            let pixels = image(colors, run);
+            let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+            let decode_time = best_of(runs, || {
+                Qoi::decode_alloc(&encoded).unwrap();
+            });
+            let encode_time = best_of(runs, || {
+                qoi.encode_alloc(&pixels).unwrap();
+            });
+
+            println!(
+                "{} run {:4}:  {:8.3}    {:8.3}      {:8.3}      {:8.3}",
+                if colors.has_alpha() { "rgba" } else { "rgb " },
+                run,
+                decode_time.as_secs_f64() * 1000.0,
+                encode_time.as_secs_f64() * 1000.0,
+                px / (decode_time.as_secs_f64() * 1_000_000.0),
+                px / (encode_time.as_secs_f64() * 1_000_000.0),
+            );
+        }
+    }
+}
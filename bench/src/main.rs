@@ -195,7 +195,7 @@ fn benchmark_directory(dirpath: &Path, runs: u32, grand_total: &mut BenchmarkRes
         if ft.is_file() {
             let filepath = Path::new(dirpath).join(path.file_name());
 
-            if filepath.extension().map_or(false, |e| e == "png") {
+            if filepath.extension().is_some_and(|e| e == "png") {
                 let res = benchmark_image(&filepath, runs);
 
                 dir_total.count += res.count;
@@ -164,7 +164,7 @@ fn main() -> Result<(), ()> {
         },
 
         Format::Raw => {
-            std::fs::write(&output, &dynamic_image.as_bytes()).map_err(|err| {
+            std::fs::write(&output, dynamic_image.as_bytes()).map_err(|err| {
                 eprintln!(
                     "Failed to write RAW image into output file {}. {:#}",
                     output.display(),
@@ -0,0 +1,461 @@
+//! Animation container storing sequence of QOI images with equal dimensions.\
+//! This is an extension of this crate, not part of QOI specification.
+//!
+//! Container starts with `b"qoia"` magic, version byte, big-endian number of frames
+//! and QOI header shared by all frames.\
+//! Each frame follows with big-endian duration in milliseconds, flags byte,
+//! big-endian size of the frame image and complete QOI image of the frame.\
+//! Key frames store pixels of the frame.
+//! Delta frames have the lowest bit of flags set and store per-channel wrapping differences
+//! from the previous frame as produced by [`Qoi::encode_delta`],
+//! so unchanged areas are encoded with long runs.
+//!
+//! Frames are decoded with [`Frames::next_frame`] into single buffer provided by the caller.
+//! [`Frames`] does not implement [`Iterator`], because each decoded [`Frame`] borrows pixels from the buffer
+//! until the next call, and delta frames are applied to pixels left in the buffer by the previous frame.
+
+use core::convert::TryInto;
+
+use super::*;
+use alloc::vec::Vec;
+
+/// Magic value at the start of animation container.
+pub const QOIA_MAGIC: [u8; 4] = *b"qoia";
+
+/// Version of animation container layout.
+const QOIA_VERSION: u8 = 1;
+
+/// Size of animation container header.
+pub const QOIA_HEADER_SIZE: usize = 4 + 1 + 4 + QOI_HEADER_SIZE;
+
+/// Size of header preceding each frame image.
+const FRAME_HEADER_SIZE: usize = 4 + 1 + 8;
+
+/// Flag of frame storing differences from the previous frame.
+const FRAME_DELTA: u8 = 1;
+
+/// Number of pixels of delta frame decoded at once.
+const DELTA_PIXELS: usize = 256;
+
+/// Encoder of animation container with frames pushed one by one.
+#[derive(Debug)]
+pub struct AnimationEncoder {
+    qoi: Qoi,
+    bytes: Vec<u8>,
+    frames: u32,
+    key_interval: u32,
+    since_key: u32,
+    previous: Vec<u8>,
+}
+
+impl AnimationEncoder {
+    /// Returns encoder of animation with frames described by `qoi`.\
+    /// Every frame is a key frame.
+    #[inline]
+    pub fn new(qoi: Qoi) -> Self {
+        Self::with_key_interval(qoi, 1)
+    }
+
+    /// Returns encoder of animation with frames described by `qoi`
+    /// storing frames as differences from the previous frame.\
+    /// Every `key_interval`-th frame starting with the first one is a key frame,
+    /// so decoding can start from it. Only the first frame is a key frame if `key_interval` is `0`.\
+    /// Encoder retains pixels of the previous frame unless every frame is a key frame.
+    pub fn with_key_interval(qoi: Qoi, key_interval: u32) -> Self {
+        let mut bytes = Vec::with_capacity(QOIA_HEADER_SIZE);
+        bytes.extend_from_slice(&QOIA_MAGIC);
+        bytes.push(QOIA_VERSION);
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(&qoi.header_bytes());
+
+        AnimationEncoder {
+            qoi,
+            bytes,
+            frames: 0,
+            key_interval,
+            since_key: 0,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Returns descriptor of frames.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns number of frames pushed so far.
+    #[inline]
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Encode raw RGB or RGBA (depending on `self.qoi().colors` value) pixels of the next frame
+    /// shown for `duration_ms` milliseconds.\
+    /// Frame is encoded as key frame or as differences from the previous frame depending on key frame interval.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded frame image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    /// Frame is not added after failure.
+    pub fn push_frame(&mut self, pixels: &[u8], duration_ms: u32) -> Result<usize, EncodeError> {
+        let key = self.frames == 0 || self.since_key == self.key_interval;
+        let flags = match key {
+            true => 0,
+            false => FRAME_DELTA,
+        };
+
+        let start = self.begin_frame(duration_ms, flags);
+        let result = match key {
+            true => self.qoi.encode_append(pixels, &mut self.bytes),
+            false => {
+                let len = self.bytes.len();
+                self.bytes.resize(len + self.qoi.encoded_size_limit(), 0);
+                let result = self
+                    .qoi
+                    .encode_delta(&self.previous, pixels, &mut self.bytes[len..]);
+                if let Ok(size) = result {
+                    self.bytes.truncate(len + size);
+                }
+                result
+            }
+        };
+
+        let size = match result {
+            Ok(size) => size,
+            Err(err) => {
+                self.bytes.truncate(start);
+                return Err(err);
+            }
+        };
+        self.end_frame(start, size, key);
+
+        if self.key_interval != 1 {
+            self.previous.clear();
+            self.previous
+                .extend_from_slice(&pixels[..self.qoi.decoded_size()]);
+        }
+        Ok(size)
+    }
+
+    /// Add already encoded QOI image from bytes slice as the next frame
+    /// shown for `duration_ms` milliseconds.\
+    /// Image is added as key frame, it is decoded if encoder retains pixels of the previous frame.
+    /// Bytes following the end marker are not added.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the added frame image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::DimensionsMismatch)` is returned if image has different dimensions or channels number.
+    pub fn push_encoded_frame(
+        &mut self,
+        bytes: &[u8],
+        duration_ms: u32,
+    ) -> Result<usize, DecodeError> {
+        let (qoi, size) = Qoi::skip_image(bytes, false)?;
+        if !self.qoi.same_frame(&qoi) {
+            return Err(DecodeError::DimensionsMismatch);
+        }
+
+        if self.key_interval != 1 {
+            self.previous.resize(qoi.decoded_size(), 0);
+            Qoi::decode(&bytes[..size], &mut self.previous)?;
+        }
+
+        let start = self.begin_frame(duration_ms, 0);
+        self.bytes.extend_from_slice(&bytes[..size]);
+        self.end_frame(start, size, true);
+        Ok(size)
+    }
+
+    /// Finish encoding and return bytes of animation container.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.bytes[5..9].copy_from_slice(&self.frames.to_be_bytes());
+        self.bytes
+    }
+
+    /// Writes frame header with unknown size and returns its offset.
+    fn begin_frame(&mut self, duration_ms: u32, flags: u8) -> usize {
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(&duration_ms.to_be_bytes());
+        self.bytes.push(flags);
+        self.bytes.extend_from_slice(&0u64.to_be_bytes());
+        start
+    }
+
+    /// Writes `size` of frame image into frame header at `start`.
+    fn end_frame(&mut self, start: usize, size: usize, key: bool) {
+        self.bytes[start + 5..start + FRAME_HEADER_SIZE]
+            .copy_from_slice(&(size as u64).to_be_bytes());
+        self.frames += 1;
+        self.since_key = match key {
+            true => 1,
+            false => self.since_key + 1,
+        };
+    }
+}
+
+/// Decoder of animation container stored in bytes slice.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationDecoder<'a> {
+    qoi: Qoi,
+    bytes: &'a [u8],
+    frames: u32,
+}
+
+impl<'a> AnimationDecoder<'a> {
+    /// Reads header of animation container from bytes slice.
+    ///
+    /// On success this function returns `Ok(decoder)` with `decoder` of the animation.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidMagic)` is returned if bytes do not start with animation container magic
+    /// and `Err(DecodeError::InvalidState)` if container has unsupported version.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < QOIA_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: QOIA_HEADER_SIZE - bytes.len(),
+            });
+        }
+
+        if bytes[..4] != QOIA_MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+        if bytes[4] != QOIA_VERSION {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let frames = u32::from_be_bytes(bytes[5..9].try_into().unwrap());
+        let qoi = Qoi::decode_header(&bytes[9..])?;
+
+        Ok(AnimationDecoder { qoi, bytes, frames })
+    }
+
+    /// Returns descriptor of frames.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns number of frames in the animation.
+    #[inline]
+    pub fn frame_count(&self) -> u32 {
+        self.frames
+    }
+
+    /// Returns decoder of frames one by one into `buffer`.\
+    /// `buffer` must hold at least `width * height * channels` bytes.
+    /// Delta frames are applied to the previous frame kept in `buffer`.
+    #[inline]
+    pub fn frames<'b>(&self, buffer: &'b mut [u8]) -> Frames<'b>
+    where
+        'a: 'b,
+    {
+        Frames {
+            qoi: self.qoi,
+            bytes: self.bytes,
+            offset: QOIA_HEADER_SIZE,
+            buffer,
+            frame: 0,
+            frames: self.frames,
+            has_previous: false,
+            done: false,
+        }
+    }
+}
+
+/// Frame of animation decoded by [`Frames::next_frame`].
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    /// Duration of the frame in milliseconds.
+    pub duration_ms: u32,
+
+    /// Header of the frame image.
+    pub qoi: Qoi,
+
+    /// `true` if the frame is a key frame that does not depend on the previous frame.
+    pub key: bool,
+
+    /// Raw RGB or RGBA pixels of the frame.
+    pub pixels: &'a [u8],
+}
+
+/// Header of encoded frame.
+struct FrameHeader {
+    duration_ms: u32,
+    key: bool,
+    start: usize,
+    size: usize,
+}
+
+/// Decoder of animation frames that yields frames one by one.\
+/// Each frame is decoded into caller provided buffer and borrowed until the next call.\
+/// Created by [`AnimationDecoder::frames`].
+#[derive(Debug)]
+pub struct Frames<'a> {
+    qoi: Qoi,
+    bytes: &'a [u8],
+    offset: usize,
+    buffer: &'a mut [u8],
+    frame: u32,
+    frames: u32,
+    has_previous: bool,
+    done: bool,
+}
+
+impl<'a> Frames<'a> {
+    /// Returns number of frames not decoded yet.
+    #[inline]
+    pub fn frames_left(&self) -> u32 {
+        self.frames - self.frame
+    }
+
+    /// Decode next frame of the animation.
+    ///
+    /// Returns `Some(Ok(frame))` with decoded `frame`,
+    /// `Some(Err(err))` with `err` describing cause of the error
+    /// or `None` if all frames are decoded or error was already returned.\
+    /// `Some(Err(DecodeError::InvalidState))` is returned for delta frame without decoded previous frame.
+    pub fn next_frame(&mut self) -> Option<Result<Frame<'_>, DecodeError>> {
+        if self.done || self.frame == self.frames {
+            return None;
+        }
+
+        match self.decode_frame() {
+            Ok(header) => Some(Ok(Frame {
+                duration_ms: header.duration_ms,
+                qoi: self.qoi,
+                key: header.key,
+                pixels: &self.buffer[..self.qoi.decoded_size()],
+            })),
+            Err(err) => {
+                self.done = true;
+                self.has_previous = false;
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Move to frame with index `frame`, so it is returned by the next call to [`Frames::next_frame`].\
+    /// Previous frame is dropped and decoding restarts from the nearest key frame preceding `frame`,
+    /// frames between them are decoded into the buffer.
+    ///
+    /// On success this function returns `Ok(())`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidRegion)` is returned if `frame` exceeds number of frames.
+    pub fn seek(&mut self, frame: u32) -> Result<(), DecodeError> {
+        if frame > self.frames {
+            return Err(DecodeError::InvalidRegion);
+        }
+
+        self.done = true;
+        self.has_previous = false;
+
+        let mut offset = QOIA_HEADER_SIZE;
+        let mut key = (0, offset);
+        let last = frame.min(self.frames.saturating_sub(1));
+        for i in (0..=last).take(self.frames as usize) {
+            let header = Self::frame_header(self.bytes, offset)?;
+            if header.key {
+                key = (i, offset);
+            }
+            offset = header.start.saturating_add(header.size);
+        }
+
+        self.frame = key.0;
+        self.offset = key.1;
+        self.done = false;
+        while self.frame < frame {
+            if let Err(err) = self.decode_frame() {
+                self.done = true;
+                self.has_previous = false;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes frame at current offset into buffer and advances past it.
+    fn decode_frame(&mut self) -> Result<FrameHeader, DecodeError> {
+        let header = Self::frame_header(self.bytes, self.offset)?;
+        if !header.key && !self.has_previous {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let start = header.start;
+        let rest = &self.bytes[start..];
+        let image = rest.get(..header.size).unwrap_or(rest);
+
+        let qoi = Qoi::decode_header(image).map_err(|err| err.at(start, 0))?;
+        if !self.qoi.same_frame(&qoi) {
+            return Err(DecodeError::DimensionsMismatch);
+        }
+
+        match header.key {
+            true => Qoi::decode(image, self.buffer).map(|_| ()),
+            false => qoi.apply_delta_in_place(image, self.buffer),
+        }
+        .map_err(|err| err.at(start, 0))?;
+
+        if image.len() < header.size {
+            // Image is complete but frame size claims more bytes.
+            return Err(DecodeError::NotEnoughData {
+                offset: start + rest.len(),
+                pixels_decoded: qoi.width as usize * qoi.height as usize,
+                needed: header.size - rest.len(),
+            });
+        }
+
+        self.offset = start + image.len();
+        self.frame += 1;
+        self.has_previous = true;
+        Ok(header)
+    }
+
+    /// Reads header of frame at `offset`.
+    fn frame_header(bytes: &[u8], offset: usize) -> Result<FrameHeader, DecodeError> {
+        let header = match bytes.get(offset..offset.saturating_add(FRAME_HEADER_SIZE)) {
+            None => {
+                return Err(DecodeError::NotEnoughData {
+                    offset,
+                    pixels_decoded: 0,
+                    needed: offset.saturating_add(FRAME_HEADER_SIZE) - bytes.len(),
+                })
+            }
+            Some(header) => header,
+        };
+
+        if header[4] & !FRAME_DELTA != 0 {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let size = u64::from_be_bytes(header[5..].try_into().unwrap());
+        Ok(FrameHeader {
+            duration_ms: u32::from_be_bytes(header[..4].try_into().unwrap()),
+            key: header[4] & FRAME_DELTA == 0,
+            start: offset + FRAME_HEADER_SIZE,
+            size: size.try_into().unwrap_or(usize::MAX),
+        })
+    }
+}
+
+impl Qoi {
+    /// Decodes delta image from bytes slice adding it to pixels of the previous frame in `output`.
+    fn apply_delta_in_place(&self, bytes: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+        let output = output
+            .get_mut(..self.decoded_size())
+            .ok_or(DecodeError::OutputIsTooSmall)?;
+
+        let mut state = DecoderState::new();
+        let mut delta = [0; DELTA_PIXELS * 4];
+        let mut pos = 0;
+        while !state.is_finished() {
+            let n = self.decode_resume(bytes, &mut state, &mut delta, DELTA_PIXELS)?;
+            let len = n * self.colors.channels();
+            for (v, d) in output[pos..pos + len].iter_mut().zip(&delta) {
+                *v = v.wrapping_add(*d);
+            }
+            pos += len;
+        }
+        Ok(())
+    }
+}
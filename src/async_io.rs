@@ -0,0 +1,117 @@
+use super::*;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Size of buffer for bytes read from reader.
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Size of buffer for encoded bytes before they are written into writer.
+const WRITE_BUFFER_SIZE: usize = 4096;
+
+/// Reads into `buf` until at least one byte is read.\
+/// Returns `Err(ReadDecodeError::Decode(DecodeError::NotEnoughData { .. }))` describing position of `decoder` if reader reached its end.
+#[inline]
+async fn read_some<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut [u8],
+    decoder: &Decoder,
+) -> Result<usize, ReadDecodeError> {
+    loop {
+        match reader.read(buf).await {
+            Ok(0) => return Err(decoder.truncated().into()),
+            Ok(n) => return Ok(n),
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from asynchronous reader.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// Bytes are read in small portions and decoded between reads,
+    /// reading stops right after the end marker, so following data is left in the reader.\
+    /// Dropping returned future abandons decoding leaving `output` partially written.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub async fn decode_from_async_reader<R: AsyncRead + Unpin>(
+        mut reader: R,
+        output: &mut [u8],
+    ) -> Result<Self, ReadDecodeError> {
+        let mut decoder = Decoder::new();
+        let mut header = [0; QOI_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < QOI_HEADER_SIZE {
+            let n = read_some(&mut reader, &mut header[filled..], &decoder).await?;
+            decoder.push(&header[filled..filled + n], &mut [])?;
+            filled += n;
+        }
+
+        let qoi = Self::decode_header(&header)?;
+
+        let px_len = qoi.width as usize * qoi.height as usize * qoi.colors.channels();
+        let mut output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall.into()),
+            Some(output) => output,
+        };
+
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        while !decoder.is_finished() {
+            let len = decoder.bytes_needed().min(READ_BUFFER_SIZE);
+            let n = read_some(&mut reader, &mut buf[..len], &decoder).await?;
+            let progress = decoder.push(&buf[..n], output)?;
+            debug_assert_eq!(progress.consumed, n);
+            output = &mut output[progress.pixels * qoi.colors.channels()..];
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image written into asynchronous writer.\
+    /// Pixels are encoded in small portions between writes,
+    /// so no buffer for whole encoded image is required.\
+    /// Dropping returned future abandons encoding leaving truncated image in the writer.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub async fn encode_to_async_writer<W: AsyncWrite + Unpin>(
+        &self,
+        pixels: &[u8],
+        mut writer: W,
+    ) -> Result<u64, WriteEncodeError> {
+        let channels = self.colors.channels();
+        let px_len = self.input_len(channels)?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                }
+                .into())
+            }
+            Some(pixels) => pixels,
+        };
+
+        let mut encoder = Encoder::new(*self);
+        let mut buf = vec![0; WRITE_BUFFER_SIZE];
+        let mut total = 0;
+
+        let mut rest = pixels;
+
+        while !rest.is_empty() {
+            let len = (encoder.pixels_fitting(buf.len()) * channels).min(rest.len());
+            let n = encoder.push_pixels(&rest[..len], &mut buf)?;
+            writer.write_all(&buf[..n]).await?;
+            total += n as u64;
+            rest = &rest[len..];
+        }
+
+        let n = encoder.finish(&mut buf)?;
+        writer.write_all(&buf[..n]).await?;
+        total += n as u64;
+
+        Ok(total)
+    }
+}
@@ -0,0 +1,99 @@
+use super::*;
+use bytes::{Buf, BufMut};
+
+/// Size of buffer for encoded bytes before they are put into `BufMut`.
+const PUT_BUFFER_SIZE: usize = 1024;
+
+impl Qoi {
+    /// Decode a QOI image from possibly non-contiguous `Buf`.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// Chunks may be split between segments of `buf`.
+    /// On success `buf` is advanced right after the end marker.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_from_buf<B: Buf>(buf: &mut B, output: &mut [u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new();
+
+        let qoi = loop {
+            if let Some(qoi) = decoder.header() {
+                break qoi;
+            }
+            if !buf.has_remaining() {
+                return Err(decoder.truncated());
+            }
+            let progress = decoder.push(buf.chunk(), &mut [])?;
+            buf.advance(progress.consumed);
+        };
+
+        let px_len = qoi.width as usize * qoi.height as usize * qoi.colors.channels();
+        let mut output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        while !decoder.is_finished() {
+            if !buf.has_remaining() {
+                return Err(decoder.truncated());
+            }
+            let progress = decoder.push(buf.chunk(), output)?;
+            buf.advance(progress.consumed);
+            output = &mut output[progress.pixels * qoi.colors.channels()..];
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image put into `BufMut`.\
+    /// Part of encoded image may be already put into `buf` on failure.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_to_bufmut<B: BufMut>(
+        &self,
+        pixels: &[u8],
+        buf: &mut B,
+    ) -> Result<usize, EncodeError> {
+        let channels = self.colors.channels();
+        let px_len = self.input_len(channels)?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => pixels,
+        };
+
+        let mut encoder = Encoder::new(*self);
+        let mut put = [0; PUT_BUFFER_SIZE];
+        let mut total = 0;
+        let mut rest = pixels;
+
+        loop {
+            let n = match rest.is_empty() {
+                true => encoder.finish(&mut put)?,
+                false => {
+                    let len = (encoder.pixels_fitting(put.len()) * channels).min(rest.len());
+                    let n = encoder.push_pixels(&rest[..len], &mut put)?;
+                    rest = &rest[len..];
+                    n
+                }
+            };
+
+            if buf.remaining_mut() < n {
+                return Err(EncodeError::OutputIsTooSmall {
+                    required: self.encoded_size_limit(),
+                });
+            }
+            buf.put_slice(&put[..n]);
+            total += n;
+
+            if encoder.is_finished() {
+                return Ok(total);
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use core::ops::ControlFlow;
+
+use super::*;
+use decode::{Inspect, Visit};
+
+/// Size of buffer for decoded bytes before they are passed to hash function.
+const CHECKSUM_BUFFER_SIZE: usize = 1024;
+
+/// Algorithm of checksum computed over decoded raw pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgo {
+    /// CRC-32 with polynomial used by zlib, gzip and PNG.\
+    /// Checksum is stored in lower 32 bits of the value.
+    Crc32,
+
+    /// 64-bit FNV-1a hash.
+    Fnv1a64,
+}
+
+/// State of hash function.
+enum State {
+    Crc32(crc32fast::Hasher),
+    Fnv1a64(u64),
+}
+
+/// Checksum of raw pixels computed as they are decoded.\
+/// Pixels are collected into small buffer that is passed to hash function at once.
+struct Checksum {
+    state: State,
+    channels: usize,
+    buf: [u8; CHECKSUM_BUFFER_SIZE],
+    len: usize,
+}
+
+impl Checksum {
+    #[inline]
+    fn new(algo: ChecksumAlgo, channels: usize) -> Self {
+        Checksum {
+            state: match algo {
+                ChecksumAlgo::Crc32 => State::Crc32(crc32fast::Hasher::new()),
+                ChecksumAlgo::Fnv1a64 => State::Fnv1a64(0xcbf29ce484222325),
+            },
+            channels,
+            buf: [0; CHECKSUM_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Adds `n` copies of the pixel with channels number of the image.
+    #[inline]
+    fn add(&mut self, px: [u8; 4], n: usize) {
+        let px = &px[..self.channels];
+        for _ in 0..n {
+            if self.len + px.len() > CHECKSUM_BUFFER_SIZE {
+                self.flush();
+            }
+            self.buf[self.len..][..px.len()].copy_from_slice(px);
+            self.len += px.len();
+        }
+    }
+
+    /// Passes buffered bytes to hash function.
+    #[inline]
+    fn flush(&mut self) {
+        let bytes = &self.buf[..self.len];
+        match &mut self.state {
+            State::Crc32(hasher) => hasher.update(bytes),
+            State::Fnv1a64(hash) => {
+                for &b in bytes {
+                    *hash = (*hash ^ b as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+        self.len = 0;
+    }
+
+    /// Returns checksum of all added pixels.
+    #[inline]
+    fn finish(mut self) -> u64 {
+        self.flush();
+        match self.state {
+            State::Crc32(hasher) => hasher.finalize() as u64,
+            State::Fnv1a64(hash) => hash,
+        }
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice computing checksum of decoded pixels.\
+    /// Decoded raw RGB or RGBA (depending on `colors` value in header) pixels are written into `output` slice.\
+    /// Checksum is the same as computed by `algo` over `output` slice holding decoded image.
+    ///
+    /// On success this function returns `Ok((qoi, checksum))` with `qoi` describing image dimensions and color space
+    /// and `checksum` of decoded pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_checksum(
+        bytes: &[u8],
+        output: &mut [u8],
+        algo: ChecksumAlgo,
+    ) -> Result<(Self, u64), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let mut checksum = Checksum::new(algo, qoi.colors.channels());
+        let f = |px, n| checksum.add(px, n);
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(
+                bytes,
+                Inspect {
+                    out: bytemuck::cast_slice_mut::<_, [u8; 4]>(output),
+                    f,
+                },
+            )?,
+            false => qoi.decode_skip_header_into(
+                bytes,
+                Inspect {
+                    out: bytemuck::cast_slice_mut::<_, [u8; 3]>(output),
+                    f,
+                },
+            )?,
+        }
+        Ok((qoi, checksum.finish()))
+    }
+
+    /// Computes checksum of raw RGB or RGBA (depending on `colors` value in header) pixels of a QOI image from bytes slice
+    /// without storing decoded pixels.\
+    /// Checksum is the same as returned by [`Qoi::decode_with_checksum`].
+    ///
+    /// On success this function returns `Ok(checksum)` with `checksum` of decoded pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn checksum_of_decoded(bytes: &[u8], algo: ChecksumAlgo) -> Result<u64, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let mut checksum = Checksum::new(algo, qoi.colors.channels());
+        qoi.decode_skip_header_into(
+            &bytes[QOI_HEADER_SIZE..],
+            Visit {
+                left: qoi.width as usize * qoi.height as usize,
+                f: |px, n| {
+                    checksum.add(px, n);
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+        Ok(checksum.finish())
+    }
+}
@@ -0,0 +1,287 @@
+//! Chunk level access to encoded QOI data.
+//!
+//! [`Op`] describes single chunk of encoded data and can be parsed from and written into bytes.\
+//! [`ChunkIter`] walks through chunks of encoded image.
+
+use super::*;
+
+/// Single chunk of encoded QOI data.\
+/// Differences are stored as signed values, wraparound is applied when they are added to channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// `QOI_OP_RGB` sets color channels of the pixel, alpha channel is taken from previous pixel.
+    Rgb {
+        /// Red channel value.
+        r: u8,
+        /// Green channel value.
+        g: u8,
+        /// Blue channel value.
+        b: u8,
+    },
+
+    /// `QOI_OP_RGBA` sets all channels of the pixel.
+    Rgba {
+        /// Red channel value.
+        r: u8,
+        /// Green channel value.
+        g: u8,
+        /// Blue channel value.
+        b: u8,
+        /// Alpha channel value.
+        a: u8,
+    },
+
+    /// `QOI_OP_INDEX` takes pixel from the index of previously seen pixels.\
+    /// Index position is in range `0..=63`.
+    Index(u8),
+
+    /// `QOI_OP_DIFF` adds small differences to color channels of previous pixel.\
+    /// Each difference is in range `-2..=1`.
+    Diff {
+        /// Red channel difference.
+        dr: i8,
+        /// Green channel difference.
+        dg: i8,
+        /// Blue channel difference.
+        db: i8,
+    },
+
+    /// `QOI_OP_LUMA` adds green channel difference to color channels of previous pixel
+    /// with red and blue channels corrected relative to it.\
+    /// Green channel difference is in range `-32..=31`, corrections are in range `-8..=7`.
+    Luma {
+        /// Green channel difference.
+        dg: i8,
+        /// Red channel difference minus green channel difference.
+        dr_dg: i8,
+        /// Blue channel difference minus green channel difference.
+        db_dg: i8,
+    },
+
+    /// `QOI_OP_RUN` repeats previous pixel.\
+    /// Run length is in range `1..=62`.
+    Run(u8),
+}
+
+impl Op {
+    /// Parses chunk at the start of `bytes` slice.
+    ///
+    /// On success this function returns `Ok((op, size))` with `op` describing the chunk and `size` of the chunk in bytes.\
+    /// On failure this function returns `Err(DecodeError::NotEnoughData { .. })` if chunk is incomplete.
+    #[inline]
+    pub fn parse(bytes: &[u8]) -> Result<(Op, usize), DecodeError> {
+        match *bytes {
+            [QOI_OP_RGB, r, g, b, ..] => Ok((Op::Rgb { r, g, b }, 4)),
+            [QOI_OP_RGBA, r, g, b, a, ..] => Ok((Op::Rgba { r, g, b, a }, 5)),
+            [b1 @ 0b00000000..=0b00111111, ..] => Ok((Op::Index(b1), 1)),
+            [b1 @ 0b01000000..=0b01111111, ..] => Ok((
+                Op::Diff {
+                    dr: ((b1 >> 4) & 0x03) as i8 - 2,
+                    dg: ((b1 >> 2) & 0x03) as i8 - 2,
+                    db: (b1 & 0x03) as i8 - 2,
+                },
+                1,
+            )),
+            [b1 @ 0b10000000..=0b10111111, b2, ..] => Ok((
+                Op::Luma {
+                    dg: (b1 & 0x3f) as i8 - 32,
+                    dr_dg: (b2 >> 4) as i8 - 8,
+                    db_dg: (b2 & 0x0f) as i8 - 8,
+                },
+                2,
+            )),
+            [b1 @ 0b11000000..=0b11111101, ..] => Ok((Op::Run((b1 & 0x3f) + 1), 1)),
+            _ => Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: match bytes.first() {
+                    None => 1,
+                    Some(&b1) => decoder::chunk_len(b1) - bytes.len(),
+                },
+            }),
+        }
+    }
+
+    /// Returns size of the chunk in bytes.
+    #[inline]
+    pub const fn size(&self) -> usize {
+        match self {
+            Op::Rgb { .. } => 4,
+            Op::Rgba { .. } => 5,
+            Op::Luma { .. } => 2,
+            Op::Index(_) | Op::Diff { .. } | Op::Run(_) => 1,
+        }
+    }
+
+    /// Writes the chunk at the start of `output` slice.\
+    /// Operands are truncated to their bit width in the chunk.
+    ///
+    /// Returns number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` is shorter than [`Op::size`] or run length is not in range `1..=62`.
+    #[inline]
+    pub fn write(&self, output: &mut [u8]) -> usize {
+        let len = self.size();
+        let output = &mut output[..len];
+
+        match *self {
+            Op::Rgb { r, g, b } => output.copy_from_slice(&[QOI_OP_RGB, r, g, b]),
+            Op::Rgba { r, g, b, a } => output.copy_from_slice(&[QOI_OP_RGBA, r, g, b, a]),
+            Op::Index(i) => output[0] = QOI_OP_INDEX | (i & 0x3f),
+            Op::Diff { dr, dg, db } => {
+                let bias = |v: i8| v.wrapping_add(2) as u8 & 0x03;
+                output[0] = QOI_OP_DIFF | bias(dr) << 4 | bias(dg) << 2 | bias(db);
+            }
+            Op::Luma { dg, dr_dg, db_dg } => {
+                let bias = |v: i8| v.wrapping_add(8) as u8 & 0x0f;
+                output[0] = QOI_OP_LUMA | (dg.wrapping_add(32) as u8 & 0x3f);
+                output[1] = bias(dr_dg) << 4 | bias(db_dg);
+            }
+            Op::Run(n) => {
+                assert!((1..=62).contains(&n), "Run length must be in range 1..=62");
+                output[0] = QOI_OP_RUN | (n - 1);
+            }
+        }
+
+        len
+    }
+
+    /// Returns number of pixels produced by the chunk.
+    #[inline]
+    pub const fn pixels(&self) -> usize {
+        match self {
+            Op::Run(n) => *n as usize,
+            _ => 1,
+        }
+    }
+
+    /// Returns kind of the chunk.\
+    /// Runs are reported as [`OpKind::Run`].
+    #[inline]
+    pub const fn kind(&self) -> OpKind {
+        match self {
+            Op::Rgb { .. } => OpKind::Rgb,
+            Op::Rgba { .. } => OpKind::Rgba,
+            Op::Index(_) => OpKind::Index,
+            Op::Diff { .. } => OpKind::Diff,
+            Op::Luma { .. } => OpKind::Luma,
+            Op::Run(_) => OpKind::Run,
+        }
+    }
+}
+
+/// Kind of chunk that produced a pixel.\
+/// Values are written by [`Qoi::opcode_map`] and stay the same between versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OpKind {
+    /// Pixel produced by `QOI_OP_RGB`.
+    Rgb = 0,
+
+    /// Pixel produced by `QOI_OP_RGBA`.
+    Rgba = 1,
+
+    /// Pixel produced by `QOI_OP_INDEX`.
+    Index = 2,
+
+    /// Pixel produced by `QOI_OP_DIFF`.
+    Diff = 3,
+
+    /// Pixel produced by `QOI_OP_LUMA`.
+    Luma = 4,
+
+    /// First pixel produced by `QOI_OP_RUN`.
+    Run = 5,
+
+    /// Following pixels produced by `QOI_OP_RUN`.
+    RunContinuation = 6,
+}
+
+/// Iterator over chunks of encoded QOI image.\
+/// Yields offset of each chunk from the start of the image along with the chunk,
+/// up to the chunk that covers last pixel of the image.\
+/// End marker is verified after the last chunk.
+/// Bytes that do not form valid chunks yield `Err(err)` as the last item.
+#[derive(Clone, Debug)]
+pub struct ChunkIter<'a> {
+    qoi: Qoi,
+    bytes: &'a [u8],
+    offset: usize,
+    left: usize,
+    done: bool,
+}
+
+impl<'a> ChunkIter<'a> {
+    /// Returns iterator over chunks of the image in `bytes` slice including header.
+    ///
+    /// On success this function returns `Ok(iter)`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let qoi = Qoi::decode_header(bytes)?;
+
+        Ok(ChunkIter {
+            qoi,
+            bytes,
+            offset: QOI_HEADER_SIZE,
+            left: qoi.width as usize * qoi.height as usize,
+            done: false,
+        })
+    }
+
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns offset of the next chunk from the start of the image.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns number of pixels not covered by yielded chunks.
+    #[inline]
+    pub fn pixels_left(&self) -> usize {
+        self.left
+    }
+}
+
+impl<'a> Iterator for ChunkIter<'a> {
+    type Item = Result<(usize, Op), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.left == 0 {
+            self.done = true;
+            return match self.qoi.decode_padding(self.bytes, self.offset) {
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            };
+        }
+
+        match Op::parse(&self.bytes[self.offset..]) {
+            Ok((op, size)) => {
+                let offset = self.offset;
+                self.offset += size;
+                self.left = self.left.saturating_sub(op.pixels());
+                Some(Ok((offset, op)))
+            }
+            Err(err) => {
+                self.done = true;
+                let total = self.qoi.width as usize * self.qoi.height as usize;
+                Some(Err(err
+                    .at(self.offset, total - self.left)
+                    .for_image(self.bytes, total)))
+            }
+        }
+    }
+}
+
+impl core::iter::FusedIterator for ChunkIter<'_> {}
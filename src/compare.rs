@@ -0,0 +1,179 @@
+use core::ops::ControlFlow;
+
+use super::*;
+
+/// Number of pixels of each image decoded at once when images are compared.
+const COMPARE_PIXELS: usize = 256;
+
+/// Decodes images `a` and `b` with the same dimensions in lockstep
+/// passing consecutive portions of their pixels converted to RGBA into `f`.\
+/// Decoding stops as soon as `f` breaks, remaining parts of the images are not verified.
+fn lockstep<F>(qa: Qoi, a: &[u8], qb: Qoi, b: &[u8], mut f: F) -> Result<(), DecodeError>
+where
+    F: FnMut(&[[u8; 4]], &[[u8; 4]]) -> ControlFlow<()>,
+{
+    let mut sa = DecoderState::new();
+    let mut sb = DecoderState::new();
+    let mut raw = [0; COMPARE_PIXELS * 4];
+    let mut pa = [[0; 4]; COMPARE_PIXELS];
+    let mut pb = [[0; 4]; COMPARE_PIXELS];
+
+    while !sa.is_finished() || !sb.is_finished() {
+        let n = qa.decode_resume(a, &mut sa, &mut raw, COMPARE_PIXELS)?;
+        expand(qa.colors, &raw, &mut pa[..n]);
+
+        let m = qb.decode_resume(b, &mut sb, &mut raw, COMPARE_PIXELS)?;
+        expand(qb.colors, &raw, &mut pb[..m]);
+
+        debug_assert_eq!(n, m);
+        if f(&pa[..n], &pb[..m]).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts raw pixels with channels number of `colors` into RGBA pixels filling `out`.
+#[inline]
+fn expand(colors: Colors, raw: &[u8], out: &mut [[u8; 4]]) {
+    match colors.has_alpha() {
+        true => out.copy_from_slice(bytemuck::cast_slice(&raw[..out.len() * 4])),
+        false => {
+            let raw = bytemuck::cast_slice::<_, [u8; 3]>(&raw[..out.len() * 3]);
+            for (px, raw) in out.iter_mut().zip(raw) {
+                *px = convert(raw);
+            }
+        }
+    }
+}
+
+impl Qoi {
+    /// Compares pixels of two QOI images from bytes slices without storing decoded images.\
+    /// Images are equal if they have the same dimensions, channels number and decoded pixels,
+    /// color space in the header is not compared.\
+    /// Both images are decoded in lockstep and decoding stops at the first differing pixel,
+    /// remaining parts of the images are not verified.
+    ///
+    /// On success this function returns `Ok(equal)` with `equal` set to `true` if pixels of images are equal.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn pixels_equal(a: &[u8], b: &[u8]) -> Result<bool, DecodeError> {
+        Self::pixels_equal_with(a, b, false)
+    }
+
+    /// Compares pixels of two QOI images from bytes slices without storing decoded images.\
+    /// Same as [`Qoi::pixels_equal`] but if `opaque_rgb` is `true`
+    /// pixels of images without alpha channel are treated as RGBA pixels with alpha `255`,
+    /// so they are equal to fully opaque pixels of the same color.
+    ///
+    /// On success this function returns `Ok(equal)` with `equal` set to `true` if pixels of images are equal.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn pixels_equal_with(a: &[u8], b: &[u8], opaque_rgb: bool) -> Result<bool, DecodeError> {
+        let qa = Self::decode_header(a)?;
+        let qb = Self::decode_header(b)?;
+
+        if qa.width != qb.width || qa.height != qb.height {
+            return Ok(false);
+        }
+        if qa.colors.channels() != qb.colors.channels() && !opaque_rgb {
+            return Ok(false);
+        }
+
+        let mut equal = true;
+        lockstep(qa, a, qb, b, |pa, pb| match pa == pb {
+            true => ControlFlow::Continue(()),
+            false => {
+                equal = false;
+                ControlFlow::Break(())
+            }
+        })?;
+        Ok(equal)
+    }
+}
+
+/// Differences between pixels of two images computed by [`Qoi::psnr`].\
+/// Channels are in RGBA order, images without alpha channel have alpha `255`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PsnrResult {
+    /// Mean squared error of each channel.
+    pub mse: [f64; 4],
+
+    /// Peak signal-to-noise ratio of each channel in decibels.\
+    /// Infinite for channels without differences.
+    pub psnr: [f64; 4],
+
+    /// Maximum absolute difference of each channel.
+    pub max_error: [u8; 4],
+}
+
+#[cfg(feature = "std")]
+impl PsnrResult {
+    /// Returns peak signal-to-noise ratio of all channels in decibels computed from mean of their squared errors.\
+    /// Alpha channel is included if `alpha` is `true`.
+    #[inline]
+    pub fn psnr_total(&self, alpha: bool) -> f64 {
+        let channels = 3 + alpha as usize;
+        let mse = self.mse[..channels].iter().sum::<f64>() / channels as f64;
+        psnr(mse)
+    }
+}
+
+/// Returns peak signal-to-noise ratio of 8 bit values with specified mean squared error.
+#[cfg(feature = "std")]
+#[inline]
+fn psnr(mse: f64) -> f64 {
+    match mse == 0.0 {
+        true => f64::INFINITY,
+        false => 10.0 * (255.0 * 255.0 / mse).log10(),
+    }
+}
+
+#[cfg(feature = "std")]
+impl Qoi {
+    /// Computes differences between pixels of two QOI images from bytes slices without storing decoded images.\
+    /// Both images are decoded in lockstep. Alpha channel is always compared,
+    /// pixels of images without alpha channel have alpha `255`.
+    ///
+    /// On success this function returns `Ok(result)` with `result` describing differences of each channel.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::DimensionsMismatch)` is returned if images have different dimensions.
+    pub fn psnr(a: &[u8], b: &[u8]) -> Result<PsnrResult, DecodeError> {
+        let qa = Self::decode_header(a)?;
+        let qb = Self::decode_header(b)?;
+
+        if qa.width != qb.width || qa.height != qb.height {
+            return Err(DecodeError::DimensionsMismatch);
+        }
+
+        let mut sums = [0u64; 4];
+        let mut max_error = [0u8; 4];
+        lockstep(qa, a, qb, b, |pa, pb| {
+            for (pa, pb) in pa.iter().zip(pb) {
+                for c in 0..4 {
+                    let d = pa[c].abs_diff(pb[c]);
+                    sums[c] += d as u64 * d as u64;
+                    max_error[c] = max_error[c].max(d);
+                }
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        let pixels = qa.width as u64 * qa.height as u64;
+        let mut mse = [0.0; 4];
+        let mut result = [0.0; 4];
+        for c in 0..4 {
+            if pixels > 0 {
+                mse[c] = sums[c] as f64 / pixels as f64;
+            }
+            result[c] = psnr(mse[c]);
+        }
+
+        Ok(PsnrResult {
+            mse,
+            psnr: result,
+            max_error,
+        })
+    }
+}
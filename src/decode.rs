@@ -1,15 +1,25 @@
-use core::convert::TryInto;
+use core::{convert::TryInto, mem::MaybeUninit, ops::ControlFlow};
 
-use super::*;
+use super::{uninit::Init, *};
 
 #[cfg(feature = "alloc")]
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, vec, vec::Vec};
 
 /// Errros that may occur during image decoding.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum DecodeError {
     /// Buffer does not contain enough encoded data.
-    NotEnoughData,
+    NotEnoughData {
+        /// Offset of incomplete header, chunk or end marker in encoded data.
+        offset: usize,
+        /// Number of pixels decoded before encoded data ended.
+        pixels_decoded: usize,
+        /// Lower bound of number of additional bytes required to proceed.\
+        /// Exact for incomplete header, chunk or end marker.
+        /// Pixels left in the image are counted as if they were encoded with the longest runs,
+        /// end marker is counted only by decoders that consume it.
+        needed: usize,
+    },
 
     /// Encoded header contains invalid magic value.\
     /// First four bytes must contain `b"qoif"`.\
@@ -28,14 +38,69 @@ pub enum DecodeError {
 
     /// Output buffer is too small to fit decoded image.
     OutputIsTooSmall,
+
+    /// Output row stride is smaller than row of decoded pixels.
+    InvalidStride,
+
+    /// Requested region does not fit into the image.
+    InvalidRegion,
+
+    /// Requested pixel coordinates are outside of the image.
+    PixelOutOfBounds,
+
+    /// Decoding options cannot be combined with each other.
+    InvalidOptions,
+
+    /// Encoded data does not end with valid end marker.
+    InvalidPadding {
+        /// Offset of the expected end marker in encoded data.
+        offset: usize,
+    },
+
+    /// Decoder state is inconsistent with the image or has unsupported serialization version.
+    InvalidState,
+
+    /// Encoded data continues after the end marker.
+    TrailingData {
+        /// Offset of the first byte following the end marker.
+        offset: usize,
+    },
+
+    /// Encoded data contains more pixels than the header specifies.\
+    /// Returned in strict mode for run that exceeds the last pixel of the image.
+    TooManyPixels {
+        /// Offset of the run chunk that exceeds the image.
+        at_byte: usize,
+    },
+
+    /// Encoded data reaches the end marker before all pixels of the image are decoded.
+    NotEnoughPixels {
+        /// Number of pixels decoded before the end marker.
+        decoded: usize,
+    },
+
+    /// Compared or combined images have different dimensions.
+    DimensionsMismatch,
+
+    /// Seek table following the end marker is corrupted or has unsupported version.
+    InvalidSeekTable,
+
+    /// ICC profile attached to the image is malformed or is not RGB matrix/TRC profile.
+    UnsupportedProfile,
 }
 
 impl Display for DecodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            DecodeError::NotEnoughData => {
-                f.write_str("Buffer does not contain enough encoded data")
-            }
+            DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded,
+                needed,
+            } => write!(
+                f,
+                "Buffer does not contain enough encoded data at offset {} after {} decoded pixels, at least {} more bytes needed",
+                offset, pixels_decoded, needed
+            ),
             DecodeError::InvalidMagic => f.write_str("Encoded header contains invalid magic value"),
             DecodeError::InvalidChannelsValue => {
                 f.write_str("Encoded header contains invalud channels number. Must be 3 or 4")
@@ -46,104 +111,2574 @@ impl Display for DecodeError {
             DecodeError::OutputIsTooSmall => {
                 f.write_str("Output buffer is too small to fit decoded image")
             }
+            DecodeError::InvalidStride => {
+                f.write_str("Output row stride is smaller than row of decoded pixels")
+            }
+            DecodeError::InvalidRegion => {
+                f.write_str("Requested region does not fit into the image")
+            }
+            DecodeError::PixelOutOfBounds => {
+                f.write_str("Requested pixel coordinates are outside of the image")
+            }
+            DecodeError::InvalidOptions => {
+                f.write_str("Decoding options cannot be combined with each other")
+            }
+            DecodeError::InvalidPadding { offset } => write!(
+                f,
+                "Encoded data does not end with valid end marker at offset {}",
+                offset
+            ),
+            DecodeError::InvalidState => f.write_str(
+                "Decoder state is inconsistent with the image or has unsupported serialization version",
+            ),
+            DecodeError::TrailingData { offset } => write!(
+                f,
+                "Encoded data continues after the end marker at offset {}",
+                offset
+            ),
+            DecodeError::TooManyPixels { at_byte } => write!(
+                f,
+                "Encoded data contains more pixels than the header specifies, run at offset {} exceeds the image",
+                at_byte
+            ),
+            DecodeError::NotEnoughPixels { decoded } => write!(
+                f,
+                "Encoded data reaches the end marker after {} decoded pixels, fewer than the header specifies",
+                decoded
+            ),
+            DecodeError::DimensionsMismatch => f.write_str("Images have different dimensions"),
+            DecodeError::InvalidSeekTable => {
+                f.write_str("Seek table is corrupted or has unsupported version")
+            }
+            DecodeError::UnsupportedProfile => {
+                f.write_str("ICC profile is malformed or is not RGB matrix/TRC profile")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl DecodeError {
+    /// Returns error with offsets into encoded data moved by `bytes`
+    /// and numbers of decoded pixels moved by `pixels`.
+    #[inline]
+    pub(crate) fn at(self, bytes: usize, pixels: usize) -> Self {
+        match self {
+            DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded,
+                needed,
+            } => DecodeError::NotEnoughData {
+                offset: offset + bytes,
+                pixels_decoded: pixels_decoded + pixels,
+                needed,
+            },
+            DecodeError::InvalidPadding { offset } => DecodeError::InvalidPadding {
+                offset: offset + bytes,
+            },
+            DecodeError::TrailingData { offset } => DecodeError::TrailingData {
+                offset: offset + bytes,
+            },
+            DecodeError::TooManyPixels { at_byte } => DecodeError::TooManyPixels {
+                at_byte: at_byte + bytes,
+            },
+            DecodeError::NotEnoughPixels { decoded } => DecodeError::NotEnoughPixels {
+                decoded: decoded + pixels,
+            },
+            err => err,
+        }
+    }
+
+    /// Returns error with number of needed bytes including pixels left in the image of `total` pixels.\
+    /// Offsets in the error are counted from the start of `bytes`.
+    #[inline]
+    pub(crate) fn for_image(self, bytes: &[u8], total: usize) -> Self {
+        match self {
+            DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded,
+                needed,
+            } => {
+                let left = total.saturating_sub(pixels_decoded);
+
+                // Incomplete chunk produces at least one pixel.
+                let needed = match offset < bytes.len() {
+                    true => needed + left.saturating_sub(1).div_ceil(62),
+                    false => needed.max(left.div_ceil(62)),
+                };
+
+                DecodeError::NotEnoughData {
+                    offset,
+                    pixels_decoded,
+                    needed,
+                }
+            }
+            err => err,
+        }
+    }
+}
+
+/// State of the decoder carried between parts of the image
+/// decoded with [`Qoi::decode_resume`].\
+/// State can be serialized with [`DecoderState::to_bytes`] and restored with [`DecoderState::from_bytes`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DecoderState {
+    index: [[u8; 4]; 64],
+    px: [u8; 4],
+    run: usize,
+    pos: usize,
+    offset: usize,
+    finished: bool,
+}
+
+impl Default for DecoderState {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecoderState {
+    /// Size of serialized state in bytes.
+    pub const SERIALIZED_SIZE: usize = 1 + 4 + 1 + 8 + 8 + 1 + 64 * 4;
+
+    /// Version of serialized state.
+    const VERSION: u8 = 1;
+
+    /// Returns initial state of the decoder.\
+    /// Previous pixel is opaque black, index is zeroed and decoding starts right after the header.
+    #[inline]
+    pub const fn new() -> Self {
+        DecoderState {
+            index: [[0; 4]; 64],
+            px: [0, 0, 0, 0xff],
+            run: 0,
+            pos: 0,
+            offset: QOI_HEADER_SIZE,
+            finished: false,
+        }
+    }
+
+    /// Returns state of the decoder about to parse chunk at `offset` starting with pixel `pos`.
+    #[inline]
+    pub(crate) const fn at_chunk(
+        index: [[u8; 4]; 64],
+        px: [u8; 4],
+        pos: usize,
+        offset: usize,
+    ) -> Self {
+        DecoderState {
+            index,
+            px,
+            run: 0,
+            pos,
+            offset,
+            finished: false,
+        }
+    }
+
+    /// Returns number of pixels decoded so far.
+    #[inline]
+    pub const fn pixels_decoded(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns offset of the next chunk in encoded image.
+    #[inline]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns `true` if all pixels of the image are decoded and end marker is verified.
+    #[inline]
+    pub const fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Serializes state into bytes.\
+    /// First byte is version of serialization, followed by previous pixel, pending run,
+    /// big-endian pixel position and input offset, finished flag and index of pixels.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE] {
+        let mut bytes = [0; Self::SERIALIZED_SIZE];
+        bytes[0] = Self::VERSION;
+        bytes[1..5].copy_from_slice(&self.px);
+        bytes[5] = self.run as u8;
+        bytes[6..14].copy_from_slice(&(self.pos as u64).to_be_bytes());
+        bytes[14..22].copy_from_slice(&(self.offset as u64).to_be_bytes());
+        bytes[22] = self.finished as u8;
+        for (dst, px) in bytes[23..].chunks_exact_mut(4).zip(&self.index) {
+            dst.copy_from_slice(px);
+        }
+        bytes
+    }
+
+    /// Restores state serialized with [`DecoderState::to_bytes`].
+    ///
+    /// On success this function returns `Ok(state)`.\
+    /// On failure this function returns `Err(DecodeError::InvalidState)`
+    /// if `bytes` have wrong size, unsupported version or invalid values.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let bytes: &[u8; Self::SERIALIZED_SIZE] = match bytes.try_into() {
+            Err(_) => return Err(DecodeError::InvalidState),
+            Ok(bytes) => bytes,
+        };
+
+        if bytes[0] != Self::VERSION || bytes[5] > 62 || bytes[22] > 1 {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let pos = u64::from_be_bytes(bytes[6..14].try_into().unwrap());
+        let offset = u64::from_be_bytes(bytes[14..22].try_into().unwrap());
+
+        let mut index = [[0; 4]; 64];
+        for (px, src) in index.iter_mut().zip(bytes[23..].chunks_exact(4)) {
+            px.copy_from_slice(src);
+        }
+
+        Ok(DecoderState {
+            index,
+            px: bytes[1..5].try_into().unwrap(),
+            run: bytes[5] as usize,
+            pos: pos.try_into().map_err(|_| DecodeError::InvalidState)?,
+            offset: offset.try_into().map_err(|_| DecodeError::InvalidState)?,
+            finished: bytes[22] == 1,
+        })
+    }
+}
+
+/// QOI header with channels number and color space values exactly as stored.\
+/// Returned by [`Qoi::decode_header_raw`] for images written by encoders that store invalid values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RawHeader {
+    /// Width of the image in pixels.
+    pub width: u32,
+
+    /// Height of the image in pixels.
+    pub height: u32,
+
+    /// Channels number byte.
+    pub channels: u8,
+
+    /// Color space byte.
+    pub colorspace: u8,
+}
+
+impl RawHeader {
+    /// Returns image descriptor if channels number and color space values are valid.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing invalid value.
+    #[inline]
+    pub fn to_qoi(&self) -> Result<Qoi, DecodeError> {
+        Ok(Qoi {
+            width: self.width,
+            height: self.height,
+            colors: match (self.channels, self.colorspace) {
+                (3, 0) => Colors::Srgb,
+                (4, 0) => Colors::SrgbLinA,
+                (3, 1) => Colors::Rgb,
+                (4, 1) => Colors::Rgba,
+                (_, 0 | 1) => return Err(DecodeError::InvalidChannelsValue),
+                (_, _) => return Err(DecodeError::InvalidColorSpaceValue),
+            },
+        })
+    }
+
+    /// Returns image descriptor with dimensions from the header and specified `colors`
+    /// regardless of channels number and color space values.\
+    /// Returned value can be used to decode chunks with [`Qoi::decode_body`] or [`Qoi::decode_skip_header`].
+    #[inline]
+    pub const fn assume(&self, colors: Colors) -> Qoi {
+        Qoi {
+            width: self.width,
+            height: self.height,
+            colors,
+        }
+    }
+
+    /// Returns QOI header with all values exactly as stored in this header.
+    #[inline]
+    pub const fn header_bytes(&self) -> [u8; QOI_HEADER_SIZE] {
+        let mut header = self.assume(Colors::Rgb).header_bytes();
+        header[12] = self.channels;
+        header[13] = self.colorspace;
+        header
+    }
+}
+
+/// Iterator over QOI images stored one after another in single bytes slice.\
+/// Created by [`Qoi::iter_concatenated`].
+#[derive(Clone, Debug)]
+pub struct Concatenated<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Concatenated<'a> {
+    type Item = Result<(Qoi, &'a [u8]), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        match Qoi::skip_image(self.bytes, false) {
+            Ok((qoi, size)) => {
+                let (image, rest) = self.bytes.split_at(size);
+                self.bytes = rest;
+                Some(Ok((qoi, image)))
+            }
+            Err(err) => {
+                self.bytes = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl core::iter::FusedIterator for Concatenated<'_> {}
+
+/// Destination for decoded pixels.\
+/// Decoding loop is generic over this trait,
+/// so each output flavor gets its own specialized loop.
+pub(crate) trait Output<const N: usize> {
+    /// Reserved place for a single pixel.
+    type Slot;
+
+    /// Reserves place for the next pixel.\
+    /// Returns `None` if no more pixels can be written.
+    fn next(&mut self) -> Option<Self::Slot>;
+
+    /// Writes pixel into reserved place.
+    fn write(&mut self, slot: Self::Slot, px: [u8; N]);
+
+    /// Writes up to `n` copies of the pixel.\
+    /// Returns number of pixels written.
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize;
+}
+
+impl<O, const N: usize> Output<N> for &mut O
+where
+    O: Output<N>,
+{
+    type Slot = O::Slot;
+
+    #[inline]
+    fn next(&mut self) -> Option<O::Slot> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn write(&mut self, slot: O::Slot, px: [u8; N]) {
+        (**self).write(slot, px)
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        (**self).fill(px, n)
+    }
+}
+
+impl<'a, const N: usize, const M: usize> Output<N> for &'a mut [[u8; M]]
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = &'a mut [u8; M];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut [u8; M]> {
+        let (slot, tail) = core::mem::take(self).split_first_mut()?;
+        *self = tail;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: &'a mut [u8; M], px: [u8; N]) {
+        *slot = convert(&px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(n);
+        runs::fill(head, convert(&px));
+        *self = tail;
+        n
+    }
+}
+
+/// Output that converts RGBA pixels into pixels with reordered channels
+/// and optionally premultiplied alpha before writing them into `O`.
+pub(crate) struct Transformed<O> {
+    pub out: O,
+    pub order: ChannelOrder,
+    pub premultiplied: bool,
+}
+
+impl<O> Transformed<O> {
+    #[inline]
+    fn transform<const N: usize>(&self, px: [u8; N]) -> [u8; 4]
+    where
+        [u8; N]: Pixel,
+    {
+        let mut px = px.rgba();
+        if self.premultiplied {
+            px = premultiply(&px);
+        }
+        let [r, g, b, a] = px;
+        match self.order {
+            ChannelOrder::Rgba => px,
+            ChannelOrder::Bgra => [b, g, r, a],
+            ChannelOrder::Argb => [a, r, g, b],
+            ChannelOrder::Abgr => [a, b, g, r],
+        }
+    }
+}
+
+impl<O, const N: usize> Output<N> for Transformed<O>
+where
+    O: Output<4>,
+    [u8; N]: Pixel,
+{
+    type Slot = O::Slot;
+
+    #[inline]
+    fn next(&mut self) -> Option<O::Slot> {
+        self.out.next()
+    }
+
+    #[inline]
+    fn write(&mut self, slot: O::Slot, px: [u8; N]) {
+        let px = self.transform(px);
+        self.out.write(slot, px)
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = self.transform(px);
+        self.out.fill(px, n)
+    }
+}
+
+/// Output into slice of pixels packed into `u32` values.\
+/// `ARGB` selects between `0xAARRGGBB` and `0xRRGGBBAA` packing.
+pub(crate) struct Packed<'a, const ARGB: bool>(pub &'a mut [u32]);
+
+impl<'a, const N: usize, const ARGB: bool> Output<N> for Packed<'a, ARGB>
+where
+    [u8; N]: Pixel,
+{
+    type Slot = &'a mut u32;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut u32> {
+        let (slot, tail) = core::mem::take(&mut self.0).split_first_mut()?;
+        self.0 = tail;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: &'a mut u32, px: [u8; N]) {
+        *slot = pack::<N, ARGB>(&px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.0.len());
+        let (head, tail) = core::mem::take(&mut self.0).split_at_mut(n);
+        head.fill(pack::<N, ARGB>(&px));
+        self.0 = tail;
+        n
+    }
+}
+
+#[inline]
+fn pack<const N: usize, const ARGB: bool>(px: &[u8; N]) -> u32
+where
+    [u8; N]: Pixel,
+{
+    let [r, g, b, a] = px.rgba();
+    match ARGB {
+        true => u32::from_be_bytes([a, r, g, b]),
+        false => u32::from_be_bytes([r, g, b, a]),
+    }
+}
+
+/// Multiplies color channels by alpha.\
+/// Each channel is computed as `(c * a + 127) / 255`,
+/// so alpha `0` produces black and alpha `255` keeps color intact.
+#[inline]
+fn premultiply<P: Pixel>(px: &P) -> P {
+    if !P::HAS_ALPHA {
+        return *px;
+    }
+
+    let a = px.a() as u16;
+    let mul = |c: u8| ((c as u16 * a + 127) / 255) as u8;
+
+    let mut out = *px;
+    out.set_rgb(mul(px.r()), mul(px.g()), mul(px.b()));
+    out
+}
+
+/// Source of destination rows for [`RowWise`] output.
+pub(crate) trait Rows<'a, const M: usize> {
+    /// Returns next row to write pixels into.\
+    /// Returns `None` if there are no more rows.
+    fn next_row(&mut self) -> Option<&'a mut [[u8; M]]>;
+}
+
+/// Output that writes pixels row by row into rows provided by `R`.
+pub(crate) struct RowWise<'a, R, const M: usize> {
+    rows: R,
+    row: &'a mut [[u8; M]],
+}
+
+impl<'a, R, const M: usize> RowWise<'a, R, M> {
+    #[inline]
+    pub fn new(rows: R) -> Self {
+        RowWise { rows, row: &mut [] }
+    }
+}
+
+impl<'a, R, const N: usize, const M: usize> Output<N> for RowWise<'a, R, M>
+where
+    R: Rows<'a, M>,
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = &'a mut [u8; M];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut [u8; M]> {
+        if self.row.is_empty() {
+            self.row = self.rows.next_row()?;
+        }
+        Output::<M>::next(&mut self.row)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: &'a mut [u8; M], px: [u8; N]) {
+        *slot = convert(&px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = convert::<_, [u8; M]>(&px);
+        let mut written = 0;
+        while written < n {
+            if self.row.is_empty() {
+                match self.rows.next_row() {
+                    None => break,
+                    Some(row) => self.row = row,
+                }
+            }
+            written += Output::<M>::fill(&mut self.row, px, n - written);
+        }
+        written
+    }
+}
+
+/// Rows of the image placed `stride` bytes apart taken from bottom to top.
+pub(crate) struct FlippedRows<'a> {
+    pub bytes: &'a mut [u8],
+    pub stride: usize,
+    pub row: usize,
+}
+
+impl<'a, const M: usize> Rows<'a, M> for FlippedRows<'a>
+where
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next_row(&mut self) -> Option<&'a mut [[u8; M]]> {
+        let bytes = core::mem::take(&mut self.bytes);
+        let mid = bytes.len().checked_sub(self.row)? / self.stride * self.stride;
+        let (head, row) = bytes.split_at_mut(mid);
+        self.bytes = head;
+        Some(bytemuck::cast_slice_mut(&mut row[..self.row]))
+    }
+}
+
+/// Rows of the image placed `stride` bytes apart.
+pub(crate) struct StridedRows<'a> {
+    pub bytes: &'a mut [u8],
+    pub stride: usize,
+    pub row: usize,
+}
+
+impl<'a, const M: usize> Rows<'a, M> for StridedRows<'a>
+where
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next_row(&mut self) -> Option<&'a mut [[u8; M]]> {
+        let bytes = core::mem::take(&mut self.bytes);
+        if bytes.len() < self.row {
+            return None;
+        }
+        let (row, tail) = bytes.split_at_mut(self.stride.min(bytes.len()));
+        self.bytes = tail;
+        Some(bytemuck::cast_slice_mut(&mut row[..self.row]))
+    }
+}
+
+/// Output that writes only pixels inside rectangular region of the image.\
+/// Pixels outside the region are skipped.
+pub(crate) struct Region<'a, const M: usize> {
+    pixels: &'a mut [[u8; M]],
+    width: usize,
+    x: core::ops::Range<usize>,
+    y: core::ops::Range<usize>,
+    col: usize,
+    row: usize,
+}
+
+impl<'a, const M: usize> Region<'a, M> {
+    /// Returns `true` when all pixels of the region were written.
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.row >= self.y.end || (self.row + 1 == self.y.end && self.col >= self.x.end)
+    }
+
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.col += n;
+        if self.col == self.width {
+            self.col = 0;
+            self.row += 1;
+        }
+    }
+}
+
+impl<'a, const N: usize, const M: usize> Output<N> for Region<'a, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = Option<&'a mut [u8; M]>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Option<&'a mut [u8; M]>> {
+        if self.is_done() {
+            return None;
+        }
+
+        let slot = match self.y.contains(&self.row) && self.x.contains(&self.col) {
+            true => Output::<M>::next(&mut self.pixels),
+            false => None,
+        };
+        self.advance(1);
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: Option<&'a mut [u8; M]>, px: [u8; N]) {
+        if let Some(slot) = slot {
+            *slot = convert(&px);
+        }
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = convert::<_, [u8; M]>(&px);
+        let mut written = 0;
+        while written < n && !self.is_done() {
+            let len = (n - written).min(self.width - self.col);
+            if self.y.contains(&self.row) {
+                let start = self.col.max(self.x.start);
+                let end = (self.col + len).min(self.x.end);
+                if start < end {
+                    Output::<M>::fill(&mut self.pixels, px, end - start);
+                }
+            }
+            self.advance(len);
+            written += len;
+        }
+        written
+    }
+}
+
+/// Output that decodes pixels into single row buffer
+/// and passes each completed row to the callback.
+pub(crate) struct RowCallback<'a, F, const M: usize> {
+    row: &'a mut [[u8; M]],
+    pos: usize,
+    y: u32,
+    height: u32,
+    f: F,
+    flow: ControlFlow<()>,
+}
+
+impl<'a, F, const M: usize> RowCallback<'a, F, M>
+where
+    F: FnMut(u32, &[u8]) -> ControlFlow<()>,
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn flush(&mut self) {
+        self.flow = (self.f)(self.y, bytemuck::cast_slice(self.row));
+        self.y += 1;
+        self.pos = 0;
+    }
+}
+
+impl<'a, F, const N: usize, const M: usize> Output<N> for RowCallback<'a, F, M>
+where
+    F: FnMut(u32, &[u8]) -> ControlFlow<()>,
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.flow.is_break() || self.y == self.height {
+            return None;
+        }
+        let slot = self.pos;
+        self.pos += 1;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: usize, px: [u8; N]) {
+        self.row[slot] = convert(&px);
+        if slot + 1 == self.row.len() {
+            self.flush();
+        }
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = convert::<_, [u8; M]>(&px);
+        let mut written = 0;
+        while written < n && self.flow.is_continue() && self.y < self.height {
+            let len = (n - written).min(self.row.len() - self.pos);
+            self.row[self.pos..][..len].fill(px);
+            self.pos += len;
+            written += len;
+            if self.pos == self.row.len() {
+                self.flush();
+            }
+        }
+        written
+    }
+}
+
+/// Output that writes only every `factor`-th pixel of every `factor`-th row.
+pub(crate) struct Subsampled<'a, const M: usize> {
+    pixels: &'a mut [[u8; M]],
+    width: usize,
+    factor: usize,
+    left: usize,
+    col: usize,
+    col_phase: usize,
+    row_phase: usize,
+}
+
+impl<'a, const M: usize> Subsampled<'a, M> {
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.left -= n;
+        self.col += n;
+        if self.col == self.width {
+            self.col = 0;
+            self.col_phase = 0;
+            self.row_phase += 1;
+            if self.row_phase == self.factor {
+                self.row_phase = 0;
+            }
+        } else if n == 1 {
+            self.col_phase += 1;
+            if self.col_phase == self.factor {
+                self.col_phase = 0;
+            }
+        } else {
+            self.col_phase = self.col % self.factor;
+        }
+    }
+}
+
+impl<'a, const N: usize, const M: usize> Output<N> for Subsampled<'a, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = Option<&'a mut [u8; M]>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Option<&'a mut [u8; M]>> {
+        if self.left == 0 {
+            return None;
+        }
+
+        let slot = match self.row_phase == 0 && self.col_phase == 0 {
+            true => Output::<M>::next(&mut self.pixels),
+            false => None,
+        };
+        self.advance(1);
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: Option<&'a mut [u8; M]>, px: [u8; N]) {
+        if let Some(slot) = slot {
+            *slot = convert(&px);
+        }
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = convert::<_, [u8; M]>(&px);
+        let n = n.min(self.left);
+        let mut written = 0;
+        while written < n {
+            let len = (n - written).min(self.width - self.col);
+            if self.row_phase == 0 {
+                let f = self.factor;
+                let kept = (self.col + len).div_ceil(f) - self.col.div_ceil(f);
+                Output::<M>::fill(&mut self.pixels, px, kept);
+            }
+            self.advance(len);
+            written += len;
+        }
+        written
+    }
+}
+
+/// Output that writes each channel into separate plane.
+pub(crate) struct Planar<'a> {
+    r: &'a mut [u8],
+    g: &'a mut [u8],
+    b: &'a mut [u8],
+    a: Option<&'a mut [u8]>,
+    pos: usize,
+}
+
+impl<'a, const N: usize> Output<N> for Planar<'a>
+where
+    [u8; N]: Pixel,
+{
+    type Slot = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.pos == self.r.len() {
+            return None;
+        }
+        let slot = self.pos;
+        self.pos += 1;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: usize, px: [u8; N]) {
+        self.r[slot] = px.r();
+        self.g[slot] = px.g();
+        self.b[slot] = px.b();
+        if let Some(a) = &mut self.a {
+            a[slot] = px.a();
+        }
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.r.len() - self.pos);
+        let range = self.pos..self.pos + n;
+        self.r[range.clone()].fill(px.r());
+        self.g[range.clone()].fill(px.g());
+        self.b[range.clone()].fill(px.b());
+        if let Some(a) = &mut self.a {
+            a[range].fill(px.a());
+        }
+        self.pos += n;
+        n
+    }
+}
+
+/// Output into uninitialized buffer of pixels with `M` channels.\
+/// Pixels are appended to initialized prefix of the buffer in order.
+pub(crate) struct Uninit<'a, 'b, const M: usize>(pub &'a mut Init<'b>);
+
+impl<'a, 'b, const N: usize, const M: usize> Output<N> for Uninit<'a, 'b, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = ();
+
+    #[inline]
+    fn next(&mut self) -> Option<()> {
+        match self.0.remaining() >= M {
+            true => Some(()),
+            false => None,
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, (): (), px: [u8; N]) {
+        self.0.push::<M>(convert(&px));
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        self.0.fill::<M>(convert(&px), n)
+    }
+}
+
+/// Output into slice of pixels filled from the end.
+pub(crate) struct Reversed<'a, const M: usize>(pub &'a mut [[u8; M]]);
+
+impl<'a, const N: usize, const M: usize> Output<N> for Reversed<'a, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = &'a mut [u8; M];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut [u8; M]> {
+        let (slot, head) = core::mem::take(&mut self.0).split_last_mut()?;
+        self.0 = head;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: &'a mut [u8; M], px: [u8; N]) {
+        *slot = convert(&px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let pixels = core::mem::take(&mut self.0);
+        let n = n.min(pixels.len());
+        let (head, tail) = pixels.split_at_mut(pixels.len() - n);
+        tail.fill(convert(&px));
+        self.0 = head;
+        n
+    }
+}
+
+/// Output into slice of pixels of the image rotated by 90 or 270 degrees.\
+/// Each row of the source image becomes a column of the output.
+pub(crate) struct Transposed<'a, const M: usize> {
+    pixels: &'a mut [[u8; M]],
+    width: usize,
+    height: usize,
+    clockwise: bool,
+    left: usize,
+    col: usize,
+    row: usize,
+    pos: usize,
+}
+
+impl<'a, const M: usize> Transposed<'a, M> {
+    #[inline]
+    fn new(pixels: &'a mut [[u8; M]], width: usize, height: usize, clockwise: bool) -> Self {
+        let mut out = Transposed {
+            pixels,
+            width,
+            height,
+            clockwise,
+            left: width * height,
+            col: 0,
+            row: 0,
+            pos: 0,
+        };
+        out.pos = out.row_start();
+        out
+    }
+
+    /// Returns output position of the first pixel of the current row.
+    #[inline]
+    fn row_start(&self) -> usize {
+        match self.clockwise {
+            true => self.height - 1 - self.row,
+            false => (self.width - 1) * self.height + self.row,
+        }
+    }
+
+    #[inline]
+    fn advance(&mut self) {
+        self.left -= 1;
+        self.col += 1;
+        if self.col == self.width {
+            self.col = 0;
+            self.row += 1;
+            if self.left > 0 {
+                self.pos = self.row_start();
+            }
+        } else if self.clockwise {
+            self.pos += self.height;
+        } else {
+            self.pos -= self.height;
+        }
+    }
+}
+
+impl<'a, const N: usize, const M: usize> Output<N> for Transposed<'a, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    type Slot = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.left == 0 {
+            return None;
+        }
+        let slot = self.pos;
+        self.advance();
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: usize, px: [u8; N]) {
+        self.pixels[slot] = convert(&px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let px = convert::<_, [u8; M]>(&px);
+        let n = n.min(self.left);
+        for _ in 0..n {
+            self.pixels[self.pos] = px;
+            self.advance();
+        }
+        n
+    }
+}
+
+/// Output that passes each pixel with number of its repetitions to `F`
+/// before writing it into inner output.
+pub(crate) struct Inspect<O, F> {
+    pub out: O,
+    pub f: F,
+}
+
+impl<O, F, const N: usize> Output<N> for Inspect<O, F>
+where
+    O: Output<N>,
+    F: FnMut([u8; 4], usize),
+    [u8; N]: Pixel,
+{
+    type Slot = O::Slot;
+
+    #[inline]
+    fn next(&mut self) -> Option<O::Slot> {
+        self.out.next()
+    }
+
+    #[inline]
+    fn write(&mut self, slot: O::Slot, px: [u8; N]) {
+        (self.f)(px.rgba(), 1);
+        self.out.write(slot, px);
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = self.out.fill(px, n);
+        (self.f)(px.rgba(), n);
+        n
+    }
+}
+
+/// Output that does not store pixels
+/// but passes each pixel with number of its repetitions to `F`.\
+/// Decoding stops after `left` pixels or when `F` breaks.
+pub(crate) struct Visit<F> {
+    pub left: usize,
+    pub f: F,
+}
+
+impl<F, const N: usize> Output<N> for Visit<F>
+where
+    F: FnMut([u8; 4], usize) -> ControlFlow<()>,
+    [u8; N]: Pixel,
+{
+    type Slot = ();
+
+    #[inline]
+    fn next(&mut self) -> Option<()> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        Some(())
+    }
+
+    #[inline]
+    fn write(&mut self, (): (), px: [u8; N]) {
+        if (self.f)(px.rgba(), 1).is_break() {
+            self.left = 0;
+        }
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.left);
+        self.left -= n;
+        if n > 0 && (self.f)(px.rgba(), n).is_break() {
+            self.left = 0;
+        }
+        n
+    }
+}
+
+/// Output that maps each pixel into single value with `F`.
+pub(crate) struct Mapped<'a, T, F> {
+    pub values: &'a mut [T],
+    pub f: F,
+}
+
+impl<'a, T, F, const N: usize> Output<N> for Mapped<'a, T, F>
+where
+    T: Copy,
+    F: Fn([u8; 4]) -> T,
+    [u8; N]: Pixel,
+{
+    type Slot = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        let (slot, tail) = core::mem::take(&mut self.values).split_first_mut()?;
+        self.values = tail;
+        Some(slot)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: &'a mut T, px: [u8; N]) {
+        *slot = (self.f)(px.rgba());
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.values.len());
+        let (head, tail) = core::mem::take(&mut self.values).split_at_mut(n);
+        head.fill((self.f)(px.rgba()));
+        self.values = tail;
+        n
+    }
+}
+
+/// Converts channels of the pixel into `0.0..=1.0` range.
+#[inline]
+fn normalize<const M: usize>(px: [u8; 4]) -> [f32; M] {
+    let mut out = [0.0; M];
+    for (out, c) in out.iter_mut().zip(px) {
+        *out = c as f32 / 255.0;
+    }
+    out
+}
+
+/// Converts channels of the pixel into `0.0..=1.0` range
+/// applying sRGB transfer function to color channels.
+#[inline]
+fn linearize<const M: usize>(px: [u8; 4]) -> [f32; M] {
+    let mut out = normalize(px);
+    for (out, c) in out.iter_mut().zip(&px[..3]) {
+        *out = crate::srgb::SRGB_TO_LINEAR[*c as usize];
+    }
+    out
+}
+
+/// Composites the pixel over opaque background color.
+#[inline]
+fn flatten([r, g, b, a]: [u8; 4], [br, bg, bb, _]: [u8; 4]) -> [u8; 3] {
+    let a = a as u32;
+    let blend = |c: u8, b: u8| ((c as u32 * a + b as u32 * (255 - a) + 127) / 255) as u8;
+    [blend(r, br), blend(g, bg), blend(b, bb)]
+}
+
+/// Widens channels of the pixel into 16 bits replicating each byte.
+#[inline]
+fn widen<const M: usize>(px: [u8; 4]) -> [u16; M] {
+    let mut out = [0; M];
+    for (out, c) in out.iter_mut().zip(px) {
+        *out = (c as u16) << 8 | c as u16;
+    }
+    out
+}
+
+/// Builds table converting sRGB encoded channel values into linear ones.\
+/// Linear values are rounded to nearest integer.
+#[inline]
+fn linear_lut() -> [u8; 256] {
+    let mut lut = [0; 256];
+    for (out, l) in lut.iter_mut().zip(&crate::srgb::SRGB_TO_LINEAR) {
+        *out = (l * 255.0 + 0.5) as u8;
+    }
+    lut
+}
+
+/// Computes luma of the pixel using BT.601 weights.
+#[inline]
+pub(crate) fn luma([r, g, b, _]: [u8; 4]) -> u8 {
+    ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8
+}
+
+impl Qoi {
+    /// Returns bytes size for the decoded image.
+    #[inline]
+    pub fn decoded_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.colors.channels()
+    }
+
+    /// Returns bytes size for the image decoded with [`Qoi::decode_rgba`].
+    #[inline]
+    pub fn decoded_size_rgba(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+
+    /// Returns bytes size for the image decoded with [`Qoi::decode_rgb`].
+    #[inline]
+    pub fn decoded_size_rgb(&self) -> usize {
+        self.width as usize * self.height as usize * 3
+    }
+
+    /// Returns bytes size for the image decoded with [`Qoi::decode_subsampled`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is zero.
+    #[inline]
+    pub fn decoded_size_subsampled(&self, factor: u32) -> usize {
+        let w = self.width.div_ceil(factor);
+        let h = self.height.div_ceil(factor);
+        w as usize * h as usize * self.colors.channels()
+    }
+
+    /// Returns bytes size for the image decoded with [`Qoi::decode_luma`].
+    #[inline]
+    pub fn decoded_size_luma(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns bytes size for the image decoded with [`Qoi::decode_alpha`].
+    #[inline]
+    pub fn decoded_size_alpha(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns number of `f32` values for the image decoded with [`Qoi::decode_f32`].
+    #[inline]
+    pub fn decoded_size_f32(&self) -> usize {
+        self.decoded_size()
+    }
+
+    /// Returns number of `u16` values for the image decoded with [`Qoi::decode_u16`].
+    #[inline]
+    pub fn decoded_size_u16(&self) -> usize {
+        self.decoded_size()
+    }
+
+    /// Returns `true` if `other` has the same dimensions and channels number.
+    #[cfg(any(feature = "animation", feature = "tiled"))]
+    #[inline]
+    pub(crate) fn same_frame(&self, other: &Qoi) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.colors.channels() == other.colors.channels()
+    }
+
+    /// Reads header from encoded QOI image.\
+    /// Returned header can be analyzed before proceeding parsing with [`Qoi::decode_skip_header`].
+    #[inline]
+    pub fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_header_raw(bytes)?.to_qoi()
+    }
+
+    /// Reads header from encoded QOI image without verifying channels number and color space values.\
+    /// Only length and magic value are verified.
+    /// Use [`RawHeader::assume`] to proceed decoding images with invalid values.
+    pub fn decode_header_raw(bytes: &[u8]) -> Result<RawHeader, DecodeError> {
+        if bytes.len() < QOI_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: QOI_HEADER_SIZE - bytes.len(),
+            });
+        }
+
+        let magic = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        if magic != QOI_MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        Ok(RawHeader {
+            width: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            height: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+            channels: bytes[12],
+            colorspace: bytes[13],
+        })
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, output, &DecodeOptions::new())
+    }
+
+    /// Returns iterator over QOI images stored one after another in `bytes`.\
+    /// Each item is header of the image and bytes slice of the whole encoded image.\
+    /// Images are walked through up to the end marker without storing decoded pixels.
+    /// Bytes that do not form a valid image yield `Err(err)` as the last item.
+    #[inline]
+    pub fn iter_concatenated(bytes: &[u8]) -> Concatenated<'_> {
+        Concatenated { bytes }
+    }
+
+    /// Verify a QOI image from bytes slice without decoding it into output.\
+    /// Every chunk is walked through keeping only state required to interpret following chunks.
+    /// Image is valid if chunks cover `width * height` pixels and are followed by the end marker.\
+    /// Bytes following the end marker are not read.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn validate(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::skip_image(bytes, false).map(|(qoi, _)| qoi)
+    }
+
+    /// Verify a QOI image from bytes slice without decoding it into output.\
+    /// Same as [`Qoi::validate`] but `bytes` must end right after the end marker
+    /// and chunks must cover exactly `width * height` pixels.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::TrailingData { offset })` is returned if any bytes follow the end marker,
+    /// `Err(DecodeError::TooManyPixels { at_byte })` if run exceeds the image
+    /// and `Err(DecodeError::NotEnoughPixels { decoded })` if the end marker is reached before the last pixel.
+    #[inline]
+    pub fn validate_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (qoi, size) = Self::skip_image(bytes, true)?;
+        Self::decode_end(bytes, size)?;
+        Ok(qoi)
+    }
+
+    /// Decodes chunks with `decode` up to the end marker at the end of `bytes`.\
+    /// Chunks that end before all pixels are decoded are reported as `DecodeError::NotEnoughPixels`.\
+    /// Whole `bytes` are passed to `decode` if they do not end with the end marker.
+    #[inline]
+    fn decode_before_end<F>(bytes: &[u8], decode: F) -> Result<usize, DecodeError>
+    where
+        F: FnOnce(&[u8]) -> Result<usize, DecodeError>,
+    {
+        match bytes {
+            [chunks @ .., 0, 0, 0, 0, 0, 0, 0, 1] => decode(chunks).map_err(|err| match err {
+                DecodeError::NotEnoughData { pixels_decoded, .. } => DecodeError::NotEnoughPixels {
+                    decoded: pixels_decoded,
+                },
+                err => err,
+            }),
+            _ => decode(bytes),
+        }
+    }
+
+    /// Verifies that `bytes` end at `offset`.
+    #[inline]
+    fn decode_end(bytes: &[u8], offset: usize) -> Result<(), DecodeError> {
+        match offset < bytes.len() {
+            true => Err(DecodeError::TrailingData { offset }),
+            false => Ok(()),
+        }
+    }
+
+    /// Walks through chunks of a QOI image up to the end marker without storing pixels.\
+    /// Returns header and size of the encoded image including header and end marker.\
+    /// If `strict` is `true` number of pixels in chunks must match the header,
+    /// see `decode_before_end`.
+    #[inline]
+    pub(crate) fn skip_image(bytes: &[u8], strict: bool) -> Result<(Self, usize), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let skip = |chunks: &[u8]| {
+            qoi.decode_chunks_into(
+                chunks,
+                Visit {
+                    left: qoi.width as usize * qoi.height as usize,
+                    f: |_, _| ControlFlow::Continue(()),
+                },
+                !strict,
+            )
+        };
+
+        let chunks = &bytes[QOI_HEADER_SIZE..];
+        let size = QOI_HEADER_SIZE
+            + match strict {
+                true => Self::decode_before_end(chunks, skip)?,
+                false => skip(chunks)?,
+            };
+
+        let size = size + qoi.decode_padding(bytes, size)?;
+        Ok((qoi, size))
+    }
+
+    /// Decode a QOI image from bytes slice verifying the end marker.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// Bytes following the end marker are not read, so multiple images stored one after another can be decoded sequentially.
+    ///
+    /// On success this function returns `Ok((qoi, size))` with `qoi` describing image dimensions and color space
+    /// and `size` of the encoded image including header and end marker.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_counted(bytes: &[u8], output: &mut [u8]) -> Result<(Self, usize), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let size = qoi.decode_body(&bytes[QOI_HEADER_SIZE..], output)?;
+        Ok((qoi, QOI_HEADER_SIZE + size))
+    }
+
+    /// Decode a QOI image without header from bytes slice verifying the end marker.\
+    /// `body` starts with the first chunk. Uses provided `Qoi` value instead of header.\
+    /// Decoded raw RGB or RGBA (depending on `self.colors` value) pixels are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the chunks including end marker.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Offsets in the error are counted as if `body` was preceded by header.
+    #[inline]
+    pub fn decode_body(&self, body: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        let px_len = self.width as usize * self.height as usize * self.colors.channels();
+        let output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let size = match self.colors.has_alpha() {
+            true => {
+                self.decode_chunks_into(body, bytemuck::cast_slice_mut::<_, [u8; 4]>(output), true)?
+            }
+            false => {
+                self.decode_chunks_into(body, bytemuck::cast_slice_mut::<_, [u8; 3]>(output), true)?
+            }
+        };
+
+        let padding = self
+            .decode_padding(body, size)
+            .map_err(|err| err.at(QOI_HEADER_SIZE, 0))?;
+        Ok(size + padding)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw pixels described by `options` are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidOptions)` is returned before decoding if options cannot be combined.
+    #[inline]
+    pub fn decode_with(
+        bytes: &[u8],
+        output: &mut [u8],
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let chunks = &bytes[QOI_HEADER_SIZE..];
+
+        #[cfg(feature = "icc")]
+        let transform = Self::srgb_transform(bytes, options)?;
+
+        let mut decode = |chunks: &[u8]| match options.channels(qoi.colors) {
+            None => Err(DecodeError::InvalidOptions),
+            Some(3) => qoi.decode_with_as::<3>(chunks, output, options),
+            Some(_) => qoi.decode_with_as::<4>(chunks, output, options),
+        };
+
+        let size = match options.strict {
+            true => Self::decode_before_end(chunks, decode)?,
+            false => decode(chunks)?,
+        };
+
+        if options.strict {
+            let end = QOI_HEADER_SIZE + size;
+            Self::decode_end(bytes, end + qoi.decode_padding(bytes, end)?)?;
+        }
+
+        #[cfg(feature = "icc")]
+        if let Some(transform) = transform {
+            qoi.convert_to_srgb(&transform, output, options);
+        }
+        Ok(qoi)
+    }
+
+    /// Decode pixels into `output` slice as pixels with `M` channels described by `options`.\
+    /// Returns number of bytes of chunks consumed.
+    #[inline]
+    fn decode_with_as<const M: usize>(
+        &self,
+        bytes: &[u8],
+        output: &mut [u8],
+        options: &DecodeOptions,
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        if options.stride.is_none() && !options.flip {
+            let px_len = self.width as usize * self.height as usize * M;
+
+            let output = match output.get_mut(..px_len) {
+                None => return Err(DecodeError::OutputIsTooSmall),
+                Some(output) => output,
+            };
+
+            return self.decode_transformed(
+                bytes,
+                bytemuck::cast_slice_mut::<_, [u8; M]>(output),
+                options,
+            );
+        }
+
+        if self.width == 0 || self.height == 0 {
+            return Ok(0);
+        }
+
+        let row = self.width as usize * M;
+        let stride = options.stride.unwrap_or(row);
+        if stride < row {
+            return Err(DecodeError::InvalidStride);
+        }
+
+        let size = stride
+            .checked_mul(self.height as usize - 1)
+            .and_then(|size| size.checked_add(row));
+
+        let output = match size.and_then(|size| output.get_mut(..size)) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match options.flip {
+            false => self.decode_transformed(
+                bytes,
+                RowWise::<_, M>::new(StridedRows {
+                    bytes: output,
+                    stride,
+                    row,
+                }),
+                options,
+            ),
+            true => self.decode_transformed(
+                bytes,
+                RowWise::<_, M>::new(FlippedRows {
+                    bytes: output,
+                    stride,
+                    row,
+                }),
+                options,
+            ),
+        }
+    }
+
+    /// Decode pixels into arbitrary output applying pixel transformations of `options`.\
+    /// Returns number of bytes of chunks consumed.
+    #[inline]
+    fn decode_transformed<O>(
+        &self,
+        bytes: &[u8],
+        out: O,
+        options: &DecodeOptions,
+    ) -> Result<usize, DecodeError>
+    where
+        O: Output<3> + Output<4>,
+    {
+        let order = options.order.unwrap_or(ChannelOrder::Rgba);
+        let clamp = !options.strict || options.clamp_runs;
+        match (order, options.premultiplied) {
+            (ChannelOrder::Rgba, false) => self.decode_chunks_into(bytes, out, clamp),
+            (order, premultiplied) => self.decode_chunks_into(
+                bytes,
+                Transformed {
+                    out,
+                    order,
+                    premultiplied,
+                },
+                clamp,
+            ),
+        }
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGBA pixels are written into `output` slice regardless of channels number in the image.\
+    /// Alpha channel is set to `255` for images without alpha channel.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_rgba(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(
+            bytes,
+            output,
+            &DecodeOptions::new().layout(PixelLayout::Rgba),
+        )
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB pixels are written into `output` slice regardless of channels number in the image.\
+    /// Alpha channel is dropped for images with alpha channel.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_rgb(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(
+            bytes,
+            output,
+            &DecodeOptions::new().layout(PixelLayout::Rgb),
+        )
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw pixels with four channels in specified order are written into `output` slice regardless of channels number in the image.\
+    /// Alpha channel is set to `255` for images without alpha channel.\
+    /// Use [`Qoi::decoded_size_rgba`] to find required size of the `output`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_order(
+        bytes: &[u8],
+        output: &mut [u8],
+        order: ChannelOrder,
+    ) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, output, &DecodeOptions::new().channel_order(order))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are packed into `u32` values according to `layout` and written into `output` slice.\
+    /// Alpha channel is set to `255` for images without alpha channel.\
+    /// `output` must hold at least `width * height` values.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_u32(
+        bytes: &[u8],
+        output: &mut [u32],
+        layout: U32Layout,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let px_len = qoi.width as usize * qoi.height as usize;
+        let output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match layout {
+            U32Layout::Argb8888 => qoi.decode_skip_header_into(bytes, Packed::<true>(output))?,
+            U32Layout::Rgba8888 => qoi.decode_skip_header_into(bytes, Packed::<false>(output))?,
+        }
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels are written into `output` slice of either RGB or RGBA pixels regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.\
+    /// `output` must hold at least `width * height` pixels.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space as stored in the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_pixels<const M: usize>(
+        bytes: &[u8],
+        output: &mut [[u8; M]],
+    ) -> Result<Self, DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let qoi = Self::decode_header(bytes)?;
+
+        let px_len = qoi.width as usize * qoi.height as usize;
+        let output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        qoi.decode_skip_header_into(&bytes[QOI_HEADER_SIZE..], output)?;
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels with color channels premultiplied by alpha are written into `output` slice.\
+    /// Each color channel is computed as `(c * a + 127) / 255`.\
+    /// Pixels without alpha channel are written as is.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_premultiplied(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, output, &DecodeOptions::new().premultiplied(true))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice with rows in bottom to top order.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_flipped(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, output, &DecodeOptions::new().flip(true))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice with rows placed `stride` bytes apart.\
+    /// Bytes between rows are left untouched.
+    ///
+    /// `stride` must be not less than `width * channels`,
+    /// and `output` must hold at least `stride * (height - 1) + width * channels` bytes.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_stride(
+        bytes: &[u8],
+        output: &mut [u8],
+        stride: usize,
+    ) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, output, &DecodeOptions::new().stride(stride))
+    }
+
+    /// Decode rectangular region of a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels of the region are written into `output` slice.\
+    /// `output` must hold at least `w * h * channels` bytes.
+    ///
+    /// Encoded data is still parsed from the beginning,
+    /// but decoding stops as soon as last pixel of the region is produced.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing whole image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_region(
+        bytes: &[u8],
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        output: &mut [u8],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        match (x.checked_add(w), y.checked_add(h)) {
+            (Some(x1), Some(y1)) if x1 <= qoi.width && y1 <= qoi.height => {}
+            _ => return Err(DecodeError::InvalidRegion),
+        }
+
+        if w == 0 || h == 0 {
+            return Ok(qoi);
+        }
+
+        let size = w as usize * h as usize * qoi.colors.channels();
+        let output = match output.get_mut(..size) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let x = x as usize..x as usize + w as usize;
+        let y = y as usize..y as usize + h as usize;
+        let width = qoi.width as usize;
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(
+                bytes,
+                Region::<4> {
+                    pixels: bytemuck::cast_slice_mut(output),
+                    width,
+                    x,
+                    y,
+                    col: 0,
+                    row: 0,
+                },
+            )?,
+            false => qoi.decode_skip_header_into(
+                bytes,
+                Region::<3> {
+                    pixels: bytemuck::cast_slice_mut(output),
+                    width,
+                    x,
+                    y,
+                    col: 0,
+                    row: 0,
+                },
+            )?,
+        }
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice row by row.\
+    /// Each row of raw RGB or RGBA pixels is decoded into `row` slice
+    /// and passed to the callback `f` along with row index.\
+    /// `row` must hold at least `width * channels` bytes.
+    ///
+    /// Callback may return `ControlFlow::Break(())` to stop decoding.
+    ///
+    /// On success this function returns `Ok((qoi, flow))` with `qoi` describing image dimensions and color space
+    /// and `flow` being `ControlFlow::Break(())` if decoding was stopped by the callback.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_rows<F>(
+        bytes: &[u8],
+        row: &mut [u8],
+        f: F,
+    ) -> Result<(Self, ControlFlow<()>), DecodeError>
+    where
+        F: FnMut(u32, &[u8]) -> ControlFlow<()>,
+    {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        if qoi.width == 0 || qoi.height == 0 {
+            return Ok((qoi, ControlFlow::Continue(())));
+        }
+
+        let size = qoi.width as usize * qoi.colors.channels();
+        let row = match row.get_mut(..size) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(row) => row,
+        };
+
+        let flow = match qoi.colors.has_alpha() {
+            true => {
+                let mut out = RowCallback::<_, 4> {
+                    row: bytemuck::cast_slice_mut(row),
+                    pos: 0,
+                    y: 0,
+                    height: qoi.height,
+                    f,
+                    flow: ControlFlow::Continue(()),
+                };
+                qoi.decode_skip_header_into(bytes, &mut out)?;
+                out.flow
+            }
+            false => {
+                let mut out = RowCallback::<_, 3> {
+                    row: bytemuck::cast_slice_mut(row),
+                    pos: 0,
+                    y: 0,
+                    height: qoi.height,
+                    f,
+                    flow: ControlFlow::Continue(()),
+                };
+                qoi.decode_skip_header_into(bytes, &mut out)?;
+                out.flow
+            }
+        };
+        Ok((qoi, flow))
+    }
+
+    /// Decode first `pixel_count` pixels of a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// `pixel_count` greater than number of pixels in the image is clamped.
+    ///
+    /// `bytes` may be truncated right after encoded data of the requested pixels.\
+    /// Use [`Qoi::decode_range`] to continue decoding with preserved state.
+    ///
+    /// On success this function returns `Ok((qoi, consumed))` with `qoi` describing image dimensions and color space
+    /// and `consumed` being number of bytes read from `bytes`, including the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `DecodeError::NotEnoughData` is returned if `bytes` ends before `pixel_count` pixels are decoded.
+    #[inline]
+    pub fn decode_prefix(
+        bytes: &[u8],
+        output: &mut [u8],
+        pixel_count: usize,
+    ) -> Result<(Self, usize), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let pixel_count = pixel_count.min(qoi.width as usize * qoi.height as usize);
+        if pixel_count == 0 {
+            return Ok((qoi, QOI_HEADER_SIZE));
+        }
+
+        let output = match output.get_mut(..pixel_count * qoi.colors.channels()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let consumed = match qoi.colors.has_alpha() {
+            true => Self::decode_range::<4>(
+                &mut [Pixel::new(); 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                bytes,
+                output,
+            )?,
+            false => Self::decode_range::<3>(
+                &mut [Pixel::new(); 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                bytes,
+                output,
+            )?,
+        };
+        Ok((qoi, QOI_HEADER_SIZE + consumed))
+    }
+
+    /// Decode a QOI image from bytes slice keeping only every `factor`-th pixel of every `factor`-th row.\
+    /// Decoded raw RGB or RGBA pixels of the `ceil(width / factor)` by `ceil(height / factor)` image are written into `output` slice.\
+    /// Use [`Qoi::decoded_size_subsampled`] to find required size of the `output`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing original image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is zero.
+    #[inline]
+    pub fn decode_subsampled(
+        bytes: &[u8],
+        factor: u32,
+        output: &mut [u8],
+    ) -> Result<Self, DecodeError> {
+        assert_ne!(factor, 0, "Subsampling factor must not be zero");
+
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size_subsampled(factor)) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let width = qoi.width as usize;
+        let factor = factor as usize;
+        let left = width * qoi.height as usize;
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(
+                bytes,
+                Subsampled::<4> {
+                    pixels: bytemuck::cast_slice_mut(output),
+                    width,
+                    factor,
+                    left,
+                    col: 0,
+                    col_phase: 0,
+                    row_phase: 0,
+                },
+            )?,
+            false => qoi.decode_skip_header_into(
+                bytes,
+                Subsampled::<3> {
+                    pixels: bytemuck::cast_slice_mut(output),
+                    width,
+                    factor,
+                    left,
+                    col: 0,
+                    col_phase: 0,
+                    row_phase: 0,
+                },
+            )?,
+        }
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Each channel of decoded pixels is written into separate plane.\
+    /// Each plane must hold at least `width * height` bytes.
+    ///
+    /// If `a` is `None` alpha channel is dropped.\
+    /// If image has no alpha channel, `a` plane is filled with `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_planar(
+        bytes: &[u8],
+        r: &mut [u8],
+        g: &mut [u8],
+        b: &mut [u8],
+        a: Option<&mut [u8]>,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let px_len = qoi.width as usize * qoi.height as usize;
+
+        let planes = (
+            r.get_mut(..px_len),
+            g.get_mut(..px_len),
+            b.get_mut(..px_len),
+            a.map(|a| a.get_mut(..px_len)),
+        );
+
+        match planes {
+            (Some(r), Some(g), Some(b), None) => {
+                qoi.decode_skip_header_into(
+                    bytes,
+                    Planar {
+                        r,
+                        g,
+                        b,
+                        a: None,
+                        pos: 0,
+                    },
+                )?;
+            }
+            (Some(r), Some(g), Some(b), Some(Some(a))) => {
+                qoi.decode_skip_header_into(
+                    bytes,
+                    Planar {
+                        r,
+                        g,
+                        b,
+                        a: Some(a),
+                        pos: 0,
+                    },
+                )?;
+            }
+            _ => return Err(DecodeError::OutputIsTooSmall),
+        }
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Luma of decoded pixels is written into `output` slice, one byte per pixel.\
+    /// Luma is computed as `(77 * r + 150 * g + 29 * b + 128) >> 8`. Alpha channel is ignored.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_luma(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let values = match output.get_mut(..qoi.decoded_size_luma()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(values) => values,
+        };
+
+        qoi.decode_skip_header_into(bytes, Mapped { values, f: luma })?;
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded pixels composited over `background` color are written into `output` slice as raw RGB pixels.\
+    /// Alpha channel of `background` is ignored.\
+    /// Fully transparent pixels become exactly `background` and fully opaque pixels are left unchanged.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_flattened(
+        bytes: &[u8],
+        output: &mut [u8],
+        background: [u8; 4],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size_rgb()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        qoi.decode_skip_header_into(
+            bytes,
+            Mapped {
+                values: bytemuck::cast_slice_mut::<_, [u8; 3]>(output),
+                f: |px| flatten(px, background),
+            },
+        )?;
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Only alpha channel of decoded pixels is written into `output` slice, one byte per pixel.\
+    /// For images without alpha channel `output` is filled with `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_alpha(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let values = match output.get_mut(..qoi.decoded_size_alpha()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(values) => values,
+        };
+
+        qoi.decode_skip_header_into(
+            bytes,
+            Mapped {
+                values,
+                f: |[_, _, _, a]: [u8; 4]| a,
+            },
+        )?;
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA pixels are written into uninitialized `output` slice.\
+    /// Use [`Qoi::decode_header`] to get image dimensions and color space.
+    ///
+    /// On success this function returns `Ok(pixels)` with `pixels` being initialized first [`Qoi::decoded_size`] bytes of the `output`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Pixels decoded before the error may be written into the start of the `output`,
+    /// bytes of the `output` are never read and no bytes are written beyond [`Qoi::decoded_size`].
+    #[inline]
+    pub fn decode_uninit<'a>(
+        bytes: &[u8],
+        output: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let mut output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => Init::new(output),
+        };
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(bytes, Uninit::<4>(&mut output))?,
+            false => qoi.decode_skip_header_into(bytes, Uninit::<3>(&mut output))?,
+        }
+        Ok(output.into_init())
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded RGB or RGBA pixels are written into `output` slice with channels converted into `0.0..=1.0` range.\
+    /// If `linear` is `true` and image is in sRGB color space, sRGB transfer function is applied to color channels.\
+    /// Alpha channel is always linear.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_f32(bytes: &[u8], output: &mut [f32], linear: bool) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size_f32()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let linear = linear && qoi.colors.is_srgb();
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_f32_as::<4>(bytes, output, linear)?,
+            false => qoi.decode_f32_as::<3>(bytes, output, linear)?,
         }
+        Ok(qoi)
     }
-}
-
-#[cfg(feature = "std")]
-impl std::error::Error for DecodeError {}
 
-impl Qoi {
-    /// Returns bytes size for the decoded image.
     #[inline]
-    pub fn decoded_size(&self) -> usize {
-        self.width as usize * self.height as usize * self.colors.channels()
+    fn decode_f32_as<const M: usize>(
+        &self,
+        bytes: &[u8],
+        output: &mut [f32],
+        linear: bool,
+    ) -> Result<(), DecodeError>
+    where
+        [f32; M]: bytemuck::Pod,
+    {
+        let values = bytemuck::cast_slice_mut::<_, [f32; M]>(output);
+        match linear {
+            true => self.decode_skip_header_into(
+                bytes,
+                Mapped {
+                    values,
+                    f: linearize::<M>,
+                },
+            ),
+            false => self.decode_skip_header_into(
+                bytes,
+                Mapped {
+                    values,
+                    f: normalize::<M>,
+                },
+            ),
+        }
     }
 
-    /// Reads header from encoded QOI image.\
-    /// Returned header can be analyzed before proceeding parsing with [`Qoi::decode_skip_header`].
-    pub fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
-        if bytes.len() < QOI_HEADER_SIZE {
-            return Err(DecodeError::NotEnoughData);
-        }
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded RGB or RGBA pixels are written into `output` slice with channels widened to 16 bits.\
+    /// Each channel value `v` is converted into `v << 8 | v`, so that `0` maps to `0` and `255` maps to `65535`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_u16(bytes: &[u8], output: &mut [u16]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
 
-        let magic = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
-        if magic != QOI_MAGIC {
-            return Err(DecodeError::InvalidMagic);
+        let output = match output.get_mut(..qoi.decoded_size_u16()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(
+                bytes,
+                Mapped {
+                    values: bytemuck::cast_slice_mut::<_, [u16; 4]>(output),
+                    f: widen::<4>,
+                },
+            )?,
+            false => qoi.decode_skip_header_into(
+                bytes,
+                Mapped {
+                    values: bytemuck::cast_slice_mut::<_, [u16; 3]>(output),
+                    f: widen::<3>,
+                },
+            )?,
         }
+        Ok(qoi)
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded RGB or RGBA pixels are written into `output` slice with color channels in linear light.\
+    /// If image is in sRGB color space, sRGB transfer function is applied to color channels
+    /// and results are rounded to nearest integer.\
+    /// Alpha channel is always linear. Images with all linear channels are decoded unchanged.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_linear(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
 
-        let w = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
-        let h = u32::from_be_bytes(bytes[8..12].try_into().unwrap());
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
 
-        let channels = bytes[12];
-        let colors = bytes[13];
+        if !qoi.colors.is_srgb() {
+            qoi.decode_skip_header(bytes, output)?;
+            return Ok(qoi);
+        }
 
-        Ok(Qoi {
-            width: w,
-            height: h,
-            colors: match (channels, colors) {
-                (3, 0) => Colors::Srgb,
-                (4, 0) => Colors::SrgbLinA,
-                (3, 1) => Colors::Rgb,
-                (4, 1) => Colors::Rgba,
-                (_, 0 | 1) => return Err(DecodeError::InvalidChannelsValue),
-                (_, _) => return Err(DecodeError::InvalidColorSpaceValue),
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_linear_as::<4>(bytes, bytemuck::cast_slice_mut(output))?,
+            false => qoi.decode_linear_as::<3>(bytes, bytemuck::cast_slice_mut(output))?,
+        }
+        Ok(qoi)
+    }
+
+    #[inline]
+    fn decode_linear_as<const M: usize>(
+        &self,
+        bytes: &[u8],
+        values: &mut [[u8; M]],
+    ) -> Result<(), DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let lut = linear_lut();
+        self.decode_skip_header_into(
+            bytes,
+            Mapped {
+                values,
+                f: |px: [u8; 4]| {
+                    let mut out = [0; M];
+                    out.copy_from_slice(&px[..M]);
+                    for c in &mut out[..3] {
+                        *c = lut[*c as usize];
+                    }
+                    out
+                },
             },
-        })
+        )
     }
 
     /// Decode a QOI image from bytes slice.\
-    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    /// Decoded raw RGB or RGBA pixels of the image rotated clockwise by specified angle are written into `output` slice.\
+    /// Width and height of the output image are swapped for [`Rotation::Rot90`] and [`Rotation::Rot270`].
     ///
-    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On success this function returns `Ok(qoi)` with `qoi` describing original image dimensions and color space.\
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
-    pub fn decode(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+    pub fn decode_rotated(
+        bytes: &[u8],
+        output: &mut [u8],
+        rotation: Rotation,
+    ) -> Result<Self, DecodeError> {
         let qoi = Self::decode_header(bytes)?;
-        qoi.decode_skip_header(&bytes[QOI_HEADER_SIZE..], output)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        match qoi.colors.has_alpha() {
+            true => {
+                qoi.decode_rotated_as::<4>(bytes, bytemuck::cast_slice_mut(output), rotation)?
+            }
+            false => {
+                qoi.decode_rotated_as::<3>(bytes, bytemuck::cast_slice_mut(output), rotation)?
+            }
+        }
         Ok(qoi)
     }
 
+    #[inline]
+    fn decode_rotated_as<const M: usize>(
+        &self,
+        bytes: &[u8],
+        pixels: &mut [[u8; M]],
+        rotation: Rotation,
+    ) -> Result<(), DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        match rotation {
+            Rotation::None => self.decode_skip_header_into(bytes, pixels),
+            Rotation::Rot180 => self.decode_skip_header_into(bytes, Reversed(pixels)),
+            Rotation::Rot90 => {
+                self.decode_skip_header_into(bytes, Transposed::new(pixels, width, height, true))
+            }
+            Rotation::Rot270 => {
+                self.decode_skip_header_into(bytes, Transposed::new(pixels, width, height, false))
+            }
+        }
+    }
+
     /// Decode a QOI image from bytes slice.\
     /// `bytes` does not include QOI header. Uses provided `Qoi` value instead.\
-    /// Decoded raw RGB or RGBA (depending on `self.colors` value) pixels are written into `output` slice.
+    /// Decoded raw RGB or RGBA (depending on `self.colors` value) pixels are written into `output` slice.\
+    /// End marker is not verified, use [`Qoi::decode_body`] to verify it.
     ///
     /// On success this function returns `Ok(())`.\
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
     pub fn decode_skip_header(&self, bytes: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
-        if self.width == 0 || self.height == 0 {
-            return Ok(());
+        match self.colors.has_alpha() {
+            true => self.decode_skip_header_as::<4>(bytes, output),
+            false => self.decode_skip_header_as::<3>(bytes, output),
         }
+    }
 
-        let px_len = self.decoded_size();
+    /// Decode pixels into `output` slice as pixels with `M` channels.
+    #[inline]
+    fn decode_skip_header_as<const M: usize>(
+        &self,
+        bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let px_len = self.width as usize * self.height as usize * M;
 
         let output = match output.get_mut(..px_len) {
             None => return Err(DecodeError::OutputIsTooSmall),
             Some(output) => output,
         };
 
+        self.decode_skip_header_into(bytes, bytemuck::cast_slice_mut::<_, [u8; M]>(output))
+    }
+
+    /// Decode pixels into arbitrary output.\
+    /// Output must be able to accept all pixels of the image.
+    #[inline]
+    pub(crate) fn decode_skip_header_into<O>(&self, bytes: &[u8], out: O) -> Result<(), DecodeError>
+    where
+        O: Output<3> + Output<4>,
+    {
+        self.decode_chunks_into(bytes, out, true)?;
+        Ok(())
+    }
+
+    /// Decode pixels into arbitrary output.\
+    /// Output must be able to accept all pixels of the image.\
+    /// Returns number of bytes of chunks consumed.\
+    /// Run exceeding the last pixel is clamped if `clamp` is `true`
+    /// and reported as `DecodeError::TooManyPixels` otherwise.\
+    /// Offsets in the error are counted as if `bytes` were preceded by header.
+    #[inline]
+    pub(crate) fn decode_chunks_into<O>(
+        &self,
+        bytes: &[u8],
+        mut out: O,
+        clamp: bool,
+    ) -> Result<usize, DecodeError>
+    where
+        O: Output<3> + Output<4>,
+    {
+        if self.width == 0 || self.height == 0 {
+            return Ok(0);
+        }
+
+        let mut run = 0;
+        let size = match self.colors.has_alpha() {
+            true => Self::decode_range_into::<4, false, _>(
+                &mut [Pixel::new(); 64],
+                &mut Pixel::new_opaque(),
+                &mut run,
+                bytes,
+                &mut out,
+            ),
+            false => Self::decode_range_into::<3, false, _>(
+                &mut [Pixel::new(); 64],
+                &mut Pixel::new_opaque(),
+                &mut run,
+                bytes,
+                &mut out,
+            ),
+        }
+        .map_err(|err| {
+            err.for_image(bytes, self.width as usize * self.height as usize)
+                .at(QOI_HEADER_SIZE, 0)
+        })?;
+
+        if run > 0 && !clamp {
+            // Run that does not fit into the output is the last consumed chunk.
+            return Err(DecodeError::TooManyPixels {
+                at_byte: QOI_HEADER_SIZE + size - 1,
+            });
+        }
+
+        Ok(size)
+    }
+
+    /// Verifies that `bytes` contain end marker at `offset`.\
+    /// Returns size of the end marker.
+    #[inline]
+    pub(crate) fn decode_padding(&self, bytes: &[u8], offset: usize) -> Result<usize, DecodeError> {
+        match bytes
+            .get(offset..)
+            .and_then(|bytes| bytes.get(..QOI_PADDING))
+        {
+            None => Err(DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded: self.width as usize * self.height as usize,
+                needed: QOI_PADDING - bytes.len().saturating_sub(offset),
+            }),
+            Some([0, 0, 0, 0, 0, 0, 0, 1]) => Ok(QOI_PADDING),
+            Some(_) => Err(DecodeError::InvalidPadding { offset }),
+        }
+    }
+
+    /// Decode part of a QOI image from bytes slice.\
+    /// `bytes` contains whole encoded image including header.\
+    /// Decoder state is taken from and updated in `state`,
+    /// so decoding consecutive parts of the image produces the same pixels as decoding whole image at once.\
+    /// Up to `max_pixels` raw RGB or RGBA (depending on `self.colors` value) pixels following already decoded ones
+    /// are written into `output` slice from its start.\
+    /// End marker is verified after the last pixel of the image.
+    ///
+    /// On success this function returns `Ok(count)` with `count` of pixels decoded.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Content of `state` is unspecified after failure.
+    #[inline]
+    pub fn decode_resume(
+        &self,
+        bytes: &[u8],
+        state: &mut DecoderState,
+        output: &mut [u8],
+        max_pixels: usize,
+    ) -> Result<usize, DecodeError> {
         match self.colors.has_alpha() {
-            true => {
-                Self::decode_range::<4>(
-                    &mut [Pixel::new(); 64],
-                    &mut Pixel::new_opaque(),
-                    &mut 0,
-                    bytes,
-                    output,
-                )?;
-            }
-            false => {
-                Self::decode_range::<3>(
-                    &mut [Pixel::new(); 64],
-                    &mut Pixel::new_opaque(),
-                    &mut 0,
-                    bytes,
-                    output,
-                )?;
-            }
+            true => self.decode_resume_as::<4, true>(bytes, state, output, max_pixels),
+            false => self.decode_resume_as::<3, true>(bytes, state, output, max_pixels),
         }
-        Ok(())
+    }
+
+    /// Decode part of the image with `N` channels.\
+    /// End marker is verified after the last pixel if `PADDING` is `true`,
+    /// otherwise `state` is not finished after the last pixel.
+    #[inline]
+    pub(crate) fn decode_resume_as<const N: usize, const PADDING: bool>(
+        &self,
+        bytes: &[u8],
+        state: &mut DecoderState,
+        output: &mut [u8],
+        max_pixels: usize,
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let max_pixels = max_pixels.min(output.len() / N);
+        self.resume_into::<N, PADDING, _>(bytes, state, max_pixels, |count| {
+            bytemuck::cast_slice_mut::<_, [u8; N]>(&mut output[..count * N])
+        })
+    }
+
+    /// Advances decoder `state` by up to `max_pixels` pixels without storing them.\
+    /// Returns number of pixels skipped.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub(crate) fn skip_resume(
+        &self,
+        bytes: &[u8],
+        state: &mut DecoderState,
+        max_pixels: usize,
+    ) -> Result<usize, DecodeError> {
+        let skip = |count| Visit {
+            left: count,
+            f: |_, _| ControlFlow::Continue(()),
+        };
+        match self.colors.has_alpha() {
+            true => self.resume_into::<4, true, _>(bytes, state, max_pixels, skip),
+            false => self.resume_into::<3, true, _>(bytes, state, max_pixels, skip),
+        }
+    }
+
+    /// Decode up to `max_pixels` pixels of the image with `N` channels into output
+    /// created by `output` for the number of pixels to decode.\
+    /// End marker is verified after the last pixel if `PADDING` is `true`.
+    #[inline]
+    fn resume_into<const N: usize, const PADDING: bool, O>(
+        &self,
+        bytes: &[u8],
+        state: &mut DecoderState,
+        max_pixels: usize,
+        output: impl FnOnce(usize) -> O,
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; N]: Pixel,
+        O: Output<N>,
+    {
+        if state.finished {
+            return Ok(0);
+        }
+
+        let total = self.width as usize * self.height as usize;
+        if state.pos > total || state.offset > bytes.len() {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let count = max_pixels.min(total - state.pos);
+        let mut pixels = output(count);
+
+        let mut index = state.index.map(|px| convert::<_, [u8; N]>(&px));
+        let mut px = convert::<_, [u8; N]>(&state.px);
+
+        if count > 0 {
+            state.offset += Self::decode_range_into::<N, false, _>(
+                &mut index,
+                &mut px,
+                &mut state.run,
+                &bytes[state.offset..],
+                &mut pixels,
+            )
+            .map_err(|err| err.at(state.offset, state.pos).for_image(bytes, total))?;
+        }
+
+        state.index = index.map(|px| convert(&px));
+        state.px = convert(&px);
+        state.pos += count;
+
+        if PADDING && state.pos == total {
+            state.offset += self.decode_padding(bytes, state.offset)?;
+            state.run = 0;
+            state.finished = true;
+        }
+
+        Ok(count)
     }
 
     /// Decode range of pixels into pixels slice.
@@ -160,134 +2695,123 @@ impl Qoi {
     {
         assert_eq!(pixels.len() % N, 0);
 
-        // let (mut pixels, rem) = pixels.as_chunks_mut::<N>();
-        // let mut pixels = pixels.chunks_exact_mut(N).map(cast_pixel::<N>);
-
-        let mut pixels = bytemuck::cast_slice_mut(pixels);
+        let mut pixels = bytemuck::cast_slice_mut::<_, [u8; N]>(pixels);
 
-        // assert!(rem.is_empty());
+        Self::decode_range_into::<N, false, _>(index, ppx, prun, bytes, &mut pixels)
+    }
 
+    /// Decode range of pixels into arbitrary output.\
+    /// If `PARTIAL` is `true` decoding stops at incomplete chunk and returns number of bytes before it,
+    /// place reserved for the next pixel is left unused.\
+    /// Offsets and numbers of pixels in the error are counted from the start of the range.
+    #[inline]
+    pub(crate) fn decode_range_into<const N: usize, const PARTIAL: bool, O>(
+        index: &mut [[u8; N]; 64],
+        ppx: &mut [u8; N],
+        prun: &mut usize,
+        bytes: &[u8],
+        out: &mut O,
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; N]: Pixel,
+        O: Output<N>,
+    {
         let mut px = *ppx;
+        let mut filled = 0;
 
         if *prun > 0 {
-            // let len = pixels.len();
-
-            let (head, tail) = pixels.split_at_mut((*prun).min(pixels.len()));
-
-            // pixels.by_ref().take(*prun).for_each(|pixel| *pixel = px);
+            filled = out.fill(px, *prun);
+            *prun -= filled;
 
-            pixels = tail;
-            head.fill(px);
-
-            if pixels.is_empty() {
+            if *prun > 0 {
                 cold();
-                *prun -= head.len();
                 return Ok(0);
-            } else {
-                *prun = 0;
             }
         }
 
         let mut rest = bytes;
 
         loop {
-            match pixels {
-                [out, tail @ ..] => {
-                    // Some(out) => {
-                    pixels = tail;
-                    match rest {
-                        [b1 @ 0b00000000..=0b00111111, tail @ ..] => {
-                            px = index[*b1 as usize];
-                            *out = px;
-
-                            rest = tail;
-                            continue;
-                        }
-                        [b1 @ 0b01000000..=0b01111111, tail @ ..] => {
-                            let vr = ((b1 >> 4) & 0x03).wrapping_sub(2);
-                            let vg = ((b1 >> 2) & 0x03).wrapping_sub(2);
-                            let vb = (b1 & 0x03).wrapping_sub(2);
-                            px.add_rgb(vr, vg, vb);
+            let slot = match out.next() {
+                Some(slot) => slot,
+                None => {
+                    cold();
+                    break;
+                }
+            };
 
-                            rest = tail;
-                        }
-                        [b1 @ 0b10000000..=0b10111111, b2, tail @ ..] => {
-                            let vg = (b1 & 0x3f).wrapping_sub(32);
-                            let vr = ((b2 >> 4) & 0x0f).wrapping_sub(8).wrapping_add(vg);
-                            let vb = (b2 & 0x0f).wrapping_sub(8).wrapping_add(vg);
-                            px.add_rgb(vr, vg, vb);
+            match rest {
+                [b1 @ 0b00000000..=0b00111111, tail @ ..] => {
+                    px = index[*b1 as usize];
+                    out.write(slot, px);
 
-                            rest = tail;
-                        }
-                        [0b11111110, b2, b3, b4, tail @ ..] => {
-                            px.set_rgb(*b2, *b3, *b4);
-                            // px[0] = *b2;
-                            // px[1] = *b3;
-                            // px[2] = *b4;
+                    rest = tail;
+                    continue;
+                }
+                [b1 @ 0b01000000..=0b01111111, tail @ ..] => {
+                    let vr = ((b1 >> 4) & 0x03).wrapping_sub(2);
+                    let vg = ((b1 >> 2) & 0x03).wrapping_sub(2);
+                    let vb = (b1 & 0x03).wrapping_sub(2);
+                    px.add_rgb(vr, vg, vb);
 
-                            rest = tail;
-                        }
-                        [0b11111111, b2, b3, b4, _b5, tail @ ..] if N == 3 => {
-                            cold();
-                            px.set_rgb(*b2, *b3, *b4);
-                            // px[0] = *b2;
-                            // px[1] = *b3;
-                            // px[2] = *b4;
-
-                            rest = tail;
-                        }
-                        [0b11111111, b2, b3, b4, b5, tail @ ..] => {
-                            px.set_rgba(*b2, *b3, *b4, *b5);
+                    rest = tail;
+                }
+                [b1 @ 0b10000000..=0b10111111, b2, tail @ ..] => {
+                    let vg = (b1 & 0x3f).wrapping_sub(32);
+                    let vr = ((b2 >> 4) & 0x0f).wrapping_sub(8).wrapping_add(vg);
+                    let vb = (b2 & 0x0f).wrapping_sub(8).wrapping_add(vg);
+                    px.add_rgb(vr, vg, vb);
 
-                            // px[0] = *b2;
-                            // px[1] = *b3;
-                            // px[2] = *b4;
-                            // px[3] = *b5;
+                    rest = tail;
+                }
+                [0b11111110, b2, b3, b4, tail @ ..] => {
+                    px.set_rgb(*b2, *b3, *b4);
 
-                            rest = tail;
-                        }
-                        [b1 @ 0b11000000..=0b11111101, dtail @ ..] => {
-                            *out = px;
-                            let run = *b1 as usize & 0x3f;
-                            let (head, tail) = pixels.split_at_mut(run);
-                            head.fill(px);
-                            pixels = tail;
-                            rest = dtail;
-
-                            // let len = pixels.len();
-                            // pixels.by_ref().take(run.min(len)).for_each(|out| *out = px);
-
-                            if unlikely(pixels.is_empty()) {
-                                *prun = run - head.len();
-                                break;
-                            }
+                    rest = tail;
+                }
+                [0b11111111, b2, b3, b4, _b5, tail @ ..] if N == 3 => {
+                    cold();
+                    px.set_rgb(*b2, *b3, *b4);
 
-                            continue;
-                        }
-                        _ => {
-                            // if unlikely(rest.len() < QOI_PADDING) {
-                            return Err(DecodeError::NotEnoughData);
-                            // }
-                            // Unreachable arm due to length check above.
-                            // unreachable();
-                        }
-                    }
-                    //     }
-                    // }
+                    rest = tail;
+                }
+                [0b11111111, b2, b3, b4, b5, tail @ ..] => {
+                    px.set_rgba(*b2, *b3, *b4, *b5);
+
+                    rest = tail;
+                }
+                [b1 @ 0b11000000..=0b11111101, tail @ ..] => {
+                    out.write(slot, px);
+                    let run = *b1 as usize & 0x3f;
+                    let written = out.fill(px, run);
+                    rest = tail;
 
-                    index[px.hash() as usize] = px;
+                    if unlikely(written < run) {
+                        *prun = run - written;
+                        break;
+                    }
 
-                    // px.write(chunk);
-                    *out = px;
-                    // output = px.write_head(output);
+                    continue;
                 }
-                [] => {
-                    // None => {
-                    // None => {
+                _ if PARTIAL => {
                     cold();
                     break;
                 }
+                _ => {
+                    let offset = bytes.len() - rest.len();
+                    return Err(DecodeError::NotEnoughData {
+                        offset,
+                        pixels_decoded: filled + chunk_pixels(&bytes[..offset]),
+                        needed: match rest.first() {
+                            None => 1,
+                            Some(&b1) => decoder::chunk_len(b1) - rest.len(),
+                        },
+                    });
+                }
             }
+
+            index[px.hash() as usize] = px;
+            out.write(slot, px);
         }
 
         *ppx = px;
@@ -296,7 +2820,7 @@ impl Qoi {
     }
 
     /// Decode a QOI image from bytes slice.\
-    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`.
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec` without zeroing it first.
     ///
     /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing raw pixels data.\
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
@@ -305,9 +2829,137 @@ impl Qoi {
     pub fn decode_alloc(bytes: &[u8]) -> Result<(Self, Vec<u8>), DecodeError> {
         let qoi = Self::decode_header(bytes)?;
 
-        let size = qoi.decoded_size();
-        let mut output = vec![0; size];
-        let qoi = Self::decode(bytes, &mut output)?;
+        let mut output = Vec::new();
+        uninit::extend(&mut output, qoi.decoded_size(), |spare| {
+            Self::decode_uninit(bytes, spare)
+        })?;
+        Ok((qoi, output))
+    }
+
+    /// Decode a QOI image from bytes slice verifying the end marker.\
+    /// Decoded raw RGB or RGBA pixels are written into allocated `Vec`.\
+    /// Bytes following the end marker are not read, so multiple images stored one after another can be decoded sequentially.
+    ///
+    /// On success this function returns `Ok((qoi, vec, size))` with `qoi` describing image dimensions and color space,
+    /// `vec` containing raw pixels data and `size` of the encoded image including header and end marker.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_alloc_counted(bytes: &[u8]) -> Result<(Self, Vec<u8>, usize), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let body = &bytes[QOI_HEADER_SIZE..];
+
+        let mut output = Vec::new();
+        let mut size = 0;
+        uninit::extend(&mut output, qoi.decoded_size(), |spare| {
+            let mut pixels = Init::new(spare);
+            size = match qoi.colors.has_alpha() {
+                true => qoi.decode_chunks_into(body, Uninit::<4>(&mut pixels), true)?,
+                false => qoi.decode_chunks_into(body, Uninit::<3>(&mut pixels), true)?,
+            };
+            size += qoi
+                .decode_padding(body, size)
+                .map_err(|err| err.at(QOI_HEADER_SIZE, 0))?;
+            Ok(pixels.into_init())
+        })?;
+        Ok((qoi, output, QOI_HEADER_SIZE + size))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded RGB or RGBA pixels are written into allocated `Vec` regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_pixels_alloc<const M: usize>(
+        bytes: &[u8],
+    ) -> Result<(Self, Vec<[u8; M]>), DecodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let qoi = Self::decode_header(bytes)?;
+
+        let size = qoi.width as usize * qoi.height as usize;
+        let mut output = vec![<[u8; M]>::new(); size];
+        let qoi = Self::decode_pixels(bytes, &mut output)?;
         Ok((qoi, output))
     }
+
+    /// Decode a QOI image from bytes slice into palette and one palette index per pixel.\
+    /// Palette contains RGBA colors in order of their first appearance in the image.\
+    /// Colors of images without alpha channel have alpha value `255`.\
+    /// Decoding stops as soon as more than 256 unique colors are found.
+    ///
+    /// On success this function returns `Ok((qoi, Some((palette, indices))))` with `qoi` describing image dimensions and color space,
+    /// or `Ok((qoi, None))` if the image has more than 256 unique colors.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn decode_indexed(
+        bytes: &[u8],
+    ) -> Result<(Self, Option<(Vec<[u8; 4]>, Vec<u8>)>), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let size = qoi.width as usize * qoi.height as usize;
+        let mut palette = Vec::new();
+        let mut slots = BTreeMap::new();
+        let mut indices = Vec::with_capacity(size);
+        let mut last = None;
+
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left: size,
+                f: |px, n| {
+                    let index = match last {
+                        Some((color, index)) if color == px => index,
+                        _ => {
+                            let index = *slots.entry(px).or_insert_with(|| {
+                                palette.push(px);
+                                palette.len() - 1
+                            });
+                            if index > 255 {
+                                return ControlFlow::Break(());
+                            }
+                            last = Some((px, index as u8));
+                            index as u8
+                        }
+                    };
+                    indices.resize(indices.len() + n, index);
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+
+        match palette.len() > 256 {
+            true => Ok((qoi, None)),
+            false => Ok((qoi, Some((palette, indices)))),
+        }
+    }
+}
+
+/// Returns number of pixels produced by complete chunks in `bytes`.
+#[inline(never)]
+fn chunk_pixels(bytes: &[u8]) -> usize {
+    let mut pixels = 0;
+    let mut rest = bytes;
+    loop {
+        let (n, size) = match *rest {
+            [QOI_OP_RGB, ..] => (1, 4),
+            [QOI_OP_RGBA, ..] => (1, 5),
+            [b1 @ 0b11000000..=0b11111101, ..] => ((b1 & 0x3f) as usize + 1, 1),
+            [0b10000000..=0b10111111, ..] => (1, 2),
+            [_, ..] => (1, 1),
+            [] => return pixels,
+        };
+        match rest.get(size..) {
+            None => return pixels,
+            Some(tail) => rest = tail,
+        }
+        pixels += n;
+    }
 }
@@ -0,0 +1,378 @@
+use super::*;
+use decode::Output;
+
+/// Errors that may occur during image decoding from generic reader.\
+/// `E` is error type of the reader, reader error is not displayed but returned as source of the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IoDecodeError<E> {
+    /// Reading from the reader failed.
+    Io(E),
+
+    /// Read bytes are not a valid QOI image.
+    Decode(DecodeError),
+}
+
+impl<E> Display for IoDecodeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoDecodeError::Io(_) => f.write_str("Failed to read encoded image"),
+            IoDecodeError::Decode(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for IoDecodeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoDecodeError::Io(err) => Some(err),
+            IoDecodeError::Decode(_) => None,
+        }
+    }
+}
+
+impl<E> From<DecodeError> for IoDecodeError<E> {
+    #[inline]
+    fn from(err: DecodeError) -> Self {
+        IoDecodeError::Decode(err)
+    }
+}
+
+/// State of the [`Decoder`] after [`Decoder::push`] call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DecodeStatus {
+    /// All provided bytes are consumed, more bytes are required to continue decoding.
+    NeedsMoreData,
+
+    /// Output is full, more space for pixels is required to continue decoding.
+    OutputIsFull,
+
+    /// Image is decoded completely including end marker.
+    Finished,
+}
+
+/// Progress made by single [`Decoder::push`] call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DecodeProgress {
+    /// Number of bytes consumed from input.
+    pub consumed: usize,
+
+    /// Number of pixels written into output.
+    pub pixels: usize,
+
+    /// State of the decoder after the call.
+    pub status: DecodeStatus,
+}
+
+/// Push-based decoder of QOI image provided in arbitrary chunks of bytes.\
+/// Header, chunks and end marker may be split between pushes at any byte.
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    header: [u8; QOI_HEADER_SIZE],
+    qoi: Option<Qoi>,
+    index: [[u8; 4]; 64],
+    px: [u8; 4],
+    run: usize,
+    pos: usize,
+    pending: [u8; 5],
+    pending_len: usize,
+    padding: usize,
+    offset: usize,
+}
+
+impl Default for Decoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Returns decoder at the start of the image.
+    #[inline]
+    pub const fn new() -> Self {
+        Decoder {
+            header: [0; QOI_HEADER_SIZE],
+            qoi: None,
+            index: [[0; 4]; 64],
+            px: [0, 0, 0, 255],
+            run: 0,
+            pos: 0,
+            pending: [0; 5],
+            pending_len: 0,
+            padding: 0,
+            offset: 0,
+        }
+    }
+
+    /// Returns header of the image.\
+    /// Returns `None` until whole header is pushed.
+    #[inline]
+    pub const fn header(&self) -> Option<Qoi> {
+        self.qoi
+    }
+
+    /// Returns number of pixels decoded so far.
+    #[inline]
+    pub const fn pixels_decoded(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns `true` if image is decoded completely including end marker.
+    #[inline]
+    pub const fn is_finished(&self) -> bool {
+        self.padding == QOI_PADDING
+    }
+
+    /// Decode next chunk of encoded image.\
+    /// Decoded raw RGB or RGBA pixels (depending on channels number in the header) following already decoded ones
+    /// are written into `output` slice from its start.\
+    /// Bytes after the end marker are not consumed.
+    ///
+    /// On success this function returns `Ok(progress)` with `progress` describing consumed bytes and produced pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn push(&mut self, bytes: &[u8], output: &mut [u8]) -> Result<DecodeProgress, DecodeError> {
+        let progress = self.push_bytes(bytes, output)?;
+        self.offset += progress.consumed;
+        Ok(progress)
+    }
+
+    /// Decode next chunk of encoded image without counting consumed bytes.
+    #[inline]
+    fn push_bytes(
+        &mut self,
+        bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<DecodeProgress, DecodeError> {
+        let mut consumed = 0;
+
+        let qoi = match self.qoi {
+            Some(qoi) => qoi,
+            None => {
+                // Header bytes are counted by `pending_len` until header is complete.
+                let filled = self.pending_len;
+                let n = (QOI_HEADER_SIZE - filled).min(bytes.len());
+                self.header[filled..][..n].copy_from_slice(&bytes[..n]);
+                self.pending_len += n;
+                consumed = n;
+
+                if self.pending_len < QOI_HEADER_SIZE {
+                    return Ok(DecodeProgress {
+                        consumed,
+                        pixels: 0,
+                        status: DecodeStatus::NeedsMoreData,
+                    });
+                }
+
+                let qoi = Qoi::decode_header(&self.header)?;
+                self.qoi = Some(qoi);
+                self.pending_len = 0;
+                qoi
+            }
+        };
+
+        let pixels = match qoi.colors.has_alpha() {
+            true => self.push_as::<4>(qoi, &bytes[consumed..], output, &mut consumed)?,
+            false => self.push_as::<3>(qoi, &bytes[consumed..], output, &mut consumed)?,
+        };
+
+        if self.pos == qoi.width as usize * qoi.height as usize {
+            while self.padding < QOI_PADDING && consumed < bytes.len() {
+                let expected = (self.padding == QOI_PADDING - 1) as u8;
+                if bytes[consumed] != expected {
+                    return Err(DecodeError::InvalidPadding {
+                        offset: self.offset + consumed - self.padding,
+                    });
+                }
+                self.padding += 1;
+                consumed += 1;
+            }
+        }
+
+        let status = if self.is_finished() {
+            DecodeStatus::Finished
+        } else if consumed < bytes.len() || self.run > 0 || self.has_pending_chunk() {
+            DecodeStatus::OutputIsFull
+        } else {
+            DecodeStatus::NeedsMoreData
+        };
+
+        Ok(DecodeProgress {
+            consumed,
+            pixels,
+            status,
+        })
+    }
+
+    /// Verifies that image is decoded completely including end marker.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn finish(&self) -> Result<Qoi, DecodeError> {
+        match (self.qoi, self.is_finished()) {
+            (Some(qoi), true) => Ok(qoi),
+            _ => Err(self.truncated()),
+        }
+    }
+
+    /// Returns error describing encoded data ended at current position.
+    #[inline]
+    pub(crate) fn truncated(&self) -> DecodeError {
+        DecodeError::NotEnoughData {
+            offset: self.offset - self.pending_len - self.padding,
+            pixels_decoded: self.pos,
+            needed: self.bytes_needed(),
+        }
+    }
+
+    /// Returns number of bytes that are certainly left in encoded image.\
+    /// Pushing no more than this number of bytes never reaches past the end marker.\
+    /// Returns `0` if image is decoded completely.
+    #[inline]
+    pub fn bytes_needed(&self) -> usize {
+        let qoi = match self.qoi {
+            None => return QOI_HEADER_SIZE - self.pending_len + QOI_PADDING,
+            Some(qoi) => qoi,
+        };
+
+        let total = qoi.width as usize * qoi.height as usize;
+        let mut pixels = (total - self.pos).saturating_sub(self.run);
+        let mut bytes = QOI_PADDING - self.padding;
+        if self.pending_len > 0 {
+            bytes += chunk_len(self.pending[0]) - self.pending_len;
+            pixels -= 1;
+        }
+
+        // Single chunk encodes at most 62 pixels.
+        bytes + pixels.div_ceil(62)
+    }
+
+    /// Returns `true` if complete chunk is kept from previous push.
+    #[inline]
+    fn has_pending_chunk(&self) -> bool {
+        self.qoi.is_some() && self.pending_len > 0 && self.pending_len == chunk_len(self.pending[0])
+    }
+
+    /// Decode pixels with `N` channels from `bytes` into `output`.\
+    /// Returns number of decoded pixels.
+    #[inline]
+    fn push_as<const N: usize>(
+        &mut self,
+        qoi: Qoi,
+        bytes: &[u8],
+        output: &mut [u8],
+        consumed: &mut usize,
+    ) -> Result<usize, DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let total = qoi.width as usize * qoi.height as usize;
+        let len = (output.len() / N).min(total - self.pos);
+        let mut out = Counted {
+            pixels: bytemuck::cast_slice_mut::<_, [u8; N]>(&mut output[..len * N]),
+            written: 0,
+        };
+
+        let mut index = self.index.map(|px| convert::<_, [u8; N]>(&px));
+        let mut px = convert::<_, [u8; N]>(&self.px);
+        let mut bytes = bytes;
+
+        if self.pending_len > 0 {
+            let need = chunk_len(self.pending[0]);
+            let n = (need - self.pending_len).min(bytes.len());
+            self.pending[self.pending_len..][..n].copy_from_slice(&bytes[..n]);
+            self.pending_len += n;
+            *consumed += n;
+            bytes = &bytes[n..];
+
+            if self.pending_len < need || len == 0 {
+                return Ok(0);
+            }
+
+            Qoi::decode_range_into::<N, true, _>(
+                &mut index,
+                &mut px,
+                &mut self.run,
+                &self.pending[..need],
+                &mut out,
+            )?;
+            self.pending_len = 0;
+        }
+
+        let n = Qoi::decode_range_into::<N, true, _>(
+            &mut index,
+            &mut px,
+            &mut self.run,
+            bytes,
+            &mut out,
+        )?;
+        *consumed += n;
+
+        if out.written < len {
+            // Decoding stopped at incomplete chunk in the end of `bytes`.
+            let tail = &bytes[n..];
+            self.pending[..tail.len()].copy_from_slice(tail);
+            self.pending_len = tail.len();
+            *consumed += tail.len();
+        }
+
+        self.index = index.map(|px| convert(&px));
+        self.px = convert(&px);
+        self.pos += out.written;
+
+        if self.pos == total {
+            // Run exceeding the image is ignored as in one-shot decoding.
+            self.run = 0;
+        }
+
+        Ok(out.written)
+    }
+}
+
+/// Returns length of the chunk starting with specified byte.
+#[inline]
+pub(crate) fn chunk_len(b1: u8) -> usize {
+    match b1 {
+        QOI_OP_RGBA => 5,
+        QOI_OP_RGB => 4,
+        0b10000000..=0b10111111 => 2,
+        _ => 1,
+    }
+}
+
+/// Output into slice of pixels that counts written pixels.\
+/// Reserved slot is taken by next pixel if it was left unused.
+struct Counted<'a, const N: usize> {
+    pixels: &'a mut [[u8; N]],
+    written: usize,
+}
+
+impl<'a, const N: usize> Output<N> for Counted<'a, N>
+where
+    [u8; N]: Pixel,
+{
+    type Slot = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.written == self.pixels.len() {
+            return None;
+        }
+        Some(self.written)
+    }
+
+    #[inline]
+    fn write(&mut self, slot: usize, px: [u8; N]) {
+        self.pixels[slot] = px;
+        self.written += 1;
+    }
+
+    #[inline]
+    fn fill(&mut self, px: [u8; N], n: usize) -> usize {
+        let n = n.min(self.pixels.len() - self.written);
+        self.pixels[self.written..][..n].fill(px);
+        self.written += n;
+        n
+    }
+}
@@ -0,0 +1,98 @@
+use super::*;
+use encode::Input;
+
+/// Input of per-channel differences between pixels of two images.
+struct Delta<'a, const M: usize> {
+    base: &'a [[u8; M]],
+    new: &'a [[u8; M]],
+}
+
+impl<'a, const N: usize, const M: usize> Input<N> for Delta<'a, M>
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let (base, tail) = self.base.split_first()?;
+        self.base = tail;
+        let (new, tail) = self.new.split_first()?;
+        self.new = tail;
+
+        let mut px = *new;
+        for (v, b) in px.iter_mut().zip(base) {
+            *v = v.wrapping_sub(*b);
+        }
+        Some(convert(&px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.new.is_empty()
+    }
+}
+
+impl Qoi {
+    /// Encode per-channel differences between two images with raw RGB or RGBA pixels into a QOI image.\
+    /// Each channel of `new_pixels` has corresponding channel of `base_pixels` subtracted with wrapping,
+    /// so unchanged regions become runs of zero pixels.
+    /// Both slices must hold at least `width * height * channels` bytes.\
+    /// Encoded image is written into `output` slice and can be applied back with [`Qoi::apply_delta`].
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_delta(
+        &self,
+        base_pixels: &[u8],
+        new_pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.input_len(self.colors.channels())?;
+        let base = Self::take_input(base_pixels, px_len)?;
+        let new = Self::take_input(new_pixels, px_len)?;
+
+        match self.colors.has_alpha() {
+            true => self.encode_from(
+                Delta::<4> {
+                    base: bytemuck::cast_slice(base),
+                    new: bytemuck::cast_slice(new),
+                },
+                output,
+            ),
+            false => self.encode_from(
+                Delta::<3> {
+                    base: bytemuck::cast_slice(base),
+                    new: bytemuck::cast_slice(new),
+                },
+                output,
+            ),
+        }
+    }
+
+    /// Decode a QOI image produced by [`Qoi::encode_delta`] and add it to `base_pixels`.\
+    /// Raw RGB or RGBA pixels of the restored image are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::DimensionsMismatch)` is returned if `base_pixels` holds fewer than
+    /// `width * height * channels` bytes of the encoded image.
+    pub fn apply_delta(
+        base_pixels: &[u8],
+        delta_bytes: &[u8],
+        output: &mut [u8],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(delta_bytes)?;
+        let px_len = qoi.width as usize * qoi.height as usize * qoi.colors.channels();
+        let base = base_pixels
+            .get(..px_len)
+            .ok_or(DecodeError::DimensionsMismatch)?;
+
+        let qoi = Self::decode(delta_bytes, output)?;
+        for (v, b) in output[..px_len].iter_mut().zip(base) {
+            *v = v.wrapping_add(*b);
+        }
+
+        Ok(qoi)
+    }
+}
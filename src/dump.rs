@@ -0,0 +1,200 @@
+use super::*;
+use chunk::Op;
+
+/// Signed difference formatted with explicit sign unless it is zero.
+struct Signed(i8);
+
+impl Display for Signed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("0"),
+            v => write!(f, "{:+}", v),
+        }
+    }
+}
+
+/// Number of chunks of each kind met by [`Qoi::dump`].
+#[derive(Default)]
+struct Counts {
+    rgb: usize,
+    rgba: usize,
+    index: usize,
+    diff: usize,
+    luma: usize,
+    run: usize,
+}
+
+impl Qoi {
+    /// Print human-readable description of a QOI image from bytes slice into `w`.\
+    /// Header is printed first, followed by one line per chunk with its offset, opcode, operands,
+    /// resulting pixel value and index of the first pixel it produces.\
+    /// Places where decoding would fail are marked with `error:` lines,
+    /// chunks are printed past invalid end marker as long as they can be parsed.\
+    /// Summary with number of chunks of each kind and number of pixels is printed in the end.
+    ///
+    /// On success this function returns `Ok(())`.\
+    /// On failure this function returns `Err(err)` with `err` describing first error met in encoded image
+    /// or `Err(DecodeError::OutputIsTooSmall)` if `w` fails.
+    pub fn dump<W>(bytes: &[u8], w: &mut W) -> Result<(), DecodeError>
+    where
+        W: fmt::Write + ?Sized,
+    {
+        let mut error = None;
+        Self::dump_into(bytes, w, &mut error).map_err(|_| DecodeError::OutputIsTooSmall)?;
+        match error {
+            None => Ok(()),
+            Some(err) => Err(err),
+        }
+    }
+
+    /// Prints description of the image, storing first error met in `error`.
+    fn dump_into<W>(bytes: &[u8], w: &mut W, error: &mut Option<DecodeError>) -> fmt::Result
+    where
+        W: fmt::Write + ?Sized,
+    {
+        let qoi = match Self::decode_header(bytes) {
+            Ok(qoi) => qoi,
+            Err(err) => {
+                *error = Some(err);
+                return writeln!(w, "0x0000 error: {}", err);
+            }
+        };
+
+        writeln!(
+            w,
+            "0x0000 HEADER {}x{} channels={} colors={:?}",
+            qoi.width,
+            qoi.height,
+            qoi.colors.channels(),
+            qoi.colors
+        )?;
+
+        let has_alpha = qoi.colors.has_alpha();
+        let total = qoi.width as usize * qoi.height as usize;
+        let mut index = [[0u8; 4]; 64];
+        let mut px = [0, 0, 0, 0xff];
+        let mut pos = 0;
+        let mut counts = Counts::default();
+        let mut offset = QOI_HEADER_SIZE;
+        let mut overrun = false;
+
+        loop {
+            let rest = &bytes[offset..];
+
+            if pos >= total {
+                match qoi.decode_padding(bytes, offset) {
+                    Ok(size) => {
+                        writeln!(w, "0x{:04x} END", offset)?;
+                        offset += size;
+                        if offset < bytes.len() {
+                            writeln!(
+                                w,
+                                "0x{:04x} {} bytes after end marker",
+                                offset,
+                                bytes.len() - offset
+                            )?;
+                        }
+                        break;
+                    }
+                    Err(err) if !overrun => {
+                        overrun = true;
+                        error.get_or_insert(err);
+                        writeln!(w, "0x{:04x} error: {}", offset, err)?;
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            let (op, size) = match Op::parse(rest) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    let err = err.at(offset, pos).for_image(bytes, total);
+                    if pos < total {
+                        error.get_or_insert(err);
+                        writeln!(w, "0x{:04x} error: {}", offset, err)?;
+                    }
+                    break;
+                }
+            };
+
+            write!(w, "0x{:04x} ", offset)?;
+            match op {
+                Op::Rgb { r, g, b } => {
+                    counts.rgb += 1;
+                    px = [r, g, b, px[3]];
+                    write!(w, "RGB r={} g={} b={}", r, g, b)?;
+                }
+                Op::Rgba { r, g, b, a } => {
+                    counts.rgba += 1;
+                    px = [r, g, b, a];
+                    write!(w, "RGBA r={} g={} b={} a={}", r, g, b, a)?;
+                }
+                Op::Index(i) => {
+                    counts.index += 1;
+                    px = index[i as usize];
+                    write!(w, "INDEX {}", i)?;
+                }
+                Op::Diff { dr, dg, db } => {
+                    counts.diff += 1;
+                    px.add_rgb(dr as u8, dg as u8, db as u8);
+                    write!(
+                        w,
+                        "DIFF dr={} dg={} db={}",
+                        Signed(dr),
+                        Signed(dg),
+                        Signed(db)
+                    )?;
+                }
+                Op::Luma { dg, dr_dg, db_dg } => {
+                    counts.luma += 1;
+                    px.add_rgb(
+                        dg.wrapping_add(dr_dg) as u8,
+                        dg as u8,
+                        dg.wrapping_add(db_dg) as u8,
+                    );
+                    write!(
+                        w,
+                        "LUMA dg={} dr={} db={}",
+                        Signed(dg),
+                        Signed(dr_dg),
+                        Signed(db_dg)
+                    )?;
+                }
+                Op::Run(n) => {
+                    counts.run += 1;
+                    write!(w, "RUN {}", n)?;
+                }
+            }
+
+            if !has_alpha {
+                // Pixels of images without alpha channel are always opaque.
+                px[3] = 0xff;
+            }
+            index[px.hash() as usize] = px;
+
+            match has_alpha {
+                true => write!(w, " -> ({}, {}, {}, {})", px[0], px[1], px[2], px[3])?,
+                false => write!(w, " -> ({}, {}, {})", px[0], px[1], px[2])?,
+            }
+            write!(w, " px={}", pos)?;
+
+            let end = pos + op.pixels();
+            if pos >= total {
+                w.write_str(" beyond image")?;
+            } else if end > total {
+                write!(w, " run exceeds image by {}", end - total)?;
+            }
+            writeln!(w)?;
+
+            pos = end;
+            offset += size;
+        }
+
+        writeln!(
+            w,
+            "chunks: RGB={} RGBA={} INDEX={} DIFF={} LUMA={} RUN={}",
+            counts.rgb, counts.rgba, counts.index, counts.diff, counts.luma, counts.run
+        )?;
+        writeln!(w, "pixels: {} of {}", pos, total)
+    }
+}
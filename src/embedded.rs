@@ -0,0 +1,116 @@
+use super::*;
+use embedded_io::{Read, Write};
+
+/// Reads into `buf` until at least one byte is read.\
+/// Returns `Err(IoDecodeError::Decode(DecodeError::NotEnoughData { .. }))` describing position of `decoder` if reader reached its end.
+#[inline]
+fn read_some<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+    decoder: &Decoder,
+) -> Result<usize, IoDecodeError<R::Error>> {
+    match reader.read(buf) {
+        Ok(0) => Err(decoder.truncated().into()),
+        Ok(n) => Ok(n),
+        Err(err) => Err(IoDecodeError::Io(err)),
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from [`embedded_io::Read`].\
+    /// Bytes are read into caller provided `scratch` buffer of any non-zero size.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// Reading stops right after the end marker, so following data is left in the reader.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scratch` is empty.
+    pub fn decode_from_embedded_reader<R: Read>(
+        reader: &mut R,
+        scratch: &mut [u8],
+        output: &mut [u8],
+    ) -> Result<Self, IoDecodeError<R::Error>> {
+        assert!(!scratch.is_empty());
+
+        let mut decoder = Decoder::new();
+        let mut header = [0; QOI_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < QOI_HEADER_SIZE {
+            let n = read_some(reader, &mut header[filled..], &decoder)?;
+            decoder.push(&header[filled..filled + n], &mut [])?;
+            filled += n;
+        }
+
+        let qoi = Self::decode_header(&header)?;
+
+        let px_len = qoi.width as usize * qoi.height as usize * qoi.colors.channels();
+        let mut output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall.into()),
+            Some(output) => output,
+        };
+
+        while !decoder.is_finished() {
+            let len = decoder.bytes_needed().min(scratch.len());
+            let n = read_some(reader, &mut scratch[..len], &decoder)?;
+            let progress = decoder.push(&scratch[..n], output)?;
+            debug_assert_eq!(progress.consumed, n);
+            output = &mut output[progress.pixels * qoi.colors.channels()..];
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image written into [`embedded_io::Write`].\
+    /// Encoded bytes are collected in caller provided `scratch` buffer before writing.
+    /// `scratch` must fit header, end marker and single pixel.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_to_embedded_writer<W: Write>(
+        &self,
+        pixels: &[u8],
+        scratch: &mut [u8],
+        writer: &mut W,
+    ) -> Result<usize, IoEncodeError<W::Error>> {
+        let channels = self.colors.channels();
+        let px_len = self.input_len(channels)?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                }
+                .into())
+            }
+            Some(pixels) => pixels,
+        };
+
+        let mut encoder = Encoder::new(*self);
+        let mut total = 0;
+        let mut rest = pixels;
+
+        while !rest.is_empty() {
+            let len = (encoder.pixels_fitting(scratch.len()) * channels).min(rest.len());
+            if len == 0 {
+                return Err(EncodeError::OutputIsTooSmall {
+                    required: QOI_HEADER_SIZE + 1 + (channels + 1) + 7,
+                }
+                .into());
+            }
+            let n = encoder.push_pixels(&rest[..len], scratch)?;
+            writer.write_all(&scratch[..n]).map_err(IoEncodeError::Io)?;
+            total += n;
+            rest = &rest[len..];
+        }
+
+        let n = encoder.finish(scratch)?;
+        writer.write_all(&scratch[..n]).map_err(IoEncodeError::Io)?;
+        total += n;
+
+        Ok(total)
+    }
+}
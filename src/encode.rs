@@ -6,101 +6,1754 @@ use alloc::{vec, vec::Vec};
 /// Errors that may occur during image encoding.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum EncodeError {
-    /// Pixels buffer is too small for the image.
-    NotEnoughPixelData,
+    /// Pixels buffer is too small for the image.\
+    /// `expected` and `got` are lengths of required and provided input in its elements.
+    InputSizeMismatch { expected: usize, got: usize },
 
-    /// Output buffer is too small to fit encoded image.
-    OutputIsTooSmall,
+    /// Output buffer is too small to fit encoded image.\
+    /// `required` is size of output buffer that fits encoded bytes in the worst case.
+    OutputIsTooSmall { required: usize },
+
+    /// Image has too many pixels to be addressed on this platform.
+    TooManyPixels,
+
+    /// Input row stride is smaller than row of pixels.
+    InvalidStride,
+
+    /// Requested region does not fit into the image.
+    InvalidRegion,
+
+    /// Encoding options cannot be combined with each other.
+    InvalidOptions,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::InputSizeMismatch { expected, got } => write!(
+                f,
+                "Pixels buffer is too small for image, expected {} elements, got {}",
+                expected, got
+            ),
+            EncodeError::OutputIsTooSmall { required } => write!(
+                f,
+                "Output buffer is too small to fit encoded image, {} bytes required",
+                required
+            ),
+            EncodeError::TooManyPixels => {
+                f.write_str("Image has too many pixels to be addressed on this platform")
+            }
+            EncodeError::InvalidStride => {
+                f.write_str("Input row stride is smaller than row of pixels")
+            }
+            EncodeError::InvalidRegion => {
+                f.write_str("Requested region does not fit into the image")
+            }
+            EncodeError::InvalidOptions => {
+                f.write_str("Encoding options cannot be combined with each other")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+/// Source of pixels for encoding.\
+/// Encoding loop is generic over this trait,
+/// so each input flavor gets its own specialized loop.
+pub(crate) trait Input<const N: usize> {
+    /// Takes the next pixel.\
+    /// Returns `None` if no more pixels left.
+    fn next(&mut self) -> Option<[u8; N]>;
+
+    /// Returns `true` if no more pixels left.
+    fn is_empty(&self) -> bool;
+
+    /// Takes up to `max` following pixels equal to `px`.\
+    /// If `keep_last` is `true` the last pixel is never taken.\
+    /// Returns number of pixels taken.
+    #[inline]
+    fn skip_run(&mut self, px: [u8; N], max: usize, keep_last: bool) -> usize {
+        let _ = (px, max, keep_last);
+        0
+    }
+}
+
+impl<I, const N: usize> Input<N> for &mut I
+where
+    I: Input<N>,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        (**self).is_empty()
+    }
+
+    #[inline]
+    fn skip_run(&mut self, px: [u8; N], max: usize, keep_last: bool) -> usize {
+        (**self).skip_run(px, max, keep_last)
+    }
+}
+
+impl<const N: usize, const M: usize> Input<N> for &[[u8; M]]
+where
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let (px, tail) = self.split_first()?;
+        *self = tail;
+        Some(convert(px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[[u8; M]]>::is_empty(self)
+    }
+
+    #[inline]
+    fn skip_run(&mut self, px: [u8; N], max: usize, keep_last: bool) -> usize {
+        if N != M {
+            // Pixels are converted, so equal `px` does not imply equal input.
+            return 0;
+        }
+
+        let max = max.min(self.len().saturating_sub(keep_last as usize));
+        let len = runs::run_len(self, convert(&px), max);
+        *self = &self[len..];
+        len
+    }
+}
+
+/// Input that converts pixels with reordered channels
+/// and optionally premultiplied alpha into RGBA pixels.
+pub(crate) struct Transformed<I> {
+    pub input: I,
+    pub order: ChannelOrder,
+    pub premultiplied: bool,
+}
+
+impl<I, const N: usize> Input<N> for Transformed<I>
+where
+    I: Input<4>,
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let px = self.input.next()?;
+        let px = match self.order {
+            ChannelOrder::Rgba => px,
+            ChannelOrder::Bgra => [px[2], px[1], px[0], px[3]],
+            ChannelOrder::Argb => [px[1], px[2], px[3], px[0]],
+            ChannelOrder::Abgr => [px[3], px[2], px[1], px[0]],
+        };
+        match self.premultiplied {
+            true => Some(convert(&unpremultiply(px))),
+            false => Some(convert(&px)),
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+}
+
+/// State of the encoder carried between parts of the image
+/// encoded with [`Qoi::encode_with_state`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EncoderState {
+    index: [[u8; 4]; 64],
+    px_prev: [u8; 4],
+    run: usize,
+    pos: usize,
+    finished: bool,
+}
+
+impl Default for EncoderState {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EncoderState {
+    /// Returns initial state of the encoder.\
+    /// Previous pixel is opaque black and index is zeroed.
+    #[inline]
+    pub const fn new() -> Self {
+        EncoderState {
+            index: [[0; 4]; 64],
+            px_prev: [0, 0, 0, 0xff],
+            run: 0,
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns number of pixels encoded so far.
+    #[inline]
+    pub const fn pixels_encoded(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns `true` if all pixels of the image are encoded and end marker is written.
+    #[inline]
+    pub const fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Destination for encoded bytes.\
+/// Encoding loop is generic over this trait,
+/// so encoding and counting encoded bytes share the same loop.
+pub(crate) trait Sink {
+    /// Returns `true` if there is room for chunks of at least one more pixel.
+    fn has_room(&self) -> bool;
+
+    /// Writes bytes of single chunk.
+    fn put<const K: usize>(&mut self, bytes: [u8; K]);
+}
+
+/// Sink that writes encoded bytes into slice.
+pub(crate) struct Slice<'a> {
+    rest: &'a mut [u8],
+    pub written: usize,
+}
+
+impl<'a> Slice<'a> {
+    #[inline]
+    pub(crate) fn new(output: &'a mut [u8]) -> Self {
+        Slice {
+            rest: output,
+            written: 0,
+        }
+    }
+}
+
+impl Sink for Slice<'_> {
+    #[inline]
+    fn has_room(&self) -> bool {
+        self.rest.len() > 7
+    }
+
+    #[inline]
+    fn put<const K: usize>(&mut self, bytes: [u8; K]) {
+        let (head, tail) = core::mem::take(&mut self.rest).split_at_mut(K);
+        head.copy_from_slice(&bytes);
+        self.rest = tail;
+        self.written += K;
+    }
+}
+
+/// Sink that only counts encoded bytes.
+pub(crate) struct Count(pub usize);
+
+impl Sink for Count {
+    #[inline]
+    fn has_room(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn put<const K: usize>(&mut self, _bytes: [u8; K]) {
+        self.0 += K;
+    }
+}
+
+/// Statistics of chunks produced by encoder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EncodeStats {
+    /// Number of `QOI_OP_RGB` chunks.
+    pub op_rgb: usize,
+
+    /// Number of `QOI_OP_RGBA` chunks.
+    pub op_rgba: usize,
+
+    /// Number of `QOI_OP_INDEX` chunks.
+    pub op_index: usize,
+
+    /// Number of `QOI_OP_DIFF` chunks.
+    pub op_diff: usize,
+
+    /// Number of `QOI_OP_LUMA` chunks.
+    pub op_luma: usize,
+
+    /// Number of `QOI_OP_RUN` chunks.
+    pub op_run: usize,
+
+    /// Number of pixels encoded with `QOI_OP_RUN` chunks.
+    pub run_pixels: usize,
+
+    /// Number of bytes taken by chunks of each kind
+    /// in order `QOI_OP_RGB`, `QOI_OP_RGBA`, `QOI_OP_INDEX`, `QOI_OP_DIFF`, `QOI_OP_LUMA`, `QOI_OP_RUN`.
+    pub bytes_per_op: [usize; 6],
+}
+
+impl EncodeStats {
+    /// Records single chunk.
+    #[inline]
+    pub(crate) fn record(&mut self, chunk: &[u8]) {
+        let (count, kind) = match (chunk.len(), chunk[0]) {
+            (5, _) => (&mut self.op_rgba, 1),
+            (4, _) => (&mut self.op_rgb, 0),
+            (2, _) => (&mut self.op_luma, 4),
+            (_, 0x00..=0x3f) => (&mut self.op_index, 2),
+            (_, 0x40..=0x7f) => (&mut self.op_diff, 3),
+            (_, b1) => {
+                self.run_pixels += (b1 & 0x3f) as usize + 1;
+                (&mut self.op_run, 5)
+            }
+        };
+        *count += 1;
+        self.bytes_per_op[kind] += chunk.len();
+    }
+}
+
+/// Size of encoded image estimated by [`Qoi::estimate_encoded_size`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SizeEstimate {
+    /// Estimated size of the encoded image.
+    pub size: usize,
+
+    /// Size of the encoded image if unsampled rows are encoded as compactly as the best sampled row.
+    pub low: usize,
+
+    /// Size of the encoded image if unsampled rows are encoded as poorly as the worst sampled row.
+    pub high: usize,
+}
+
+impl SizeEstimate {
+    /// Returns `true` if all rows were sampled and estimated size is exact.
+    #[inline]
+    pub fn is_exact(&self) -> bool {
+        self.low == self.high
+    }
+}
+
+/// Sink that records statistics of chunks written into inner sink.
+pub(crate) struct Stats<'a, S> {
+    sink: S,
+    stats: &'a mut EncodeStats,
+}
+
+impl<S> Sink for Stats<'_, S>
+where
+    S: Sink,
+{
+    #[inline]
+    fn has_room(&self) -> bool {
+        self.sink.has_room()
+    }
+
+    #[inline]
+    fn put<const K: usize>(&mut self, bytes: [u8; K]) {
+        self.stats.record(&bytes);
+        self.sink.put(bytes);
+    }
+}
+
+/// Input from rows of raw pixels.\
+/// Only first `width` pixels of each row are used.\
+/// Rows are taken until `height` rows are read or a short row is found.
+pub(crate) struct Rows<'a, R, const M: usize> {
+    rows: R,
+    row: &'a [[u8; M]],
+    width: usize,
+    left: usize,
+    short: bool,
+    got: usize,
 }
 
-impl Display for EncodeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            EncodeError::NotEnoughPixelData => f.write_str("Pixels buffer is too small for image"),
-            EncodeError::OutputIsTooSmall => {
-                f.write_str("Output buffer is too small to fit encoded image")
+impl<'a, R, const M: usize> Rows<'a, R, M>
+where
+    R: Iterator<Item = &'a [u8]>,
+{
+    #[inline]
+    fn new(rows: R, width: usize, height: usize) -> Self {
+        let mut input = Rows {
+            rows,
+            row: &[],
+            width,
+            left: if width == 0 { 0 } else { height },
+            short: false,
+            got: 0,
+        };
+        input.next_row();
+        input
+    }
+
+    #[inline]
+    fn next_row(&mut self) {
+        if self.left == 0 {
+            return;
+        }
+        match self.rows.next() {
+            Some(row) if row.len() >= self.width * M => {
+                self.row = bytemuck::cast_slice(&row[..self.width * M]);
+                self.left -= 1;
+                self.got += self.width * M;
+            }
+            Some(row) => {
+                cold();
+                self.short = true;
+                self.left = 0;
+                self.got += row.len();
+            }
+            None => {
+                cold();
+                self.short = true;
+                self.left = 0;
+            }
+        }
+    }
+}
+
+impl<'a, R, const N: usize, const M: usize> Input<N> for Rows<'a, R, M>
+where
+    R: Iterator<Item = &'a [u8]>,
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let (px, tail) = self.row.split_first()?;
+        self.row = tail;
+        if tail.is_empty() {
+            self.next_row();
+        }
+        Some(convert(px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.row.is_empty()
+    }
+}
+
+/// Input that replaces pixels with cheaper to encode approximations.\
+/// Each color channel of approximation differs from original by at most `tolerance`.\
+/// Approximations are computed relative to the previous produced pixel,
+/// so the error does not accumulate.
+pub(crate) struct Lossy<I, const N: usize> {
+    pub input: I,
+    pub prev: [u8; N],
+    pub tolerance: u8,
+}
+
+impl<I, const N: usize> Lossy<I, N>
+where
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn approximate(&self, px: [u8; N]) -> [u8; N] {
+        let prev = self.prev;
+        if N == 4 && px.a() != prev.a() {
+            return px;
+        }
+
+        let t = self.tolerance;
+        let near = |c: [u8; 3]| c.iter().zip(px.rgb()).all(|(c, p)| c.abs_diff(p) <= t);
+
+        let d = |p: u8, q: u8| p as i16 - q as i16;
+        let [dr, dg, db] = [
+            d(px.r(), prev.r()),
+            d(px.g(), prev.g()),
+            d(px.b(), prev.b()),
+        ];
+
+        let mut out = prev;
+        if near(prev.rgb()) {
+            return out;
+        }
+
+        let diff = |d: i16| d.clamp(-2, 1) as u8;
+        let c = [
+            prev.r().wrapping_add(diff(dr)),
+            prev.g().wrapping_add(diff(dg)),
+            prev.b().wrapping_add(diff(db)),
+        ];
+        if near(c) {
+            out.set_rgb(c[0], c[1], c[2]);
+            return out;
+        }
+
+        let vg = dg.clamp(-32, 31);
+        let c = [
+            prev.r().wrapping_add(((dr - vg).clamp(-8, 7) + vg) as u8),
+            prev.g().wrapping_add(vg as u8),
+            prev.b().wrapping_add(((db - vg).clamp(-8, 7) + vg) as u8),
+        ];
+        if near(c) {
+            out.set_rgb(c[0], c[1], c[2]);
+            return out;
+        }
+
+        px
+    }
+}
+
+impl<I, const N: usize, const M: usize> Input<N> for Lossy<I, M>
+where
+    I: Input<M>,
+    [u8; N]: Pixel,
+    [u8; M]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let px = self.input.next()?;
+        let px = self.approximate(px);
+        self.prev = px;
+        Some(convert(&px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+}
+
+/// Input that makes pixels matching key color transparent.
+pub(crate) struct ColorKeyed<I> {
+    pub input: I,
+    pub key: [u8; 3],
+    pub keep_rgb: bool,
+}
+
+impl<I, const N: usize> Input<N> for ColorKeyed<I>
+where
+    I: Input<4>,
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let mut px = self.input.next()?;
+        if px.rgb() == self.key {
+            match self.keep_rgb {
+                true => px[3] = 0,
+                false => px = [0; 4],
+            }
+        }
+        Some(convert(&px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+}
+
+/// Input from slice of RGB pixels and separate alpha plane.
+pub(crate) struct WithAlpha<'a> {
+    pub rgb: &'a [[u8; 3]],
+    pub alpha: &'a [u8],
+}
+
+impl<'a, const N: usize> Input<N> for WithAlpha<'a>
+where
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let ([r, g, b], rgb) = self.rgb.split_first()?;
+        let (a, alpha) = self.alpha.split_first()?;
+        self.rgb = rgb;
+        self.alpha = alpha;
+        Some(convert(&[*r, *g, *b, *a]))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.rgb.is_empty()
+    }
+}
+
+/// Input from slice of grayscale values.\
+/// Each value is used for all color channels of opaque pixel.
+pub(crate) struct Luma<'a>(pub &'a [u8]);
+
+impl<'a, const N: usize> Input<N> for Luma<'a>
+where
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let (v, tail) = self.0.split_first()?;
+        self.0 = tail;
+        let mut px = <[u8; N]>::new_opaque();
+        px.set_rgb(*v, *v, *v);
+        Some(px)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Divides color channels of the pixel by alpha.\
+/// Each color channel is computed as `min((c * 255 + a / 2) / a, 255)`.\
+/// Fully transparent pixels become `[0, 0, 0, 0]`.
+#[inline]
+fn unpremultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    match a {
+        0 => [0; 4],
+        255 => [r, g, b, a],
+        _ => {
+            let a16 = a as u16;
+            let div = |c: u8| ((c as u16 * 255 + a16 / 2) / a16).min(255) as u8;
+            [div(r), div(g), div(b), a]
+        }
+    }
+}
+
+/// Input from slice of pixels packed into `u32` values.\
+/// `ARGB` selects between `0xAARRGGBB` and `0xRRGGBBAA` packing.
+pub(crate) struct Packed<'a, const ARGB: bool>(pub &'a [u32]);
+
+impl<'a, const N: usize, const ARGB: bool> Input<N> for Packed<'a, ARGB>
+where
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let (px, tail) = self.0.split_first()?;
+        self.0 = tail;
+        let px = match ARGB {
+            true => px.rotate_left(8).to_be_bytes(),
+            false => px.to_be_bytes(),
+        };
+        Some(convert(&px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Input from separate planes for each channel.
+pub(crate) struct Planar<'a> {
+    pub r: &'a [u8],
+    pub g: &'a [u8],
+    pub b: &'a [u8],
+    pub a: Option<&'a [u8]>,
+    pub pos: usize,
+}
+
+impl<'a, const N: usize> Input<N> for Planar<'a>
+where
+    [u8; N]: Pixel,
+{
+    #[inline]
+    fn next(&mut self) -> Option<[u8; N]> {
+        let pos = self.pos;
+        let r = *self.r.get(pos)?;
+        let px = [r, self.g[pos], self.b[pos], self.a.map_or(255, |a| a[pos])];
+        self.pos += 1;
+        Some(convert(&px))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pos == self.r.len()
+    }
+}
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(())`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new())
+    }
+
+    /// Encode raw pixels described by `options` into a QOI image.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InvalidOptions)` is returned before encoding if options cannot be combined.
+    #[inline]
+    pub fn encode_with(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError> {
+        let encoded = match options.channels(self.colors) {
+            None => Err(EncodeError::InvalidOptions),
+            Some(3) => self.encode_with_as::<3>(pixels, output, options),
+            Some(_) => self.encode_with_as::<4>(pixels, output, options),
+        };
+
+        #[cfg(feature = "metadata")]
+        if let Some(profile) = options.icc_profile {
+            return Self::attach_icc_profile(encoded, output, profile);
+        }
+        encoded
+    }
+
+    /// Sets color space byte of encoded image to `0` and appends metadata trailer with ICC profile after it.
+    #[cfg(feature = "metadata")]
+    #[inline]
+    fn attach_icc_profile(
+        encoded: Result<usize, EncodeError>,
+        output: &mut [u8],
+        profile: &[u8],
+    ) -> Result<usize, EncodeError> {
+        let entries = [(metadata::ICC_PROFILE_KEY, profile)];
+        let trailer = metadata::trailer_size(&entries);
+
+        let size = match encoded {
+            Err(EncodeError::OutputIsTooSmall { required }) => {
+                return Err(EncodeError::OutputIsTooSmall {
+                    required: required.saturating_add(trailer),
+                })
+            }
+            encoded => encoded?,
+        };
+
+        if output.len() - size < trailer {
+            return Err(EncodeError::OutputIsTooSmall {
+                required: size + trailer,
+            });
+        }
+
+        output[13] = 0;
+        Ok(size + metadata::encode_trailer(&entries, &mut output[size..]))
+    }
+
+    /// Encode raw pixels with `M` channels described by `options` into a QOI image.
+    #[inline]
+    fn encode_with_as<const M: usize>(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        if options.stride.is_none() && !options.flip {
+            let px_len = self.input_len(M)?;
+
+            let pixels = match pixels.get(..px_len) {
+                None => {
+                    cold();
+                    return Err(EncodeError::InputSizeMismatch {
+                        expected: px_len,
+                        got: pixels.len(),
+                    });
+                }
+                Some(pixels) => pixels,
+            };
+
+            return self.encode_transformed(
+                bytemuck::cast_slice::<_, [u8; M]>(pixels),
+                output,
+                options,
+            );
+        }
+
+        if self.width == 0 || self.height == 0 {
+            return self.encode_transformed(&[[0; M]; 0][..], output, options);
+        }
+
+        let row = match (self.width as usize).checked_mul(M) {
+            None => return Err(EncodeError::TooManyPixels),
+            Some(row) => row,
+        };
+
+        let stride = options.stride.unwrap_or(row);
+        if stride < row {
+            return Err(EncodeError::InvalidStride);
+        }
+
+        let size = match stride
+            .checked_mul(self.height as usize - 1)
+            .and_then(|size| size.checked_add(row))
+        {
+            None => return Err(EncodeError::TooManyPixels),
+            Some(size) => size,
+        };
+
+        let pixels = match pixels.get(..size) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: size,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => pixels,
+        };
+
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        match options.flip {
+            false => self.encode_transformed(
+                Rows::<_, M>::new(pixels.chunks(stride), width, height),
+                output,
+                options,
+            ),
+            true => self.encode_transformed(
+                Rows::<_, M>::new(pixels.chunks(stride).rev(), width, height),
+                output,
+                options,
+            ),
+        }
+    }
+
+    /// Encode pixels from arbitrary input applying pixel transformations of `options`.
+    #[inline]
+    fn encode_transformed<I>(
+        &self,
+        input: I,
+        output: &mut [u8],
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError>
+    where
+        I: Input<3> + Input<4>,
+    {
+        let order = options.order.unwrap_or(ChannelOrder::Rgba);
+        match (order, options.premultiplied) {
+            (ChannelOrder::Rgba, false) => self.encode_reference_as(input, output, options),
+            (order, premultiplied) => self.encode_reference_as(
+                Transformed {
+                    input,
+                    order,
+                    premultiplied,
+                },
+                output,
+                options,
+            ),
+        }
+    }
+
+    /// Encode pixels from arbitrary input choosing reference compatible chunks if requested by `options`.
+    #[inline]
+    fn encode_reference_as<I>(
+        &self,
+        input: I,
+        output: &mut [u8],
+        options: &EncodeOptions,
+    ) -> Result<usize, EncodeError>
+    where
+        I: Input<3> + Input<4>,
+    {
+        match options.reference_compatible {
+            true => self.encode_from_as::<true, _>(input, output),
+            false => self.encode_from_as::<false, _>(input, output),
+        }
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image producing exactly the same bytes as reference encoder.\
+    /// Default encoder may choose different chunks of the same size in some cases,
+    /// this function can be used when encoded bytes are compared or hashed.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_reference_compatible(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(
+            pixels,
+            output,
+            &EncodeOptions::new().reference_compatible(true),
+        )
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// If image has alpha channel but all pixels are fully opaque,
+    /// alpha channel is dropped and image is encoded with three channels.\
+    /// Pixels are scanned for non-opaque alpha before encoding.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok((qoi, size))` with `qoi` describing encoded image and `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_optimized(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<(Self, usize), EncodeError> {
+        if !self.colors.has_alpha() {
+            return Ok((*self, self.encode(pixels, output)?));
+        }
+
+        let px_len = self.input_len(4)?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+        };
+
+        let qoi = match pixels.iter().all(|px| px[3] == 255) {
+            true => Qoi {
+                colors: self.colors.without_alpha(),
+                ..*self
+            },
+            false => *self,
+        };
+
+        Ok((qoi, qoi.encode_from(pixels, output)?))
+    }
+
+    /// Encode part of raw RGB or RGBA pixels of an image into a QOI image.\
+    /// Encoder state is taken from and updated in `state`,
+    /// so encoding consecutive parts of the image produces the same bytes as encoding whole image at once.\
+    /// Header is written before the first part with pixels and end marker is written after the last pixel of the image.\
+    /// Pixels beyond the end of the image are ignored.\
+    /// Encoded bytes of this part are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of bytes written.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Content of `state` is unspecified after failure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` length is not multiple of channels number.
+    #[inline]
+    pub fn encode_with_state(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        state: &mut EncoderState,
+    ) -> Result<usize, EncodeError> {
+        let channels = self.colors.channels();
+        assert_eq!(pixels.len() % channels, 0);
+
+        if state.finished {
+            return Ok(0);
+        }
+
+        let total = self.input_len(1)?;
+        let count = (pixels.len() / channels).min(total - state.pos);
+        let pixels = &pixels[..count * channels];
+        let last = state.pos + count == total;
+        if count == 0 && !last {
+            return Ok(0);
+        }
+
+        let required = (state.pos == 0) as usize * QOI_HEADER_SIZE
+            + (state.run > 0) as usize
+            + count * (channels + 1)
+            + if last { QOI_PADDING } else { 7 };
+
+        let mut written = 0;
+        if state.pos == 0 {
+            if output.len() < QOI_HEADER_SIZE {
+                return Err(EncodeError::OutputIsTooSmall { required });
+            }
+            self.write_header(output);
+            written = QOI_HEADER_SIZE;
+        }
+
+        let mut sink = Slice::new(&mut output[written..]);
+        match self.colors.has_alpha() {
+            true => Self::encode_range_into::<4, false, _, _>(
+                &mut state.index,
+                &mut state.px_prev,
+                &mut state.run,
+                &mut bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                &mut sink,
+                last,
+                required,
+            )?,
+            false => {
+                let mut px_prev = convert(&state.px_prev);
+                Self::encode_range_into::<3, false, _, _>(
+                    &mut state.index,
+                    &mut px_prev,
+                    &mut state.run,
+                    &mut bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                    &mut sink,
+                    last,
+                    required,
+                )?;
+                state.px_prev = convert(&px_prev);
+            }
+        }
+        written += sink.written;
+        state.pos += count;
+
+        if last {
+            written += Self::encode_padding(&mut output[written..], required)?;
+            state.finished = true;
+        }
+
+        Ok(written)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image with loss of precision.\
+    /// Color channels of pixels are nudged towards values that take less bytes to encode.\
+    /// Each color channel differs from original value by at most `(100 - quality) / 8`,
+    /// alpha channel is always preserved.\
+    /// The bound changes every 8 quality steps: `quality` from `93` to `100` and above produces lossless image,
+    /// `85..=92` allows error of `1`, `77..=84` allows `2` and so on up to `12` for `quality` below `5`.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_lossy(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        quality: u8,
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.input_len(self.colors.channels())?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => pixels,
+        };
+
+        let tolerance = (100 - quality.min(100)) / 8;
+        if tolerance == 0 {
+            return self.encode(pixels, output);
+        }
+
+        match self.colors.has_alpha() {
+            true => self.encode_from(
+                Lossy::<_, 4> {
+                    input: bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                    prev: Pixel::new_opaque(),
+                    tolerance,
+                },
+                output,
+            ),
+            false => self.encode_from(
+                Lossy::<_, 3> {
+                    input: bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                    prev: Pixel::new_opaque(),
+                    tolerance,
+                },
+                output,
+            ),
+        }
+    }
+
+    /// Encode raw pixels with specified layout into a QOI image, making pixels of `key` color transparent.\
+    /// Pixels which color channels are equal to `key` get alpha channel of `0`.
+    /// Their color channels are kept if `keep_rgb` is `true` and set to `0` otherwise.\
+    /// Image should have alpha channel, otherwise `key` has no effect.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_color_keyed(
+        &self,
+        pixels: &[u8],
+        layout: PixelLayout,
+        key: [u8; 3],
+        keep_rgb: bool,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.input_len(layout.channels())?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => pixels,
+        };
+
+        match layout {
+            PixelLayout::Rgb => self.encode_from(
+                ColorKeyed {
+                    input: bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                    key,
+                    keep_rgb,
+                },
+                output,
+            ),
+            PixelLayout::Rgba => self.encode_from(
+                ColorKeyed {
+                    input: bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                    key,
+                    keep_rgb,
+                },
+                output,
+            ),
+        }
+    }
+
+    /// Encode raw RGB pixels combined with separate alpha plane into a QOI image.\
+    /// `rgb` must hold at least `width * height * 3` bytes and `alpha` at least `width * height` bytes.\
+    /// Alpha plane is ignored if image has no alpha channel.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_rgb_plus_alpha(
+        &self,
+        rgb: &[u8],
+        alpha: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.output_pixels()?;
+
+        let rgb = Self::take_input(rgb, px_len * 3)?;
+        let alpha = Self::take_input(alpha, px_len)?;
+
+        self.encode_from(
+            WithAlpha {
+                rgb: bytemuck::cast_slice(rgb),
+                alpha,
+            },
+            output,
+        )
+    }
+
+    /// Encode grayscale pixels with one byte per pixel into a QOI image.\
+    /// Each value is used for all color channels, alpha channel is set to `255`.\
+    /// `pixels` must hold at least `width * height` bytes.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_luma(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let px_len = self.output_pixels()?;
+
+        match pixels.get(..px_len) {
+            None => Err(EncodeError::InputSizeMismatch {
+                expected: px_len,
+                got: pixels.len(),
+            }),
+            Some(pixels) => self.encode_from(Luma(pixels), output),
+        }
+    }
+
+    /// Encode raw RGBA pixels with color channels premultiplied by alpha into a QOI image.\
+    /// Color channels are divided by alpha as pixels are read, so encoded image has straight alpha.\
+    /// Each color channel is computed as `min((c * 255 + a / 2) / a, 255)`,
+    /// fully transparent pixels are encoded as `[0, 0, 0, 0]`.\
+    /// Images without alpha channel are encoded unchanged.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_unpremultiplied(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new().premultiplied(true))
+    }
+
+    /// Encode raw pixels with specified layout into a QOI image regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_with_layout(
+        &self,
+        pixels: &[u8],
+        layout: PixelLayout,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new().layout(layout))
+    }
+
+    /// Encode raw pixels with four channels in specified order into a QOI image.\
+    /// Alpha channel is ignored if image has no alpha channel,
+    /// so BGRX pixels can be encoded with [`ChannelOrder::Bgra`] and three channels color space.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_with_order(
+        &self,
+        pixels: &[u8],
+        order: ChannelOrder,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new().channel_order(order))
+    }
+
+    /// Encode pixels packed into `u32` values according to `layout` into a QOI image.\
+    /// Alpha channel is ignored if image has no alpha channel.\
+    /// `pixels` must hold at least `width * height` values.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_u32(
+        &self,
+        pixels: &[u32],
+        layout: U32Layout,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.output_pixels()?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
             }
+            Some(pixels) => pixels,
+        };
+
+        match layout {
+            U32Layout::Argb8888 => self.encode_from(Packed::<true>(pixels), output),
+            U32Layout::Rgba8888 => self.encode_from(Packed::<false>(pixels), output),
         }
     }
-}
 
-#[cfg(feature = "std")]
-impl std::error::Error for EncodeError {}
+    /// Encode pixels with each channel stored in separate plane into a QOI image.\
+    /// Each plane must hold at least `width * height` bytes.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// If `a` is `None` alpha channel is set to `255`.\
+    /// If image has no alpha channel, `a` plane is ignored.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_planar(
+        &self,
+        r: &[u8],
+        g: &[u8],
+        b: &[u8],
+        a: Option<&[u8]>,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let px_len = self.output_pixels()?;
 
-impl Qoi {
-    /// Encode raw RGB or RGBA pixels into a QOI image.\
+        let r = Self::take_input(r, px_len)?;
+        let g = Self::take_input(g, px_len)?;
+        let b = Self::take_input(b, px_len)?;
+        let a = match a {
+            None => None,
+            Some(a) => Some(Self::take_input(a, px_len)?),
+        };
+
+        self.encode_from(Planar { r, g, b, a, pos: 0 }, output)
+    }
+
+    /// Encode RGB or RGBA pixels into a QOI image regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.\
+    /// `pixels` must hold at least `width * height` pixels.\
     /// Encoded image is written into `output` slice.
     ///
-    /// On success this function returns `Ok(())`.\
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
     /// On failure this function returns `Err(err)` with `err` describing cause of the error.
     #[inline]
-    pub fn encode(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
-        if output.len() <= QOI_HEADER_SIZE {
-            return Err(EncodeError::OutputIsTooSmall);
+    pub fn encode_pixels<const M: usize>(
+        &self,
+        pixels: &[[u8; M]],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let px_len = self.output_pixels()?;
+
+        match pixels.get(..px_len) {
+            None => Err(EncodeError::InputSizeMismatch {
+                expected: px_len,
+                got: pixels.len(),
+            }),
+            Some(pixels) => self.encode_from(pixels, output),
+        }
+    }
+
+    /// Encode raw RGB or RGBA pixels with rows placed `stride` bytes apart into a QOI image.\
+    /// Bytes between rows are ignored.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// `stride` must be not less than `width * channels`,
+    /// and `pixels` must hold at least `stride * (height - 1) + width * channels` bytes.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_with_stride(
+        &self,
+        pixels: &[u8],
+        stride: usize,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new().stride(stride))
+    }
+
+    /// Encode raw RGB or RGBA pixels with rows in bottom to top order into a QOI image.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_flipped(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        self.encode_with(pixels, output, &EncodeOptions::new().flip(true))
+    }
+
+    /// Encode raw RGB or RGBA pixels with rows in bottom to top order placed `stride` bytes apart into a QOI image.\
+    /// Bytes between rows are ignored.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// `stride` must be not less than `width * channels`,
+    /// and `pixels` must hold at least `stride * (height - 1) + width * channels` bytes.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_flipped_with_stride(
+        &self,
+        pixels: &[u8],
+        stride: usize,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        self.encode_with(
+            pixels,
+            output,
+            &EncodeOptions::new().stride(stride).flip(true),
+        )
+    }
+
+    /// Encode rectangular region of raw RGB or RGBA pixels into a QOI image.\
+    /// `self` describes dimensions of the whole image in `pixels`,
+    /// encoded image has `w` by `h` pixels taken starting from `x` column and `y` row.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_region(
+        &self,
+        pixels: &[u8],
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        match (x.checked_add(w), y.checked_add(h)) {
+            (Some(x1), Some(y1)) if x1 <= self.width && y1 <= self.height => {}
+            _ => return Err(EncodeError::InvalidRegion),
         }
 
-        output[0..4].copy_from_slice(&QOI_MAGIC.to_be_bytes());
-        output[4..8].copy_from_slice(&self.width.to_be_bytes());
-        output[8..12].copy_from_slice(&self.height.to_be_bytes());
+        let px_len = self.input_len(self.colors.channels())?;
 
-        let channels = match self.colors {
-            Colors::Rgb => {
-                output[12] = 3;
-                output[13] = 1;
-                3
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
             }
-            Colors::Rgba => {
-                output[12] = 4;
-                output[13] = 1;
-                4
+            Some(pixels) => pixels,
+        };
+
+        let region = Qoi {
+            width: w,
+            height: h,
+            colors: self.colors,
+        };
+
+        if w == 0 || h == 0 {
+            return region.encode(&[], output);
+        }
+
+        let row = self.width as usize * self.colors.channels();
+        let offset = x as usize * self.colors.channels();
+        let rows = pixels
+            .chunks(row)
+            .skip(y as usize)
+            .map(|row| &row[offset..]);
+
+        region.encode_rows(rows, output)
+    }
+
+    /// Encode raw RGB or RGBA pixels provided row by row into a QOI image.\
+    /// Each row must hold at least `width * channels` bytes, excess bytes are ignored.\
+    /// Only first `height` rows are used.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InputSizeMismatch { .. })` is returned if there are less than `height` rows or any of them is too short.
+    #[inline]
+    pub fn encode_from_rows<'a, R>(&self, rows: R, output: &mut [u8]) -> Result<usize, EncodeError>
+    where
+        R: IntoIterator<Item = &'a [u8]>,
+    {
+        self.encode_rows(rows.into_iter(), output)
+    }
+
+    /// Encode pixels from rows of raw RGB or RGBA pixels into a QOI image.
+    #[inline]
+    fn encode_rows<'a, R>(&self, rows: R, output: &mut [u8]) -> Result<usize, EncodeError>
+    where
+        R: Iterator<Item = &'a [u8]>,
+    {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let expected = self.input_len(self.colors.channels())?;
+
+        let (size, short, got) = match self.colors.has_alpha() {
+            true => {
+                let mut input = Rows::<_, 4>::new(rows, width, height);
+                (
+                    self.encode_from(&mut input, output)?,
+                    input.short,
+                    input.got,
+                )
             }
-            Colors::Srgb => {
-                output[12] = 3;
-                output[13] = 0;
-                3
+            false => {
+                let mut input = Rows::<_, 3>::new(rows, width, height);
+                (
+                    self.encode_from(&mut input, output)?,
+                    input.short,
+                    input.got,
+                )
             }
-            Colors::SrgbLinA => {
-                output[12] = 4;
-                output[13] = 0;
-                4
+        };
+
+        match short {
+            true => Err(EncodeError::InputSizeMismatch { expected, got }),
+            false => Ok(size),
+        }
+    }
+
+    /// Returns QOI header describing the image.
+    #[inline]
+    pub const fn header_bytes(&self) -> [u8; QOI_HEADER_SIZE] {
+        let magic = QOI_MAGIC.to_be_bytes();
+        let width = self.width.to_be_bytes();
+        let height = self.height.to_be_bytes();
+
+        let (channels, colorspace) = match self.colors {
+            Colors::Srgb => (3, 0),
+            Colors::SrgbLinA => (4, 0),
+            Colors::Rgb => (3, 1),
+            Colors::Rgba => (4, 1),
+        };
+
+        [
+            magic[0], magic[1], magic[2], magic[3], width[0], width[1], width[2], width[3],
+            height[0], height[1], height[2], height[3], channels, colorspace,
+        ]
+    }
+
+    /// Encode QOI header describing the image into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_header(&self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        match output.get_mut(..QOI_HEADER_SIZE) {
+            None => Err(EncodeError::OutputIsTooSmall {
+                required: QOI_HEADER_SIZE,
+            }),
+            Some(output) => {
+                output.copy_from_slice(&self.header_bytes());
+                Ok(QOI_HEADER_SIZE)
             }
+        }
+    }
+
+    /// Writes QOI header into `output` slice.
+    #[inline]
+    pub(crate) fn write_header(&self, output: &mut [u8]) {
+        output[..QOI_HEADER_SIZE].copy_from_slice(&self.header_bytes());
+    }
+
+    /// Encode pixels from arbitrary input into a QOI image.\
+    /// Input must provide exactly `width * height` pixels.
+    #[inline]
+    pub(crate) fn encode_from<I>(&self, input: I, output: &mut [u8]) -> Result<usize, EncodeError>
+    where
+        I: Input<3> + Input<4>,
+    {
+        self.encode_from_as::<false, _>(input, output)
+    }
+
+    /// Encode pixels from arbitrary input into a QOI image.\
+    /// If `REFERENCE` is `true` chunks are chosen exactly as reference encoder does.
+    #[inline]
+    fn encode_from_as<const REFERENCE: bool, I>(
+        &self,
+        mut input: I,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        I: Input<3> + Input<4>,
+    {
+        let required = self.size_limit()?;
+        if output.len() <= QOI_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+
+        self.write_header(output);
+
+        let mut sink = Slice::new(&mut output[QOI_HEADER_SIZE..]);
+        match self.colors.has_alpha() {
+            true => Self::encode_range_into::<4, REFERENCE, _, _>(
+                &mut [[0; 4]; 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                &mut input,
+                &mut sink,
+                true,
+                required,
+            )?,
+            false => Self::encode_range_into::<3, REFERENCE, _, _>(
+                &mut [[0; 4]; 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                &mut input,
+                &mut sink,
+                true,
+                required,
+            )?,
+        }
+
+        let size = QOI_HEADER_SIZE + sink.written;
+        Ok(size + Self::encode_padding(&mut output[size..], required)?)
+    }
+
+    /// Writes end marker into `output` slice.\
+    /// `required` is reported in error if `output` is too small.
+    #[inline]
+    pub(crate) fn encode_padding(output: &mut [u8], required: usize) -> Result<usize, EncodeError> {
+        let output = match output.get_mut(..QOI_PADDING) {
+            None => return Err(EncodeError::OutputIsTooSmall { required }),
+            Some(output) => output,
         };
+        output[..QOI_PADDING - 1].fill(0);
+        output[QOI_PADDING - 1] = 1;
+        Ok(QOI_PADDING)
+    }
 
-        let px_len = self.width as usize * self.height as usize * channels;
+    /// Encode raw RGB or RGBA pixels into a QOI image collecting statistics of produced chunks.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok((size, stats))` with `size` of the encoded image and `stats` of its chunks.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode_with_stats(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+    ) -> Result<(usize, EncodeStats), EncodeError> {
+        let px_len = self.input_len(self.colors.channels())?;
 
         let pixels = match pixels.get(..px_len) {
             None => {
-                cold();
-                return Err(EncodeError::NotEnoughPixelData);
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
             }
             Some(pixels) => pixels,
         };
 
-        let size = match self.colors.has_alpha() {
-            true => Self::encode_range::<4>(
+        let required = self.size_limit()?;
+        if output.len() <= QOI_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+
+        self.write_header(output);
+
+        let mut stats = EncodeStats::default();
+        let mut sink = Stats {
+            sink: Slice::new(&mut output[QOI_HEADER_SIZE..]),
+            stats: &mut stats,
+        };
+
+        match self.colors.has_alpha() {
+            true => Self::encode_range_into::<4, false, _, _>(
                 &mut [[0; 4]; 64],
                 &mut Pixel::new_opaque(),
                 &mut 0,
-                pixels,
-                &mut output[QOI_HEADER_SIZE..],
+                &mut bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                &mut sink,
+                true,
+                required,
             )?,
-            false => Self::encode_range::<3>(
+            false => Self::encode_range_into::<3, false, _, _>(
                 &mut [[0; 4]; 64],
                 &mut Pixel::new_opaque(),
                 &mut 0,
-                pixels,
-                &mut output[QOI_HEADER_SIZE..],
+                &mut bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                &mut sink,
+                true,
+                required,
             )?,
+        }
+
+        let size = QOI_HEADER_SIZE + sink.sink.written;
+        let size = size + Self::encode_padding(&mut output[size..], required)?;
+        Ok((size, stats))
+    }
+
+    /// Computes exact size of raw RGB or RGBA pixels encoded into a QOI image without writing it.\
+    /// Returned size always matches size returned by [`Qoi::encode`] for the same pixels.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encoded_size(&self, pixels: &[u8]) -> Result<usize, EncodeError> {
+        let px_len = self.input_len(self.colors.channels())?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                })
+            }
+            Some(pixels) => pixels,
         };
 
-        if output.len() < size + QOI_PADDING + QOI_HEADER_SIZE {
-            return Err(EncodeError::OutputIsTooSmall);
+        let mut count = Count(0);
+        match self.colors.has_alpha() {
+            true => Self::encode_range_into::<4, false, _, _>(
+                &mut [[0; 4]; 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                &mut bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                &mut count,
+                true,
+                0,
+            )?,
+            false => Self::encode_range_into::<3, false, _, _>(
+                &mut [[0; 4]; 64],
+                &mut Pixel::new_opaque(),
+                &mut 0,
+                &mut bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                &mut count,
+                true,
+                0,
+            )?,
+        }
+
+        Ok(count.0 + QOI_PADDING + QOI_HEADER_SIZE)
+    }
+
+    /// Estimates size of raw RGB or RGBA pixels encoded into a QOI image by encoding only some of its rows.\
+    /// Up to `sample_rows` evenly spaced rows are encoded with the same chunk selection as [`Qoi::encode`]
+    /// and their size is extrapolated to the whole image.
+    /// If `sample_rows` is not less than image height, all rows are encoded and estimated size is exact.\
+    /// For photographs and synthetic images with smooth gradients or noise,
+    /// estimated size sampling 16 rows or more is typically within 15% of the actual size.
+    /// Images with large flat areas or varying content may be estimated less accurately,
+    /// bounds of the estimate give range spanned by best and worst compressed sampled rows.
+    ///
+    /// On success this function returns `Ok(estimate)` with `estimate` of the encoded image size.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn estimate_encoded_size(
+        &self,
+        pixels: &[u8],
+        sample_rows: usize,
+    ) -> Result<SizeEstimate, EncodeError> {
+        let px_len = self.input_len(self.colors.channels())?;
+        let pixels = Self::take_input(pixels, px_len)?;
+
+        match self.colors.has_alpha() {
+            true => self.estimate_as::<4>(pixels, sample_rows),
+            false => self.estimate_as::<3>(pixels, sample_rows),
+        }
+    }
+
+    /// Estimates size of the encoded image with `N` channels sampling up to `sample_rows` rows.
+    fn estimate_as<const N: usize>(
+        &self,
+        pixels: &[u8],
+        sample_rows: usize,
+    ) -> Result<SizeEstimate, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let height = self.height as usize;
+        if sample_rows >= height {
+            let size = self.encoded_size(pixels)?;
+            return Ok(SizeEstimate {
+                size,
+                low: size,
+                high: size,
+            });
         }
 
-        output[QOI_HEADER_SIZE + size..][..QOI_PADDING - 1].fill(0);
-        output[QOI_HEADER_SIZE + size + QOI_PADDING - 1] = 1;
+        let row_len = self.width as usize * N;
+        let mut index = [[0; 4]; 64];
+        let mut px_prev = Pixel::new_opaque();
+        let mut run = 0;
+        let mut count = Count(0);
+        let mut min = usize::MAX;
+        let mut max = 0;
+
+        // Rows are sampled from the middle of equal bands of the image.
+        let samples = sample_rows.max(1);
+        for i in 0..samples {
+            let row = (2 * i + 1) * height / (2 * samples);
+            let before = count.0;
+            Self::encode_range_into::<N, false, _, _>(
+                &mut index,
+                &mut px_prev,
+                &mut run,
+                &mut bytemuck::cast_slice::<_, [u8; N]>(&pixels[row * row_len..][..row_len]),
+                &mut count,
+                false,
+                0,
+            )?;
+            if i + 1 == samples && run > 0 {
+                count.0 += 1;
+            }
+            min = min.min(count.0 - before);
+            max = max.max(count.0 - before);
+        }
 
-        Ok(size + QOI_PADDING + QOI_HEADER_SIZE)
+        let other = height - samples;
+        let size = (count.0 as u128 * height as u128 / samples as u128) as usize;
+        let fixed = QOI_HEADER_SIZE + QOI_PADDING;
+        let limit = self.size_limit()?;
+        let clamp = |size: usize| size.saturating_add(fixed).min(limit);
+        Ok(SizeEstimate {
+            size: clamp(size),
+            low: clamp(count.0 + min * other),
+            high: clamp(count.0.saturating_add(max.saturating_mul(other))),
+        })
     }
 
     /// Encode range of pixels into output slice.
@@ -115,121 +1768,158 @@ impl Qoi {
     where
         [u8; N]: Pixel,
     {
-        let mut rest = &mut *output;
-
         assert_eq!(pixels.len() % N, 0);
 
-        // let mut chunks = pixels.chunks_exact(N);
-        let mut pixels = bytemuck::cast_slice::<_, [u8; N]>(pixels);
+        let required = (*run > 0) as usize + pixels.len() / N * (N + 1) + 7;
+
+        let mut sink = Slice::new(output);
+        Self::encode_range_into::<N, false, _, _>(
+            index,
+            px_prev,
+            run,
+            &mut bytemuck::cast_slice::<_, [u8; N]>(pixels),
+            &mut sink,
+            true,
+            required,
+        )?;
+        Ok(sink.written)
+    }
 
+    /// Encode range of pixels from arbitrary input into arbitrary sink.\
+    /// If `flush` is `true` run is not left open after the last pixel of the range.\
+    /// If `REFERENCE` is `true` chunks are chosen exactly as reference encoder does.\
+    /// `required` is reported in error if sink runs out of room.
+    #[inline]
+    pub(crate) fn encode_range_into<const N: usize, const REFERENCE: bool, I, S>(
+        index: &mut [[u8; 4]; 64],
+        px_prev: &mut [u8; N],
+        run: &mut usize,
+        pixels: &mut I,
+        rest: &mut S,
+        flush: bool,
+        required: usize,
+    ) -> Result<(), EncodeError>
+    where
+        [u8; N]: Pixel,
+        I: Input<N>,
+        S: Sink,
+    {
         loop {
-            match pixels {
-                // Some(chunk) => {
-                [px, tail @ ..] => {
-                    pixels = tail;
-                    if likely(rest.len() > 7) {
-                        if *px == *px_prev {
-                            if *run == 61 || unlikely(pixels.is_empty()) {
-                                rest[0] = QOI_OP_RUN | (*run as u8);
-                                rest = &mut rest[1..];
+            match pixels.next() {
+                Some(px) => {
+                    if likely(rest.has_room()) {
+                        if px == *px_prev {
+                            if *run == 61 || unlikely(flush && pixels.is_empty()) {
+                                rest.put([QOI_OP_RUN | (*run as u8)]);
                                 *run = 0;
                             } else {
                                 *run += 1;
+                                *run += pixels.skip_run(px, 61 - *run, flush);
                             }
                         } else {
                             match run {
                                 0 => {}
-                                1 => {
+                                1 if !REFERENCE => {
                                     // While not following reference encoder
                                     // this produces valid QOI and have the exactly same size.
                                     // Decoding is slightly faster.
                                     let index_pos = px_prev.hash();
                                     if unlikely(index_pos == 0x35 && index[0x35] == [0; 4]) {
-                                        rest[0] = QOI_OP_RUN;
+                                        rest.put([QOI_OP_RUN]);
                                     } else {
-                                        rest[0] = QOI_OP_INDEX | index_pos as u8;
+                                        rest.put([QOI_OP_INDEX | index_pos]);
                                     }
-                                    rest = &mut rest[1..];
                                     *run = 0;
                                 }
                                 _ => {
-                                    rest[0] = QOI_OP_RUN | (*run - 1) as u8;
-                                    rest = &mut rest[1..];
+                                    rest.put([QOI_OP_RUN | (*run - 1) as u8]);
                                     *run = 0;
                                 }
                             }
 
-                            match rest {
-                                [b1, b2, b3, b4, b5, ..] => {
-                                    let index_pos = px.hash();
+                            let index_pos = px.hash();
 
-                                    if index[index_pos as usize] == px.rgba() {
-                                        *b1 = QOI_OP_INDEX | index_pos as u8;
-                                        rest = &mut rest[1..];
+                            if index[index_pos as usize] == px.rgba() {
+                                rest.put([QOI_OP_INDEX | index_pos]);
+                            } else {
+                                index[index_pos as usize] = px.rgba();
+
+                                if N == 4 && px_prev.a() != px.a() {
+                                    cold();
+                                    let [r, g, b, a] = px.rgba();
+                                    rest.put([QOI_OP_RGBA, r, g, b, a]);
+                                } else {
+                                    let v = px.var(px_prev);
+
+                                    if let Some(diff) = v.diff() {
+                                        rest.put([diff]);
+                                    } else if let Some(luma) = v.luma() {
+                                        rest.put(luma);
                                     } else {
-                                        index[index_pos as usize] = px.rgba();
-
-                                        if N == 4 && px_prev.a() != px.a() {
-                                            cold();
-                                            let [r, g, b, a] = px.rgba();
-                                            *b1 = QOI_OP_RGBA;
-                                            *b2 = r;
-                                            *b3 = g;
-                                            *b4 = b;
-                                            *b5 = a;
-                                            rest = &mut rest[5..];
-                                        } else {
-                                            let v = px.var(px_prev);
-
-                                            if let Some(diff) = v.diff() {
-                                                *b1 = diff;
-                                                rest = &mut rest[1..];
-                                            } else if let Some([lu, ma]) = v.luma() {
-                                                *b1 = lu;
-                                                *b2 = ma;
-                                                rest = &mut rest[2..];
-                                            } else {
-                                                let [r, g, b] = px.rgb();
-                                                *b1 = QOI_OP_RGB;
-                                                *b2 = r;
-                                                *b3 = g;
-                                                *b4 = b;
-                                                rest = &mut rest[4..];
-                                            }
-                                        }
+                                        let [r, g, b] = px.rgb();
+                                        rest.put([QOI_OP_RGB, r, g, b]);
                                     }
-                                    *px_prev = *px;
-                                }
-                                _ => {
-                                    cold();
-                                    unreachable!()
                                 }
                             }
+                            *px_prev = px;
                         }
                     } else {
-                        return Err(EncodeError::OutputIsTooSmall);
+                        return Err(EncodeError::OutputIsTooSmall { required });
                     }
                 }
-                // None => {
-                [] => {
+                None => {
                     cold();
                     break;
                 }
             }
         }
 
-        let tail = rest.len();
-
-        Ok(output.len() - tail)
+        Ok(())
     }
 
     /// Returns maximum size of the `Qoi::encode` output size.\
-    /// Using smaller slice may cause `Qoi::encode` to return `Err(EncodeError::OutputIsTooSmall)`.
+    /// Using smaller slice may cause `Qoi::encode` to return `Err(EncodeError::OutputIsTooSmall { .. })`.\
+    /// Returns `usize::MAX` if the size cannot be represented on this platform.
     #[inline]
     pub fn encoded_size_limit(&self) -> usize {
-        self.width as usize * self.height as usize * (self.colors.has_alpha() as usize + 4)
-            + QOI_HEADER_SIZE
-            + QOI_PADDING
+        self.size_limit().unwrap_or(usize::MAX)
+    }
+
+    /// Returns maximum size of encoded image.
+    #[inline]
+    pub(crate) fn size_limit(&self) -> Result<usize, EncodeError> {
+        self.input_len(self.colors.channels() + 1)?
+            .checked_add(QOI_HEADER_SIZE + QOI_PADDING)
+            .ok_or(EncodeError::TooManyPixels)
+    }
+
+    /// Returns number of pixels of the image encoded into single output slice.\
+    /// Fails if encoded image may not fit into addressable memory even when input does.
+    #[inline]
+    pub(crate) fn output_pixels(&self) -> Result<usize, EncodeError> {
+        self.size_limit()?;
+        self.input_len(1)
+    }
+
+    /// Returns length of input holding `per_pixel` elements for each pixel of the image.
+    #[inline]
+    pub(crate) fn input_len(&self, per_pixel: usize) -> Result<usize, EncodeError> {
+        (self.width as usize)
+            .checked_mul(self.height as usize)
+            .and_then(|len| len.checked_mul(per_pixel))
+            .ok_or(EncodeError::TooManyPixels)
+    }
+
+    /// Returns first `len` elements of `input`.
+    #[inline]
+    pub(crate) fn take_input<T>(input: &[T], len: usize) -> Result<&[T], EncodeError> {
+        match input.get(..len) {
+            None => Err(EncodeError::InputSizeMismatch {
+                expected: len,
+                got: input.len(),
+            }),
+            Some(input) => Ok(input),
+        }
     }
 
     /// Encode raw RGB or RGBA pixels into a QOI image.\
@@ -240,15 +1930,75 @@ impl Qoi {
     #[cfg(feature = "alloc")]
     #[inline]
     pub fn encode_alloc(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
-        let limit = self.encoded_size_limit();
+        let limit = self.size_limit()?;
         let mut output = vec![0; limit];
-        match self.encode(pixels, &mut output) {
+        let size = self.encode(pixels, &mut output)?;
+        output.truncate(size);
+        Ok(output)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image is appended to the `output` `Vec` without intermediate buffer.\
+    /// Reserved space is zero-filled before encoding.\
+    /// Existing content of `output` is not modified. On failure `output` is restored to its original length.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_append(&self, pixels: &[u8], output: &mut Vec<u8>) -> Result<usize, EncodeError> {
+        let len = output.len();
+        let end = len
+            .checked_add(self.size_limit()?)
+            .ok_or(EncodeError::TooManyPixels)?;
+        output.resize(end, 0);
+        match self.encode(pixels, &mut output[len..]) {
             Ok(size) => {
-                output.truncate(size);
-                Ok(output)
+                output.truncate(len + size);
+                Ok(size)
+            }
+            Err(err) => {
+                output.truncate(len);
+                Err(err)
             }
-            Err(EncodeError::OutputIsTooSmall) => unreachable(),
-            Err(err) => Err(err),
         }
     }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image.\
+    /// Encoded image is written into allocated `Vec` of exactly required capacity.\
+    /// Pixels are processed twice, first pass computes size of the encoded image.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_exact_alloc(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        let size = self.encoded_size(pixels)?;
+        let mut output = vec![0; size];
+        let written = self.encode(pixels, &mut output)?;
+        debug_assert_eq!(written, size);
+        Ok(output)
+    }
+
+    /// Encode RGB or RGBA pixels into a QOI image regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.\
+    /// Encoded image is written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_pixels_alloc<const M: usize>(
+        &self,
+        pixels: &[[u8; M]],
+    ) -> Result<Vec<u8>, EncodeError>
+    where
+        [u8; M]: Pixel,
+    {
+        let limit = self.size_limit()?;
+        let mut output = vec![0; limit];
+        let size = self.encode_pixels(pixels, &mut output)?;
+        output.truncate(size);
+        Ok(output)
+    }
 }
@@ -0,0 +1,310 @@
+use super::*;
+use encode::Slice;
+
+/// Errors that may occur during image encoding into generic writer.\
+/// `E` is error type of the writer, writer error is not displayed but returned as source of the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IoEncodeError<E> {
+    /// Writing into the writer failed.
+    Io(E),
+
+    /// Pixels cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl<E> Display for IoEncodeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoEncodeError::Io(_) => f.write_str("Failed to write encoded image"),
+            IoEncodeError::Encode(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for IoEncodeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoEncodeError::Io(err) => Some(err),
+            IoEncodeError::Encode(_) => None,
+        }
+    }
+}
+
+impl<E> From<EncodeError> for IoEncodeError<E> {
+    #[inline]
+    fn from(err: EncodeError) -> Self {
+        IoEncodeError::Encode(err)
+    }
+}
+
+/// Reason why [`Encoder::encode_step`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepResult {
+    /// Output has no room for the next chunk, more space is required to continue encoding.
+    OutputFull,
+
+    /// All provided pixels are consumed, more pixels are required to continue encoding.
+    NeedMorePixels,
+
+    /// All pixels of the image and end marker are written.
+    Finished,
+}
+
+/// Push-based encoder of QOI image with pixels provided in arbitrary chunks.\
+/// Produces exactly the same bytes as [`Qoi::encode`] of all pushed pixels at once.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    qoi: Qoi,
+    index: [[u8; 4]; 64],
+    px_prev: [u8; 4],
+    run: usize,
+    pos: usize,
+    header_written: usize,
+    finished: bool,
+}
+
+impl Encoder {
+    /// Returns encoder of the image described by `qoi`.
+    #[inline]
+    pub const fn new(qoi: Qoi) -> Self {
+        Encoder {
+            qoi,
+            index: [[0; 4]; 64],
+            px_prev: [0, 0, 0, 0xff],
+            run: 0,
+            pos: 0,
+            header_written: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns descriptor of the image.
+    #[inline]
+    pub const fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns number of pixels pushed so far.
+    #[inline]
+    pub const fn pixels_encoded(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns `true` if end marker is written.
+    #[inline]
+    pub const fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns number of pixels that can be pushed at once
+    /// without failing due to `output` slice of length `output_len` being too small.
+    #[inline]
+    pub fn pixels_fitting(&self, output_len: usize) -> usize {
+        let reserved = self.header_size() + 1 + 7;
+        output_len.saturating_sub(reserved) / (self.qoi.colors.channels() + 1)
+    }
+
+    /// Encode next raw RGB or RGBA (depending on `self.qoi().colors` value) pixels of the image.\
+    /// Header is written before the first pixels.\
+    /// Encoded bytes are written into `output` slice.
+    /// Pending run of pixels is kept in the encoder and may be written by later calls.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of bytes written.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// State of the encoder is unspecified after failure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` length is not multiple of channels number.
+    pub fn push_pixels(&mut self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let channels = self.qoi.colors.channels();
+        assert_eq!(pixels.len() % channels, 0);
+
+        let total = self.qoi.input_len(1)?;
+        let remaining = match self.finished {
+            true => 0,
+            false => total - self.pos,
+        };
+        let count = pixels.len() / channels;
+        if count > remaining {
+            return Err(EncodeError::InputSizeMismatch {
+                expected: remaining * channels,
+                got: pixels.len(),
+            });
+        }
+
+        let required = self.header_size() + (self.run > 0) as usize + count * (channels + 1) + 7;
+
+        let written = self.write_header(output, required)?;
+        let mut sink = Slice::new(&mut output[written..]);
+        match self.qoi.colors.has_alpha() {
+            true => Qoi::encode_range_into::<4, false, _, _>(
+                &mut self.index,
+                &mut self.px_prev,
+                &mut self.run,
+                &mut bytemuck::cast_slice::<_, [u8; 4]>(pixels),
+                &mut sink,
+                false,
+                required,
+            )?,
+            false => {
+                let mut px_prev = convert(&self.px_prev);
+                Qoi::encode_range_into::<3, false, _, _>(
+                    &mut self.index,
+                    &mut px_prev,
+                    &mut self.run,
+                    &mut bytemuck::cast_slice::<_, [u8; 3]>(pixels),
+                    &mut sink,
+                    false,
+                    required,
+                )?;
+                self.px_prev = convert(&px_prev);
+            }
+        }
+        self.pos += count;
+
+        Ok(written + sink.written)
+    }
+
+    /// Encode as many complete chunks of raw RGB or RGBA (depending on `self.qoi().colors` value) pixels
+    /// as fit into `output` slice.\
+    /// `pixels` slice is advanced past consumed pixels,
+    /// incomplete pixel in the end and pixels exceeding the image are left in it.\
+    /// Header is written before the first pixels and may be split between outputs,
+    /// chunks and end marker are never split.
+    /// Pending run of pixels is kept in the encoder,
+    /// so concatenation of all outputs is the same as produced by [`Qoi::encode`].\
+    /// End marker is written as soon as all pixels of the image are consumed.
+    ///
+    /// On success this function returns `Ok((size, result))` with `size` of bytes written
+    /// and `result` telling why encoding stopped.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_step(
+        &mut self,
+        pixels: &mut &[u8],
+        output: &mut [u8],
+    ) -> Result<(usize, StepResult), EncodeError> {
+        if self.finished {
+            return Ok((0, StepResult::Finished));
+        }
+
+        let channels = self.qoi.colors.channels();
+        let total = self.qoi.input_len(1)?;
+
+        let mut written = self.write_header_part(output);
+        if self.header_size() > 0 {
+            return Ok((written, StepResult::OutputFull));
+        }
+
+        loop {
+            let room = output.len() - written;
+            let count = (total - self.pos).min(pixels.len() / channels);
+
+            if count == 0 {
+                if self.pos < total {
+                    return Ok((written, StepResult::NeedMorePixels));
+                }
+
+                let required = (self.run > 0) as usize + QOI_PADDING;
+                if room < required {
+                    if self.run > 0 && room > 0 {
+                        output[written] = QOI_OP_RUN | (self.run - 1) as u8;
+                        self.run = 0;
+                        written += 1;
+                    }
+                    return Ok((written, StepResult::OutputFull));
+                }
+                written += self.finish(&mut output[written..])?;
+                return Ok((written, StepResult::Finished));
+            }
+
+            let fitting = self.pixels_fitting(room).min(count);
+            if fitting > 0 {
+                let (head, tail) = pixels.split_at(fitting * channels);
+                written += self.push_pixels(head, &mut output[written..])?;
+                *pixels = tail;
+                continue;
+            }
+
+            // Near the end of the output single pixel is encoded into temporary buffer
+            // and written only if produced chunks fit.
+            let saved = self.clone();
+            let mut chunk = [0; 16];
+            let size = self.push_pixels(&pixels[..channels], &mut chunk)?;
+            if size > room {
+                *self = saved;
+                return Ok((written, StepResult::OutputFull));
+            }
+            output[written..][..size].copy_from_slice(&chunk[..size]);
+            written += size;
+            *pixels = &pixels[channels..];
+        }
+    }
+
+    /// Finish encoding of the image.\
+    /// Pending run of pixels and end marker are written into `output` slice.\
+    /// Does nothing if encoding is already finished.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of bytes written.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn finish(&mut self, output: &mut [u8]) -> Result<usize, EncodeError> {
+        if self.finished {
+            return Ok(0);
+        }
+
+        let channels = self.qoi.colors.channels();
+        let total = self.qoi.input_len(1)?;
+        if self.pos < total {
+            return Err(EncodeError::InputSizeMismatch {
+                expected: total * channels,
+                got: self.pos * channels,
+            });
+        }
+
+        let required = self.header_size() + (self.run > 0) as usize + QOI_PADDING;
+        if output.len() < required {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+
+        let mut written = self.write_header(output, required)?;
+        if self.run > 0 {
+            output[written] = QOI_OP_RUN | (self.run - 1) as u8;
+            self.run = 0;
+            written += 1;
+        }
+        written += Qoi::encode_padding(&mut output[written..], required)?;
+        self.finished = true;
+
+        Ok(written)
+    }
+
+    /// Returns size of header that is not written yet.
+    #[inline]
+    fn header_size(&self) -> usize {
+        QOI_HEADER_SIZE - self.header_written
+    }
+
+    /// Writes rest of the header into `output` slice if it is not written yet.
+    #[inline]
+    fn write_header(&mut self, output: &mut [u8], required: usize) -> Result<usize, EncodeError> {
+        let size = self.header_size();
+        if output.len() < size {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+        self.write_header_part(output);
+        Ok(size)
+    }
+
+    /// Writes as much of the rest of the header as fits into `output` slice.\
+    /// Returns number of bytes written.
+    #[inline]
+    fn write_header_part(&mut self, output: &mut [u8]) -> usize {
+        let header = self.qoi.header_bytes();
+        let rest = &header[self.header_written..];
+        let n = rest.len().min(output.len());
+        output[..n].copy_from_slice(&rest[..n]);
+        self.header_written += n;
+        n
+    }
+}
@@ -0,0 +1,250 @@
+use super::*;
+use srgb::SRGB_TO_LINEAR;
+
+/// Offset of the tag table in ICC profile.
+const TAG_TABLE: usize = 128;
+
+/// Matrix converting D50 adapted XYZ of profile connection space into linear sRGB.
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.133856, -1.616867, -0.4906146],
+    [-0.9787684, 1.916142, 0.033454],
+    [0.0719453, -0.2289914, 1.405243],
+];
+
+/// Conversion of pixels described by RGB matrix/TRC profile into sRGB.
+pub(crate) struct SrgbTransform {
+    /// Linear light intensity of each value of each color channel.
+    curves: [[f32; 256]; 3],
+
+    /// Matrix converting linear channels of the profile into linear sRGB.
+    matrix: [[f32; 3]; 3],
+}
+
+impl SrgbTransform {
+    /// Parses RGB matrix/TRC ICC profile.\
+    /// Returns `None` if profile is malformed or uses other kind of transform.
+    pub(crate) fn new(profile: &[u8]) -> Option<Self> {
+        if profile.get(16..24)? != b"RGB XYZ " {
+            return None;
+        }
+
+        let mut primaries = [[0.0; 3]; 3];
+        let mut curves = [[0.0; 256]; 3];
+        for (c, (xyz, trc)) in [(b"rXYZ", b"rTRC"), (b"gXYZ", b"gTRC"), (b"bXYZ", b"bTRC")]
+            .iter()
+            .enumerate()
+        {
+            primaries[c] = parse_xyz(find_tag(profile, xyz)?)?;
+            parse_curve(find_tag(profile, trc)?, &mut curves[c])?;
+        }
+
+        // Primaries are columns of the matrix converting linear channels into XYZ.
+        let mut matrix = [[0.0; 3]; 3];
+        for (row, xyz) in matrix.iter_mut().zip(&XYZ_TO_SRGB) {
+            for (value, primary) in row.iter_mut().zip(&primaries) {
+                *value = (0..3).map(|i| xyz[i] * primary[i]).sum();
+            }
+        }
+
+        Some(SrgbTransform { curves, matrix })
+    }
+
+    /// Converts color channels of a pixel into sRGB.
+    #[inline]
+    pub(crate) fn apply(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let linear = [
+            self.curves[0][rgb[0] as usize],
+            self.curves[1][rgb[1] as usize],
+            self.curves[2][rgb[2] as usize],
+        ];
+
+        let mut out = [0; 3];
+        for (out, row) in out.iter_mut().zip(&self.matrix) {
+            let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            *out = linear_to_srgb(value);
+        }
+        out
+    }
+}
+
+/// Returns sRGB value with linear light intensity nearest to `value`.
+#[inline]
+fn linear_to_srgb(value: f32) -> u8 {
+    let above = SRGB_TO_LINEAR.partition_point(|&v| v < value);
+    match above {
+        0 => 0,
+        256 => 255,
+        _ if value - SRGB_TO_LINEAR[above - 1] < SRGB_TO_LINEAR[above] - value => (above - 1) as u8,
+        _ => above as u8,
+    }
+}
+
+/// Returns big-endian `u32` at `offset`.
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let field = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(field.try_into().unwrap()))
+}
+
+/// Returns big-endian `u16` at `offset`.
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let field = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes(field.try_into().unwrap()))
+}
+
+/// Returns `s15Fixed16Number` at `offset`.
+#[inline]
+fn read_fixed(bytes: &[u8], offset: usize) -> Option<f32> {
+    Some(read_u32(bytes, offset)? as i32 as f32 / 65536.0)
+}
+
+/// Returns data of the tag with `signature`.
+fn find_tag<'a>(profile: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u32(profile, TAG_TABLE)? as usize;
+    (0..count).find_map(|i| {
+        let entry = TAG_TABLE + 4 + i * 12;
+        if profile.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = read_u32(profile, entry + 4)? as usize;
+        let size = read_u32(profile, entry + 8)? as usize;
+        profile.get(offset..offset.checked_add(size)?)
+    })
+}
+
+/// Parses `XYZType` tag with single value.
+fn parse_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+    if tag.get(..4)? != b"XYZ " {
+        return None;
+    }
+    Some([
+        read_fixed(tag, 8)?,
+        read_fixed(tag, 12)?,
+        read_fixed(tag, 16)?,
+    ])
+}
+
+/// Parses `curveType` or `parametricCurveType` tag into linear light intensity of each channel value.
+fn parse_curve(tag: &[u8], curve: &mut [f32; 256]) -> Option<()> {
+    match tag.get(..4)? {
+        b"curv" => match read_u32(tag, 8)? {
+            0 => fill(curve, |x| x),
+            1 => {
+                let gamma = read_u16(tag, 12)? as f32 / 256.0;
+                fill(curve, |x| x.powf(gamma));
+            }
+            n => {
+                let last = n as usize - 1;
+                let table = tag.get(12..)?.get(..(n as usize).checked_mul(2)?)?;
+                let entry = |i: usize| read_u16(table, i * 2).unwrap() as f32 / 65535.0;
+                fill(curve, |x| {
+                    let pos = x * last as f32;
+                    let i = (pos as usize).min(last.saturating_sub(1));
+                    let t = pos - i as f32;
+                    match last {
+                        0 => entry(0),
+                        _ => entry(i) * (1.0 - t) + entry(i + 1) * t,
+                    }
+                });
+            }
+        },
+        b"para" => {
+            let params = match read_u16(tag, 8)? {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return None,
+            };
+            let mut p = [0.0; 7];
+            for (i, p) in p.iter_mut().enumerate().take(params) {
+                *p = read_fixed(tag, 12 + i * 4)?;
+            }
+            let [g, a, b, c, d, e, f] = p;
+            fill(curve, |x| match params {
+                1 => x.powf(g),
+                3 if x >= -b / a => (a * x + b).powf(g),
+                3 => 0.0,
+                4 if x >= -b / a => (a * x + b).powf(g) + c,
+                4 => c,
+                5 if x >= d => (a * x + b).powf(g),
+                5 => c * x,
+                _ if x >= d => (a * x + b).powf(g) + e,
+                _ => c * x + f,
+            });
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Fills `curve` with values of `f` for channel values normalized into `0.0..=1.0` range.
+#[inline]
+fn fill(curve: &mut [f32; 256], f: impl Fn(f32) -> f32) {
+    for (v, out) in curve.iter_mut().enumerate() {
+        *out = f(v as f32 / 255.0).clamp(0.0, 1.0);
+    }
+}
+
+impl Qoi {
+    /// Returns conversion into sRGB requested by `options` for the image in `bytes`.\
+    /// Returns `Ok(None)` if conversion is not requested or the image has no ICC profile attached.
+    pub(crate) fn srgb_transform(
+        bytes: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<Option<SrgbTransform>, DecodeError> {
+        if !options.to_srgb {
+            return Ok(None);
+        }
+        if options.premultiplied {
+            return Err(DecodeError::InvalidOptions);
+        }
+
+        match Self::icc_profile(bytes) {
+            None => Ok(None),
+            Some(profile) => match SrgbTransform::new(profile) {
+                None => Err(DecodeError::UnsupportedProfile),
+                transform => Ok(transform),
+            },
+        }
+    }
+
+    /// Converts color channels of pixels decoded into `output` with `options` into sRGB.
+    pub(crate) fn convert_to_srgb(
+        &self,
+        transform: &SrgbTransform,
+        output: &mut [u8],
+        options: &DecodeOptions,
+    ) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let channels = options.channels(self.colors).unwrap();
+        let [r, g, b] = match options.order {
+            None | Some(ChannelOrder::Rgba) => [0, 1, 2],
+            Some(ChannelOrder::Bgra) => [2, 1, 0],
+            Some(ChannelOrder::Argb) => [1, 2, 3],
+            Some(ChannelOrder::Abgr) => [3, 2, 1],
+        };
+
+        let row = self.width as usize * channels;
+        let stride = options.stride.unwrap_or(row);
+        let mut last = None;
+        for line in output.chunks_mut(stride).take(self.height as usize) {
+            for px in line[..row].chunks_exact_mut(channels) {
+                let rgb = [px[r], px[g], px[b]];
+                let converted = match last {
+                    Some((prev, converted)) if prev == rgb => converted,
+                    _ => transform.apply(rgb),
+                };
+                last = Some((rgb, converted));
+                px[r] = converted[0];
+                px[g] = converted[1];
+                px[b] = converted[2];
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use super::*;
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Errors that may occur during image decoding from a reader.
+#[derive(Debug)]
+pub enum ReadDecodeError {
+    /// Reading from the reader failed.
+    Io(io::Error),
+
+    /// Read bytes are not a valid QOI image.
+    Decode(DecodeError),
+}
+
+impl Display for ReadDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadDecodeError::Io(err) => write!(f, "Failed to read encoded image: {}", err),
+            ReadDecodeError::Decode(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for ReadDecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadDecodeError::Io(err) => Some(err),
+            ReadDecodeError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ReadDecodeError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        ReadDecodeError::Io(err)
+    }
+}
+
+impl From<DecodeError> for ReadDecodeError {
+    #[inline]
+    fn from(err: DecodeError) -> Self {
+        ReadDecodeError::Decode(err)
+    }
+}
+
+/// Errors that may occur during image encoding into a writer.
+#[derive(Debug)]
+pub enum WriteEncodeError {
+    /// Writing into the writer failed.
+    Io(io::Error),
+
+    /// Pixels cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl Display for WriteEncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteEncodeError::Io(err) => write!(f, "Failed to write encoded image: {}", err),
+            WriteEncodeError::Encode(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for WriteEncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WriteEncodeError::Io(err) => Some(err),
+            WriteEncodeError::Encode(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for WriteEncodeError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        WriteEncodeError::Io(err)
+    }
+}
+
+impl From<EncodeError> for WriteEncodeError {
+    #[inline]
+    fn from(err: EncodeError) -> Self {
+        WriteEncodeError::Encode(err)
+    }
+}
+
+/// Size of buffer for bytes read from reader.
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Size of buffer for encoded bytes before they are written into writer.
+const WRITE_BUFFER_SIZE: usize = 4096;
+
+/// Reads into `buf` until at least one byte is read.\
+/// Returns `Err(ReadDecodeError::Decode(DecodeError::NotEnoughData { .. }))` describing position of `decoder` if reader reached its end.
+#[inline]
+fn read_some<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+    decoder: &Decoder,
+) -> Result<usize, ReadDecodeError> {
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return Err(decoder.truncated().into()),
+            Ok(n) => return Ok(n),
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from reader.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.\
+    /// Bytes are read in small portions and reading stops right after the end marker,
+    /// so following data is left in the reader.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_from_reader<R: Read>(
+        mut reader: R,
+        output: &mut [u8],
+    ) -> Result<Self, ReadDecodeError> {
+        let mut decoder = Decoder::new();
+        let mut header = [0; QOI_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < QOI_HEADER_SIZE {
+            let n = read_some(&mut reader, &mut header[filled..], &decoder)?;
+            decoder.push(&header[filled..filled + n], &mut [])?;
+            filled += n;
+        }
+        let qoi = Self::decode_header(&header)?;
+
+        let px_len = qoi.width as usize * qoi.height as usize * qoi.colors.channels();
+        let mut output = match output.get_mut(..px_len) {
+            None => return Err(DecodeError::OutputIsTooSmall.into()),
+            Some(output) => output,
+        };
+
+        let mut buf = [0; READ_BUFFER_SIZE];
+        while !decoder.is_finished() {
+            let len = decoder.bytes_needed().min(READ_BUFFER_SIZE);
+            let n = read_some(&mut reader, &mut buf[..len], &decoder)?;
+            let progress = decoder.push(&buf[..n], output)?;
+            debug_assert_eq!(progress.consumed, n);
+            output = &mut output[progress.pixels * qoi.colors.channels()..];
+        }
+
+        Ok(qoi)
+    }
+
+    /// Encode raw RGB or RGBA pixels into a QOI image written into writer.\
+    /// Encoded bytes are written in small portions, so no buffer for whole encoded image is required.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_to_writer<W: Write>(
+        &self,
+        pixels: &[u8],
+        mut writer: W,
+    ) -> Result<u64, WriteEncodeError> {
+        let channels = self.colors.channels();
+        let px_len = self.input_len(channels)?;
+
+        let pixels = match pixels.get(..px_len) {
+            None => {
+                return Err(EncodeError::InputSizeMismatch {
+                    expected: px_len,
+                    got: pixels.len(),
+                }
+                .into())
+            }
+            Some(pixels) => pixels,
+        };
+
+        let mut encoder = Encoder::new(*self);
+        let mut buf = [0; WRITE_BUFFER_SIZE];
+        let mut total = 0;
+        let mut rest = pixels;
+
+        while !rest.is_empty() {
+            let len = (encoder.pixels_fitting(buf.len()) * channels).min(rest.len());
+            let n = encoder.push_pixels(&rest[..len], &mut buf)?;
+            writer.write_all(&buf[..n])?;
+            total += n as u64;
+            rest = &rest[len..];
+        }
+
+        let n = encoder.finish(&mut buf)?;
+        writer.write_all(&buf[..n])?;
+        total += n as u64;
+
+        Ok(total)
+    }
+}
+
+/// Reader of raw RGB or RGBA pixels decoded from QOI image read from inner reader.\
+/// Pixels are decoded on demand in small portions,
+/// so no buffer for whole decoded image is required.
+#[derive(Debug)]
+pub struct QoiReader<R> {
+    reader: R,
+    qoi: Qoi,
+    decoder: Decoder,
+    input: Box<[u8]>,
+    input_start: usize,
+    input_end: usize,
+    output: Box<[u8]>,
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<R: Read> QoiReader<R> {
+    /// Returns reader of pixels decoded from `reader`.\
+    /// Header of the image is read immediately.
+    ///
+    /// On success this function returns `Ok(reader)`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn new(mut reader: R) -> Result<Self, ReadDecodeError> {
+        let mut header = [0; QOI_HEADER_SIZE];
+        let mut filled = 0;
+        let mut decoder = Decoder::new();
+        while filled < QOI_HEADER_SIZE {
+            let n = read_some(&mut reader, &mut header[filled..], &decoder)?;
+            decoder.push(&header[filled..filled + n], &mut [])?;
+            filled += n;
+        }
+
+        let qoi = Qoi::decode_header(&header)?;
+
+        Ok(QoiReader {
+            reader,
+            qoi,
+            decoder,
+            input: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+            input_start: 0,
+            input_end: 0,
+            output: vec![0; READ_BUFFER_SIZE / 12 * 12].into_boxed_slice(),
+            output_start: 0,
+            output_end: 0,
+        })
+    }
+
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns total number of bytes of decoded pixels.
+    #[inline]
+    pub fn decoded_size(&self) -> usize {
+        self.qoi.width as usize * self.qoi.height as usize * self.qoi.colors.channels()
+    }
+
+    /// Returns inner reader.\
+    /// Inner reader is positioned right after the end marker if all pixels are read.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for QoiReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.output_start < self.output_end || buf.is_empty() {
+                let n = (self.output_end - self.output_start).min(buf.len());
+                buf[..n].copy_from_slice(&self.output[self.output_start..][..n]);
+                self.output_start += n;
+                return Ok(n);
+            }
+
+            if self.decoder.is_finished() {
+                return Ok(0);
+            }
+
+            if self.input_start == self.input_end {
+                let len = self.decoder.bytes_needed().min(self.input.len());
+                let n = match read_some(&mut self.reader, &mut self.input[..len], &self.decoder) {
+                    Ok(n) => n,
+                    Err(ReadDecodeError::Io(err)) => return Err(err),
+                    Err(ReadDecodeError::Decode(err)) => {
+                        return Err(io::Error::new(ErrorKind::UnexpectedEof, err))
+                    }
+                };
+                self.input_start = 0;
+                self.input_end = n;
+            }
+
+            let progress = self
+                .decoder
+                .push(
+                    &self.input[self.input_start..self.input_end],
+                    &mut self.output,
+                )
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+            self.input_start += progress.consumed;
+            self.output_start = 0;
+            self.output_end = progress.pixels * self.qoi.colors.channels();
+        }
+    }
+}
+
+/// Writer that encodes raw RGB or RGBA pixels written into it
+/// and writes encoded QOI image into inner writer.\
+/// [`QoiWriter::finish`] must be called after all pixels are written,
+/// otherwise encoded image is truncated.
+#[derive(Debug)]
+pub struct QoiWriter<W> {
+    writer: W,
+    encoder: Encoder,
+    pending: [u8; 4],
+    pending_len: usize,
+    buf: Box<[u8]>,
+}
+
+impl<W: Write> QoiWriter<W> {
+    /// Returns writer that encodes image described by `qoi` into `writer`.
+    #[inline]
+    pub fn new(qoi: Qoi, writer: W) -> Self {
+        QoiWriter {
+            writer,
+            encoder: Encoder::new(qoi),
+            pending: [0; 4],
+            pending_len: 0,
+            buf: vec![0; WRITE_BUFFER_SIZE].into_boxed_slice(),
+        }
+    }
+
+    /// Returns descriptor of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.encoder.qoi()
+    }
+
+    /// Returns number of bytes of raw pixels that are left to write.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        let qoi = self.encoder.qoi();
+        let total = qoi.width as usize * qoi.height as usize;
+        (total - self.encoder.pixels_encoded()) * qoi.colors.channels() - self.pending_len
+    }
+
+    /// Finish encoding of the image.\
+    /// Pending run of pixels and end marker are written into inner writer.
+    ///
+    /// On success this function returns `Ok(writer)` with inner writer.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn finish(mut self) -> Result<W, WriteEncodeError> {
+        let n = self.encoder.finish(&mut self.buf)?;
+        self.writer.write_all(&self.buf[..n])?;
+        Ok(self.writer)
+    }
+
+    /// Encodes complete pixels and writes encoded bytes into inner writer.
+    #[inline]
+    fn push(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let n = self
+            .encoder
+            .push_pixels(pixels, &mut self.buf)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+        self.writer.write_all(&self.buf[..n])
+    }
+}
+
+impl<W: Write> Write for QoiWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.remaining();
+        if buf.len() > remaining {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                EncodeError::InputSizeMismatch {
+                    expected: remaining,
+                    got: buf.len(),
+                },
+            ));
+        }
+
+        let channels = self.qoi().colors.channels();
+        let mut rest = buf;
+
+        if self.pending_len > 0 {
+            let n = (channels - self.pending_len).min(rest.len());
+            self.pending[self.pending_len..][..n].copy_from_slice(&rest[..n]);
+            self.pending_len += n;
+            rest = &rest[n..];
+
+            if self.pending_len < channels {
+                return Ok(buf.len());
+            }
+
+            let pending = self.pending;
+            self.push(&pending[..channels])?;
+            self.pending_len = 0;
+        }
+
+        while rest.len() >= channels {
+            let len = self
+                .encoder
+                .pixels_fitting(self.buf.len())
+                .min(rest.len() / channels);
+            self.push(&rest[..len * channels])?;
+            rest = &rest[len * channels..];
+        }
+
+        let tail = rest;
+        self.pending[..tail.len()].copy_from_slice(tail);
+        self.pending_len = tail.len();
+
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
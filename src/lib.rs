@@ -144,7 +144,7 @@
 //! 8-bit green channel value\
 //! 8-bit  blue channel value\
 //! 8-bit alpha channel value
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 #![deny(missing_copy_implementations)]
 #![deny(missing_debug_implementations)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -157,11 +157,73 @@ use core::{
     fmt::{self, Display},
 };
 
+#[cfg(feature = "animation")]
+pub mod animation;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "bytes")]
+mod buf;
+#[cfg(feature = "checksum")]
+mod checksum;
+pub mod chunk;
+mod compare;
 mod decode;
+mod decoder;
+mod delta;
+mod dump;
+#[cfg(feature = "embedded-io")]
+mod embedded;
 mod encode;
+mod encoder;
+#[cfg(feature = "icc")]
+mod icc;
+#[cfg(feature = "io")]
+mod io;
+#[cfg(feature = "mask")]
+mod mask;
+#[cfg(feature = "metadata")]
+pub mod metadata;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "qoi16")]
+mod qoi16;
+mod retag;
+mod row_decoder;
+mod runs;
+mod seek;
+mod srgb;
+mod stats;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+#[cfg(feature = "alloc")]
+mod transcode;
+#[allow(unsafe_code)]
+mod uninit;
+
+#[cfg(feature = "checksum")]
+pub use checksum::ChecksumAlgo;
+#[cfg(feature = "std")]
+pub use compare::PsnrResult;
+pub use decode::{Concatenated, DecodeError, DecoderState, RawHeader};
+pub use decoder::{DecodeProgress, DecodeStatus, Decoder, IoDecodeError};
+pub use encode::{EncodeError, EncodeStats, EncoderState, SizeEstimate};
+pub use encoder::{Encoder, IoEncodeError, StepResult};
+#[cfg(feature = "io")]
+pub use io::{QoiReader, QoiWriter, ReadDecodeError, WriteEncodeError};
+#[cfg(feature = "mask")]
+pub use mask::QoiMask;
+pub use options::{DecodeOptions, EncodeOptions};
+#[cfg(feature = "qoi16")]
+pub use qoi16::{Qoi16, QOI16_MAGIC};
+pub use retag::RetagError;
+pub use row_decoder::{RowDecoder, Rows};
+pub use stats::{Histogram, StreamStats};
 
-pub use decode::DecodeError;
-pub use encode::EncodeError;
+#[cfg(feature = "alloc")]
+pub use stats::UniqueColors;
+#[cfg(feature = "alloc")]
+pub use transcode::TranscodeError;
 
 const QOI_OP_INDEX: u8 = 0x00; /* 00xxxxxx */
 const QOI_OP_DIFF: u8 = 0x40; /* 01xxxxxx */
@@ -170,8 +232,12 @@ const QOI_OP_RUN: u8 = 0xc0; /* 11xxxxxx */
 const QOI_OP_RGB: u8 = 0xfe; /* 11111110 */
 const QOI_OP_RGBA: u8 = 0xff; /* 11111111 */
 
-const QOI_MAGIC: u32 = u32::from_be_bytes(*b"qoif");
-const QOI_HEADER_SIZE: usize = 14;
+/// Magic bytes `"qoif"` at the start of QOI header as big-endian value.
+pub const QOI_MAGIC: u32 = u32::from_be_bytes(*b"qoif");
+
+/// Size of QOI header in bytes.
+pub const QOI_HEADER_SIZE: usize = 14;
+
 const QOI_PADDING: usize = 8;
 
 /// Trait for pixel types.
@@ -479,6 +545,25 @@ impl Pixel for [u8; 4] {
     }
 }
 
+/// Computes 32 bit FNV-1a hash of `bytes` used as checksum of extension data stored after the end marker.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Converts pixel between channel layouts.\
+/// Alpha channel is dropped or set to `255` as needed.
+#[inline]
+fn convert<P: Pixel, Q: Pixel>(px: &P) -> Q {
+    let mut out = Q::new_opaque();
+    out.set_rgb(px.r(), px.g(), px.b());
+    if Q::HAS_ALPHA {
+        out.set_a(px.a());
+    }
+    out
+}
+
 /// Color variance value.
 /// Wrapping difference between two pixels.
 #[derive(Clone, Copy, Debug)]
@@ -497,7 +582,7 @@ impl Var {
         let b = self.b.wrapping_add(2);
 
         match r | g | b {
-            0x00..=0x03 => Some(QOI_OP_DIFF | (r << 4) as u8 | (g << 2) as u8 | b as u8),
+            0x00..=0x03 => Some(QOI_OP_DIFF | r << 4 | g << 2 | b),
             _ => None,
         }
     }
@@ -542,6 +627,16 @@ impl Colors {
         }
     }
 
+    /// Returns `true` if color channels are in sRGB color space.
+    /// Returns `false` otherwise.
+    #[inline]
+    pub const fn is_srgb(&self) -> bool {
+        match self {
+            Colors::Srgb | Colors::SrgbLinA => true,
+            Colors::Rgb | Colors::Rgba => false,
+        }
+    }
+
     /// Returns `4` if color space has alpha channel.
     /// Returns `3` otherwise.
     #[inline]
@@ -551,6 +646,81 @@ impl Colors {
             Colors::Rgba | Colors::SrgbLinA => 4,
         }
     }
+
+    /// Returns same color space without alpha channel.
+    #[inline]
+    pub const fn without_alpha(&self) -> Self {
+        match self {
+            Colors::Rgb | Colors::Rgba => Colors::Rgb,
+            Colors::Srgb | Colors::SrgbLinA => Colors::Srgb,
+        }
+    }
+}
+
+/// Layout of raw pixels in memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PixelLayout {
+    /// Three bytes per pixel: red, green, blue.
+    Rgb,
+
+    /// Four bytes per pixel: red, green, blue, alpha.
+    Rgba,
+}
+
+impl PixelLayout {
+    /// Returns number of bytes per pixel.
+    #[inline]
+    pub const fn channels(&self) -> usize {
+        match self {
+            PixelLayout::Rgb => 3,
+            PixelLayout::Rgba => 4,
+        }
+    }
+}
+
+/// Order of channels in four-channel pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelOrder {
+    /// Red, green, blue, alpha.
+    Rgba,
+
+    /// Blue, green, red, alpha.
+    Bgra,
+
+    /// Alpha, red, green, blue.
+    Argb,
+
+    /// Alpha, blue, green, red.
+    Abgr,
+}
+
+/// Packing of pixel channels into `u32` value.\
+/// Packing is defined on the value, so it does not depend on target endianness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum U32Layout {
+    /// `0xAARRGGBB`.
+    Argb8888,
+
+    /// `0xRRGGBBAA`.
+    Rgba8888,
+}
+
+/// Clockwise rotation of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    /// No rotation.
+    None,
+
+    /// Rotation by 90 degrees.\
+    /// Width and height of the image are swapped.
+    Rot90,
+
+    /// Rotation by 180 degrees.
+    Rot180,
+
+    /// Rotation by 270 degrees.\
+    /// Width and height of the image are swapped.
+    Rot270,
 }
 
 /// QOI descriptor value.\
@@ -0,0 +1,309 @@
+//! Single channel images such as alpha masks with one byte per pixel.\
+//! This is an extension of this crate, not part of QOI specification.
+//!
+//! Header is the same as QOI header with channels number set to `1`,
+//! so standard decoders reject it with `DecodeError::InvalidChannelsValue`.
+//! Chunks are simplified for single channel values:
+//! ```text
+//! .- QOI_OP_INDEX ----------.
+//! |         Byte[0]         |
+//! |  7  6  5  4  3  2  1  0 |
+//! |-------+-----------------|
+//! |  0  0 |     index       |
+//! `-------------------------`
+//! 6-bit index into array of previously seen values at position `(v * 11) % 64`.
+//!
+//! .- QOI_OP_DIFF -----------.
+//! |         Byte[0]         |
+//! |-------+-----------------|
+//! |  0  1 |      diff       |
+//! `-------------------------`
+//! 6-bit difference from the previous value -32..31 with bias of 32.
+//!
+//! .- QOI_OP_LONG_RUN -------------------------------.
+//! |         Byte[0]         |        Byte[1]        |
+//! |-------+-----------------+-----------------------|
+//! |  1  0 |                run                      |
+//! `-------------------------------------------------`
+//! 14-bit run-length repeating the previous value 1..16384 with bias of -1.
+//!
+//! .- QOI_OP_RUN ------------.
+//! |         Byte[0]         |
+//! |-------+-----------------|
+//! |  1  1 |       run       |
+//! `-------------------------`
+//! 6-bit run-length repeating the previous value 1..63 with bias of -1.
+//!
+//! .- QOI_OP_VALUE ------------------------.
+//! |         Byte[0]         |   Byte[1]   |
+//! |-------------------------+-------------|
+//! |  1  1  1  1  1  1  1  1 |    value    |
+//! `---------------------------------------`
+//! ```
+//! Decoding starts with `0` as the previous value, end marker is the same as in QOI.
+
+use core::convert::TryInto;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use super::*;
+
+/// Tag of chunk with long run of the previous value.
+const QOI_OP_LONG_RUN: u8 = 0x80; /* 10xxxxxx */
+
+/// Tag of chunk with literal value.
+const QOI_OP_VALUE: u8 = 0xff; /* 11111111 */
+
+/// Longest run encoded with `QOI_OP_RUN`.
+const MAX_RUN: usize = 63;
+
+/// Longest run encoded with `QOI_OP_LONG_RUN`.
+const MAX_LONG_RUN: usize = 1 << 14;
+
+/// Descriptor of single channel image.\
+/// This value is parsed from image header during decoding.\
+/// Or provided by caller to drive encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct QoiMask {
+    /// Width of the image in pixels.
+    pub width: u32,
+
+    /// Height of the image in pixels.
+    pub height: u32,
+}
+
+/// Returns position of value in the index.
+#[inline]
+fn hash(v: u8) -> usize {
+    v as usize * 11 % 64
+}
+
+impl QoiMask {
+    /// Returns bytes size for the decoded image.
+    #[inline]
+    pub fn decoded_size(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns maximum size of the `QoiMask::encode` output size.\
+    /// Using smaller slice may cause `QoiMask::encode` to return `Err(EncodeError::OutputIsTooSmall { .. })`.\
+    /// Returns `usize::MAX` if the size cannot be represented on this platform.
+    #[inline]
+    pub fn encoded_size_limit(&self) -> usize {
+        self.size_limit().unwrap_or(usize::MAX)
+    }
+
+    /// Returns descriptor of the image with the same dimensions and specified colors.
+    #[inline]
+    fn with_colors(&self, colors: Colors) -> Qoi {
+        Qoi {
+            width: self.width,
+            height: self.height,
+            colors,
+        }
+    }
+
+    /// Returns maximum size of encoded image.
+    #[inline]
+    fn size_limit(&self) -> Result<usize, EncodeError> {
+        self.with_colors(Colors::Rgb)
+            .input_len(2)?
+            .checked_add(QOI_HEADER_SIZE + QOI_PADDING)
+            .ok_or(EncodeError::TooManyPixels)
+    }
+
+    /// Returns header describing the image.\
+    /// Color space value is `1` since the channel is not color.
+    #[inline]
+    pub const fn header_bytes(&self) -> [u8; QOI_HEADER_SIZE] {
+        let mut header = Qoi {
+            width: self.width,
+            height: self.height,
+            colors: Colors::Rgb,
+        }
+        .header_bytes();
+
+        header[12] = 1;
+        header
+    }
+
+    /// Reads header from encoded single channel image.\
+    /// QOI images with three or four channels are rejected with `Err(DecodeError::InvalidChannelsValue)`.
+    pub fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < QOI_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: QOI_HEADER_SIZE - bytes.len(),
+            });
+        }
+
+        let magic = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        if magic != QOI_MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        match (bytes[12], bytes[13]) {
+            (1, 0 | 1) => Ok(QoiMask {
+                width: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+                height: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+            }),
+            (_, 0 | 1) => Err(DecodeError::InvalidChannelsValue),
+            (_, _) => Err(DecodeError::InvalidColorSpaceValue),
+        }
+    }
+
+    /// Encode single channel pixels with one byte per pixel into an image.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode(&self, pixels: &[u8], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let px_len = self.with_colors(Colors::Rgb).input_len(1)?;
+        let pixels = Qoi::take_input(pixels, px_len)?;
+
+        let required = self.size_limit()?;
+        if output.len() <= QOI_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+        output[..QOI_HEADER_SIZE].copy_from_slice(&self.header_bytes());
+
+        let mut index = [0u8; 64];
+        let mut prev = 0u8;
+        let mut pos = QOI_HEADER_SIZE;
+        let mut rest = pixels;
+
+        while let Some(&v) = rest.first() {
+            let run = rest
+                .iter()
+                .take(MAX_LONG_RUN)
+                .position(|&p| p != prev)
+                .unwrap_or(rest.len().min(MAX_LONG_RUN));
+
+            let (chunk, len) = if run > MAX_RUN {
+                let run = (run - 1) as u16;
+                ([QOI_OP_LONG_RUN | (run >> 8) as u8, run as u8], 2)
+            } else if run > 0 {
+                ([QOI_OP_RUN | (run - 1) as u8, 0], 1)
+            } else if index[hash(v)] == v {
+                ([QOI_OP_INDEX | hash(v) as u8, 0], 1)
+            } else {
+                index[hash(v)] = v;
+                match v.wrapping_sub(prev) as i8 {
+                    diff @ -32..=31 => ([QOI_OP_DIFF | (diff + 32) as u8, 0], 1),
+                    _ => ([QOI_OP_VALUE, v], 2),
+                }
+            };
+
+            match output.get_mut(pos..pos + len) {
+                None => return Err(EncodeError::OutputIsTooSmall { required }),
+                Some(out) => out.copy_from_slice(&chunk[..len]),
+            }
+            pos += len;
+
+            rest = &rest[run.max(1)..];
+            prev = match run {
+                0 => v,
+                _ => prev,
+            };
+        }
+
+        Ok(pos + Qoi::encode_padding(&mut output[pos..], required)?)
+    }
+
+    /// Encode single channel pixels with one byte per pixel into an image.\
+    /// Encoded image is written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_alloc(&self, pixels: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        let limit = self.size_limit()?;
+        let mut output = vec![0; limit];
+        let size = self.encode(pixels, &mut output)?;
+        output.truncate(size);
+        Ok(output)
+    }
+
+    /// Decode single channel image from bytes slice.\
+    /// Decoded pixels with one byte per pixel are written into `output` slice.\
+    /// End marker is verified after the last pixel, bytes following it are not read.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode(bytes: &[u8], output: &mut [u8]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let mut index = [0u8; 64];
+        let mut prev = 0u8;
+        let mut pos = QOI_HEADER_SIZE;
+        let mut decoded = 0;
+
+        while decoded < output.len() {
+            let truncated = |size: usize| DecodeError::NotEnoughData {
+                offset: pos,
+                pixels_decoded: decoded,
+                needed: pos + size - bytes.len(),
+            };
+
+            let tag = *bytes.get(pos).ok_or_else(|| truncated(1))?;
+            let size = match tag {
+                QOI_OP_VALUE => 2,
+                _ if tag & 0xc0 == QOI_OP_LONG_RUN => 2,
+                _ => 1,
+            };
+            let chunk = bytes.get(pos..pos + size).ok_or_else(|| truncated(size))?;
+            pos += size;
+
+            let run = match tag {
+                QOI_OP_VALUE => {
+                    prev = chunk[1];
+                    index[hash(prev)] = prev;
+                    1
+                }
+                _ => match tag & 0xc0 {
+                    QOI_OP_INDEX => {
+                        prev = index[tag as usize];
+                        1
+                    }
+                    QOI_OP_DIFF => {
+                        prev = prev.wrapping_add(tag & 0x3f).wrapping_sub(32);
+                        index[hash(prev)] = prev;
+                        1
+                    }
+                    QOI_OP_LONG_RUN => u16::from_be_bytes([tag & 0x3f, chunk[1]]) as usize + 1,
+                    _ => (tag & 0x3f) as usize + 1,
+                },
+            };
+
+            let end = output.len().min(decoded + run);
+            output[decoded..end].fill(prev);
+            decoded = end;
+        }
+
+        qoi.with_colors(Colors::Rgb).decode_padding(bytes, pos)?;
+        Ok(qoi)
+    }
+
+    /// Decode single channel image from bytes slice.\
+    /// Decoded pixels with one byte per pixel are written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and `vec` containing pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_alloc(bytes: &[u8]) -> Result<(Self, Vec<u8>), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let mut output = vec![0; qoi.decoded_size()];
+        let qoi = Self::decode(bytes, &mut output)?;
+        Ok((qoi, output))
+    }
+}
@@ -0,0 +1,260 @@
+//! Metadata trailer storing key-value entries after the end marker of QOI image with `metadata` feature.\
+//! This is an extension of this crate, not part of QOI specification.
+//! Decoders stop at the end marker, so images with trailer are decoded as usual.
+//!
+//! Trailer starts with `b"qmet"` magic, version byte and big-endian number of entries.
+//! Each entry consists of big-endian key size, UTF-8 key, big-endian value size and value.\
+//! Trailer ends with big-endian 32 bit FNV-1a checksum of preceding trailer bytes,
+//! big-endian size of the whole trailer and `b"qmet"` magic again.
+
+use core::convert::TryInto;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use super::*;
+
+/// Magic value at the start and the end of metadata trailer.
+pub const TRAILER_MAGIC: [u8; 4] = *b"qmet";
+
+/// Version of metadata trailer layout.
+const TRAILER_VERSION: u8 = 1;
+
+/// Size of trailer footer with checksum, trailer size and magic.
+const TRAILER_FOOTER_SIZE: usize = 4 + 4 + 4;
+
+/// Number of bytes in the end of image searched for trailer footer.
+const TRAILER_SCAN_LIMIT: usize = 4096;
+
+/// Key of the entry with ICC profile attached by [`EncodeOptions::icc_profile`].
+pub const ICC_PROFILE_KEY: &str = "icc";
+
+/// Errors that may occur during metadata trailer reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrailerError {
+    /// Checksum of the trailer does not match its content.
+    Checksum,
+
+    /// Trailer has unsupported version.
+    UnsupportedVersion,
+
+    /// Entries of the trailer do not fit into it or keys are not valid UTF-8.
+    Malformed,
+}
+
+impl Display for TrailerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrailerError::Checksum => f.write_str("Checksum of metadata trailer does not match"),
+            TrailerError::UnsupportedVersion => {
+                f.write_str("Metadata trailer has unsupported version")
+            }
+            TrailerError::Malformed => f.write_str("Metadata trailer is malformed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TrailerError {}
+
+/// Appends metadata trailer with `entries` to `out`.\
+/// `out` is expected to contain encoded QOI image ending with the end marker.
+///
+/// # Panics
+///
+/// Panics if number of entries, any key or value or the whole trailer exceeds `u32::MAX`.
+#[cfg(feature = "alloc")]
+pub fn write_trailer(out: &mut Vec<u8>, entries: &[(&str, &[u8])]) {
+    let start = out.len();
+    out.resize(start + trailer_size(entries), 0);
+    encode_trailer(entries, &mut out[start..]);
+}
+
+/// Returns size of metadata trailer with `entries`.
+pub(crate) fn trailer_size(entries: &[(&str, &[u8])]) -> usize {
+    entries
+        .iter()
+        .fold(9 + TRAILER_FOOTER_SIZE, |size, (key, value)| {
+            size + 8 + key.len() + value.len()
+        })
+}
+
+/// Writes metadata trailer with `entries` into the start of `out`
+/// that must hold at least [`trailer_size`] bytes.\
+/// Returns size of the trailer.
+///
+/// # Panics
+///
+/// Panics if number of entries, any key or value or the whole trailer exceeds `u32::MAX`.
+pub(crate) fn encode_trailer(entries: &[(&str, &[u8])], out: &mut [u8]) -> usize {
+    let mut pos = 0;
+    let mut put = |bytes: &[u8]| {
+        out[pos..pos + bytes.len()].copy_from_slice(bytes);
+        pos += bytes.len();
+    };
+
+    put(&TRAILER_MAGIC);
+    put(&[TRAILER_VERSION]);
+    put(&length(entries.len()).to_be_bytes());
+    for (key, value) in entries {
+        put(&length(key.len()).to_be_bytes());
+        put(key.as_bytes());
+        put(&length(value.len()).to_be_bytes());
+        put(value);
+    }
+
+    let checksum = fnv1a(&out[..pos]);
+    out[pos..pos + 4].copy_from_slice(&checksum.to_be_bytes());
+    out[pos + 4..pos + 8].copy_from_slice(&length(pos + TRAILER_FOOTER_SIZE).to_be_bytes());
+    out[pos + 8..pos + TRAILER_FOOTER_SIZE].copy_from_slice(&TRAILER_MAGIC);
+    pos + TRAILER_FOOTER_SIZE
+}
+
+/// Converts length into trailer field.
+#[inline]
+fn length(len: usize) -> u32 {
+    len.try_into().expect("metadata trailer is too large")
+}
+
+/// Reads entries of metadata trailer in the end of `bytes`.\
+/// Trailer footer is searched in the last 4096 bytes, so data appended after trailer is tolerated.
+/// Bytes without trailer have no entries.
+///
+/// On success this function returns `Ok(entries)` with keys and values in order they were written.\
+/// On failure this function returns `Err(err)` with `err` describing cause of the error.
+#[cfg(feature = "alloc")]
+pub fn read_trailer(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, TrailerError> {
+    let entries = match find_trailer(bytes) {
+        None => return Ok(Vec::new()),
+        Some(trailer) => Entries::new(trailer)?,
+    };
+
+    let mut result = Vec::with_capacity(entries.left.min(entries.rest.len() / 8));
+    for entry in entries {
+        let (key, value) = entry?;
+        let key = core::str::from_utf8(key).map_err(|_| TrailerError::Malformed)?;
+        result.push((String::from(key), value.to_vec()));
+    }
+    Ok(result)
+}
+
+/// Returns value of the last entry with `key` in valid metadata trailers in the end of `bytes`.\
+/// Trailers appended one after another are searched from the last one.
+pub(crate) fn find_entry<'a>(mut bytes: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    while let Some(trailer) = find_trailer(bytes) {
+        let start = trailer.as_ptr() as usize - bytes.as_ptr() as usize;
+        if let Ok(entries) = Entries::new(trailer) {
+            let mut found = None;
+            for entry in entries {
+                match entry {
+                    Ok((k, value)) if k == key.as_bytes() => found = Some(value),
+                    Ok(_) => {}
+                    Err(_) => {
+                        found = None;
+                        break;
+                    }
+                }
+            }
+            if found.is_some() {
+                return found;
+            }
+        }
+        bytes = &bytes[..start];
+    }
+    None
+}
+
+/// Returns the last trailer with footer in the end of `bytes`.
+fn find_trailer(bytes: &[u8]) -> Option<&[u8]> {
+    let min = 9 + TRAILER_FOOTER_SIZE;
+    let scan = bytes.len().saturating_sub(TRAILER_SCAN_LIMIT);
+
+    (scan..bytes.len().saturating_sub(TRAILER_MAGIC.len() - 1))
+        .rev()
+        .filter(|&at| bytes[at..at + 4] == TRAILER_MAGIC)
+        .find_map(|at| {
+            let end = at + 4;
+            let size = bytes.get(end.checked_sub(8)?..at)?;
+            let size = u32::from_be_bytes(size.try_into().unwrap()) as usize;
+            let trailer = bytes.get(end.checked_sub(size)?..end)?;
+            match size >= min && trailer[..4] == TRAILER_MAGIC {
+                true => Some(trailer),
+                false => None,
+            }
+        })
+}
+
+/// Iterator over raw keys and values of metadata trailer.
+struct Entries<'a> {
+    rest: &'a [u8],
+    left: usize,
+}
+
+impl<'a> Entries<'a> {
+    /// Verifies checksum and version of `trailer` and returns iterator over its entries.
+    fn new(trailer: &'a [u8]) -> Result<Self, TrailerError> {
+        let footer = trailer.len() - TRAILER_FOOTER_SIZE;
+        let checksum = u32::from_be_bytes(trailer[footer..footer + 4].try_into().unwrap());
+        if fnv1a(&trailer[..footer]) != checksum {
+            return Err(TrailerError::Checksum);
+        }
+        if trailer[4] != TRAILER_VERSION {
+            return Err(TrailerError::UnsupportedVersion);
+        }
+
+        Ok(Entries {
+            rest: &trailer[9..footer],
+            left: u32::from_be_bytes(trailer[5..9].try_into().unwrap()) as usize,
+        })
+    }
+
+    /// Takes field prefixed with big-endian size.
+    fn take_field(&mut self) -> Result<&'a [u8], TrailerError> {
+        if self.rest.len() < 4 {
+            return Err(TrailerError::Malformed);
+        }
+        let (size, tail) = self.rest.split_at(4);
+        let size = u32::from_be_bytes(size.try_into().unwrap()) as usize;
+        if tail.len() < size {
+            return Err(TrailerError::Malformed);
+        }
+        let (field, tail) = tail.split_at(size);
+        self.rest = tail;
+        Ok(field)
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<(&'a [u8], &'a [u8]), TrailerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return match self.rest.is_empty() {
+                true => None,
+                false => {
+                    self.rest = &[];
+                    Some(Err(TrailerError::Malformed))
+                }
+            };
+        }
+
+        self.left -= 1;
+        let entry = self
+            .take_field()
+            .and_then(|key| Ok((key, self.take_field()?)));
+        if entry.is_err() {
+            self.left = 0;
+            self.rest = &[];
+        }
+        Some(entry)
+    }
+}
+
+impl Qoi {
+    /// Returns ICC profile attached to a QOI image from bytes slice with [`EncodeOptions::icc_profile`].\
+    /// Returns `None` if bytes do not end with valid metadata trailer containing profile.
+    #[inline]
+    pub fn icc_profile(bytes: &[u8]) -> Option<&[u8]> {
+        find_entry(bytes, ICC_PROFILE_KEY)
+    }
+}
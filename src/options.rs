@@ -0,0 +1,247 @@
+use super::*;
+
+/// Options of image encoding used by [`Qoi::encode_with`].\
+/// Options can be combined with each other.
+/// Default options describe tightly packed pixels with channels number of the image in RGB or RGBA order,
+/// same as expected by [`Qoi::encode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EncodeOptions<'a> {
+    pub(crate) layout: Option<PixelLayout>,
+    pub(crate) order: Option<ChannelOrder>,
+    pub(crate) stride: Option<usize>,
+    pub(crate) flip: bool,
+    pub(crate) premultiplied: bool,
+    pub(crate) reference_compatible: bool,
+    #[cfg_attr(not(feature = "metadata"), allow(dead_code))]
+    pub(crate) icc_profile: Option<&'a [u8]>,
+}
+
+impl Default for EncodeOptions<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> EncodeOptions<'a> {
+    /// Returns default options.
+    #[inline]
+    pub const fn new() -> Self {
+        EncodeOptions {
+            layout: None,
+            order: None,
+            stride: None,
+            flip: false,
+            premultiplied: false,
+            reference_compatible: false,
+            icc_profile: None,
+        }
+    }
+
+    /// Sets layout of input pixels regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.
+    #[inline]
+    pub const fn layout(mut self, layout: PixelLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Sets order of channels in input pixels.\
+    /// Implies four channels in input pixels,
+    /// orders other than [`ChannelOrder::Rgba`] cannot be combined with [`PixelLayout::Rgb`].
+    #[inline]
+    pub const fn channel_order(mut self, order: ChannelOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Sets distance in bytes between starts of consecutive rows of input pixels.\
+    /// Bytes between rows are ignored.
+    #[inline]
+    pub const fn stride(mut self, stride: usize) -> Self {
+        self.stride = Some(stride);
+        self
+    }
+
+    /// Sets whether rows of input pixels are in bottom to top order.
+    #[inline]
+    pub const fn flip(mut self, flip: bool) -> Self {
+        self.flip = flip;
+        self
+    }
+
+    /// Sets whether color channels of input pixels are premultiplied by alpha.\
+    /// Has no effect on pixels without alpha channel.
+    #[inline]
+    pub const fn premultiplied(mut self, premultiplied: bool) -> Self {
+        self.premultiplied = premultiplied;
+        self
+    }
+
+    /// Sets whether encoded bytes must be exactly the same as produced by reference encoder.
+    #[inline]
+    pub const fn reference_compatible(mut self, reference_compatible: bool) -> Self {
+        self.reference_compatible = reference_compatible;
+        self
+    }
+
+    /// Sets ICC profile describing color space of input pixels.\
+    /// Profile is stored in metadata trailer after the end marker under `"icc"` key
+    /// and color space in the header is set to [`Colors::Srgb`] or [`Colors::SrgbLinA`]
+    /// since it is superseded by the profile.\
+    /// Use [`Qoi::icc_profile`] to read it back.
+    #[cfg(feature = "metadata")]
+    #[inline]
+    pub const fn icc_profile(mut self, profile: &'a [u8]) -> Self {
+        self.icc_profile = Some(profile);
+        self
+    }
+
+    /// Returns channels number of input pixels.\
+    /// Returns `None` if options cannot be combined.
+    #[inline]
+    pub(crate) fn channels(&self, colors: Colors) -> Option<usize> {
+        channels(self.layout, self.order, colors)
+    }
+}
+
+/// Options of image decoding used by [`Qoi::decode_with`].\
+/// Options can be combined with each other.
+/// Default options describe tightly packed pixels with channels number of the image in RGB or RGBA order,
+/// same as produced by [`Qoi::decode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    pub(crate) layout: Option<PixelLayout>,
+    pub(crate) order: Option<ChannelOrder>,
+    pub(crate) stride: Option<usize>,
+    pub(crate) flip: bool,
+    pub(crate) premultiplied: bool,
+    pub(crate) strict: bool,
+    pub(crate) clamp_runs: bool,
+    #[cfg(feature = "icc")]
+    pub(crate) to_srgb: bool,
+}
+
+impl Default for DecodeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecodeOptions {
+    /// Returns default options.
+    #[inline]
+    pub const fn new() -> Self {
+        DecodeOptions {
+            layout: None,
+            order: None,
+            stride: None,
+            flip: false,
+            premultiplied: false,
+            strict: false,
+            clamp_runs: false,
+            #[cfg(feature = "icc")]
+            to_srgb: false,
+        }
+    }
+
+    /// Sets layout of output pixels regardless of channels number in the image.\
+    /// Alpha channel is dropped or set to `255` as needed.
+    #[inline]
+    pub const fn layout(mut self, layout: PixelLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Sets order of channels in output pixels.\
+    /// Implies four channels in output pixels,
+    /// orders other than [`ChannelOrder::Rgba`] cannot be combined with [`PixelLayout::Rgb`].
+    #[inline]
+    pub const fn channel_order(mut self, order: ChannelOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Sets distance in bytes between starts of consecutive rows of output pixels.\
+    /// Bytes between rows are left untouched.
+    #[inline]
+    pub const fn stride(mut self, stride: usize) -> Self {
+        self.stride = Some(stride);
+        self
+    }
+
+    /// Sets whether rows of output pixels are written in bottom to top order.
+    #[inline]
+    pub const fn flip(mut self, flip: bool) -> Self {
+        self.flip = flip;
+        self
+    }
+
+    /// Sets whether color channels of output pixels are premultiplied by alpha.\
+    /// Each color channel is computed as `(c * a + 127) / 255`.
+    #[inline]
+    pub const fn premultiplied(mut self, premultiplied: bool) -> Self {
+        self.premultiplied = premultiplied;
+        self
+    }
+
+    /// Sets whether end marker must follow the chunk with the last pixel
+    /// and encoded data must end right after it.\
+    /// Missing end marker is reported as `DecodeError::NotEnoughData`,
+    /// corrupted one as `DecodeError::InvalidPadding`
+    /// and bytes following it as `DecodeError::TrailingData`.\
+    /// Run exceeding the last pixel is reported as `DecodeError::TooManyPixels`
+    /// and end marker reached before the last pixel as `DecodeError::NotEnoughPixels`.\
+    /// Use [`Qoi::decode_counted`] to accept trailing bytes and find where encoded image ends.
+    #[inline]
+    pub const fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether run exceeding the last pixel is clamped to the image size in strict mode,
+    /// same as reference decoder does.\
+    /// Such runs are always clamped if decoding is not strict.
+    #[inline]
+    pub const fn clamp_runs(mut self, clamp_runs: bool) -> Self {
+        self.clamp_runs = clamp_runs;
+        self
+    }
+
+    /// Sets whether decoded pixels are converted into sRGB color space
+    /// using ICC profile stored in the image with [`EncodeOptions::icc_profile`].\
+    /// Only RGB matrix/TRC profiles are supported,
+    /// images without profile are decoded as is.\
+    /// Cannot be combined with [`DecodeOptions::premultiplied`].
+    #[cfg(feature = "icc")]
+    #[inline]
+    pub const fn to_srgb(mut self, to_srgb: bool) -> Self {
+        self.to_srgb = to_srgb;
+        self
+    }
+
+    /// Returns channels number of output pixels.\
+    /// Returns `None` if options cannot be combined.
+    #[inline]
+    pub(crate) fn channels(&self, colors: Colors) -> Option<usize> {
+        channels(self.layout, self.order, colors)
+    }
+}
+
+/// Returns channels number of raw pixels with specified layout and channels order.
+#[inline]
+fn channels(
+    layout: Option<PixelLayout>,
+    order: Option<ChannelOrder>,
+    colors: Colors,
+) -> Option<usize> {
+    match (layout, order) {
+        (Some(PixelLayout::Rgb), None | Some(ChannelOrder::Rgba)) => Some(3),
+        (Some(PixelLayout::Rgb), Some(_)) => None,
+        (Some(PixelLayout::Rgba), _) | (None, Some(_)) => Some(4),
+        (None, None) => Some(colors.channels()),
+    }
+}
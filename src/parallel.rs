@@ -0,0 +1,238 @@
+//! Encoding and decoding of single image on multiple threads with `rayon` feature.
+//!
+//! Image is split into bands of rows.
+//! State of the encoder at the start of each band is reconstructed without encoding:
+//! each pixel that differs from the previous one is stored in the index,
+//! and run length is the number of trailing repeated pixels modulo maximum run.\
+//! Bands are then encoded concurrently from their entry states and concatenated,
+//! producing exactly the same bytes as serial encoder.
+//!
+//! Decoding walks chunks of the whole image first without storing pixels,
+//! taking snapshot of the decoder state at the start of each band.\
+//! Bands are then decoded concurrently from their snapshots into their parts of the output.
+
+use alloc::{vec, vec::Vec};
+use rayon::prelude::*;
+
+use super::*;
+use encode::Slice;
+
+/// Images with fewer pixels are encoded and decoded serially.
+const PARALLEL_MIN_PIXELS: usize = 1 << 20;
+
+/// Number of pixels in a run chunk with the longest run.
+const MAX_RUN: usize = 62;
+
+/// Changes of encoder state made by a band of pixels.
+struct BandState {
+    /// Last pixel stored into each slot of the index by the band.
+    slots: [Option<[u8; 4]>; 64],
+
+    /// Number of trailing pixels equal to the pixel preceding them.
+    repeated: usize,
+}
+
+impl BandState {
+    /// Scans `pixels` following `prev` pixel.
+    fn new<const N: usize>(pixels: &[[u8; N]], mut prev: [u8; N]) -> Self
+    where
+        [u8; N]: Pixel,
+    {
+        let mut slots = [None; 64];
+        let mut repeated = 0;
+        for &px in pixels {
+            if px == prev {
+                repeated += 1;
+            } else {
+                slots[px.hash() as usize] = Some(px.rgba());
+                repeated = 0;
+                prev = px;
+            }
+        }
+        BandState { slots, repeated }
+    }
+}
+
+/// Entry state of the encoder for a band.
+struct Entry<const N: usize> {
+    index: [[u8; 4]; 64],
+    px_prev: [u8; N],
+    run: usize,
+}
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image using multiple threads.\
+    /// Image is split into `threads` bands of rows encoded concurrently on rayon global thread pool,
+    /// `0` uses number of threads in the pool.\
+    /// Encoded image is exactly the same as produced by `Qoi::encode`,
+    /// small images are encoded serially.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn encode_parallel(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<usize, EncodeError> {
+        let threads = match threads {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        };
+
+        let total = self.input_len(1)?;
+        if threads < 2 || total < PARALLEL_MIN_PIXELS {
+            return self.encode(pixels, output);
+        }
+
+        match self.colors.has_alpha() {
+            true => self.encode_parallel_as::<4>(pixels, output, threads),
+            false => self.encode_parallel_as::<3>(pixels, output, threads),
+        }
+    }
+
+    /// Encode pixels with `N` channels splitting the image into `threads` bands.
+    fn encode_parallel_as<const N: usize>(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let px_len = self.input_len(N)?;
+        let pixels = bytemuck::cast_slice::<_, [u8; N]>(Self::take_input(pixels, px_len)?);
+
+        let required = self.size_limit()?;
+        if output.len() <= QOI_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+
+        let rows = (self.height as usize).div_ceil(threads);
+        let bands = pixels
+            .chunks(rows * self.width as usize)
+            .collect::<Vec<_>>();
+
+        let initial = <[u8; N]>::new_opaque();
+        let states = bands
+            .par_iter()
+            .enumerate()
+            .map(|(i, band)| match i {
+                0 => BandState::new(band, initial),
+                _ => BandState::new(band, *bands[i - 1].last().unwrap()),
+            })
+            .collect::<Vec<_>>();
+
+        let mut entries = Vec::with_capacity(bands.len());
+        let mut entry = Entry {
+            index: [[0; 4]; 64],
+            px_prev: initial,
+            run: 0,
+        };
+        for (band, state) in bands.iter().zip(&states) {
+            let next = Entry {
+                index: core::array::from_fn(|i| state.slots[i].unwrap_or(entry.index[i])),
+                px_prev: *band.last().unwrap(),
+                run: match state.repeated == band.len() {
+                    true => (entry.run + band.len()) % MAX_RUN,
+                    false => state.repeated % MAX_RUN,
+                },
+            };
+            entries.push(core::mem::replace(&mut entry, next));
+        }
+
+        let last = bands.len() - 1;
+        let encoded = bands
+            .par_iter()
+            .zip(entries)
+            .enumerate()
+            .map(|(i, (band, mut entry))| {
+                // Room for pending run, chunks of all pixels and spare bytes checked by the sink.
+                let mut encoded = vec![0; 1 + band.len() * (N + 1) + QOI_PADDING];
+                let mut sink = Slice::new(&mut encoded);
+                Self::encode_range_into::<N, false, _, _>(
+                    &mut entry.index,
+                    &mut entry.px_prev,
+                    &mut entry.run,
+                    &mut &band[..],
+                    &mut sink,
+                    i == last,
+                    required,
+                )?;
+                let written = sink.written;
+                encoded.truncate(written);
+                Ok(encoded)
+            })
+            .collect::<Result<Vec<_>, EncodeError>>()?;
+
+        self.write_header(output);
+        let mut size = QOI_HEADER_SIZE;
+        for band in &encoded {
+            match output.get_mut(size..size + band.len()) {
+                None => return Err(EncodeError::OutputIsTooSmall { required }),
+                Some(output) => output.copy_from_slice(band),
+            }
+            size += band.len();
+        }
+
+        Ok(size + Self::encode_padding(&mut output[size..], required)?)
+    }
+
+    /// Decode a QOI image from bytes slice using multiple threads.\
+    /// Image is split into `threads` bands of rows decoded concurrently on rayon global thread pool,
+    /// `0` uses number of threads in the pool.\
+    /// Chunks preceding each band are walked through before decoding bands,
+    /// decoded pixels and errors are the same as with `Qoi::decode`,
+    /// small images are decoded serially.\
+    /// Decoded raw RGB or RGBA pixels are written into `output` slice.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn decode_parallel(
+        bytes: &[u8],
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<Self, DecodeError> {
+        let threads = match threads {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        };
+
+        let qoi = Self::decode_header(bytes)?;
+        let total = qoi.width as usize * qoi.height as usize;
+        if threads < 2 || total < PARALLEL_MIN_PIXELS {
+            return Self::decode(bytes, output);
+        }
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let band = (qoi.height as usize).div_ceil(threads) * qoi.width as usize;
+        let bands = total.div_ceil(band);
+
+        // Snapshots are taken up to the last band, end marker is not verified as in `Qoi::decode`.
+        let mut states = vec![DecoderState::new()];
+        while states.len() < bands {
+            let mut state = states.last().unwrap().clone();
+            qoi.skip_resume(bytes, &mut state, band)?;
+            states.push(state);
+        }
+
+        output
+            .par_chunks_mut(band * qoi.colors.channels())
+            .zip(states)
+            .try_for_each(|(output, mut state)| {
+                match qoi.colors.has_alpha() {
+                    true => qoi.decode_resume_as::<4, false>(bytes, &mut state, output, band),
+                    false => qoi.decode_resume_as::<3, false>(bytes, &mut state, output, band),
+                }
+                .map(|_| ())
+            })?;
+
+        Ok(qoi)
+    }
+}
@@ -0,0 +1,461 @@
+//! Extended QOI format with 16 bits per channel with `qoi16` feature.\
+//! This is an extension of this crate, not part of QOI specification,
+//! images of this format are not readable by other QOI decoders.
+//!
+//! Header has the same layout as QOI header but starts with `b"qoi2"` magic,
+//! so standard decoders reject it with `DecodeError::InvalidMagic`.
+//! Chunks follow the same scheme with channel values widened to 16 bits.
+//! Literal channels are stored as big-endian `u16` values and differences wrap around `65536`.
+//! ```text
+//! .- QOI_OP_INDEX ----------.
+//! |         Byte[0]         |
+//! |  7  6  5  4  3  2  1  0 |
+//! |-------+-----------------|
+//! |  0  0 |     index       |
+//! `-------------------------`
+//!
+//! .- QOI_OP_DIFF -----------------------------------------.
+//! |         Byte[0]         |         Byte[1]             |
+//! |  7  6  5  4  3  2  1  0 |  7  6  5  4 | 3  2  1  0    |
+//! |-------+-----------------+-------------+---------------|
+//! |  0  1 |   diff green    |  dr - dg    |  db - dg      |
+//! `-------------------------------------------------------`
+//! 6-bit green channel difference from the previous pixel -32..31,
+//! 4-bit red and blue channel differences minus green channel difference -8..7.
+//!
+//! .- QOI_OP_LUMA ---------------------------------------------------------.
+//! |         Byte[0]         |         Byte[1]         |     Byte[2]       |
+//! |-------+-----------------+-------------+-----------+-------------------|
+//! |  1  0 |          diff green           |  dr - dg  |      db - dg      |
+//! `-----------------------------------------------------------------------`
+//! 10-bit green channel difference from the previous pixel -512..511,
+//! 6-bit red and blue channel differences minus green channel difference -32..31.
+//!
+//! .- QOI_OP_RUN ------------.
+//! |         Byte[0]         |
+//! |-------+-----------------|
+//! |  1  1 |       run       |
+//! `-------------------------`
+//! 6-bit run-length repeating the previous pixel 1..62 with bias of -1.
+//!
+//! .- QOI_OP_RGB ------------------------------------------.
+//! |  Byte[0]  | Byte[1..3] | Byte[3..5] | Byte[5..7]      |
+//! |-----------+------------+------------+-----------------|
+//! | 11111110  |    red     |   green    |     blue        |
+//! `-------------------------------------------------------`
+//!
+//! .- QOI_OP_RGBA -----------------------------------------------------.
+//! |  Byte[0]  | Byte[1..3] | Byte[3..5] | Byte[5..7] | Byte[7..9]     |
+//! |-----------+------------+------------+------------+----------------|
+//! | 11111111  |    red     |   green    |    blue    |    alpha       |
+//! `-------------------------------------------------------------------`
+//! ```
+//! Decoding starts with `{r: 0, g: 0, b: 0, a: 65535}` as the previous pixel value,
+//! hash function and end marker are the same as in QOI.
+
+use core::convert::TryInto;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use super::*;
+
+/// Magic value of the extended format header.
+pub const QOI16_MAGIC: u32 = u32::from_be_bytes(*b"qoi2");
+
+/// Maximum size of a single chunk.
+const MAX_CHUNK_SIZE: usize = 9;
+
+/// Descriptor of an image in extended format with 16 bits per channel.\
+/// This value is parsed from image header during decoding.\
+/// Or provided by caller to drive encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct Qoi16 {
+    /// Width of the image in pixels.
+    pub width: u32,
+
+    /// Height of the image in pixels.
+    pub height: u32,
+
+    /// Specifies image color space.
+    pub colors: Colors,
+}
+
+impl From<Qoi> for Qoi16 {
+    #[inline]
+    fn from(qoi: Qoi) -> Self {
+        Qoi16 {
+            width: qoi.width,
+            height: qoi.height,
+            colors: qoi.colors,
+        }
+    }
+}
+
+impl From<Qoi16> for Qoi {
+    #[inline]
+    fn from(qoi: Qoi16) -> Self {
+        Qoi {
+            width: qoi.width,
+            height: qoi.height,
+            colors: qoi.colors,
+        }
+    }
+}
+
+/// Returns position of pixel in the index.
+#[inline]
+fn hash(px: [u16; 4]) -> usize {
+    let [r, g, b, a] = px.map(u32::from);
+    (r * 3 + g * 5 + b * 7 + a * 11) as usize % 64
+}
+
+impl Qoi16 {
+    /// Returns number of `u16` values for the decoded image.
+    #[inline]
+    pub fn decoded_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.colors.channels()
+    }
+
+    /// Returns maximum size of the `Qoi16::encode` output size.\
+    /// Using smaller slice may cause `Qoi16::encode` to return `Err(EncodeError::OutputIsTooSmall { .. })`.\
+    /// Returns `usize::MAX` if the size cannot be represented on this platform.
+    #[inline]
+    pub fn encoded_size_limit(&self) -> usize {
+        self.size_limit().unwrap_or(usize::MAX)
+    }
+
+    /// Returns maximum size of encoded image.
+    #[inline]
+    fn size_limit(&self) -> Result<usize, EncodeError> {
+        Qoi::from(*self)
+            .input_len(self.colors.channels() * 2 + 1)?
+            .checked_add(QOI_HEADER_SIZE + QOI_PADDING)
+            .ok_or(EncodeError::TooManyPixels)
+    }
+
+    /// Returns header describing the image.
+    #[inline]
+    pub const fn header_bytes(&self) -> [u8; QOI_HEADER_SIZE] {
+        let mut header = Qoi {
+            width: self.width,
+            height: self.height,
+            colors: self.colors,
+        }
+        .header_bytes();
+
+        let magic = QOI16_MAGIC.to_be_bytes();
+        header[0] = magic[0];
+        header[1] = magic[1];
+        header[2] = magic[2];
+        header[3] = magic[3];
+        header
+    }
+
+    /// Reads header from encoded image in extended format.\
+    /// Standard QOI images are rejected with `Err(DecodeError::InvalidMagic)`.
+    pub fn decode_header(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < QOI_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: QOI_HEADER_SIZE - bytes.len(),
+            });
+        }
+
+        let magic = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        if magic != QOI16_MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        // Fields following magic have the same layout as in QOI header.
+        let mut header: [u8; QOI_HEADER_SIZE] = bytes[..QOI_HEADER_SIZE].try_into().unwrap();
+        header[..4].copy_from_slice(&QOI_MAGIC.to_be_bytes());
+        Qoi::decode_header(&header).map(Self::from)
+    }
+
+    /// Encode RGB or RGBA pixels with 16 bits per channel into an image in extended format.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn encode(&self, pixels: &[u16], output: &mut [u8]) -> Result<usize, EncodeError> {
+        let px_len = Qoi::from(*self).input_len(self.colors.channels())?;
+        let pixels = Qoi::take_input(pixels, px_len)?;
+
+        match self.colors.has_alpha() {
+            true => self.encode_as::<4>(pixels, output),
+            false => self.encode_as::<3>(pixels, output),
+        }
+    }
+
+    /// Encode pixels with `N` channels.
+    fn encode_as<const N: usize>(
+        &self,
+        pixels: &[u16],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let required = self.size_limit()?;
+        if output.len() <= QOI_HEADER_SIZE {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+
+        output[..QOI_HEADER_SIZE].copy_from_slice(&self.header_bytes());
+
+        let mut index = [[0u16; 4]; 64];
+        let mut px_prev = [0, 0, 0, u16::MAX];
+        let mut run = 0;
+        let mut pos = QOI_HEADER_SIZE;
+
+        let chunks = pixels.chunks_exact(N);
+        let last = chunks.len().wrapping_sub(1);
+        for (i, chunk) in chunks.enumerate() {
+            let mut px = [0, 0, 0, u16::MAX];
+            px[..N].copy_from_slice(chunk);
+
+            // Run may be flushed before the chunk of this pixel.
+            let mut buf = [0; 1 + MAX_CHUNK_SIZE];
+            let mut len = 0;
+
+            if px == px_prev {
+                run += 1;
+                if run == 62 || i == last {
+                    buf[0] = QOI_OP_RUN | (run - 1);
+                    len = 1;
+                    run = 0;
+                }
+            } else {
+                if run > 0 {
+                    buf[0] = QOI_OP_RUN | (run - 1);
+                    len = 1;
+                    run = 0;
+                }
+
+                let h = hash(px);
+                len += if index[h] == px {
+                    buf[len] = QOI_OP_INDEX | h as u8;
+                    1
+                } else if px[3] == px_prev[3] {
+                    index[h] = px;
+                    Self::encode_rgb(px, px_prev, &mut buf[len..])
+                } else {
+                    index[h] = px;
+                    buf[len] = QOI_OP_RGBA;
+                    for (c, v) in px.iter().enumerate() {
+                        buf[len + 1 + c * 2..][..2].copy_from_slice(&v.to_be_bytes());
+                    }
+                    MAX_CHUNK_SIZE
+                };
+                px_prev = px;
+            }
+
+            match output.get_mut(pos..pos + len) {
+                None => return Err(EncodeError::OutputIsTooSmall { required }),
+                Some(out) => out.copy_from_slice(&buf[..len]),
+            }
+            pos += len;
+        }
+
+        Ok(pos + Qoi::encode_padding(&mut output[pos..], required)?)
+    }
+
+    /// Encode pixel `px` with the same alpha as `px_prev` choosing the smallest chunk.\
+    /// Returns size of the chunk.
+    #[inline]
+    fn encode_rgb(px: [u16; 4], px_prev: [u16; 4], output: &mut [u8]) -> usize {
+        let dr = px[0].wrapping_sub(px_prev[0]) as i16;
+        let dg = px[1].wrapping_sub(px_prev[1]) as i16;
+        let db = px[2].wrapping_sub(px_prev[2]) as i16;
+        let dr_dg = dr.wrapping_sub(dg);
+        let db_dg = db.wrapping_sub(dg);
+
+        if (-32..32).contains(&dg) && (-8..8).contains(&dr_dg) && (-8..8).contains(&db_dg) {
+            let v = ((dg + 32) as u16) << 8 | ((dr_dg + 8) as u16) << 4 | (db_dg + 8) as u16;
+            output[0] = QOI_OP_DIFF | (v >> 8) as u8;
+            output[1] = v as u8;
+            2
+        } else if (-512..512).contains(&dg)
+            && (-32..32).contains(&dr_dg)
+            && (-32..32).contains(&db_dg)
+        {
+            let v = ((dg + 512) as u32) << 12 | ((dr_dg + 32) as u32) << 6 | (db_dg + 32) as u32;
+            output[0] = QOI_OP_LUMA | (v >> 16) as u8;
+            output[1] = (v >> 8) as u8;
+            output[2] = v as u8;
+            3
+        } else {
+            output[0] = QOI_OP_RGB;
+            for (c, v) in px[..3].iter().enumerate() {
+                output[1 + c * 2..][..2].copy_from_slice(&v.to_be_bytes());
+            }
+            7
+        }
+    }
+
+    /// Encode RGB or RGBA pixels with 16 bits per channel into an image in extended format.\
+    /// Encoded image is written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_alloc(&self, pixels: &[u16]) -> Result<Vec<u8>, EncodeError> {
+        let limit = self.size_limit()?;
+        let mut output = vec![0; limit];
+        let size = self.encode(pixels, &mut output)?;
+        output.truncate(size);
+        Ok(output)
+    }
+
+    /// Decode an image in extended format from bytes slice.\
+    /// Decoded RGB or RGBA pixels with 16 bits per channel are written into `output` slice.\
+    /// End marker is verified after the last pixel, bytes following it are not read.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode(bytes: &[u8], output: &mut [u16]) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let end = match qoi.colors.has_alpha() {
+            true => qoi.decode_as::<4>(bytes, output)?,
+            false => qoi.decode_as::<3>(bytes, output)?,
+        };
+        Qoi::from(qoi).decode_padding(bytes, end)?;
+        Ok(qoi)
+    }
+
+    /// Decode pixels with `N` channels.\
+    /// Returns offset of the end marker.
+    fn decode_as<const N: usize>(
+        &self,
+        bytes: &[u8],
+        output: &mut [u16],
+    ) -> Result<usize, DecodeError> {
+        let mut index = [[0u16; 4]; 64];
+        let mut px = [0, 0, 0, u16::MAX];
+        let mut pos = QOI_HEADER_SIZE;
+        let mut pixels = output.chunks_exact_mut(N).enumerate();
+
+        while let Some((decoded, out)) = pixels.next() {
+            let truncated = |size: usize| DecodeError::NotEnoughData {
+                offset: pos,
+                pixels_decoded: decoded,
+                needed: pos + size - bytes.len(),
+            };
+
+            let tag = *bytes.get(pos).ok_or_else(|| truncated(1))?;
+            let size = match tag {
+                QOI_OP_RGB => 7,
+                QOI_OP_RGBA => 9,
+                _ => match tag & 0xc0 {
+                    QOI_OP_DIFF => 2,
+                    QOI_OP_LUMA => 3,
+                    _ => 1,
+                },
+            };
+            let chunk = bytes.get(pos..pos + size).ok_or_else(|| truncated(size))?;
+            let channel = |c: usize| u16::from_be_bytes([chunk[1 + c * 2], chunk[2 + c * 2]]);
+            pos += size;
+
+            match tag {
+                QOI_OP_RGB => {
+                    px[0] = channel(0);
+                    px[1] = channel(1);
+                    px[2] = channel(2);
+                }
+                QOI_OP_RGBA => px = [channel(0), channel(1), channel(2), channel(3)],
+                _ => match tag & 0xc0 {
+                    QOI_OP_INDEX => px = index[tag as usize],
+                    QOI_OP_DIFF => {
+                        let v = u16::from_be_bytes([tag & 0x3f, chunk[1]]);
+                        let dg = (v >> 8) as i16 - 32;
+                        let dr = dg + (v >> 4 & 0xf) as i16 - 8;
+                        let db = dg + (v & 0xf) as i16 - 8;
+                        Self::add_rgb(&mut px, dr, dg, db);
+                    }
+                    QOI_OP_LUMA => {
+                        let v = u32::from_be_bytes([0, tag & 0x3f, chunk[1], chunk[2]]);
+                        let dg = (v >> 12) as i16 - 512;
+                        let dr = dg + (v >> 6 & 0x3f) as i16 - 32;
+                        let db = dg + (v & 0x3f) as i16 - 32;
+                        Self::add_rgb(&mut px, dr, dg, db);
+                    }
+                    _ => {
+                        let run = (tag & 0x3f) as usize;
+                        out.copy_from_slice(&px[..N]);
+                        for (_, out) in pixels.by_ref().take(run) {
+                            out.copy_from_slice(&px[..N]);
+                        }
+                        continue;
+                    }
+                },
+            }
+
+            index[hash(px)] = px;
+            out.copy_from_slice(&px[..N]);
+        }
+
+        Ok(pos)
+    }
+
+    /// Adds wrapping differences to color channels of `px`.
+    #[inline]
+    fn add_rgb(px: &mut [u16; 4], dr: i16, dg: i16, db: i16) {
+        px[0] = px[0].wrapping_add(dr as u16);
+        px[1] = px[1].wrapping_add(dg as u16);
+        px[2] = px[2].wrapping_add(db as u16);
+    }
+
+    /// Decode an image in extended format from bytes slice.\
+    /// Decoded RGB or RGBA pixels with 16 bits per channel are written into allocated `Vec`.
+    ///
+    /// On success this function returns `Ok((qoi, vec))` with `qoi` describing image dimensions and color space and `vec` containing pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_alloc(bytes: &[u8]) -> Result<(Self, Vec<u16>), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let mut output = vec![0; qoi.decoded_size()];
+        let qoi = Self::decode(bytes, &mut output)?;
+        Ok((qoi, output))
+    }
+
+    /// Convert a QOI image from bytes slice into image in extended format.\
+    /// Each channel value `v` is widened into `v << 8 | v`, same as [`Qoi::decode_u16`] does.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing converted image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    pub fn from_qoi(bytes: &[u8]) -> Result<Vec<u8>, TranscodeError> {
+        let qoi = Qoi::decode_header(bytes)?;
+
+        let mut pixels = vec![0; qoi.decoded_size_u16()];
+        Qoi::decode_u16(bytes, &mut pixels)?;
+        Ok(Self::from(qoi).encode_alloc(&pixels)?)
+    }
+
+    /// Convert an image in extended format from bytes slice into QOI image.\
+    /// Each channel value is rounded to the nearest 8 bit value,
+    /// so images converted with [`Qoi16::from_qoi`] are converted back exactly.
+    ///
+    /// On success this function returns `Ok(vec)` with `vec` containing converted image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    pub fn to_qoi(bytes: &[u8]) -> Result<Vec<u8>, TranscodeError> {
+        let (qoi, pixels) = Self::decode_alloc(bytes)?;
+
+        let pixels = pixels
+            .iter()
+            .map(|&v| ((v as u32 * 255 + 32767) / 65535) as u8)
+            .collect::<Vec<_>>();
+        Ok(Qoi::from(qoi).encode_alloc(&pixels)?)
+    }
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+/// Errors that may occur during color space retagging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RetagError {
+    /// Bytes are not a valid QOI image.
+    Decode(DecodeError),
+
+    /// Requested color space has different channels number than the image.
+    ChannelsMismatch {
+        /// Channels number of the image.
+        channels: usize,
+    },
+}
+
+impl Display for RetagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetagError::Decode(err) => Display::fmt(err, f),
+            RetagError::ChannelsMismatch { channels } => write!(
+                f,
+                "Requested color space does not match {} channels of the image",
+                channels
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RetagError {}
+
+impl From<DecodeError> for RetagError {
+    #[inline]
+    fn from(err: DecodeError) -> Self {
+        RetagError::Decode(err)
+    }
+}
+
+impl Qoi {
+    /// Rewrite color space in the header of a QOI image stored in bytes slice without re-encoding it.\
+    /// Header is verified before it is modified, chunks are not read,
+    /// so pixels of the image are left exactly as they are.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image with updated header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error, `bytes` are not modified.\
+    /// `Err(RetagError::ChannelsMismatch { channels })` is returned if `colors` has different channels number than the image.
+    #[inline]
+    pub fn retag_colorspace(bytes: &mut [u8], colors: Colors) -> Result<Self, RetagError> {
+        let qoi = Self::decode_header(bytes)?;
+        Self::retag(bytes, qoi, colors)
+    }
+
+    /// Rewrite color space in the header of a QOI image stored in bytes slice without re-encoding it.\
+    /// Same as [`Qoi::retag_colorspace`] but the whole image is verified with [`Qoi::validate`] first.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image with updated header.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error, `bytes` are not modified.
+    #[inline]
+    pub fn retag_colorspace_validated(
+        bytes: &mut [u8],
+        colors: Colors,
+    ) -> Result<Self, RetagError> {
+        let qoi = Self::validate(bytes)?;
+        Self::retag(bytes, qoi, colors)
+    }
+
+    /// Writes header of image `qoi` with `colors` into `bytes`.
+    #[inline]
+    fn retag(bytes: &mut [u8], qoi: Qoi, colors: Colors) -> Result<Self, RetagError> {
+        if qoi.colors.channels() != colors.channels() {
+            return Err(RetagError::ChannelsMismatch {
+                channels: qoi.colors.channels(),
+            });
+        }
+
+        let retagged = Qoi { colors, ..qoi };
+        retagged.write_header(bytes);
+        Ok(retagged)
+    }
+}
@@ -0,0 +1,309 @@
+use super::*;
+
+/// Decoder that pulls encoded bytes through a callback
+/// and yields decoded image row by row.\
+/// Requires only caller provided buffers for a portion of input and for a single row of pixels.
+pub struct RowDecoder<'a, F> {
+    fill: F,
+    state: RowState<'a>,
+}
+
+impl<F> fmt::Debug for RowDecoder<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowDecoder")
+            .field("qoi", &self.state.qoi)
+            .field("rows_left", &self.state.rows_left)
+            .finish_non_exhaustive()
+    }
+}
+
+/// State of the [`RowDecoder`] independent of its callback type.
+struct RowState<'a> {
+    qoi: Qoi,
+    decoder: Decoder,
+    input: &'a mut [u8],
+    start: usize,
+    end: usize,
+    row: &'a mut [u8],
+    row_len: usize,
+    filled: usize,
+    rows_left: u32,
+}
+
+impl<'a> RowState<'a> {
+    /// Decodes buffered input into current row.\
+    /// Returns `true` if current row is complete or whole image is decoded,
+    /// and `false` if more input is required.
+    fn decode(&mut self) -> Result<bool, DecodeError> {
+        loop {
+            if self.rows_left > 0 && self.filled == self.row_len {
+                return Ok(true);
+            }
+            if self.rows_left == 0 && self.decoder.is_finished() {
+                return Ok(true);
+            }
+            if self.start == self.end {
+                return Ok(false);
+            }
+
+            let progress = self.decoder.push(
+                &self.input[self.start..self.end],
+                &mut self.row[self.filled..self.row_len],
+            )?;
+            self.start += progress.consumed;
+            self.filled += progress.pixels * self.qoi.colors.channels();
+        }
+    }
+
+    /// Returns part of input buffer to be filled with next bytes.\
+    /// It never spans past the end marker.
+    #[inline]
+    fn spare(&mut self) -> &mut [u8] {
+        let len = self.decoder.bytes_needed().min(self.input.len());
+        &mut self.input[..len]
+    }
+
+    /// Marks first `n` bytes of input buffer as filled.
+    #[inline]
+    fn filled_input(&mut self, n: usize) -> Result<(), DecodeError> {
+        if n == 0 {
+            return Err(self.decoder.truncated());
+        }
+        self.start = 0;
+        self.end = n;
+        Ok(())
+    }
+
+    /// Takes completed row.\
+    /// Returns `None` if whole image is decoded.
+    #[inline]
+    fn take_row(&mut self) -> Option<&[u8]> {
+        if self.rows_left == 0 {
+            return None;
+        }
+        self.rows_left -= 1;
+        self.filled = 0;
+        Some(&self.row[..self.row_len])
+    }
+}
+
+impl<'a, F, E> RowDecoder<'a, F>
+where
+    F: FnMut(&mut [u8]) -> Result<usize, E>,
+{
+    /// Returns decoder that reads encoded bytes with `fill` callback into `input` buffer
+    /// and decodes rows of raw RGB or RGBA pixels (depending on channels number in the header) into `row` buffer.\
+    /// `fill` callback writes bytes into provided slice and returns number of bytes written,
+    /// returning `0` means that no more bytes are available.\
+    /// Header of the image is read immediately.
+    ///
+    /// On success this function returns `Ok(decoder)`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` is empty.
+    pub fn new(
+        input: &'a mut [u8],
+        row: &'a mut [u8],
+        mut fill: F,
+    ) -> Result<Self, IoDecodeError<E>> {
+        assert!(!input.is_empty());
+
+        let mut decoder = Decoder::new();
+        let mut start = 0;
+        let mut end = 0;
+
+        let qoi = loop {
+            if let Some(qoi) = decoder.header() {
+                break qoi;
+            }
+            if start == end {
+                let len = decoder.bytes_needed().min(input.len());
+                let n = fill(&mut input[..len]).map_err(IoDecodeError::Io)?;
+                if n == 0 {
+                    return Err(decoder.truncated().into());
+                }
+                start = 0;
+                end = n;
+            }
+            let progress = decoder.push(&input[start..end], &mut [])?;
+            start += progress.consumed;
+        };
+
+        let row_len = qoi.width as usize * qoi.colors.channels();
+        if row.len() < row_len {
+            return Err(DecodeError::OutputIsTooSmall.into());
+        }
+
+        Ok(RowDecoder {
+            fill,
+            state: RowState {
+                qoi,
+                decoder,
+                input,
+                start,
+                end,
+                row,
+                row_len,
+                filled: 0,
+                rows_left: qoi.height,
+            },
+        })
+    }
+
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.state.qoi
+    }
+
+    /// Decode next row of the image.\
+    /// End marker is verified after the last row.
+    ///
+    /// On success this function returns `Ok(Some(row))` with `row` of decoded pixels
+    /// or `Ok(None)` if all rows are decoded.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    pub fn next_row(&mut self) -> Result<Option<&[u8]>, IoDecodeError<E>> {
+        while !self.state.decode()? {
+            self.refill()?;
+        }
+        Ok(self.state.take_row())
+    }
+
+    /// Reads next bytes into input buffer.
+    #[inline]
+    fn refill(&mut self) -> Result<(), IoDecodeError<E>> {
+        let n = (self.fill)(self.state.spare()).map_err(IoDecodeError::Io)?;
+        self.state.filled_input(n)?;
+        Ok(())
+    }
+}
+
+/// Decoder of QOI image stored in bytes slice that yields decoded image row by row.\
+/// Each row is decoded into caller provided buffer and borrowed until the next call.\
+/// Created by [`Qoi::rows`].
+#[derive(Debug)]
+pub struct Rows<'a> {
+    qoi: Qoi,
+    bytes: &'a [u8],
+    offset: usize,
+    index: [[u8; 4]; 64],
+    px: [u8; 4],
+    run: usize,
+    row: &'a mut [u8],
+    row_len: usize,
+    rows_left: u32,
+    done: bool,
+}
+
+impl<'a> Rows<'a> {
+    /// Returns header of the image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Decode next row of the image.\
+    /// End marker is verified by the call following the last row.
+    ///
+    /// Returns `Some(Ok(row))` with `row` of decoded pixels,
+    /// `Some(Err(err))` with `err` describing cause of the error
+    /// or `None` if all rows are decoded or error was already returned.
+    pub fn next_row(&mut self) -> Option<Result<&[u8], DecodeError>> {
+        if self.done {
+            return None;
+        }
+
+        if self.rows_left == 0 {
+            self.done = true;
+            return match self.qoi.decode_padding(self.bytes, self.offset) {
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            };
+        }
+
+        let result = match self.qoi.colors.has_alpha() {
+            true => self.decode_row::<4>(),
+            false => self.decode_row::<3>(),
+        };
+
+        match result {
+            Ok(()) => Some(Ok(&self.row[..self.row_len])),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Decode next row of pixels with `N` channels into row buffer.
+    #[inline]
+    fn decode_row<const N: usize>(&mut self) -> Result<(), DecodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let mut pixels = bytemuck::cast_slice_mut::<_, [u8; N]>(&mut self.row[..self.row_len]);
+
+        let mut index = self.index.map(|px| convert::<_, [u8; N]>(&px));
+        let mut px = convert::<_, [u8; N]>(&self.px);
+
+        let n = Qoi::decode_range_into::<N, false, _>(
+            &mut index,
+            &mut px,
+            &mut self.run,
+            &self.bytes[self.offset..],
+            &mut pixels,
+        )
+        .map_err(|err| {
+            let rows = (self.qoi.height - self.rows_left) as usize;
+            let total = self.qoi.width as usize * self.qoi.height as usize;
+            err.at(self.offset, rows * self.qoi.width as usize)
+                .for_image(self.bytes, total)
+        })?;
+
+        self.offset += n;
+        self.index = index.map(|px| convert(&px));
+        self.px = convert(&px);
+        self.rows_left -= 1;
+
+        if self.rows_left == 0 {
+            // Run exceeding the image is ignored as in one-shot decoding.
+            self.run = 0;
+        }
+
+        Ok(())
+    }
+}
+
+impl Qoi {
+    /// Decode a QOI image from bytes slice row by row without copying rows.\
+    /// Rows of raw RGB or RGBA pixels (depending on channels number in the header) are decoded into `row` slice
+    /// and borrowed by [`Rows::next_row`] until the next call.\
+    /// `row` must hold at least `width * channels` bytes.
+    ///
+    /// On success this function returns `Ok(rows)` with decoder positioned at the first row.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn rows<'a>(bytes: &'a [u8], row: &'a mut [u8]) -> Result<Rows<'a>, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let row_len = qoi.width as usize * qoi.colors.channels();
+        if row.len() < row_len {
+            return Err(DecodeError::OutputIsTooSmall);
+        }
+
+        Ok(Rows {
+            qoi,
+            bytes,
+            offset: QOI_HEADER_SIZE,
+            index: [[0; 4]; 64],
+            px: [0, 0, 0, 0xff],
+            run: 0,
+            row,
+            row_len,
+            rows_left: qoi.height,
+            done: false,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+//! Block copies and comparisons for runs of pixels.
+//!
+//! No unsafe code is used here, so `core::arch` intrinsics are not used.\
+//! Instead runs of decoded RGB pixels are written as copies of 48 bytes blocks holding 16 pixels
+//! and runs in the encoder input are found by comparing such blocks,
+//! which compiler lowers into vector loads and stores of the target baseline.\
+//! Only runs are affected, literal, index and difference chunks are written pixel by pixel.
+//! Runs of RGBA pixels are already written with vector stores by `slice::fill`.
+
+use super::*;
+
+/// Block size in bytes, multiple of RGB and RGBA pixel sizes and of 16 byte vectors.
+pub(crate) const BLOCK: usize = 48;
+
+/// Runs shorter than this are written pixel by pixel.
+pub(crate) const MIN_RUN: usize = 8;
+
+/// Writes `px` into every pixel of `pixels`.
+#[inline]
+pub(crate) fn fill<const M: usize>(pixels: &mut [[u8; M]], px: [u8; M])
+where
+    [u8; M]: Pixel,
+{
+    if M != 3 || pixels.len() < MIN_RUN {
+        pixels.fill(px);
+    } else {
+        fill_rgb(pixels, px);
+    }
+}
+
+/// Returns block of `BLOCK` bytes filled with copies of `px`.
+#[inline]
+pub(crate) fn block<const M: usize>(px: [u8; M]) -> [u32; BLOCK / 4]
+where
+    [u8; M]: Pixel,
+{
+    // Three words hold four RGB pixels or three RGBA pixels.
+    let (r, g, b) = (px.r(), px.g(), px.b());
+    let words = match M {
+        3 => [
+            u32::from_ne_bytes([r, g, b, r]),
+            u32::from_ne_bytes([g, b, r, g]),
+            u32::from_ne_bytes([b, r, g, b]),
+        ],
+        _ => [u32::from_ne_bytes(px.rgba()); 3],
+    };
+
+    let mut block = [0; BLOCK / 4];
+    for chunk in block.chunks_exact_mut(3) {
+        chunk.copy_from_slice(&words);
+    }
+    block
+}
+
+/// Writes RGB pixel `px` into every pixel of `pixels` with copies of the block.\
+/// Pixels that do not fill whole block are copied from block prefix.
+#[inline]
+fn fill_rgb<const M: usize>(pixels: &mut [[u8; M]], px: [u8; M])
+where
+    [u8; M]: Pixel,
+{
+    let block = block(px);
+    let block = bytemuck::cast_slice::<_, u8>(&block);
+
+    let mut chunks = pixels.chunks_exact_mut(BLOCK / M);
+    for chunk in &mut chunks {
+        bytemuck::cast_slice_mut::<_, u8>(chunk).copy_from_slice(block);
+    }
+
+    let tail = bytemuck::cast_slice_mut::<_, u8>(chunks.into_remainder());
+    let len = tail.len();
+    tail.copy_from_slice(&block[..len]);
+}
+
+/// Returns number of leading pixels of `pixels` equal to `px`, up to `max`.\
+/// Pixels are compared in blocks of `BLOCK` bytes.
+#[inline(never)]
+pub(crate) fn run_len<const M: usize>(pixels: &[[u8; M]], px: [u8; M], max: usize) -> usize
+where
+    [u8; M]: Pixel,
+{
+    let pixels = &pixels[..max.min(pixels.len())];
+    let mut len = pixels
+        .iter()
+        .take(MIN_RUN)
+        .take_while(|&&p| p == px)
+        .count();
+    if len < MIN_RUN {
+        // Short runs are not worth building the block.
+        return len;
+    }
+
+    let block = block(px);
+    let block = bytemuck::cast_slice::<_, u8>(&block);
+
+    for chunk in pixels[len..].chunks_exact(BLOCK / M) {
+        if bytemuck::cast_slice::<_, u8>(chunk) != block {
+            break;
+        }
+        len += chunk.len();
+    }
+    len + pixels[len..].iter().take_while(|&&p| p == px).count()
+}
@@ -0,0 +1,227 @@
+use core::{convert::TryInto, ops::Range};
+
+use super::*;
+use encode::Slice;
+
+/// Magic value stored in the last bytes of the seek table.
+const SEEK_TABLE_MAGIC: [u8; 4] = *b"qsek";
+
+/// Version of the seek table layout.
+const SEEK_TABLE_VERSION: u8 = 1;
+
+/// Size of the seek table footer following snapshots.\
+/// Footer holds big-endian row interval, number of snapshots and checksum, version and magic.
+const SEEK_FOOTER_SIZE: usize = 4 + 4 + 4 + 1 + 4;
+
+/// Number of pixels decoded at once while skipping pixels preceding requested rows.
+const SKIP_PIXELS: usize = 256;
+
+impl Qoi {
+    /// Encode raw RGB or RGBA pixels into a QOI image followed by seek table
+    /// for [`Qoi::decode_rows_seek`].\
+    /// Chunks are the same as produced by [`Qoi::encode`].
+    /// Decoder state is recorded at the first pixel of every `row_interval`-th row
+    /// and stored after the end marker, so other decoders ignore it,
+    /// though strict decoding rejects it as trailing data.\
+    /// Seek table takes [`DecoderState::SERIALIZED_SIZE`] bytes per recorded row and 17 more bytes.\
+    /// Encoded image is written into `output` slice.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded image including seek table.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InvalidOptions)` is returned if `row_interval` is `0`.
+    pub fn encode_with_seek_table(
+        &self,
+        pixels: &[u8],
+        output: &mut [u8],
+        row_interval: u32,
+    ) -> Result<usize, EncodeError> {
+        if row_interval == 0 {
+            return Err(EncodeError::InvalidOptions);
+        }
+
+        let px_len = self.input_len(self.colors.channels())?;
+        let pixels = Self::take_input(pixels, px_len)?;
+
+        let count = (self.height.saturating_sub(1) / row_interval) as usize;
+        let table_len = count
+            .checked_mul(DecoderState::SERIALIZED_SIZE)
+            .and_then(|len| len.checked_add(SEEK_FOOTER_SIZE))
+            .ok_or(EncodeError::TooManyPixels)?;
+        let required = self
+            .size_limit()?
+            .checked_add(table_len)
+            .ok_or(EncodeError::TooManyPixels)?;
+
+        if output.len() <= QOI_HEADER_SIZE + table_len {
+            return Err(EncodeError::OutputIsTooSmall { required });
+        }
+        self.write_header(output);
+
+        // Snapshots are stored in the end of the output until size of the chunks is known.
+        let (body, tail) = output.split_at_mut(output.len() - table_len);
+        let size = match self.colors.has_alpha() {
+            true => self.encode_seekable::<4>(pixels, body, tail, row_interval, required)?,
+            false => self.encode_seekable::<3>(pixels, body, tail, row_interval, required)?,
+        };
+
+        let snapshots = count * DecoderState::SERIALIZED_SIZE;
+        let start = output.len() - table_len;
+        output.copy_within(start..start + snapshots, size);
+
+        let footer = size + snapshots;
+        output[footer..][..4].copy_from_slice(&row_interval.to_be_bytes());
+        output[footer + 4..][..4].copy_from_slice(&(count as u32).to_be_bytes());
+        let checksum = fnv1a(&output[size..footer + 8]);
+        output[footer + 8..][..4].copy_from_slice(&checksum.to_be_bytes());
+        output[footer + 12] = SEEK_TABLE_VERSION;
+        output[footer + 13..][..4].copy_from_slice(&SEEK_TABLE_MAGIC);
+
+        Ok(size + table_len)
+    }
+
+    /// Encodes chunks and end marker of the image with `N` channels into `body` following the header,
+    /// writing serialized decoder state at every `row_interval`-th row into `snapshots`.\
+    /// Returns size of the image up to the end marker.
+    fn encode_seekable<const N: usize>(
+        &self,
+        pixels: &[u8],
+        body: &mut [u8],
+        snapshots: &mut [u8],
+        row_interval: u32,
+        required: usize,
+    ) -> Result<usize, EncodeError>
+    where
+        [u8; N]: Pixel,
+    {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let interval = row_interval as usize;
+        let bands = height.saturating_sub(1) / interval + 1;
+
+        let mut index = [[0; 4]; 64];
+        let mut px_prev = Pixel::new_opaque();
+        let mut run = 0;
+        let mut sink = Slice::new(&mut body[QOI_HEADER_SIZE..]);
+
+        for band in 0..bands {
+            let start = band * interval;
+            if band > 0 {
+                // Pending run is encoded by the next chunk, so decoding restarts at its first pixel.
+                let state = DecoderState::at_chunk(
+                    index,
+                    convert(&px_prev),
+                    start * width - run,
+                    QOI_HEADER_SIZE + sink.written,
+                );
+                snapshots[(band - 1) * DecoderState::SERIALIZED_SIZE..]
+                    [..DecoderState::SERIALIZED_SIZE]
+                    .copy_from_slice(&state.to_bytes());
+            }
+
+            let end = (start + interval).min(height);
+            Self::encode_range_into::<N, false, _, _>(
+                &mut index,
+                &mut px_prev,
+                &mut run,
+                &mut bytemuck::cast_slice::<_, [u8; N]>(
+                    &pixels[start * width * N..end * width * N],
+                ),
+                &mut sink,
+                band + 1 == bands,
+                required,
+            )?;
+        }
+
+        let size = QOI_HEADER_SIZE + sink.written;
+        Ok(size + Self::encode_padding(&mut body[size..], required)?)
+    }
+
+    /// Decode rows of a QOI image from bytes slice using seek table written by [`Qoi::encode_with_seek_table`].\
+    /// Decoding starts from the nearest recorded row preceding `rows`,
+    /// so only pixels between it and the last requested row are decoded.
+    /// Image without seek table is decoded from the beginning.\
+    /// Decoded raw RGB or RGBA pixels of `rows` are written into `output` slice.\
+    /// `output` must hold at least `rows.len() * width * channels` bytes.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing whole image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidSeekTable)` is returned if seek table is corrupted or has unsupported version.
+    pub fn decode_rows_seek(
+        bytes: &[u8],
+        rows: Range<u32>,
+        output: &mut [u8],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        if rows.start > rows.end || rows.end > qoi.height {
+            return Err(DecodeError::InvalidRegion);
+        }
+
+        let width = qoi.width as usize;
+        let channels = qoi.colors.channels();
+        let rows = rows.start as usize..rows.end as usize;
+        let output = output
+            .get_mut(..rows.len() * width * channels)
+            .ok_or(DecodeError::OutputIsTooSmall)?;
+        if rows.is_empty() {
+            return Ok(qoi);
+        }
+
+        let mut state = match Self::seek_table(bytes)? {
+            Some((interval, snapshots)) => match rows.start / interval {
+                0 => DecoderState::new(),
+                k => {
+                    let size = DecoderState::SERIALIZED_SIZE;
+                    let snapshot = snapshots
+                        .get((k - 1) * size..k * size)
+                        .ok_or(DecodeError::InvalidSeekTable)?;
+                    DecoderState::from_bytes(snapshot).map_err(|_| DecodeError::InvalidSeekTable)?
+                }
+            },
+            None => DecoderState::new(),
+        };
+
+        let first = rows.start * width;
+        if state.pixels_decoded() > first || state.is_finished() {
+            return Err(DecodeError::InvalidSeekTable);
+        }
+
+        let mut skipped = [0; SKIP_PIXELS * 4];
+        while state.pixels_decoded() < first {
+            let left = first - state.pixels_decoded();
+            qoi.decode_resume(bytes, &mut state, &mut skipped, left.min(SKIP_PIXELS))?;
+        }
+
+        qoi.decode_resume(bytes, &mut state, output, usize::MAX)?;
+        Ok(qoi)
+    }
+
+    /// Returns row interval and serialized snapshots of the seek table in the end of `bytes`
+    /// or `None` if `bytes` do not end with seek table magic.
+    fn seek_table(bytes: &[u8]) -> Result<Option<(usize, &[u8])>, DecodeError> {
+        let footer = match bytes.len().checked_sub(SEEK_FOOTER_SIZE) {
+            Some(footer) if bytes[footer + 13..] == SEEK_TABLE_MAGIC => footer,
+            _ => return Ok(None),
+        };
+
+        if bytes[footer + 12] != SEEK_TABLE_VERSION {
+            return Err(DecodeError::InvalidSeekTable);
+        }
+
+        let field = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        let interval = field(footer) as usize;
+        let count = field(footer + 4) as usize;
+        let checksum = field(footer + 8);
+
+        let start = count
+            .checked_mul(DecoderState::SERIALIZED_SIZE)
+            .and_then(|len| footer.checked_sub(len))
+            .filter(|&start| start >= QOI_HEADER_SIZE)
+            .ok_or(DecodeError::InvalidSeekTable)?;
+
+        if interval == 0 || fnv1a(&bytes[start..footer + 8]) != checksum {
+            return Err(DecodeError::InvalidSeekTable);
+        }
+
+        Ok(Some((interval, &bytes[start..footer])))
+    }
+}
@@ -0,0 +1,506 @@
+use core::ops::ControlFlow;
+
+use super::*;
+use chunk::{ChunkIter, Op, OpKind};
+use decode::{luma, Inspect, Visit};
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+
+/// Histograms of red, green, blue channels and luma of image pixels.\
+/// Luma is computed with BT.601 weights.\
+/// Bins are `u64` so they can not overflow for any valid image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Histogram {
+    /// Counts of red channel values.
+    pub r: [u64; 256],
+
+    /// Counts of green channel values.
+    pub g: [u64; 256],
+
+    /// Counts of blue channel values.
+    pub b: [u64; 256],
+
+    /// Counts of luma values.
+    pub luma: [u64; 256],
+}
+
+impl Default for Histogram {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histogram {
+    /// Returns histogram with all bins set to zero.
+    #[inline]
+    pub const fn new() -> Self {
+        Histogram {
+            r: [0; 256],
+            g: [0; 256],
+            b: [0; 256],
+            luma: [0; 256],
+        }
+    }
+
+    /// Adds `n` occurrences of the pixel.
+    #[inline]
+    pub fn add(&mut self, px: [u8; 4], n: usize) {
+        let n = n as u64;
+        self.r[px[0] as usize] += n;
+        self.g[px[1] as usize] += n;
+        self.b[px[2] as usize] += n;
+        self.luma[luma(px) as usize] += n;
+    }
+}
+
+/// Result of counting unique colors of the image.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UniqueColors {
+    /// All unique RGBA colors of the image in ascending order.
+    Exact(Vec<[u8; 4]>),
+
+    /// Image has more unique colors than specified limit.
+    MoreThan(usize),
+}
+
+/// Statistics of chunks of encoded image computed by [`Qoi::analyze`].
+#[derive(Clone, Copy, Debug)]
+pub struct StreamStats {
+    /// Header of the image.
+    pub qoi: Qoi,
+
+    /// Numbers of chunks of each kind and bytes taken by them.\
+    /// Runs are counted with their encoded length.
+    pub chunks: EncodeStats,
+
+    /// Length of the shortest run or `0` if there are no runs.
+    pub min_run: usize,
+
+    /// Length of the longest run or `0` if there are no runs.
+    pub max_run: usize,
+
+    /// Size of the encoded image including header and end marker.
+    pub size: usize,
+}
+
+impl StreamStats {
+    /// Returns average length of runs or `0.0` if there are no runs.
+    #[inline]
+    pub fn mean_run(&self) -> f64 {
+        match self.chunks.op_run {
+            0 => 0.0,
+            runs => self.chunks.run_pixels as f64 / runs as f64,
+        }
+    }
+
+    /// Returns number of encoded bytes per pixel including header and end marker
+    /// or `0.0` if the image is empty.
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> f64 {
+        match self.qoi.width as usize * self.qoi.height as usize {
+            0 => 0.0,
+            pixels => self.size as f64 / pixels as f64,
+        }
+    }
+
+    /// Returns ratio of raw pixels size to encoded image size.
+    #[inline]
+    pub fn compression_ratio(&self) -> f64 {
+        self.qoi.decoded_size() as f64 / self.size as f64
+    }
+}
+
+/// Returns per-channel average of accumulated sums rounded to nearest integer.
+#[inline]
+fn average(sums: [u64; 4], count: u64) -> [u8; 4] {
+    let mut px = [0; 4];
+    for (c, sum) in px.iter_mut().zip(sums) {
+        *c = ((sum + count / 2) / count) as u8;
+    }
+    px
+}
+
+impl Qoi {
+    /// Computes histogram of a QOI image from bytes slice without storing decoded pixels.
+    ///
+    /// On success this function returns `Ok((qoi, hist))` with `qoi` describing image dimensions and color space
+    /// and `hist` containing counts of the image pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn histogram(bytes: &[u8]) -> Result<(Self, Histogram), DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let mut hist = Histogram::new();
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left: qoi.width as usize * qoi.height as usize,
+                f: |px, n| {
+                    hist.add(px, n);
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+        Ok((qoi, hist))
+    }
+
+    /// Decode a QOI image from bytes slice.\
+    /// Decoded raw RGB or RGBA (depending on `colors` value in header) pixels are written into `output` slice.\
+    /// Counts of decoded pixels are added to `hist`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn decode_with_histogram(
+        bytes: &[u8],
+        output: &mut [u8],
+        hist: &mut Histogram,
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..qoi.decoded_size()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        let f = |px, n| hist.add(px, n);
+        match qoi.colors.has_alpha() {
+            true => qoi.decode_skip_header_into(
+                bytes,
+                Inspect {
+                    out: bytemuck::cast_slice_mut::<_, [u8; 4]>(output),
+                    f,
+                },
+            )?,
+            false => qoi.decode_skip_header_into(
+                bytes,
+                Inspect {
+                    out: bytemuck::cast_slice_mut::<_, [u8; 3]>(output),
+                    f,
+                },
+            )?,
+        }
+        Ok(qoi)
+    }
+
+    /// Collects unique colors of a QOI image from bytes slice without storing decoded pixels.\
+    /// Colors of images without alpha channel have alpha value `255`.\
+    /// Decoding stops as soon as number of unique colors exceeds `limit`.
+    ///
+    /// On success this function returns `Ok(UniqueColors::Exact(colors))` with all unique colors of the image
+    /// or `Ok(UniqueColors::MoreThan(limit))` if there are more than `limit` of them.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn unique_colors(bytes: &[u8], limit: usize) -> Result<UniqueColors, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let mut colors = BTreeSet::new();
+        let mut last = None;
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left: qoi.width as usize * qoi.height as usize,
+                f: |px, _| {
+                    if last != Some(px) {
+                        last = Some(px);
+                        colors.insert(px);
+                        if colors.len() > limit {
+                            return ControlFlow::Break(());
+                        }
+                    }
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+
+        match colors.len() > limit {
+            true => Ok(UniqueColors::MoreThan(limit)),
+            false => Ok(UniqueColors::Exact(colors.into_iter().collect())),
+        }
+    }
+
+    /// Looks up single pixel of a QOI image from bytes slice without storing decoded pixels.\
+    /// Only part of the image up to requested pixel is decoded.\
+    /// Pixels of images without alpha channel have alpha value `255`.
+    ///
+    /// On success this function returns `Ok(px)` with `px` being RGBA value of the pixel at `x` column and `y` row.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn pixel_at(bytes: &[u8], x: u32, y: u32) -> Result<[u8; 4], DecodeError> {
+        let mut px = [0; 4];
+        Self::pixels_at(bytes, &[(x, y)], core::slice::from_mut(&mut px))?;
+        Ok(px)
+    }
+
+    /// Looks up multiple pixels of a QOI image from bytes slice in single pass without storing decoded pixels.\
+    /// RGBA value of the pixel at each `(x, y)` coordinates pair in `coords` is written into `output` slice.\
+    /// Pixels of images without alpha channel have alpha value `255`.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `coords` are not sorted by row and then by column.
+    #[inline]
+    pub fn pixels_at(
+        bytes: &[u8],
+        coords: &[(u32, u32)],
+        output: &mut [[u8; 4]],
+    ) -> Result<Self, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let output = match output.get_mut(..coords.len()) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+
+        if coords
+            .iter()
+            .any(|&(x, y)| x >= qoi.width || y >= qoi.height)
+        {
+            return Err(DecodeError::PixelOutOfBounds);
+        }
+
+        assert!(
+            coords
+                .windows(2)
+                .all(|w| (w[0].1, w[0].0) <= (w[1].1, w[1].0)),
+            "Coordinates must be sorted"
+        );
+
+        let position = |(x, y): (u32, u32)| y as usize * qoi.width as usize + x as usize;
+
+        let left = match coords.last() {
+            None => return Ok(qoi),
+            Some(&last) => position(last) + 1,
+        };
+
+        let mut pos = 0;
+        let mut next = 0;
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left,
+                f: |px, n| {
+                    pos += n;
+                    while next < coords.len() && position(coords[next]) < pos {
+                        output[next] = px;
+                        next += 1;
+                    }
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+        Ok(qoi)
+    }
+
+    /// Computes average color of a QOI image from bytes slice without storing decoded pixels.\
+    /// Each channel is averaged separately and rounded to nearest integer.\
+    /// Pixels of images without alpha channel have alpha value `255`.
+    ///
+    /// On success this function returns `Ok(px)` with `px` being average RGBA value of the image pixels,
+    /// or `[0, 0, 0, 255]` for images without pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn average_color(bytes: &[u8]) -> Result<[u8; 4], DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let count = qoi.width as usize * qoi.height as usize;
+        if count == 0 {
+            return Ok([0, 0, 0, 255]);
+        }
+
+        let mut sums = [0u64; 4];
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left: count,
+                f: |px: [u8; 4], n| {
+                    for (sum, c) in sums.iter_mut().zip(px) {
+                        *sum += c as u64 * n as u64;
+                    }
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+        Ok(average(sums, count as u64))
+    }
+
+    /// Computes dominant color of a QOI image from bytes slice without storing decoded pixels.\
+    /// Pixels are counted in coarse cube with 4 bits per color channel,
+    /// dominant color is average of pixels in the most populated cell.\
+    /// Pixels of images without alpha channel have alpha value `255`.
+    ///
+    /// On success this function returns `Ok(px)` with `px` being dominant RGBA value of the image pixels,
+    /// or `[0, 0, 0, 255]` for images without pixels.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn dominant_color(bytes: &[u8]) -> Result<[u8; 4], DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let bytes = &bytes[QOI_HEADER_SIZE..];
+
+        let count = qoi.width as usize * qoi.height as usize;
+        if count == 0 {
+            return Ok([0, 0, 0, 255]);
+        }
+
+        let mut cells = vec![(0u64, [0u64; 4]); 4096];
+        qoi.decode_skip_header_into(
+            bytes,
+            Visit {
+                left: count,
+                f: |px: [u8; 4], n| {
+                    let cell = (px[0] as usize >> 4) << 8
+                        | (px[1] as usize >> 4) << 4
+                        | px[2] as usize >> 4;
+                    let (cnt, sums) = &mut cells[cell];
+                    *cnt += n as u64;
+                    for (sum, c) in sums.iter_mut().zip(px) {
+                        *sum += c as u64 * n as u64;
+                    }
+                    ControlFlow::Continue(())
+                },
+            },
+        )?;
+
+        let mut best = &cells[0];
+        for cell in &cells[1..] {
+            if cell.0 > best.0 {
+                best = cell;
+            }
+        }
+        Ok(average(best.1, best.0))
+    }
+
+    /// Checks whether all pixels of a QOI image from bytes slice are fully opaque without storing decoded pixels.\
+    /// Images without alpha channel are opaque and only their header is read.
+    /// Otherwise chunks are walked through until the first pixel with alpha other than `255`,
+    /// colors are tracked as well since they select positions in the index of previously seen pixels.
+    ///
+    /// On success this function returns `Ok(opaque)` with `opaque` set to `true` if every pixel has alpha `255`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn is_opaque(bytes: &[u8]) -> Result<bool, DecodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        if !qoi.colors.has_alpha() {
+            return Ok(true);
+        }
+
+        let mut opaque = true;
+        qoi.decode_skip_header_into(
+            &bytes[QOI_HEADER_SIZE..],
+            Visit {
+                left: qoi.width as usize * qoi.height as usize,
+                f: |px: [u8; 4], _| match px[3] {
+                    0xff => ControlFlow::Continue(()),
+                    _ => {
+                        opaque = false;
+                        ControlFlow::Break(())
+                    }
+                },
+            },
+        )?;
+        Ok(opaque)
+    }
+
+    /// Writes kind of chunk that produced each pixel of a QOI image from bytes slice into `out` without decoding pixels.\
+    /// One byte per pixel is written with value of [`OpKind`],
+    /// pixels repeated by a run after the first one are marked as [`OpKind::RunContinuation`].\
+    /// Image is verified the same way as by [`Qoi::validate`].
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn opcode_map(bytes: &[u8], out: &mut [u8]) -> Result<Self, DecodeError> {
+        Self::map_chunks(bytes, out, |op, out| {
+            out[0] = op.kind() as u8;
+            out[1..].fill(OpKind::RunContinuation as u8);
+        })
+    }
+
+    /// Writes number of encoded bits spent on each pixel of a QOI image from bytes slice into `out` without decoding pixels.\
+    /// Size of a run is spread evenly over pixels it produces.
+    /// Header and end marker are not counted.\
+    /// Image is verified the same way as by [`Qoi::validate`].
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing image dimensions and color space.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn cost_map(bytes: &[u8], out: &mut [f32]) -> Result<Self, DecodeError> {
+        Self::map_chunks(bytes, out, |op, out| {
+            out.fill((op.size() * 8) as f32 / out.len() as f32);
+        })
+    }
+
+    /// Walks through chunks of the image passing each chunk with part of `out` for pixels it produces to `f`.\
+    /// Run exceeding the image is given only part for pixels inside the image.
+    #[inline]
+    fn map_chunks<T, F>(bytes: &[u8], out: &mut [T], mut f: F) -> Result<Self, DecodeError>
+    where
+        F: FnMut(Op, &mut [T]),
+    {
+        let mut iter = ChunkIter::new(bytes)?;
+        let qoi = iter.qoi();
+
+        let mut out = match out.get_mut(..qoi.width as usize * qoi.height as usize) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(out) => out,
+        };
+
+        for chunk in &mut iter {
+            let (_, op) = chunk?;
+            let n = op.pixels().min(out.len());
+            let (pixels, rest) = core::mem::take(&mut out).split_at_mut(n);
+            f(op, pixels);
+            out = rest;
+        }
+
+        Ok(qoi)
+    }
+
+    /// Computes statistics of chunks of a QOI image from bytes slice without decoding pixels.\
+    /// Image is verified the same way as by [`Qoi::validate`].
+    ///
+    /// On success this function returns `Ok(stats)` with `stats` of the image chunks.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.
+    #[inline]
+    pub fn analyze(bytes: &[u8]) -> Result<StreamStats, DecodeError> {
+        let mut iter = ChunkIter::new(bytes)?;
+
+        let mut stats = StreamStats {
+            qoi: iter.qoi(),
+            chunks: EncodeStats::default(),
+            min_run: 0,
+            max_run: 0,
+            size: 0,
+        };
+
+        for chunk in &mut iter {
+            let (offset, op) = chunk?;
+            stats.chunks.record(&bytes[offset..][..op.size()]);
+
+            if let Op::Run(n) = op {
+                let n = n as usize;
+                stats.min_run = match stats.min_run {
+                    0 => n,
+                    min => min.min(n),
+                };
+                stats.max_run = stats.max_run.max(n);
+            }
+        }
+
+        stats.size = iter.offset() + QOI_PADDING;
+        Ok(stats)
+    }
+}
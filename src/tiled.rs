@@ -0,0 +1,453 @@
+//! Tiled container storing large image as grid of independent QOI images.\
+//! This is an extension of this crate, not part of QOI specification.
+//!
+//! Container starts with `b"qoit"` magic, version byte, QOI header of the whole image
+//! and big-endian size of square tiles.\
+//! Tile directory follows with big-endian offset from the start of the container
+//! and big-endian size of each tile image, both 64 bits wide, in row-major order of tiles.\
+//! Each tile is a complete QOI image, tiles in the last column and row are smaller
+//! if image dimensions are not divisible by tile size.
+//! Tiles do not depend on each other, so they can be encoded and decoded in parallel
+//! with [`TiledEncoder::encode_tile`] and [`TiledDecoder::decode_tile`].
+
+use core::convert::TryInto;
+
+use super::*;
+use alloc::vec::Vec;
+
+/// Magic value at the start of tiled container.
+pub const QOIT_MAGIC: [u8; 4] = *b"qoit";
+
+/// Version of tiled container layout.
+const QOIT_VERSION: u8 = 1;
+
+/// Size of tiled container header.
+pub const QOIT_HEADER_SIZE: usize = 4 + 1 + QOI_HEADER_SIZE + 4;
+
+/// Size of tile directory entry.
+const TILE_ENTRY_SIZE: usize = 8 + 8;
+
+/// Returns number of tile columns and rows of image described by `qoi`.
+#[inline]
+fn grid(qoi: &Qoi, tile_size: u32) -> (u32, u32) {
+    (
+        qoi.width.div_ceil(tile_size),
+        qoi.height.div_ceil(tile_size),
+    )
+}
+
+/// Returns descriptor of tile at `tx` column and `ty` row.
+#[inline]
+fn tile_qoi(qoi: &Qoi, tile_size: u32, tx: u32, ty: u32) -> Qoi {
+    let x = tx * tile_size;
+    let y = ty * tile_size;
+    Qoi {
+        width: tile_size.min(qoi.width - x),
+        height: tile_size.min(qoi.height - y),
+        colors: qoi.colors,
+    }
+}
+
+/// Encoder of tiled container.
+#[derive(Clone, Copy, Debug)]
+pub struct TiledEncoder {
+    qoi: Qoi,
+}
+
+impl TiledEncoder {
+    /// Returns encoder of image described by `qoi`.
+    #[inline]
+    pub fn new(qoi: Qoi) -> Self {
+        TiledEncoder { qoi }
+    }
+
+    /// Returns descriptor of the whole image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns number of tile columns and rows for tiles of `tile_size` by `tile_size` pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_size` is zero.
+    #[inline]
+    pub fn tiles(&self, tile_size: u32) -> (u32, u32) {
+        grid(&self.qoi, tile_size)
+    }
+
+    /// Encode raw RGB or RGBA (depending on `self.qoi().colors` value) pixels of the whole image
+    /// into tiled container with tiles of `tile_size` by `tile_size` pixels.\
+    /// Container is appended to `out`.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the container.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InvalidOptions)` is returned if `tile_size` is zero.
+    /// Contents of `out` following its original length are unspecified after failure.
+    pub fn encode(
+        &self,
+        pixels: &[u8],
+        tile_size: u32,
+        out: &mut Vec<u8>,
+    ) -> Result<usize, EncodeError> {
+        if tile_size == 0 {
+            return Err(EncodeError::InvalidOptions);
+        }
+        let px_len = self.qoi.input_len(self.qoi.colors.channels())?;
+        let pixels = Qoi::take_input(pixels, px_len)?;
+
+        let (tiles_x, tiles_y) = self.tiles(tile_size);
+        let start = out.len();
+        self.write_header(tile_size, out);
+
+        let mut entry = start + QOIT_HEADER_SIZE;
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let offset = out.len() - start;
+                let size = self.encode_tile(pixels, tile_size, tx, ty, out)?;
+                write_entry(&mut out[entry..], offset, size);
+                entry += TILE_ENTRY_SIZE;
+            }
+        }
+
+        Ok(out.len() - start)
+    }
+
+    /// Encode tile at `tx` column and `ty` row of raw RGB or RGBA pixels of the whole image
+    /// into QOI image appended to `out`.\
+    /// Tiles encoded separately, possibly in parallel, are combined into container with [`TiledEncoder::assemble`].
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the encoded tile.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InvalidRegion)` is returned if tile is outside of the image.
+    pub fn encode_tile(
+        &self,
+        pixels: &[u8],
+        tile_size: u32,
+        tx: u32,
+        ty: u32,
+        out: &mut Vec<u8>,
+    ) -> Result<usize, EncodeError> {
+        if tile_size == 0 {
+            return Err(EncodeError::InvalidOptions);
+        }
+
+        let (tiles_x, tiles_y) = self.tiles(tile_size);
+        if tx >= tiles_x || ty >= tiles_y {
+            return Err(EncodeError::InvalidRegion);
+        }
+
+        let tile = tile_qoi(&self.qoi, tile_size, tx, ty);
+        let len = out.len();
+        out.resize(len + tile.encoded_size_limit(), 0);
+        let result = self.qoi.encode_region(
+            pixels,
+            tx * tile_size,
+            ty * tile_size,
+            tile.width,
+            tile.height,
+            &mut out[len..],
+        );
+
+        match result {
+            Ok(size) => {
+                out.truncate(len + size);
+                Ok(size)
+            }
+            Err(err) => {
+                out.truncate(len);
+                Err(err)
+            }
+        }
+    }
+
+    /// Combine encoded QOI images of all tiles in row-major order into tiled container appended to `out`.\
+    /// Tiles are not decoded, only their headers are verified.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of the container.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(EncodeError::InputSizeMismatch { .. })` is returned if number of tiles does not match the grid
+    /// and `Err(EncodeError::InvalidRegion)` if any tile has unexpected dimensions or channels number.
+    pub fn assemble<T>(
+        &self,
+        tile_size: u32,
+        tiles: &[T],
+        out: &mut Vec<u8>,
+    ) -> Result<usize, EncodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        if tile_size == 0 {
+            return Err(EncodeError::InvalidOptions);
+        }
+
+        let (tiles_x, tiles_y) = self.tiles(tile_size);
+        let count = tiles_x as usize * tiles_y as usize;
+        if tiles.len() != count {
+            return Err(EncodeError::InputSizeMismatch {
+                expected: count,
+                got: tiles.len(),
+            });
+        }
+
+        for (i, tile) in tiles.iter().enumerate() {
+            let expected = tile_qoi(&self.qoi, tile_size, i as u32 % tiles_x, i as u32 / tiles_x);
+            match Qoi::decode_header(tile.as_ref()) {
+                Ok(qoi) if qoi.same_frame(&expected) => {}
+                _ => return Err(EncodeError::InvalidRegion),
+            }
+        }
+
+        let start = out.len();
+        let mut offset = self.write_header(tile_size, out);
+        for (i, tile) in tiles.iter().enumerate() {
+            let size = tile.as_ref().len();
+            write_entry(
+                &mut out[start + QOIT_HEADER_SIZE + i * TILE_ENTRY_SIZE..],
+                offset,
+                size,
+            );
+            offset += size;
+        }
+        for tile in tiles {
+            out.extend_from_slice(tile.as_ref());
+        }
+
+        Ok(out.len() - start)
+    }
+
+    /// Appends container header and zeroed tile directory to `out`.\
+    /// Returns size of both.
+    fn write_header(&self, tile_size: u32, out: &mut Vec<u8>) -> usize {
+        let (tiles_x, tiles_y) = self.tiles(tile_size);
+        let size = QOIT_HEADER_SIZE + tiles_x as usize * tiles_y as usize * TILE_ENTRY_SIZE;
+
+        out.reserve(size);
+        out.extend_from_slice(&QOIT_MAGIC);
+        out.push(QOIT_VERSION);
+        out.extend_from_slice(&self.qoi.header_bytes());
+        out.extend_from_slice(&tile_size.to_be_bytes());
+        out.resize(out.len() + size - QOIT_HEADER_SIZE, 0);
+        size
+    }
+}
+
+/// Writes tile directory entry with `offset` and `size` of the tile into the start of `out`.
+#[inline]
+fn write_entry(out: &mut [u8], offset: usize, size: usize) {
+    out[..8].copy_from_slice(&(offset as u64).to_be_bytes());
+    out[8..TILE_ENTRY_SIZE].copy_from_slice(&(size as u64).to_be_bytes());
+}
+
+/// Decoder of tiled container stored in bytes slice.\
+/// Decoder only borrows the bytes, so tiles can be decoded from multiple threads at once.
+#[derive(Clone, Copy, Debug)]
+pub struct TiledDecoder<'a> {
+    qoi: Qoi,
+    bytes: &'a [u8],
+    tile_size: u32,
+    tiles_x: u32,
+    tiles_y: u32,
+}
+
+impl<'a> TiledDecoder<'a> {
+    /// Reads header and verifies tile directory of tiled container from bytes slice.
+    ///
+    /// On success this function returns `Ok(decoder)` with `decoder` of the container.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidMagic)` is returned if bytes do not start with tiled container magic
+    /// and `Err(DecodeError::InvalidState)` if container has unsupported version or zero tile size.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < QOIT_HEADER_SIZE {
+            return Err(DecodeError::NotEnoughData {
+                offset: 0,
+                pixels_decoded: 0,
+                needed: QOIT_HEADER_SIZE - bytes.len(),
+            });
+        }
+
+        if bytes[..4] != QOIT_MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+        if bytes[4] != QOIT_VERSION {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let qoi = Qoi::decode_header(&bytes[5..])?;
+        let tile_size = u32::from_be_bytes(bytes[5 + QOI_HEADER_SIZE..][..4].try_into().unwrap());
+        if tile_size == 0 {
+            return Err(DecodeError::InvalidState);
+        }
+
+        let (tiles_x, tiles_y) = grid(&qoi, tile_size);
+        let end = (tiles_x as usize)
+            .checked_mul(tiles_y as usize)
+            .and_then(|count| count.checked_mul(TILE_ENTRY_SIZE))
+            .and_then(|size| size.checked_add(QOIT_HEADER_SIZE))
+            .unwrap_or(usize::MAX);
+        if bytes.len() < end {
+            return Err(DecodeError::NotEnoughData {
+                offset: QOIT_HEADER_SIZE,
+                pixels_decoded: 0,
+                needed: end - bytes.len(),
+            });
+        }
+
+        Ok(TiledDecoder {
+            qoi,
+            bytes,
+            tile_size,
+            tiles_x,
+            tiles_y,
+        })
+    }
+
+    /// Returns descriptor of the whole image.
+    #[inline]
+    pub fn qoi(&self) -> Qoi {
+        self.qoi
+    }
+
+    /// Returns size of tiles in pixels.
+    #[inline]
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Returns number of tile columns and rows.
+    #[inline]
+    pub fn tiles(&self) -> (u32, u32) {
+        (self.tiles_x, self.tiles_y)
+    }
+
+    /// Returns descriptor of tile at `tx` column and `ty` row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if tile is outside of the image.
+    #[inline]
+    pub fn tile_qoi(&self, tx: u32, ty: u32) -> Qoi {
+        assert!(tx < self.tiles_x && ty < self.tiles_y);
+        tile_qoi(&self.qoi, self.tile_size, tx, ty)
+    }
+
+    /// Returns encoded QOI image of tile at `tx` column and `ty` row.
+    ///
+    /// On success this function returns `Ok(bytes)` with `bytes` of the tile image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidRegion)` is returned if tile is outside of the image.
+    pub fn tile_bytes(&self, tx: u32, ty: u32) -> Result<&'a [u8], DecodeError> {
+        if tx >= self.tiles_x || ty >= self.tiles_y {
+            return Err(DecodeError::InvalidRegion);
+        }
+
+        let entry = QOIT_HEADER_SIZE
+            + (ty as usize * self.tiles_x as usize + tx as usize) * TILE_ENTRY_SIZE;
+        let field = |at: usize| {
+            let value = u64::from_be_bytes(self.bytes[at..at + 8].try_into().unwrap());
+            value.try_into().unwrap_or(usize::MAX)
+        };
+        let offset: usize = field(entry);
+        let size: usize = field(entry + 8);
+
+        let end = offset.saturating_add(size);
+        match self.bytes.get(offset..end) {
+            Some(tile) => Ok(tile),
+            None => Err(DecodeError::NotEnoughData {
+                offset: offset.min(self.bytes.len()),
+                pixels_decoded: 0,
+                needed: end - self.bytes.len().max(offset),
+            }),
+        }
+    }
+
+    /// Decode tile at `tx` column and `ty` row.\
+    /// Decoded raw RGB or RGBA pixels of the tile are written into `output` slice.\
+    /// `output` must hold at least `w * h * channels` bytes of [`TiledDecoder::tile_qoi`].
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing the tile.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidRegion)` is returned if tile is outside of the image
+    /// and `Err(DecodeError::DimensionsMismatch)` if tile image does not match its place in the grid.
+    pub fn decode_tile(&self, tx: u32, ty: u32, output: &mut [u8]) -> Result<Qoi, DecodeError> {
+        let bytes = self.tile_bytes(tx, ty)?;
+        let qoi = Qoi::decode_header(bytes)?;
+        if !qoi.same_frame(&self.tile_qoi(tx, ty)) {
+            return Err(DecodeError::DimensionsMismatch);
+        }
+        Qoi::decode(bytes, output)
+    }
+
+    /// Decode rectangular region of the image.\
+    /// Decoded raw RGB or RGBA pixels of the region are written into `output` slice.\
+    /// `output` must hold at least `w * h * channels` bytes.
+    ///
+    /// Only tiles overlapping the region are decoded,
+    /// each of them up to the last pixel of the region.
+    ///
+    /// On success this function returns `Ok(())`.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(DecodeError::InvalidRegion)` is returned if region does not fit into the image.
+    pub fn decode_region(
+        &self,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        output: &mut [u8],
+    ) -> Result<(), DecodeError> {
+        let fits =
+            |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(x, w, self.qoi.width) || !fits(y, h, self.qoi.height) {
+            return Err(DecodeError::InvalidRegion);
+        }
+
+        let channels = self.qoi.colors.channels();
+        let row = w as usize * channels;
+        let output = match output.get_mut(..row * h as usize) {
+            None => return Err(DecodeError::OutputIsTooSmall),
+            Some(output) => output,
+        };
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        let ts = self.tile_size;
+        let mut scratch = Vec::new();
+        for ty in y / ts..=(y + h - 1) / ts {
+            for tx in x / ts..=(x + w - 1) / ts {
+                let tile = self.tile_qoi(tx, ty);
+                let bytes = self.tile_bytes(tx, ty)?;
+                if !Qoi::decode_header(bytes)?.same_frame(&tile) {
+                    return Err(DecodeError::DimensionsMismatch);
+                }
+
+                // Part of the region covered by the tile in image coordinates.
+                let left = x.max(tx * ts);
+                let top = y.max(ty * ts);
+                let right = (x + w).min(tx * ts + tile.width);
+                let bottom = (y + h).min(ty * ts + tile.height);
+
+                let part_row = (right - left) as usize * channels;
+                scratch.resize(part_row * (bottom - top) as usize, 0);
+                Qoi::decode_region(
+                    bytes,
+                    left - tx * ts,
+                    top - ty * ts,
+                    right - left,
+                    bottom - top,
+                    &mut scratch,
+                )?;
+
+                let column = (left - x) as usize * channels;
+                for (i, part) in scratch.chunks_exact(part_row).enumerate() {
+                    let start = (top - y) as usize * row + i * row + column;
+                    output[start..start + part_row].copy_from_slice(part);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use super::*;
+use alloc::vec::Vec;
+
+/// Number of pixels decoded and encoded at once by [`Qoi::transcode`].
+const TRANSCODE_PIXELS: usize = 256;
+
+/// Errors that may occur during image transcoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TranscodeError {
+    /// Source bytes are not a valid QOI image.
+    Decode(DecodeError),
+
+    /// Transcoded image cannot be encoded.
+    Encode(EncodeError),
+
+    /// Alpha channel of the pixel with specified index is not `255` and cannot be dropped.
+    NotOpaque {
+        /// Index of the first pixel that is not fully opaque.
+        pixel: usize,
+    },
+
+    /// Verification found pixel of re-encoded image that differs from the source image.
+    Mismatch {
+        /// Index of the first differing pixel.
+        pixel: usize,
+    },
+}
+
+impl Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscodeError::Decode(err) => Display::fmt(err, f),
+            TranscodeError::Encode(err) => Display::fmt(err, f),
+            TranscodeError::NotOpaque { pixel } => write!(
+                f,
+                "Pixel {} is not fully opaque, alpha channel cannot be dropped",
+                pixel
+            ),
+            TranscodeError::Mismatch { pixel } => write!(
+                f,
+                "Pixel {} of re-encoded image differs from the source image",
+                pixel
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TranscodeError {}
+
+impl From<DecodeError> for TranscodeError {
+    #[inline]
+    fn from(err: DecodeError) -> Self {
+        TranscodeError::Decode(err)
+    }
+}
+
+impl From<EncodeError> for TranscodeError {
+    #[inline]
+    fn from(err: EncodeError) -> Self {
+        TranscodeError::Encode(err)
+    }
+}
+
+impl Qoi {
+    /// Transcode a QOI image from bytes slice into image with `target` colors appended to `out`.\
+    /// Pixels are decoded and encoded again in small portions, so no buffer for whole decoded image is required.\
+    /// Alpha channel is dropped or set to `255` as needed.
+    /// If channels number does not change, only color space in the header is rewritten and chunks are copied as is.\
+    /// Bytes following the end marker are not read.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing transcoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Contents of `out` following its original length are unspecified after failure.
+    #[inline]
+    pub fn transcode(
+        bytes: &[u8],
+        target: Colors,
+        out: &mut Vec<u8>,
+    ) -> Result<Self, TranscodeError> {
+        Self::transcode_checked(bytes, target, out, false)
+    }
+
+    /// Transcode a QOI image from bytes slice into image with `target` colors appended to `out`.\
+    /// Same as [`Qoi::transcode`] but alpha channel is dropped only from fully opaque pixels.
+    ///
+    /// On success this function returns `Ok(qoi)` with `qoi` describing transcoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(TranscodeError::NotOpaque { pixel })` is returned if alpha channel of any pixel is not `255`.
+    #[inline]
+    pub fn transcode_opaque(
+        bytes: &[u8],
+        target: Colors,
+        out: &mut Vec<u8>,
+    ) -> Result<Self, TranscodeError> {
+        Self::transcode_checked(bytes, target, out, true)
+    }
+
+    /// Re-encode a QOI image from bytes slice with this crate's encoder appending it to `out`.\
+    /// Header is preserved exactly and decoded pixels are the same as of the source image.\
+    /// Pixels are decoded and encoded again in small portions, so no buffer for whole decoded image is required.\
+    /// Bytes following the end marker are not read.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of re-encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// Contents of `out` following its original length are unspecified after failure.
+    #[inline]
+    pub fn reencode(bytes: &[u8], out: &mut Vec<u8>) -> Result<usize, TranscodeError> {
+        let qoi = Self::decode_header(bytes)?;
+
+        let start = out.len();
+        match qoi.colors.has_alpha() {
+            true => qoi.transcode_as::<4, 4>(bytes, qoi, out, false)?,
+            false => qoi.transcode_as::<3, 3>(bytes, qoi, out, false)?,
+        }
+        Ok(out.len() - start)
+    }
+
+    /// Re-encode a QOI image from bytes slice with this crate's encoder appending it to `out`.\
+    /// Same as [`Qoi::reencode`] but re-encoded image is decoded again and compared with the source image.
+    ///
+    /// On success this function returns `Ok(size)` with `size` of re-encoded image.\
+    /// On failure this function returns `Err(err)` with `err` describing cause of the error.\
+    /// `Err(TranscodeError::Mismatch { pixel })` is returned if re-encoded image differs from the source image.
+    #[inline]
+    pub fn reencode_verified(bytes: &[u8], out: &mut Vec<u8>) -> Result<usize, TranscodeError> {
+        let start = out.len();
+        let size = Self::reencode(bytes, out)?;
+        let reencoded = &out[start..];
+
+        let qoi = Self::decode_header(bytes)?;
+        if reencoded.get(..QOI_HEADER_SIZE) != Some(&bytes[..QOI_HEADER_SIZE]) {
+            return Err(TranscodeError::Mismatch { pixel: 0 });
+        }
+
+        let mut source = DecoderState::new();
+        let mut target = DecoderState::new();
+        let mut expected = [0; TRANSCODE_PIXELS * 4];
+        let mut decoded = [0; TRANSCODE_PIXELS * 4];
+        let channels = qoi.colors.channels();
+
+        while !source.is_finished() {
+            let pos = source.pixels_decoded();
+            let n = qoi.decode_resume(bytes, &mut source, &mut expected, usize::MAX)?;
+            let m = qoi.decode_resume(reencoded, &mut target, &mut decoded, usize::MAX);
+
+            let mismatch = match m {
+                Ok(m) if m == n => expected[..n * channels]
+                    .chunks_exact(channels)
+                    .zip(decoded[..n * channels].chunks_exact(channels))
+                    .position(|(a, b)| a != b),
+                _ => Some(0),
+            };
+            if let Some(i) = mismatch {
+                return Err(TranscodeError::Mismatch { pixel: pos + i });
+            }
+        }
+
+        if !target.is_finished() {
+            return Err(TranscodeError::Mismatch {
+                pixel: source.pixels_decoded(),
+            });
+        }
+
+        Ok(size)
+    }
+
+    /// Transcodes image, verifying that dropped alpha channel is opaque if `opaque` is `true`.
+    #[inline]
+    fn transcode_checked(
+        bytes: &[u8],
+        target: Colors,
+        out: &mut Vec<u8>,
+        opaque: bool,
+    ) -> Result<Self, TranscodeError> {
+        let qoi = Self::decode_header(bytes)?;
+        let transcoded = Qoi {
+            colors: target,
+            ..qoi
+        };
+
+        match (qoi.colors.has_alpha(), target.has_alpha()) {
+            (true, false) => qoi.transcode_as::<4, 3>(bytes, transcoded, out, opaque)?,
+            (false, true) => qoi.transcode_as::<3, 4>(bytes, transcoded, out, false)?,
+            _ => {
+                // Chunks do not depend on color space.
+                let (_, size) = Self::skip_image(bytes, false)?;
+                let start = out.len();
+                out.extend_from_slice(&bytes[..size]);
+                transcoded.encode_header(&mut out[start..])?;
+            }
+        }
+
+        Ok(transcoded)
+    }
+
+    /// Transcodes pixels with `N` channels into image `transcoded` with `M` channels.
+    fn transcode_as<const N: usize, const M: usize>(
+        &self,
+        bytes: &[u8],
+        transcoded: Qoi,
+        out: &mut Vec<u8>,
+        opaque: bool,
+    ) -> Result<(), TranscodeError>
+    where
+        [u8; N]: Pixel,
+        [u8; M]: Pixel,
+    {
+        let mut decoder = Decoder::new();
+        let mut encoder = Encoder::new(transcoded);
+        let mut decoded = [[0; N]; TRANSCODE_PIXELS];
+        let mut pixels = [[0; M]; TRANSCODE_PIXELS];
+        let mut rest = bytes;
+
+        while !decoder.is_finished() {
+            if rest.is_empty() {
+                return Err(decoder.truncated().into());
+            }
+
+            let pos = decoder.pixels_decoded();
+            let progress = decoder.push(rest, bytemuck::cast_slice_mut(&mut decoded))?;
+            rest = &rest[progress.consumed..];
+
+            let decoded = &decoded[..progress.pixels];
+            for (i, (px, out)) in decoded.iter().zip(&mut pixels).enumerate() {
+                if opaque && px.a() != 0xff {
+                    return Err(TranscodeError::NotOpaque { pixel: pos + i });
+                }
+                *out = convert(px);
+            }
+
+            let len = out.len();
+            out.resize(len + QOI_HEADER_SIZE + 1 + progress.pixels * (M + 1) + 7, 0);
+            let n = encoder.push_pixels(
+                bytemuck::cast_slice(&pixels[..progress.pixels]),
+                &mut out[len..],
+            )?;
+            out.truncate(len + n);
+        }
+
+        let len = out.len();
+        out.resize(len + QOI_HEADER_SIZE + 1 + QOI_PADDING, 0);
+        let n = encoder.finish(&mut out[len..])?;
+        out.truncate(len + n);
+
+        Ok(())
+    }
+}
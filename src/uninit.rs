@@ -0,0 +1,106 @@
+//! Writing into uninitialized memory.
+//!
+//! This is the only module of the crate allowed to use unsafe code.\
+//! Bytes are written only through [`Init`] which counts bytes written from the start of the buffer,
+//! so the written prefix is returned as initialized slice regardless of how the decoder uses it.
+
+use core::mem::MaybeUninit;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use super::*;
+
+/// Uninitialized buffer with initialized prefix.
+pub(crate) struct Init<'a> {
+    buf: &'a mut [MaybeUninit<u8>],
+    len: usize,
+}
+
+impl<'a> Init<'a> {
+    /// Wraps `buf` with empty initialized prefix.
+    #[inline]
+    pub fn new(buf: &'a mut [MaybeUninit<u8>]) -> Self {
+        Init { buf, len: 0 }
+    }
+
+    /// Returns number of bytes left after initialized prefix.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.len
+    }
+
+    /// Appends `px` to initialized prefix.\
+    /// Panics if there is no room for the pixel.
+    #[inline]
+    pub fn push<const M: usize>(&mut self, px: [u8; M]) {
+        let slot = &mut self.buf[self.len..self.len + M];
+        for (slot, byte) in slot.iter_mut().zip(px) {
+            slot.write(byte);
+        }
+        self.len += M;
+    }
+
+    /// Appends up to `n` copies of `px` to initialized prefix.\
+    /// Returns number of pixels written.
+    #[inline]
+    pub fn fill<const M: usize>(&mut self, px: [u8; M], n: usize) -> usize
+    where
+        [u8; M]: Pixel,
+    {
+        let n = n.min(self.remaining() / M);
+        let bytes = &mut self.buf[self.len..self.len + n * M];
+
+        if M != 3 || n < runs::MIN_RUN {
+            for slot in bytes.chunks_exact_mut(M) {
+                for (slot, byte) in slot.iter_mut().zip(px) {
+                    slot.write(byte);
+                }
+            }
+        } else {
+            let block = runs::block(px);
+            let block = bytemuck::cast_slice::<_, u8>(&block);
+            for chunk in bytes.chunks_mut(block.len()) {
+                for (slot, &byte) in chunk.iter_mut().zip(block) {
+                    slot.write(byte);
+                }
+            }
+        }
+
+        self.len += n * M;
+        n
+    }
+
+    /// Returns initialized prefix of the buffer.
+    #[inline]
+    pub fn into_init(self) -> &'a mut [u8] {
+        let init = &mut self.buf[..self.len];
+        // SAFETY: every byte before `len` was written by `push` or `fill`,
+        // `MaybeUninit<u8>` has the same layout as `u8`.
+        unsafe { core::slice::from_raw_parts_mut(init.as_mut_ptr().cast::<u8>(), init.len()) }
+    }
+}
+
+/// Appends up to `additional` bytes to `vec` initialized by `f`.\
+/// `f` receives uninitialized spare capacity of `additional` bytes
+/// and returns its initialized prefix that becomes part of `vec`.
+#[cfg(feature = "alloc")]
+#[inline]
+pub(crate) fn extend<E>(
+    vec: &mut Vec<u8>,
+    additional: usize,
+    f: impl FnOnce(&mut [MaybeUninit<u8>]) -> Result<&mut [u8], E>,
+) -> Result<(), E> {
+    vec.reserve(additional);
+    let len = vec.len();
+    let spare = &mut vec.spare_capacity_mut()[..additional];
+    let start = spare.as_ptr().cast::<u8>();
+
+    let init = f(spare)?;
+    assert!(init.as_ptr() == start && init.len() <= additional);
+
+    let len = len + init.len();
+    // SAFETY: `init` is initialized slice placed at the start of spare capacity.
+    unsafe { vec.set_len(len) };
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Allocating encoding and decoding functions.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{gradient, runs};
+use rapid_qoi::{Colors, DecodeError, EncodeError, Qoi};
+
+#[test]
+fn decode_alloc_as_decode() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let channels = colors.channels();
+        for pixels in [gradient(13, 7, channels), runs(13, 7, channels)] {
+            let qoi = Qoi {
+                width: 13,
+                height: 7,
+                colors,
+            };
+            let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+            let (_, decoded) = Qoi::decode_alloc(&encoded).unwrap();
+            assert_eq!(decoded, pixels);
+
+            let mut concatenated = encoded.clone();
+            concatenated.extend_from_slice(&encoded);
+            let (_, decoded, size) = Qoi::decode_alloc_counted(&concatenated).unwrap();
+            assert_eq!(decoded, pixels);
+            assert_eq!(size, encoded.len());
+        }
+    }
+}
+
+#[test]
+fn decode_alloc_empty_image() {
+    for (width, height) in [(0, 0), (0, 5), (5, 0)] {
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Srgb,
+        };
+        let encoded = qoi.encode_alloc(&[]).unwrap();
+
+        let (_, decoded) = Qoi::decode_alloc(&encoded).unwrap();
+        assert!(decoded.is_empty());
+
+        let (_, decoded, size) = Qoi::decode_alloc_counted(&encoded).unwrap();
+        assert!(decoded.is_empty());
+        assert_eq!(size, encoded.len());
+    }
+}
+
+#[test]
+fn decode_alloc_fails_as_decode() {
+    let qoi = Qoi {
+        width: 13,
+        height: 7,
+        colors: Colors::Rgba,
+    };
+    let pixels = runs(13, 7, 4);
+    let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+    let mut output = vec![0; pixels.len()];
+    for end in 0..encoded.len() {
+        let truncated = &encoded[..end];
+        assert_eq!(
+            Qoi::decode_alloc(truncated).err(),
+            Qoi::decode(truncated, &mut output).err(),
+            "truncated at {}",
+            end
+        );
+        assert_eq!(
+            Qoi::decode_alloc_counted(truncated).err(),
+            Qoi::decode_counted(truncated, &mut output).err(),
+            "truncated at {}",
+            end
+        );
+    }
+
+    let mut corrupted = encoded.clone();
+    *corrupted.last_mut().unwrap() = 2;
+    assert_eq!(
+        Qoi::decode_alloc_counted(&corrupted).err(),
+        Some(DecodeError::InvalidPadding {
+            offset: encoded.len() - 8
+        })
+    );
+}
+
+#[test]
+fn encode_append_keeps_existing_content() {
+    let qoi = Qoi {
+        width: 13,
+        height: 7,
+        colors: Colors::Rgba,
+    };
+    let pixels = gradient(13, 7, 4);
+    let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+    let mut output = b"prefix".to_vec();
+    let size = qoi.encode_append(&pixels, &mut output).unwrap();
+    assert_eq!(size, encoded.len());
+    assert_eq!(&output[..6], b"prefix");
+    assert_eq!(&output[6..], &encoded[..]);
+}
+
+#[test]
+fn encode_append_restores_length_on_failure() {
+    let qoi = Qoi {
+        width: 13,
+        height: 7,
+        colors: Colors::Rgba,
+    };
+    let pixels = gradient(13, 7, 4);
+
+    let mut output = b"prefix".to_vec();
+    let err = qoi.encode_append(&pixels[1..], &mut output).unwrap_err();
+    assert!(matches!(err, EncodeError::InputSizeMismatch { .. }));
+    assert_eq!(output, b"prefix");
+}
+
+#[test]
+fn encode_oversized_image_fails() {
+    let qoi = Qoi {
+        width: u32::MAX,
+        height: u32::MAX,
+        colors: Colors::Rgba,
+    };
+
+    let mut output = b"prefix".to_vec();
+    assert!(matches!(
+        qoi.encode_append(&[], &mut output),
+        Err(EncodeError::TooManyPixels)
+    ));
+    assert!(matches!(
+        qoi.encode_alloc(&[]),
+        Err(EncodeError::TooManyPixels)
+    ));
+    assert_eq!(output, b"prefix");
+}
@@ -0,0 +1,275 @@
+//! Animation container encoding and decoding.
+
+#![cfg(feature = "animation")]
+
+mod common;
+
+use common::{gradient, photo, Rng};
+use rapid_qoi::{
+    animation::{AnimationDecoder, AnimationEncoder, QOIA_HEADER_SIZE},
+    Colors, DecodeError, EncodeError, Qoi,
+};
+
+/// Returns frames of `qoi` with random content and durations.
+fn frames(qoi: &Qoi, count: usize, rng: &mut Rng) -> Vec<(u32, Vec<u8>)> {
+    let channels = qoi.colors.channels();
+    (0..count)
+        .map(|i| {
+            let pixels = match i % 3 {
+                0 => photo(qoi.width, qoi.height, channels),
+                1 => gradient(qoi.width, qoi.height, channels),
+                _ => (0..qoi.decoded_size()).map(|_| rng.next() as u8).collect(),
+            };
+            (rng.next() % 1000, pixels)
+        })
+        .collect()
+}
+
+fn encode(qoi: Qoi, frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut encoder = AnimationEncoder::new(qoi);
+    for (duration_ms, pixels) in frames {
+        encoder.push_frame(pixels, *duration_ms).unwrap();
+    }
+    assert_eq!(encoder.frames() as usize, frames.len());
+    encoder.finish()
+}
+
+#[test]
+fn frames_decode_as_encoded() {
+    let mut rng = Rng(85);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        for (width, height, count) in [(16, 12, 5), (1, 1, 1), (33, 7, 0)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let frames = frames(&qoi, count, &mut rng);
+            let bytes = encode(qoi, &frames);
+
+            let decoder = AnimationDecoder::new(&bytes).unwrap();
+            assert_eq!(decoder.frame_count() as usize, count);
+            assert_eq!((decoder.qoi().width, decoder.qoi().height), (width, height));
+
+            let mut buffer = vec![0; qoi.decoded_size()];
+            let mut decoded = decoder.frames(&mut buffer);
+            for (duration_ms, pixels) in &frames {
+                let frame = decoded.next_frame().unwrap().unwrap();
+                assert_eq!(frame.duration_ms, *duration_ms);
+                assert_eq!((frame.qoi.width, frame.qoi.height), (width, height));
+                assert!(frame.key);
+                assert!(frame.pixels == &pixels[..]);
+            }
+            assert_eq!(decoded.frames_left(), 0);
+            assert!(decoded.next_frame().is_none());
+        }
+    }
+}
+
+#[test]
+fn frames_with_other_dimensions_fail() {
+    let qoi = Qoi {
+        width: 16,
+        height: 12,
+        colors: Colors::Rgba,
+    };
+    let mut encoder = AnimationEncoder::new(qoi);
+    encoder.push_frame(&photo(16, 12, 4), 40).unwrap();
+
+    assert!(matches!(
+        encoder.push_frame(&photo(16, 11, 4), 40),
+        Err(EncodeError::InputSizeMismatch { .. })
+    ));
+    for other in [
+        Qoi {
+            width: 12,
+            height: 16,
+            ..qoi
+        },
+        Qoi {
+            colors: Colors::Srgb,
+            ..qoi
+        },
+    ] {
+        let pixels = photo(other.width, other.height, other.colors.channels());
+        let image = other.encode_alloc(&pixels).unwrap();
+        assert_eq!(
+            encoder.push_encoded_frame(&image, 40),
+            Err(DecodeError::DimensionsMismatch)
+        );
+    }
+    assert_eq!(encoder.frames(), 1);
+
+    let image = qoi.encode_alloc(&gradient(16, 12, 4)).unwrap();
+    encoder.push_encoded_frame(&image, 40).unwrap();
+    let bytes = encoder.finish();
+    assert_eq!(AnimationDecoder::new(&bytes).unwrap().frame_count(), 2);
+}
+
+#[test]
+fn plain_qoi_is_not_animation() {
+    let qoi = Qoi {
+        width: 16,
+        height: 12,
+        colors: Colors::Rgba,
+    };
+    let image = qoi.encode_alloc(&photo(16, 12, 4)).unwrap();
+    assert_eq!(
+        AnimationDecoder::new(&image).err(),
+        Some(DecodeError::InvalidMagic)
+    );
+}
+
+#[test]
+fn truncated_animation_ends_with_error() {
+    let mut rng = Rng(850);
+    let qoi = Qoi {
+        width: 9,
+        height: 5,
+        colors: Colors::Rgba,
+    };
+    let frames = frames(&qoi, 4, &mut rng);
+    let bytes = encode(qoi, &frames);
+    let mut buffer = vec![0; qoi.decoded_size()];
+
+    for len in 0..bytes.len() {
+        let decoder = match AnimationDecoder::new(&bytes[..len]) {
+            Ok(decoder) => decoder,
+            Err(err) => {
+                assert!(matches!(err, DecodeError::NotEnoughData { .. }), "{}", len);
+                continue;
+            }
+        };
+
+        let mut decoded = decoder.frames(&mut buffer);
+        let mut complete = 0;
+        let err = loop {
+            match decoded.next_frame() {
+                Some(Ok(frame)) => {
+                    assert!(frame.pixels == &frames[complete].1[..], "{}", len);
+                    complete += 1;
+                }
+                Some(Err(err)) => break err,
+                None => panic!("truncated at {} ends without error", len),
+            }
+        };
+        assert!(matches!(err, DecodeError::NotEnoughData { .. }), "{}", len);
+        assert!(complete < frames.len());
+        assert!(decoded.next_frame().is_none());
+    }
+}
+
+/// Returns frames of cursor moving over static photo-like background.
+fn cursor_frames(qoi: &Qoi, count: u32) -> Vec<Vec<u8>> {
+    let channels = qoi.colors.channels();
+    let background = photo(qoi.width, qoi.height, channels);
+    (0..count)
+        .map(|i| {
+            let mut pixels = background.clone();
+            let (cx, cy) = (i * 7 % (qoi.width - 16), i * 5 % (qoi.height - 16));
+            for y in cy..cy + 16 {
+                for x in cx..cx + 16 - (y - cy) {
+                    let at = (y * qoi.width + x) as usize * channels;
+                    let px = [255, 255, 255, 255][..channels].to_vec();
+                    pixels[at..at + channels].copy_from_slice(&px);
+                }
+            }
+            pixels
+        })
+        .collect()
+}
+
+fn encode_with_key_interval(qoi: Qoi, frames: &[Vec<u8>], key_interval: u32) -> Vec<u8> {
+    let mut encoder = AnimationEncoder::with_key_interval(qoi, key_interval);
+    for pixels in frames {
+        encoder.push_frame(pixels, 16).unwrap();
+    }
+    encoder.finish()
+}
+
+#[test]
+fn delta_frames_are_order_of_magnitude_smaller() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 320,
+            height: 240,
+            colors,
+        };
+        let frames = cursor_frames(&qoi, 30);
+        let keys = encode_with_key_interval(qoi, &frames, 1).len();
+        let deltas = encode_with_key_interval(qoi, &frames, 0).len();
+        assert!(deltas * 10 < keys, "deltas {} keys {}", deltas, keys);
+    }
+}
+
+#[test]
+fn delta_frames_decode_bit_exact() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 64,
+            height: 48,
+            colors,
+        };
+        let frames = cursor_frames(&qoi, 20);
+        for key_interval in [0, 1, 2, 7] {
+            let bytes = encode_with_key_interval(qoi, &frames, key_interval);
+            let decoder = AnimationDecoder::new(&bytes).unwrap();
+            let mut buffer = vec![0; qoi.decoded_size()];
+            let mut decoded = decoder.frames(&mut buffer);
+
+            for (i, pixels) in frames.iter().enumerate() {
+                let frame = decoded.next_frame().unwrap().unwrap();
+                let key = match key_interval {
+                    0 => i == 0,
+                    n => i % n as usize == 0,
+                };
+                assert_eq!(frame.key, key, "frame {} interval {}", i, key_interval);
+                assert!(
+                    frame.pixels == &pixels[..],
+                    "frame {} interval {}",
+                    i,
+                    key_interval
+                );
+            }
+            assert!(decoded.next_frame().is_none());
+        }
+    }
+}
+
+#[test]
+fn seek_restarts_from_key_frame() {
+    let qoi = Qoi {
+        width: 64,
+        height: 48,
+        colors: Colors::Rgba,
+    };
+    let frames = cursor_frames(&qoi, 20);
+    let mut bytes = encode_with_key_interval(qoi, &frames, 8);
+    let mut buffer = vec![0; qoi.decoded_size()];
+
+    {
+        let decoder = AnimationDecoder::new(&bytes).unwrap();
+        let mut decoded = decoder.frames(&mut buffer);
+        for frame in [19, 3, 8, 0, 16, 9, 9, 15] {
+            decoded.seek(frame).unwrap();
+            let decoded = decoded.next_frame().unwrap().unwrap();
+            assert!(decoded.pixels == &frames[frame as usize][..], "{}", frame);
+        }
+        decoded.seek(20).unwrap();
+        assert!(decoded.next_frame().is_none());
+        assert_eq!(decoded.seek(21), Err(DecodeError::InvalidRegion));
+    }
+
+    // Frames before the key frame preceding the target are not decoded.
+    // Image of the first frame follows duration, flags and size of the frame.
+    let first = QOIA_HEADER_SIZE + 4 + 1 + 8;
+    bytes[first..first + 4].copy_from_slice(b"bad!");
+    let decoder = AnimationDecoder::new(&bytes).unwrap();
+    let mut decoded = decoder.frames(&mut buffer);
+    for frame in [8, 12, 19] {
+        decoded.seek(frame).unwrap();
+        let decoded = decoded.next_frame().unwrap().unwrap();
+        assert!(decoded.pixels == &frames[frame as usize][..], "{}", frame);
+    }
+    assert!(decoded.seek(5).is_err());
+}
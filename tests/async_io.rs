@@ -0,0 +1,182 @@
+//! Decoding from asynchronous readers and encoding into asynchronous writers compared with synchronous API.
+
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use common::{photo, runs, FIXTURES};
+use rapid_qoi::{Colors, DecodeError, Qoi, ReadDecodeError};
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+/// Polls both futures until they are complete.\
+/// Futures used here only wait for each other, so waking is not required.
+fn join<A: Future, B: Future>(a: A, b: B) -> (A::Output, B::Output) {
+    let mut a = Box::pin(a);
+    let mut b = Box::pin(b);
+    let (mut out_a, mut out_b) = (None, None);
+    let mut cx = Context::from_waker(Waker::noop());
+    while out_a.is_none() || out_b.is_none() {
+        if out_a.is_none() {
+            if let Poll::Ready(out) = a.as_mut().poll(&mut cx) {
+                out_a = Some(out);
+            }
+        }
+        if out_b.is_none() {
+            if let Poll::Ready(out) = b.as_mut().poll(&mut cx) {
+                out_b = Some(out);
+            }
+        }
+    }
+    (out_a.unwrap(), out_b.unwrap())
+}
+
+/// Decodes `bytes` written into duplex stream with `capacity` bytes buffer in writes of `chunk` bytes.\
+/// Returns decoding result and bytes left in the stream after decoding.
+fn decode_through_duplex(
+    bytes: &[u8],
+    capacity: usize,
+    chunk: usize,
+    output: &mut [u8],
+) -> (Result<Qoi, ReadDecodeError>, Vec<u8>) {
+    let (mut tx, mut rx) = duplex(capacity);
+    let (result, ()) = join(
+        async {
+            let result = Qoi::decode_from_async_reader(&mut rx, output).await;
+            // Read the rest while writer may still wait for space.
+            let mut rest = Vec::new();
+            rx.read_to_end(&mut rest).await.unwrap();
+            (result, rest)
+        },
+        async move {
+            for part in bytes.chunks(chunk) {
+                tx.write_all(part).await.unwrap();
+            }
+            drop(tx);
+        },
+    );
+    result
+}
+
+#[test]
+fn decode_in_tiny_chunks_as_slice() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut expected).unwrap();
+        let mut data = bytes[..size].to_vec();
+        data.extend_from_slice(b"next");
+
+        for (capacity, chunk) in [(1, 1), (3, 2), (5, 7), (64, 13), (8192, 8192)] {
+            let mut output = vec![0; qoi.decoded_size()];
+            let (result, rest) = decode_through_duplex(&data, capacity, chunk, &mut output);
+            let decoded = result.unwrap();
+            assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+            assert!(output == expected, "{} chunk {}", name, chunk);
+            assert_eq!(rest, b"next", "{}", name);
+        }
+    }
+}
+
+#[test]
+fn truncated_stream_fails() {
+    let (_, bytes) = FIXTURES[0];
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut output = vec![0; qoi.decoded_size()];
+    for len in [0, 5, 14, 20, bytes.len() - 1] {
+        let (result, _) = decode_through_duplex(&bytes[..len], 4, 3, &mut output);
+        assert!(
+            matches!(
+                result,
+                Err(ReadDecodeError::Decode(DecodeError::NotEnoughData { .. }))
+            ),
+            "truncated at {}",
+            len
+        );
+    }
+}
+
+#[test]
+fn encode_through_duplex_as_slice() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        for (width, height) in [(97, 43), (1, 1), (0, 0)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            for pixels in [
+                photo(width, height, colors.channels()),
+                runs(width, height, colors.channels()),
+            ] {
+                let mut expected = vec![0; qoi.encoded_size_limit()];
+                let size = qoi.encode(&pixels, &mut expected).unwrap();
+                expected.truncate(size);
+
+                for capacity in [1, 7, 5000] {
+                    let (mut tx, mut rx) = duplex(capacity);
+                    let (written, encoded) = join(
+                        async {
+                            let written = qoi.encode_to_async_writer(&pixels, &mut tx).await;
+                            drop(tx);
+                            written
+                        },
+                        async {
+                            let mut encoded = Vec::new();
+                            rx.read_to_end(&mut encoded).await.unwrap();
+                            encoded
+                        },
+                    );
+                    assert_eq!(written.unwrap(), size as u64);
+                    assert!(
+                        encoded == expected,
+                        "{}x{} capacity {}",
+                        width,
+                        height,
+                        capacity
+                    );
+
+                    // Encoded stream decodes asynchronously as well.
+                    let mut output = vec![0; pixels.len()];
+                    let (result, _) = decode_through_duplex(&encoded, capacity, 3, &mut output);
+                    result.unwrap();
+                    assert!(output == pixels);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn dropped_decode_is_abandoned() {
+    let (_, bytes) = FIXTURES[0];
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut output = vec![0; qoi.decoded_size()];
+
+    let (mut tx, mut rx) = duplex(16);
+    {
+        let mut future = Box::pin(Qoi::decode_from_async_reader(&mut rx, &mut output));
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut written = 0;
+        for _ in 0..8 {
+            assert!(matches!(future.as_mut().poll(&mut cx), Poll::Pending));
+            let mut write = Box::pin(tx.write(&bytes[written..]));
+            if let Poll::Ready(n) = Pin::new(&mut write).poll(&mut cx) {
+                written += n.unwrap();
+            }
+        }
+        assert!(written < bytes.len());
+    }
+
+    // Abandoned decoding does not affect decoding into the same output again.
+    let mut expected = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut expected).unwrap();
+    let (result, _) = decode_through_duplex(bytes, 16, 5, &mut output);
+    result.unwrap();
+    assert!(output == expected);
+}
@@ -0,0 +1,75 @@
+//! Decoding chunks without header compared with decoding whole images.
+
+mod common;
+
+use common::FIXTURES;
+use rapid_qoi::{DecodeError, Qoi};
+
+const HEADER: usize = 14;
+
+#[test]
+fn body_decodes_as_whole_image() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut expected).unwrap();
+
+        let mut output = vec![0; qoi.decoded_size()];
+        assert_eq!(
+            qoi.decode_body(&bytes[HEADER..], &mut output).unwrap() + HEADER,
+            size,
+            "{}",
+            name
+        );
+        assert!(output == expected, "{}", name);
+
+        // Old entry point still ignores the end marker.
+        let mut output = vec![0; qoi.decoded_size()];
+        qoi.decode_skip_header(&bytes[HEADER..], &mut output)
+            .unwrap();
+        assert!(output == expected, "{}", name);
+
+        let mut short = vec![0; qoi.decoded_size() - 1];
+        assert_eq!(
+            qoi.decode_body(&bytes[HEADER..], &mut short),
+            Err(DecodeError::OutputIsTooSmall)
+        );
+    }
+}
+
+#[test]
+fn body_errors_are_counted_with_header() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut output = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut output).unwrap();
+
+        let mut broken = Vec::new();
+        let step = (size / 97).max(1);
+        for len in (HEADER..size).step_by(step).chain(size - 8..size) {
+            broken.push(bytes[..len].to_vec());
+        }
+        for at in size - 8..size {
+            let mut corrupted = bytes[..size].to_vec();
+            corrupted[at] ^= 0x02;
+            broken.push(corrupted);
+        }
+
+        for bytes in &broken {
+            let expected = Qoi::decode_counted(bytes, &mut output).map(|(_, size)| size);
+            let result = qoi
+                .decode_body(&bytes[HEADER..], &mut output)
+                .map(|size| size + HEADER);
+            assert!(expected.is_err(), "{} {}", name, bytes.len());
+            assert_eq!(result, expected, "{} {}", name, bytes.len());
+        }
+
+        // Invalid marker is reported at its offset in the whole image.
+        let mut corrupted = bytes[..size].to_vec();
+        corrupted[size - 1] = 0;
+        assert_eq!(
+            qoi.decode_body(&corrupted[HEADER..], &mut output),
+            Err(DecodeError::InvalidPadding { offset: size - 8 })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Decoding from non-contiguous `Buf` and encoding into `BufMut` compared with slice API.
+
+#![cfg(feature = "bytes")]
+
+mod common;
+
+use std::collections::VecDeque;
+
+use bytes::{Buf, Bytes, BytesMut};
+use common::{photo, runs, Rng, FIXTURES};
+use rapid_qoi::{chunk::ChunkIter, Colors, DecodeError, EncodeError, Qoi};
+
+/// Chain of `Bytes` segments.
+struct Segments(VecDeque<Bytes>);
+
+impl Segments {
+    /// Splits `bytes` at sorted `offsets`, ignoring offsets beyond the end.
+    fn split(bytes: &[u8], offsets: &[usize]) -> Self {
+        let bytes = Bytes::copy_from_slice(bytes);
+        let mut parts = VecDeque::new();
+        let mut start = 0;
+        for &offset in offsets.iter().chain(&[bytes.len()]) {
+            if start < offset && offset <= bytes.len() {
+                parts.push_back(bytes.slice(start..offset));
+                start = offset;
+            }
+        }
+        Segments(parts)
+    }
+}
+
+impl Buf for Segments {
+    fn remaining(&self) -> usize {
+        self.0.iter().map(Bytes::len).sum()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.0.front().map_or(&[], |part| &part[..])
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while cnt > 0 {
+            let part = self.0.front_mut().unwrap();
+            let n = cnt.min(part.len());
+            part.advance(n);
+            cnt -= n;
+            if part.is_empty() {
+                self.0.pop_front();
+            }
+        }
+    }
+}
+
+/// Offsets of every chunk and offsets inside multi-byte chunks.
+fn adversarial_offsets(bytes: &[u8]) -> Vec<usize> {
+    let mut offsets = vec![1, 4, 13];
+    for chunk in ChunkIter::new(bytes).unwrap() {
+        let (offset, _) = chunk.unwrap();
+        offsets.extend_from_slice(&[offset, offset + 1, offset + 3]);
+    }
+    offsets.push(bytes.len() - 4);
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets.retain(|&offset| offset < bytes.len());
+    offsets
+}
+
+#[test]
+fn decode_from_segments_as_slice() {
+    let mut rng = Rng(56);
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut expected).unwrap();
+        let mut data = bytes[..size].to_vec();
+        data.extend_from_slice(b"footer");
+
+        let mut splits = vec![adversarial_offsets(&data[..size]), Vec::new()];
+        if size < 4096 {
+            splits.push((1..data.len()).collect());
+        }
+        for _ in 0..8 {
+            let mut offsets: Vec<_> = (0..16).map(|_| rng.next() as usize % data.len()).collect();
+            offsets.sort_unstable();
+            splits.push(offsets);
+        }
+
+        for offsets in &splits {
+            let mut buf = Segments::split(&data, offsets);
+            let mut output = vec![0; qoi.decoded_size()];
+            let decoded = Qoi::decode_from_buf(&mut buf, &mut output).unwrap();
+            assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+            assert!(output == expected, "{} {:?}", name, offsets);
+
+            // Cursor is right after the end marker.
+            assert_eq!(
+                buf.copy_to_bytes(buf.remaining()),
+                &b"footer"[..],
+                "{}",
+                name
+            );
+        }
+
+        for len in [0, 13, size - 1] {
+            let mut buf = Segments::split(&data[..len], &adversarial_offsets(&data[..size]));
+            assert!(
+                matches!(
+                    Qoi::decode_from_buf(&mut buf, &mut expected),
+                    Err(DecodeError::NotEnoughData { .. })
+                ),
+                "{} truncated at {}",
+                name,
+                len
+            );
+        }
+    }
+}
+
+#[test]
+fn encode_to_bufmut_as_slice() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        for (width, height) in [(201, 77), (1, 1), (0, 0)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            for pixels in [
+                photo(width, height, colors.channels()),
+                runs(width, height, colors.channels()),
+            ] {
+                let mut expected = vec![0; qoi.encoded_size_limit()];
+                let size = qoi.encode(&pixels, &mut expected).unwrap();
+                expected.truncate(size);
+
+                let mut vec = b"header".to_vec();
+                assert_eq!(qoi.encode_to_bufmut(&pixels, &mut vec).unwrap(), size);
+                assert!(vec[6..] == expected[..]);
+
+                let mut bytes = BytesMut::new();
+                assert_eq!(qoi.encode_to_bufmut(&pixels, &mut bytes).unwrap(), size);
+                assert!(bytes[..] == expected[..]);
+
+                let mut slice = vec![0; size];
+                assert_eq!(
+                    qoi.encode_to_bufmut(&pixels, &mut &mut slice[..]).unwrap(),
+                    size
+                );
+                assert!(slice == expected);
+
+                let mut short = vec![0; size - 1];
+                assert!(matches!(
+                    qoi.encode_to_bufmut(&pixels, &mut &mut short[..]),
+                    Err(EncodeError::OutputIsTooSmall { .. })
+                ));
+            }
+        }
+    }
+}
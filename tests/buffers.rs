@@ -0,0 +1,419 @@
+//! Encoding into fixed size buffers and reporting of buffer sizes.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{
+    chunk::ChunkIter, ChannelOrder, Colors, DecodeError, EncodeError, Encoder, EncoderState,
+    PixelLayout, Qoi, StepResult, U32Layout,
+};
+
+/// Returns offsets where output buffer may end: inside header, between chunks and before end marker.
+fn boundaries(encoded: &[u8]) -> Vec<usize> {
+    let mut offsets = (0..=14).collect::<Vec<_>>();
+    let mut chunks = ChunkIter::new(encoded).unwrap();
+    for chunk in &mut chunks {
+        offsets.push(chunk.unwrap().0);
+    }
+    offsets.push(chunks.offset());
+    offsets.push(encoded.len());
+    offsets
+}
+
+/// Encodes `pixels` with `encode_step` into buffers of random sizes from `min` bytes.
+fn encode_steps(qoi: Qoi, pixels: &[u8], rng: &mut Rng, min: usize, max: usize) -> Vec<Vec<u8>> {
+    let mut encoder = Encoder::new(qoi);
+    let mut pixels = pixels;
+    let mut buffers = Vec::new();
+    loop {
+        let mut buffer = vec![0; min + rng.next() as usize % (max - min + 1)];
+        let (size, result) = encoder.encode_step(&mut pixels, &mut buffer).unwrap();
+        buffer.truncate(size);
+        buffers.push(buffer);
+        match result {
+            StepResult::Finished => return buffers,
+            StepResult::OutputFull => {}
+            StepResult::NeedMorePixels => panic!("all pixels are provided"),
+        }
+    }
+}
+
+#[test]
+fn steps_match_encode_without_splitting_chunks() {
+    let mut rng = Rng(64);
+    for &(name, fixture) in FIXTURES {
+        let (qoi, pixels) = Qoi::decode_alloc(fixture).unwrap();
+        let expected = qoi.encode_alloc(&pixels).unwrap();
+        let boundaries = boundaries(&expected);
+
+        for &(min, max) in &[(8, 8), (8, 16), (9, 100), (64, 4096)] {
+            let buffers = encode_steps(qoi, &pixels, &mut rng, min, max);
+
+            let mut end = 0;
+            for buffer in &buffers {
+                end += buffer.len();
+                assert!(boundaries.contains(&end), "{} chunk split at {}", name, end);
+            }
+            assert!(
+                buffers.concat() == expected,
+                "{} buffers {}..={}",
+                name,
+                min,
+                max
+            );
+        }
+    }
+}
+
+#[test]
+fn steps_with_pixels_in_parts() {
+    let (qoi, pixels) = Qoi::decode_alloc(FIXTURES[0].1).unwrap();
+    let expected = qoi.encode_alloc(&pixels).unwrap();
+    let (head, tail) = pixels.split_at(pixels.len() / 2 + 1);
+
+    let mut encoder = Encoder::new(qoi);
+    let mut output = vec![0; expected.len()];
+    let mut rest = head;
+    let (first, result) = encoder.encode_step(&mut rest, &mut output).unwrap();
+    assert_eq!(result, StepResult::NeedMorePixels);
+    assert!(rest.len() < qoi.colors.channels());
+
+    let joined = [rest, tail].concat();
+    let mut rest = &joined[..];
+    let (second, result) = encoder
+        .encode_step(&mut rest, &mut output[first..])
+        .unwrap();
+    assert_eq!(result, StepResult::Finished);
+    assert!(output[..first + second] == expected[..]);
+}
+
+#[test]
+fn encode_into_exact_size() {
+    for &(name, fixture) in FIXTURES {
+        let (qoi, pixels) = Qoi::decode_alloc(fixture).unwrap();
+        let size = qoi.encoded_size(&pixels).unwrap();
+        assert_eq!(size, fixture.len(), "{}", name);
+
+        let mut output = vec![0; size];
+        assert_eq!(qoi.encode(&pixels, &mut output).unwrap(), size);
+
+        let mut output = vec![0; size - 1];
+        match qoi.encode(&pixels, &mut output) {
+            Err(EncodeError::OutputIsTooSmall { required }) => {
+                assert_eq!(required, qoi.encoded_size_limit())
+            }
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+}
+
+#[test]
+fn decode_from_one_byte_short() {
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+        let mut output = vec![0; qoi.decoded_size()];
+        match Qoi::decode_counted(&fixture[..fixture.len() - 1], &mut output) {
+            Err(DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded,
+                needed,
+            }) => {
+                assert_eq!(offset, fixture.len() - 8, "{}", name);
+                assert_eq!(pixels_decoded, qoi.width as usize * qoi.height as usize);
+                assert_eq!(needed, 1);
+            }
+            other => panic!("{}: {:?}", name, other),
+        }
+
+        match Qoi::decode_header(&fixture[..13]) {
+            Err(DecodeError::NotEnoughData { needed, .. }) => assert_eq!(needed, 1),
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+}
+
+#[test]
+fn decode_into_exact_size() {
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+
+        let mut output = vec![0; qoi.decoded_size()];
+        Qoi::decode(fixture, &mut output).unwrap();
+
+        let mut output = vec![0; qoi.decoded_size() - 1];
+        assert!(
+            matches!(
+                Qoi::decode(fixture, &mut output),
+                Err(DecodeError::OutputIsTooSmall)
+            ),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn zero_pixel_images() {
+    for &(width, height) in &[(0, 0), (0, 7), (7, 0)] {
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            assert_eq!(qoi.decoded_size(), 0);
+
+            let encoded = qoi.encode_alloc(&[]).unwrap();
+            assert_eq!(encoded.len(), 14 + 8);
+            assert_eq!(qoi.encoded_size(&[]).unwrap(), encoded.len());
+
+            let mut output = [0; 21];
+            assert!(matches!(
+                qoi.encode(&[], &mut output),
+                Err(EncodeError::OutputIsTooSmall { .. })
+            ));
+
+            let decoded = Qoi::decode(&encoded, &mut []).unwrap();
+            assert_eq!((decoded.width, decoded.height), (width, height));
+            assert_eq!(
+                Qoi::decode_counted(&encoded, &mut []).unwrap().1,
+                encoded.len()
+            );
+            assert!(matches!(
+                Qoi::decode_counted(&encoded[..21], &mut []),
+                Err(DecodeError::NotEnoughData { needed: 1, .. })
+            ));
+
+            let mut encoder = Encoder::new(qoi);
+            let mut pixels = &[][..];
+            let mut output = [0; 8];
+            let (size, result) = encoder.encode_step(&mut pixels, &mut output).unwrap();
+            assert_eq!((size, result), (8, StepResult::OutputFull));
+            let (size, result) = encoder.encode_step(&mut pixels, &mut output).unwrap();
+            assert_eq!((size, result), (6, StepResult::OutputFull));
+            let (size, result) = encoder.encode_step(&mut pixels, &mut output).unwrap();
+            assert_eq!((size, result), (8, StepResult::Finished));
+        }
+    }
+}
+
+type EncodeFn = fn(&Qoi, &[u8], &mut [u8]) -> Result<usize, EncodeError>;
+
+/// Drops alpha channel of RGBA pixels if the image has no alpha channel.
+fn raw(qoi: &Qoi, rgba: &[u8]) -> Vec<u8> {
+    let channels = qoi.colors.channels();
+    rgba.chunks_exact(4)
+        .flat_map(|px| px[..channels].to_vec())
+        .collect()
+}
+
+fn channel(rgba: &[u8], c: usize) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|px| px[c]).collect()
+}
+
+/// Encoding entry points taking RGBA pixels converted into their input.
+fn entry_points() -> Vec<(&'static str, EncodeFn)> {
+    vec![
+        ("encode", |qoi, rgba, out| qoi.encode(&raw(qoi, rgba), out)),
+        ("encode_reference_compatible", |qoi, rgba, out| {
+            qoi.encode_reference_compatible(&raw(qoi, rgba), out)
+        }),
+        ("encode_optimized", |qoi, rgba, out| {
+            Ok(qoi.encode_optimized(&raw(qoi, rgba), out)?.1)
+        }),
+        ("encode_lossy", |qoi, rgba, out| {
+            qoi.encode_lossy(&raw(qoi, rgba), out, 90)
+        }),
+        ("encode_unpremultiplied", |qoi, rgba, out| {
+            qoi.encode_unpremultiplied(&raw(qoi, rgba), out)
+        }),
+        ("encode_with_state", |qoi, rgba, out| {
+            let pixels = raw(qoi, rgba);
+            let mut state = EncoderState::new();
+            let size = qoi.encode_with_state(&pixels, out, &mut state)?;
+            match state.is_finished() {
+                true => Ok(size),
+                false => Err(EncodeError::InputSizeMismatch {
+                    expected: qoi.width as usize * qoi.height as usize,
+                    got: state.pixels_encoded(),
+                }),
+            }
+        }),
+        ("encode_with_stats", |qoi, rgba, out| {
+            Ok(qoi.encode_with_stats(&raw(qoi, rgba), out)?.0)
+        }),
+        ("encode_with_layout", |qoi, rgba, out| {
+            qoi.encode_with_layout(rgba, PixelLayout::Rgba, out)
+        }),
+        ("encode_with_order", |qoi, rgba, out| {
+            qoi.encode_with_order(rgba, ChannelOrder::Rgba, out)
+        }),
+        ("encode_with_stride", |qoi, rgba, out| {
+            let stride = qoi.width as usize * qoi.colors.channels();
+            qoi.encode_with_stride(&raw(qoi, rgba), stride, out)
+        }),
+        ("encode_flipped", |qoi, rgba, out| {
+            qoi.encode_flipped(&raw(qoi, rgba), out)
+        }),
+        ("encode_region", |qoi, rgba, out| {
+            qoi.encode_region(&raw(qoi, rgba), 0, 0, qoi.width, qoi.height, out)
+        }),
+        ("encode_u32", |qoi, rgba, out| {
+            let pixels: Vec<u32> = rgba
+                .chunks_exact(4)
+                .map(|px| u32::from_be_bytes([px[0], px[1], px[2], px[3]]))
+                .collect();
+            qoi.encode_u32(&pixels, U32Layout::Rgba8888, out)
+        }),
+        ("encode_pixels", |qoi, rgba, out| {
+            let pixels: Vec<[u8; 4]> = rgba
+                .chunks_exact(4)
+                .map(|px| [px[0], px[1], px[2], px[3]])
+                .collect();
+            qoi.encode_pixels(&pixels, out)
+        }),
+        ("encode_luma", |qoi, rgba, out| {
+            qoi.encode_luma(&channel(rgba, 0), out)
+        }),
+        ("encode_planar", |qoi, rgba, out| {
+            let a = channel(rgba, 3);
+            qoi.encode_planar(
+                &channel(rgba, 0),
+                &channel(rgba, 1),
+                &channel(rgba, 2),
+                Some(&a),
+                out,
+            )
+        }),
+        ("encode_rgb_plus_alpha", |qoi, rgba, out| {
+            let rgb: Vec<u8> = rgba
+                .chunks_exact(4)
+                .flat_map(|px| px[..3].to_vec())
+                .collect();
+            qoi.encode_rgb_plus_alpha(&rgb, &channel(rgba, 3), out)
+        }),
+        ("encode_color_keyed", |qoi, rgba, out| {
+            qoi.encode_color_keyed(rgba, PixelLayout::Rgba, [1, 2, 3], false, out)
+        }),
+    ]
+}
+
+#[test]
+fn every_entry_point_reports_errors() {
+    let rgba = common::photo(23, 11, 4);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 23,
+            height: 11,
+            colors,
+        };
+
+        for (name, encode) in entry_points() {
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            assert!(encode(&qoi, &rgba, &mut output).is_ok(), "{}", name);
+
+            match encode(&qoi, &rgba[..rgba.len() - 4], &mut output) {
+                Err(err @ EncodeError::InputSizeMismatch { expected, got }) => {
+                    assert!(got < expected, "{}", name);
+                    let message = err.to_string();
+                    assert!(message.contains(&expected.to_string()), "{}", message);
+                    assert!(message.contains(&got.to_string()), "{}", message);
+                }
+                other => panic!("{}: {:?}", name, other),
+            }
+
+            // Growing output buffer to required size once is enough.
+            let mut output = vec![0; 20];
+            match encode(&qoi, &rgba, &mut output) {
+                Err(err @ EncodeError::OutputIsTooSmall { required }) => {
+                    assert!(err.to_string().contains(&required.to_string()));
+                    let mut output = vec![0; required];
+                    assert!(encode(&qoi, &rgba, &mut output).is_ok(), "{}", name);
+                }
+                other => panic!("{}: {:?}", name, other),
+            }
+        }
+
+        assert_eq!(
+            qoi.encode(&raw(&qoi, &rgba[4..]), &mut []),
+            Err(EncodeError::InputSizeMismatch {
+                expected: qoi.decoded_size(),
+                got: qoi.decoded_size() - colors.channels()
+            })
+        );
+    }
+
+    let huge = Qoi {
+        width: u32::MAX,
+        height: u32::MAX,
+        colors: Colors::Rgba,
+    };
+    for (name, encode) in entry_points() {
+        // Parts of the image encoded with state do not need to address whole image.
+        if name == "encode_with_state" {
+            continue;
+        }
+        assert_eq!(
+            encode(&huge, &[], &mut []),
+            Err(EncodeError::TooManyPixels),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn needed_bytes_hint_is_lower_bound() {
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+        let mut output = vec![0; qoi.decoded_size()];
+        let size = fixture.len();
+
+        // Start offset of the chunk containing each byte.
+        let mut starts = vec![0; 14];
+        let mut chunks = ChunkIter::new(fixture).unwrap();
+        for chunk in &mut chunks {
+            let offset = chunk.unwrap().0;
+            starts.resize(offset, *starts.last().unwrap());
+            starts.push(offset);
+        }
+        starts.resize(chunks.offset(), *starts.last().unwrap());
+        starts.resize(size, chunks.offset());
+
+        let lengths = (0..size.min(2048)).chain((2048..size).step_by(7));
+        for len in lengths {
+            let err = Qoi::decode_counted(&fixture[..len], &mut output).unwrap_err();
+            let (offset, needed) = match err {
+                DecodeError::NotEnoughData { offset, needed, .. } => (offset, needed),
+                other => panic!("{} truncated at {}: {:?}", name, len, other),
+            };
+            assert!(needed > 0, "{} truncated at {}", name, len);
+            assert!(len + needed <= size, "{} truncated at {}", name, len);
+
+            if len < 14 {
+                assert_eq!((offset, needed), (0, 14 - len), "{}", name);
+            } else if starts[len] == starts[len - 1] {
+                // Incomplete chunk or end marker is reported with its whole size.
+                let start = starts[len];
+                let end = starts[len..]
+                    .iter()
+                    .find(|&&s| s != start)
+                    .map_or(size, |&s| s);
+                assert_eq!(offset, start, "{} truncated at {}", name, len);
+                assert!(len + needed >= end, "{} truncated at {}", name, len);
+            }
+        }
+
+        // Fetching hinted number of bytes converges to the whole image.
+        let mut len = 0;
+        while let Err(err) = Qoi::decode_counted(&fixture[..len], &mut output) {
+            match err {
+                DecodeError::NotEnoughData { needed, .. } => len += needed,
+                other => panic!("{}: {:?}", name, other),
+            }
+        }
+        assert_eq!(len, size, "{}", name);
+    }
+}
@@ -0,0 +1,409 @@
+//! Chunk parsing and writing compared with encoded bytes, encoder statistics, stream analysis, opcode maps and the decoder verifying end marker.
+
+mod common;
+
+use common::{random_runs, Rng, FIXTURES};
+use rapid_qoi::{
+    chunk::{ChunkIter, Op, OpKind},
+    Colors, DecodeError, EncodeStats, Qoi,
+};
+
+/// Applies chunks yielded by `ChunkIter` to pixels as specification describes.
+fn decode_chunks(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let chunks = ChunkIter::new(bytes)?;
+    let qoi = chunks.qoi();
+    let channels = qoi.colors.channels();
+
+    let mut index = [[0u8; 4]; 64];
+    let mut px = [0, 0, 0, 255u8];
+    let mut pixels = Vec::new();
+
+    for chunk in chunks {
+        let (_, op) = chunk?;
+        let add = |c: u8, d: i8| c.wrapping_add(d as u8);
+        match op {
+            Op::Rgb { r, g, b } => px = [r, g, b, px[3]],
+            Op::Rgba { r, g, b, a } => px = [r, g, b, a],
+            Op::Index(i) => px = index[i as usize],
+            Op::Diff { dr, dg, db } => px = [add(px[0], dr), add(px[1], dg), add(px[2], db), px[3]],
+            Op::Luma { dg, dr_dg, db_dg } => {
+                px = [
+                    add(px[0], dg.wrapping_add(dr_dg)),
+                    add(px[1], dg),
+                    add(px[2], dg.wrapping_add(db_dg)),
+                    px[3],
+                ]
+            }
+            Op::Run(_) => {}
+        }
+        let [r, g, b, a] = px.map(|c| c as usize);
+        index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = px;
+
+        for _ in 0..op.pixels() {
+            pixels.extend_from_slice(&px[..channels]);
+        }
+    }
+
+    pixels.truncate(qoi.decoded_size());
+    Ok(pixels)
+}
+
+#[test]
+fn chunks_write_as_parsed() {
+    for &(name, bytes) in FIXTURES {
+        let mut output = [0; 5];
+        let mut end = 14;
+        for chunk in ChunkIter::new(bytes).unwrap() {
+            let (offset, op) = chunk.unwrap();
+            assert_eq!(offset, end, "{}", name);
+
+            let size = op.write(&mut output);
+            assert_eq!(size, op.size(), "{} at {}", name, offset);
+            assert_eq!(
+                output[..size],
+                bytes[offset..offset + size],
+                "{} at {}",
+                name,
+                offset
+            );
+            assert_eq!(Op::parse(&output[..size]), Ok((op, size)));
+            end += size;
+        }
+        assert_eq!(bytes.len(), end + 8, "{}", name);
+    }
+}
+
+#[test]
+fn all_chunks_write_as_parsed() {
+    let mut output = [0; 5];
+    for b1 in 0..=0xfd_u8 {
+        for b2 in 0..=0xff_u8 {
+            let bytes = [b1, b2, 0, 0, 0];
+            let (op, size) = Op::parse(&bytes).unwrap();
+            assert_eq!(op.write(&mut output), size);
+            assert_eq!(output[..size], bytes[..size], "{:?}", op);
+        }
+    }
+}
+
+#[test]
+fn out_of_range_operands_are_truncated() {
+    let mut output = [0; 2];
+    let ops = [
+        (Op::Index(0xff), Op::Index(0x3f)),
+        (
+            Op::Diff {
+                dr: i8::MAX,
+                dg: i8::MIN,
+                db: -3,
+            },
+            Op::Diff {
+                dr: -1,
+                dg: 0,
+                db: 1,
+            },
+        ),
+        (
+            Op::Luma {
+                dg: i8::MAX,
+                dr_dg: i8::MIN,
+                db_dg: 8,
+            },
+            Op::Luma {
+                dg: -1,
+                dr_dg: 0,
+                db_dg: -8,
+            },
+        ),
+    ];
+    for (op, truncated) in ops {
+        let size = op.write(&mut output);
+        assert_eq!(
+            Op::parse(&output[..size]),
+            Ok((truncated, size)),
+            "{:?}",
+            op
+        );
+    }
+}
+
+#[test]
+fn chunks_decode_as_decoder() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut output = vec![0; qoi.decoded_size()];
+        Qoi::decode(bytes, &mut output).unwrap();
+        assert!(decode_chunks(bytes).unwrap() == output, "{}", name);
+    }
+}
+
+#[test]
+fn corrupted_chunks_fail_as_decoder() {
+    let mut rng = Rng(66);
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut output = vec![0; qoi.decoded_size()];
+
+        for _ in 0..16 {
+            let mut corrupted = bytes.to_vec();
+            match rng.next() % 2 {
+                0 => corrupted.truncate(14 + rng.next() as usize % (bytes.len() - 14)),
+                _ => {
+                    let at = 14 + rng.next() as usize % (bytes.len() - 14);
+                    corrupted[at] = rng.next() as u8;
+                }
+            }
+
+            let expected = Qoi::decode_counted(&corrupted, &mut output).map(|_| output.clone());
+            assert_eq!(
+                decode_chunks(&corrupted).err(),
+                expected.clone().err(),
+                "{}",
+                name
+            );
+            if let Ok(expected) = expected {
+                assert!(decode_chunks(&corrupted).unwrap() == expected, "{}", name);
+            }
+        }
+    }
+}
+
+/// Counts chunks yielded by `ChunkIter`.\
+/// Returns statistics of chunks along with lengths of runs.
+fn parse_stats(bytes: &[u8]) -> (EncodeStats, Vec<usize>) {
+    let mut stats = EncodeStats::default();
+    let mut runs = Vec::new();
+    for chunk in ChunkIter::new(bytes).unwrap() {
+        let (offset, op) = chunk.unwrap();
+        let (count, kind) = match op {
+            Op::Rgb { .. } => (&mut stats.op_rgb, 0),
+            Op::Rgba { .. } => (&mut stats.op_rgba, 1),
+            Op::Index(_) => (&mut stats.op_index, 2),
+            Op::Diff { .. } => (&mut stats.op_diff, 3),
+            Op::Luma { .. } => (&mut stats.op_luma, 4),
+            Op::Run(_) => (&mut stats.op_run, 5),
+        };
+        *count += 1;
+        stats.bytes_per_op[kind] += Op::parse(&bytes[offset..]).unwrap().1;
+        if let Op::Run(_) = op {
+            stats.run_pixels += op.pixels();
+            runs.push(op.pixels());
+        }
+    }
+    (stats, runs)
+}
+
+#[test]
+fn encode_stats_as_parsed_chunks() {
+    let mut rng = Rng(40);
+    let mut images: Vec<_> = FIXTURES
+        .iter()
+        .map(|&(name, bytes)| {
+            let qoi = Qoi::decode_header(bytes).unwrap();
+            let mut pixels = vec![0; qoi.decoded_size()];
+            Qoi::decode(bytes, &mut pixels).unwrap();
+            (name, qoi, pixels)
+        })
+        .collect();
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 41,
+            height: 23,
+            colors,
+        };
+        images.push((
+            "random runs",
+            qoi,
+            random_runs(41, 23, colors.channels(), 70, &mut rng),
+        ));
+    }
+
+    for (name, qoi, pixels) in images {
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        let (size, stats) = qoi.encode_with_stats(&pixels, &mut output).unwrap();
+        let bytes = &output[..size];
+        let mut plain = vec![0; qoi.encoded_size_limit()];
+        let plain_size = qoi.encode(&pixels, &mut plain).unwrap();
+        assert!(bytes == &plain[..plain_size], "{}", name);
+
+        assert_eq!(stats, parse_stats(bytes).0, "{}", name);
+        assert_eq!(
+            stats.bytes_per_op.iter().sum::<usize>(),
+            size - 14 - 8,
+            "{}",
+            name
+        );
+    }
+}
+
+/// Fixtures along with images of random runs with four channels.
+fn encoded_images(rng: &mut Rng) -> Vec<(&'static str, Vec<u8>)> {
+    let mut images: Vec<_> = FIXTURES
+        .iter()
+        .map(|&(name, bytes)| (name, bytes.to_vec()))
+        .collect();
+    for (width, height, max) in [(41, 23, 70), (67, 5, 300), (1, 1, 1), (0, 0, 1)] {
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Rgba,
+        };
+        let pixels = random_runs(width, height, 4, max, rng);
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        let size = qoi.encode(&pixels, &mut output).unwrap();
+        output.truncate(size);
+        images.push(("random runs", output));
+    }
+    images
+}
+
+#[test]
+fn analyze_as_parsed_chunks() {
+    for (name, bytes) in encoded_images(&mut Rng(76)) {
+        let stats = Qoi::analyze(&bytes).unwrap();
+        let (chunks, runs) = parse_stats(&bytes);
+        assert_eq!(stats.chunks, chunks, "{}", name);
+        assert_eq!(stats.min_run, runs.iter().copied().min().unwrap_or(0));
+        assert_eq!(stats.max_run, runs.iter().copied().max().unwrap_or(0));
+        assert_eq!(stats.size, bytes.len(), "{}", name);
+        assert_eq!(
+            chunks.bytes_per_op.iter().sum::<usize>() + 14 + 8,
+            stats.size
+        );
+
+        let qoi = Qoi::decode_header(&bytes).unwrap();
+        let pixels = qoi.width as usize * qoi.height as usize;
+        assert_eq!((stats.qoi.width, stats.qoi.height), (qoi.width, qoi.height));
+        match runs.len() {
+            0 => assert_eq!(stats.mean_run(), 0.0),
+            n => assert_eq!(stats.mean_run(), chunks.run_pixels as f64 / n as f64),
+        }
+        match pixels {
+            0 => assert_eq!(stats.bytes_per_pixel(), 0.0),
+            n => assert_eq!(stats.bytes_per_pixel(), bytes.len() as f64 / n as f64),
+        }
+        assert_eq!(
+            stats.compression_ratio(),
+            qoi.decoded_size() as f64 / bytes.len() as f64
+        );
+
+        // Trailing bytes are not counted.
+        let mut trailed = bytes.clone();
+        trailed.extend_from_slice(b"trailer");
+        assert_eq!(Qoi::analyze(&trailed).unwrap().size, bytes.len());
+    }
+}
+
+#[test]
+fn analyze_fails_as_validate() {
+    for &(name, bytes) in FIXTURES {
+        for len in [0, 13, 14, bytes.len() / 2, bytes.len() - 8, bytes.len() - 1] {
+            assert_eq!(
+                Qoi::analyze(&bytes[..len]).err(),
+                Qoi::validate(&bytes[..len]).err(),
+                "{} truncated at {}",
+                name,
+                len
+            );
+            assert!(Qoi::analyze(&bytes[..len]).is_err());
+        }
+
+        let mut corrupted = bytes.to_vec();
+        corrupted[bytes.len() - 1] = 0;
+        assert_eq!(
+            Qoi::analyze(&corrupted).err(),
+            Some(DecodeError::InvalidPadding {
+                offset: bytes.len() - 8
+            }),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn maps_add_up_to_analyze_totals() {
+    for (name, bytes) in encoded_images(&mut Rng(81)) {
+        let stats = Qoi::analyze(&bytes).unwrap();
+        let pixels = stats.qoi.width as usize * stats.qoi.height as usize;
+
+        let mut kinds = vec![0xff; pixels + 3];
+        Qoi::opcode_map(&bytes, &mut kinds).unwrap();
+        assert_eq!(kinds[pixels..], [0xff; 3], "{}", name);
+        let count = |kind: OpKind| kinds[..pixels].iter().filter(|&&k| k == kind as u8).count();
+
+        let chunks = stats.chunks;
+        assert_eq!(count(OpKind::Rgb), chunks.op_rgb, "{}", name);
+        assert_eq!(count(OpKind::Rgba), chunks.op_rgba, "{}", name);
+        assert_eq!(count(OpKind::Index), chunks.op_index, "{}", name);
+        assert_eq!(count(OpKind::Diff), chunks.op_diff, "{}", name);
+        assert_eq!(count(OpKind::Luma), chunks.op_luma, "{}", name);
+        assert_eq!(count(OpKind::Run), chunks.op_run, "{}", name);
+        assert_eq!(
+            count(OpKind::Run) + count(OpKind::RunContinuation),
+            chunks.run_pixels,
+            "{}",
+            name
+        );
+
+        let mut costs = vec![-1.0; pixels];
+        Qoi::cost_map(&bytes, &mut costs).unwrap();
+        let total: f64 = costs.iter().map(|&c| c as f64).sum();
+        let bits = chunks.bytes_per_op.iter().sum::<usize>() * 8;
+        assert!(
+            (total - bits as f64).abs() < 1e-3 * bits.max(1) as f64,
+            "{}",
+            name
+        );
+
+        for (&kind, &cost) in kinds.iter().zip(&costs) {
+            let expected = match kind {
+                k if k == OpKind::Rgb as u8 => 32.0,
+                k if k == OpKind::Rgba as u8 => 40.0,
+                k if k == OpKind::Luma as u8 => 16.0,
+                k if k == OpKind::Index as u8 || k == OpKind::Diff as u8 => 8.0,
+                _ => {
+                    assert!(cost > 0.0 && cost <= 8.0, "{}", name);
+                    continue;
+                }
+            };
+            assert_eq!(cost, expected, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn maps_fail_as_analyze() {
+    for &(name, bytes) in FIXTURES {
+        let stats = Qoi::analyze(bytes).unwrap();
+        let pixels = stats.qoi.width as usize * stats.qoi.height as usize;
+        let mut kinds = vec![0; pixels];
+        let mut costs = vec![0.0; pixels];
+
+        assert_eq!(
+            Qoi::opcode_map(bytes, &mut kinds[1..]).err(),
+            Some(DecodeError::OutputIsTooSmall)
+        );
+        assert_eq!(
+            Qoi::cost_map(bytes, &mut costs[1..]).err(),
+            Some(DecodeError::OutputIsTooSmall)
+        );
+
+        for len in [0, 14, bytes.len() / 2, bytes.len() - 1] {
+            let expected = Qoi::analyze(&bytes[..len]).err();
+            assert!(expected.is_some());
+            assert_eq!(
+                Qoi::opcode_map(&bytes[..len], &mut kinds).err(),
+                expected,
+                "{}",
+                name
+            );
+            assert_eq!(
+                Qoi::cost_map(&bytes[..len], &mut costs).err(),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Fixtures shared by tests.
+
+#![allow(dead_code)]
+
+/// Images encoded with the reference encoder by `tests/fixtures/reference/generate.c`.
+pub const FIXTURES: &[(&str, &[u8])] = &[
+    (
+        "alpha_rgba",
+        include_bytes!("../fixtures/reference/alpha_rgba.qoi"),
+    ),
+    (
+        "column_rgb",
+        include_bytes!("../fixtures/reference/column_rgb.qoi"),
+    ),
+    (
+        "gradient_rgb",
+        include_bytes!("../fixtures/reference/gradient_rgb.qoi"),
+    ),
+    (
+        "gradient_rgba",
+        include_bytes!("../fixtures/reference/gradient_rgba.qoi"),
+    ),
+    (
+        "noise_rgb",
+        include_bytes!("../fixtures/reference/noise_rgb.qoi"),
+    ),
+    (
+        "noise_rgba",
+        include_bytes!("../fixtures/reference/noise_rgba.qoi"),
+    ),
+    (
+        "palette_rgb",
+        include_bytes!("../fixtures/reference/palette_rgb.qoi"),
+    ),
+    (
+        "palette_rgba",
+        include_bytes!("../fixtures/reference/palette_rgba.qoi"),
+    ),
+    (
+        "runs_rgb",
+        include_bytes!("../fixtures/reference/runs_rgb.qoi"),
+    ),
+    (
+        "runs_rgba",
+        include_bytes!("../fixtures/reference/runs_rgba.qoi"),
+    ),
+    (
+        "single_rgba",
+        include_bytes!("../fixtures/reference/single_rgba.qoi"),
+    ),
+    (
+        "walk_rgb",
+        include_bytes!("../fixtures/reference/walk_rgb.qoi"),
+    ),
+    (
+        "walk_rgba",
+        include_bytes!("../fixtures/reference/walk_rgba.qoi"),
+    ),
+];
+
+/// Xorshift generator for reproducible random test data.
+pub struct Rng(pub u32);
+
+impl Rng {
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
+
+/// Returns pixels with distinct neighbours and all channels varying.
+pub fn gradient(width: u32, height: u32, channels: usize) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = ((i % width) as u8, (i / width) as u8);
+            [x, y, x ^ y, 255 - x][..channels].to_vec()
+        })
+        .collect()
+}
+
+/// Returns pixels with runs from single pixel up to several rows long.
+pub fn runs(width: u32, height: u32, channels: usize) -> Vec<u8> {
+    let mut pixels = Vec::new();
+    let mut run = 1;
+    while pixels.len() < (width * height) as usize * channels {
+        let px = [run as u8, 0, !run as u8, 255][..channels].to_vec();
+        for _ in 0..run.min((width * height) as usize - pixels.len() / channels) {
+            pixels.extend_from_slice(&px);
+        }
+        run = run * 3 / 2 + 1;
+    }
+    pixels
+}
+
+/// Smooth gradients with mild noise, like a photo.
+pub fn photo(width: u32, height: u32, channels: usize) -> Vec<u8> {
+    let mut rng = Rng(0x2545_f491);
+    let mut pixels = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let r = (x * 255 / width) as u8;
+            let g = (y * 255 / height) as u8;
+            let b = ((x + y) * 127 / (width + height)) as u8;
+            let a = if (x / 16 + y / 16) % 5 == 0 { 128 } else { 255 };
+            let px = [
+                r.wrapping_add((rng.next() % 5) as u8),
+                g.wrapping_add((rng.next() % 5) as u8),
+                b.wrapping_add((rng.next() % 5) as u8),
+                a,
+            ];
+            pixels.extend_from_slice(&px[..channels]);
+        }
+    }
+    pixels
+}
+
+/// Returns pixels with random runs up to `max_run` pixels long
+/// separated by random pixels and small differences.
+pub fn random_runs(
+    width: u32,
+    height: u32,
+    channels: usize,
+    max_run: usize,
+    rng: &mut Rng,
+) -> Vec<u8> {
+    let len = width as usize * height as usize * channels;
+    let mut pixels = Vec::with_capacity(len);
+    let mut px = [0, 0, 0, 255u8];
+    while pixels.len() < len {
+        // Long runs span many rows, short ones keep index and difference chunks in use.
+        let run = match rng.next() % 4 {
+            0 => 1 + rng.next() as usize % max_run,
+            _ => 1 + rng.next() as usize % 8,
+        };
+        for _ in 0..run.min((len - pixels.len()) / channels) {
+            pixels.extend_from_slice(&px[..channels]);
+        }
+        let bits = rng.next();
+        px = match bits % 3 {
+            0 => bits.to_le_bytes(),
+            _ => [
+                px[0].wrapping_add(bits as u8 & 3),
+                px[1].wrapping_sub((bits >> 8) as u8 & 7),
+                px[2],
+                px[3],
+            ],
+        };
+    }
+    pixels
+}
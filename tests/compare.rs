@@ -0,0 +1,79 @@
+//! Comparison of pixels of encoded images.
+
+#![cfg(feature = "std")]
+
+use rapid_qoi::{Colors, DecodeError, Qoi};
+
+fn encode(colors: Colors, width: u32, pixels: &[u8]) -> Vec<u8> {
+    let qoi = Qoi {
+        width,
+        height: (pixels.len() / width as usize / colors.channels()) as u32,
+        colors,
+    };
+    qoi.encode_alloc(pixels).unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-8,
+        "{} is not {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn psnr_golden_values() {
+    #[rustfmt::skip]
+    let a = encode(Colors::Rgba, 2, &[
+        100, 100, 100, 255,  100, 100, 100, 255,
+        100, 100, 100, 255,  100, 100, 100, 255,
+    ]);
+    #[rustfmt::skip]
+    let b = encode(Colors::Rgba, 2, &[
+        110, 100, 100, 255,  100,  97, 100, 255,
+        100, 100, 100, 250,  100, 100, 100, 255,
+    ]);
+
+    let result = Qoi::psnr(&a, &b).unwrap();
+    assert_eq!(result.mse, [25.0, 2.25, 0.0, 6.25]);
+    assert_eq!(result.max_error, [10, 3, 0, 5]);
+
+    // 10 * log10(255^2 / mse)
+    assert_close(result.psnr[0], 34.151403522);
+    assert_close(result.psnr[1], 44.608978428);
+    assert_eq!(result.psnr[2], f64::INFINITY);
+    assert_close(result.psnr[3], 40.172003435);
+    assert_close(result.psnr_total(false), 38.548351090);
+    assert_close(result.psnr_total(true), 38.900955452);
+
+    assert_eq!(Qoi::psnr(&b, &a).unwrap(), result);
+}
+
+#[test]
+fn psnr_of_unit_error_and_equal_images() {
+    let a = encode(Colors::Srgb, 3, &[0, 10, 20, 30, 40, 50, 60, 70, 80]);
+    let b = encode(Colors::Srgb, 3, &[1, 11, 21, 31, 41, 51, 59, 69, 79]);
+
+    let result = Qoi::psnr(&a, &b).unwrap();
+    assert_eq!(result.mse, [1.0, 1.0, 1.0, 0.0]);
+    assert_eq!(result.max_error, [1, 1, 1, 0]);
+    for psnr in &result.psnr[..3] {
+        assert_close(*psnr, 48.130803609);
+    }
+    assert_close(result.psnr_total(false), 48.130803609);
+
+    // Images without alpha channel are compared as opaque.
+    let opaque = encode(
+        Colors::Rgba,
+        3,
+        &[0, 10, 20, 255, 30, 40, 50, 255, 60, 70, 80, 255],
+    );
+    let result = Qoi::psnr(&a, &opaque).unwrap();
+    assert_eq!(result.mse, [0.0; 4]);
+    assert_eq!(result.psnr, [f64::INFINITY; 4]);
+    assert_eq!(result.psnr_total(true), f64::INFINITY);
+
+    let other = encode(Colors::Srgb, 1, &[0, 10, 20, 30, 40, 50, 60, 70, 80]);
+    assert_eq!(Qoi::psnr(&a, &other), Err(DecodeError::DimensionsMismatch));
+}
@@ -0,0 +1,94 @@
+//! Decoding images stored one after another in single bytes slice.
+
+mod common;
+
+use common::{photo, runs, FIXTURES};
+use rapid_qoi::{Colors, DecodeError, Qoi};
+
+fn decode(bytes: &[u8]) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    (qoi, pixels)
+}
+
+/// Fixtures and generated images of different sizes placed back to back followed by footer.
+fn blob() -> (Vec<Vec<u8>>, Vec<u8>) {
+    let mut images: Vec<_> = FIXTURES.iter().map(|&(_, bytes)| bytes.to_vec()).collect();
+    for (width, height, colors) in [
+        (31, 7, Colors::Rgba),
+        (1, 1, Colors::Srgb),
+        (0, 0, Colors::Srgb),
+    ] {
+        let qoi = Qoi {
+            width,
+            height,
+            colors,
+        };
+        for pixels in [
+            photo(width, height, colors.channels()),
+            runs(width, height, colors.channels()),
+        ] {
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode(&pixels, &mut output).unwrap();
+            output.truncate(size);
+            images.push(output);
+        }
+    }
+
+    let mut blob = images.concat();
+    blob.extend_from_slice(b"custom footer");
+    (images, blob)
+}
+
+#[test]
+fn images_decode_sequentially() {
+    let (images, blob) = blob();
+
+    let mut rest = &blob[..];
+    for image in &images {
+        let (qoi, expected) = decode(image);
+        let mut output = vec![0; qoi.decoded_size()];
+        let (decoded, size) = Qoi::decode_counted(rest, &mut output).unwrap();
+        assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+        assert_eq!(size, image.len());
+        assert!(output == expected);
+
+        #[cfg(feature = "alloc")]
+        {
+            let (_, output, size) = Qoi::decode_alloc_counted(rest).unwrap();
+            assert_eq!(size, image.len());
+            assert!(output == expected);
+        }
+
+        rest = &rest[size..];
+    }
+    assert_eq!(rest, b"custom footer");
+}
+
+#[test]
+fn end_marker_is_verified() {
+    for &(name, bytes) in FIXTURES {
+        let (qoi, _) = decode(bytes);
+        let mut output = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut output).unwrap();
+
+        for at in size - 8..size {
+            let mut corrupted = bytes.to_vec();
+            corrupted[at] ^= 0x01;
+            assert!(
+                Qoi::decode_counted(&corrupted, &mut output).is_err(),
+                "{} corrupted at {}",
+                name,
+                at
+            );
+        }
+
+        // Bytes after the end marker are not read.
+        assert!(Qoi::decode_counted(&bytes[..size], &mut output).is_ok());
+        assert!(matches!(
+            Qoi::decode_counted(&bytes[..size - 1], &mut output),
+            Err(DecodeError::NotEnoughData { .. })
+        ));
+    }
+}
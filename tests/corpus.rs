@@ -0,0 +1,223 @@
+//! Decoding and encoding of synthetic corpus and random chunk streams
+//! compared with straightforward scalar implementation of QOI specification.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use core::convert::TryInto;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{Colors, Qoi};
+
+/// Decodes image pixel by pixel as specification describes.
+fn reference_decode(bytes: &[u8]) -> Vec<u8> {
+    let width = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let channels = bytes[12] as usize;
+
+    let mut index = [[0u8; 4]; 64];
+    let mut px = [0, 0, 0, 255u8];
+    let mut pixels = Vec::with_capacity(width * height * channels);
+    let mut pos = 14;
+    let mut run = 0;
+
+    for _ in 0..width * height {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let tag = bytes[pos];
+            pos += 1;
+            match tag {
+                0xfe => {
+                    px[..3].copy_from_slice(&bytes[pos..pos + 3]);
+                    pos += 3;
+                }
+                0xff => {
+                    px.copy_from_slice(&bytes[pos..pos + 4]);
+                    pos += 4;
+                }
+                _ => match tag >> 6 {
+                    0 => px = index[tag as usize],
+                    1 => {
+                        px[0] = px[0].wrapping_add((tag >> 4) & 3).wrapping_sub(2);
+                        px[1] = px[1].wrapping_add((tag >> 2) & 3).wrapping_sub(2);
+                        px[2] = px[2].wrapping_add(tag & 3).wrapping_sub(2);
+                    }
+                    2 => {
+                        let dg = (tag & 0x3f).wrapping_sub(32);
+                        let next = bytes[pos];
+                        pos += 1;
+                        px[0] = px[0].wrapping_add(dg.wrapping_sub(8).wrapping_add(next >> 4));
+                        px[1] = px[1].wrapping_add(dg);
+                        px[2] = px[2].wrapping_add(dg.wrapping_sub(8).wrapping_add(next & 0xf));
+                    }
+                    _ => run = (tag & 0x3f) as usize,
+                },
+            }
+            let [r, g, b, a] = px.map(|c| c as usize);
+            index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = px;
+        }
+        pixels.extend_from_slice(&px[..channels]);
+    }
+    pixels
+}
+
+/// Returns images with runs of various lengths, flat areas and noise.
+fn corpus() -> Vec<(&'static str, Qoi, Vec<u8>)> {
+    let mut rng = Rng(94);
+    let mut images = Vec::new();
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let channels = colors.channels();
+        let mut image = |name: &'static str,
+                         width: u32,
+                         height: u32,
+                         f: &mut dyn FnMut(u32, u32) -> [u8; 4]| {
+            let pixels = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .flat_map(|(x, y)| f(x, y)[..channels].to_vec())
+                .collect();
+            images.push((
+                name,
+                Qoi {
+                    width,
+                    height,
+                    colors,
+                },
+                pixels,
+            ));
+        };
+
+        image("solid", 517, 33, &mut |_, _| [40, 80, 120, 255]);
+        image("bands", 640, 48, &mut |_, y| [y as u8 * 5, 0, 255, 255]);
+        image("stripes", 333, 21, &mut |x, _| [(x / 7) as u8, 1, 2, 255]);
+
+        let mut run = 0;
+        let mut px = [0; 4];
+        image("random_runs", 401, 97, &mut |_, _| {
+            if run == 0 {
+                run = 1 + rng.next() % 130;
+                px = [
+                    rng.next() as u8,
+                    rng.next() as u8 & 3,
+                    0,
+                    255 - (rng.next() % 3) as u8,
+                ];
+            }
+            run -= 1;
+            px
+        });
+        image("noise", 129, 65, &mut |_, _| rng.next().to_le_bytes());
+        image("column", 1, 300, &mut |_, y| [(y / 50) as u8, 0, 0, 255]);
+    }
+    images
+}
+
+/// Returns random chunk stream covering `width * height` pixels.\
+/// Index chunks refer only to slots filled by previous pixels other than runs,
+/// as the encoder never emits anything else.
+fn random_stream(rng: &mut Rng, width: u32, height: u32, channels: u8) -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[channels, 0]);
+
+    let mut index = [None; 64];
+    let mut px = [0, 0, 0, 255u8];
+    let mut left = width as usize * height as usize;
+    while left > 0 {
+        let ops = if channels == 4 { 6 } else { 5 };
+        let slot = rng.next() as usize & 0x3f;
+        let pixels = match rng.next() % ops {
+            0 if index[slot].is_some() => {
+                bytes.push(slot as u8);
+                px = index[slot].unwrap();
+                1
+            }
+            1 => {
+                let tag = rng.next() as u8 & 0x3f;
+                bytes.push(0x40 | tag);
+                px[0] = px[0].wrapping_add((tag >> 4) & 3).wrapping_sub(2);
+                px[1] = px[1].wrapping_add((tag >> 2) & 3).wrapping_sub(2);
+                px[2] = px[2].wrapping_add(tag & 3).wrapping_sub(2);
+                1
+            }
+            2 => {
+                let (dg, next) = (rng.next() as u8 & 0x3f, rng.next() as u8);
+                bytes.extend_from_slice(&[0x80 | dg, next]);
+                let dg = dg.wrapping_sub(32);
+                px[0] = px[0].wrapping_add(dg.wrapping_sub(8).wrapping_add(next >> 4));
+                px[1] = px[1].wrapping_add(dg);
+                px[2] = px[2].wrapping_add(dg.wrapping_sub(8).wrapping_add(next & 0xf));
+                1
+            }
+            3 => {
+                let run = (1 + rng.next() as usize % 62).min(left);
+                bytes.push(0xc0 | (run - 1) as u8);
+                left -= run;
+                continue;
+            }
+            5 => {
+                px = rng.next().to_le_bytes();
+                bytes.push(0xff);
+                bytes.extend_from_slice(&px);
+                1
+            }
+            _ => {
+                px[..3].copy_from_slice(&rng.next().to_le_bytes()[..3]);
+                bytes.push(0xfe);
+                bytes.extend_from_slice(&px[..3]);
+                1
+            }
+        };
+        let [r, g, b, a] = px.map(|c| c as usize);
+        index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = Some(px);
+        left -= pixels;
+    }
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    bytes
+}
+
+#[test]
+fn corpus_decodes_as_reference() {
+    for (name, qoi, pixels) in corpus() {
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+        assert!(reference_decode(&encoded) == pixels, "{}", name);
+
+        let (_, decoded) = Qoi::decode_alloc(&encoded).unwrap();
+        assert!(decoded == pixels, "{}", name);
+    }
+
+    for &(name, fixture) in FIXTURES {
+        let (_, decoded) = Qoi::decode_alloc(fixture).unwrap();
+        assert!(decoded == reference_decode(fixture), "{}", name);
+    }
+}
+
+#[test]
+fn random_streams_decode_as_reference() {
+    let mut rng = Rng(95);
+    for _ in 0..200 {
+        let (width, height) = (1 + rng.next() % 200, 1 + rng.next() % 8);
+        let channels = 3 + (rng.next() % 2) as u8;
+        let stream = random_stream(&mut rng, width, height, channels);
+
+        let (_, decoded) = Qoi::decode_alloc(&stream).unwrap();
+        assert!(decoded == reference_decode(&stream), "stream {:?}", stream);
+        assert!(Qoi::validate_strict(&stream).is_ok());
+    }
+}
+
+#[test]
+fn corpus_encodes_as_row_by_row() {
+    for (name, qoi, pixels) in corpus() {
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+        let row = qoi.width as usize * qoi.colors.channels();
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        let size = qoi
+            .encode_from_rows(pixels.chunks(row), &mut output)
+            .unwrap();
+        assert!(output[..size] == encoded[..], "{}", name);
+    }
+}
@@ -0,0 +1,99 @@
+//! Push-based decoder fed with image split at arbitrary bytes.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{DecodeError, DecodeStatus, Decoder, Qoi};
+
+/// Decodes `bytes` pushed in pieces ending at `splits`.
+fn decode_split(bytes: &[u8], splits: &[usize]) -> Result<(Qoi, Vec<u8>), DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let channels = qoi.colors.channels();
+    let size = qoi.decoded_size();
+    let mut output = vec![0; size];
+    let mut written = 0;
+
+    let mut decoder = Decoder::new();
+    let mut start = 0;
+    for end in splits.iter().copied().chain(Some(bytes.len())) {
+        let mut piece = &bytes[start..end];
+        start = end;
+        loop {
+            let progress = decoder.push(piece, &mut output[written..])?;
+            written += progress.pixels * channels;
+            piece = &piece[progress.consumed..];
+
+            match progress.status {
+                DecodeStatus::OutputIsFull => {
+                    assert!(progress.consumed > 0 || progress.pixels > 0)
+                }
+                _ => break,
+            }
+        }
+    }
+
+    let finished = decoder.finish()?;
+    assert_eq!((finished.width, finished.height), (qoi.width, qoi.height));
+    assert_eq!(written, size);
+    Ok((qoi, output))
+}
+
+#[test]
+fn split_at_every_byte() {
+    for &(name, fixture) in FIXTURES {
+        let (_, expected) = Qoi::decode_alloc(fixture).unwrap();
+        for split in 0..=fixture.len() {
+            let (_, decoded) = decode_split(fixture, &[split]).unwrap();
+            assert!(decoded == expected, "{} split at {}", name, split);
+        }
+    }
+}
+
+#[test]
+fn pushed_byte_by_byte() {
+    for &(name, fixture) in FIXTURES {
+        let (_, expected) = Qoi::decode_alloc(fixture).unwrap();
+        let splits = (1..fixture.len()).collect::<Vec<_>>();
+        let (_, decoded) = decode_split(fixture, &splits).unwrap();
+        assert!(decoded == expected, "{}", name);
+    }
+}
+
+#[test]
+fn split_at_random_bytes() {
+    let mut rng = Rng(49);
+    for &(name, fixture) in FIXTURES {
+        let (_, expected) = Qoi::decode_alloc(fixture).unwrap();
+        for _ in 0..32 {
+            let mut splits = Vec::new();
+            let mut end = 0;
+            loop {
+                end += rng.next() as usize % 23;
+                if end >= fixture.len() {
+                    break;
+                }
+                splits.push(end);
+            }
+            let (_, decoded) = decode_split(fixture, &splits).unwrap();
+            assert!(decoded == expected, "{} split at {:?}", name, splits);
+        }
+    }
+}
+
+#[test]
+fn truncated_image_is_not_finished() {
+    for &(name, fixture) in FIXTURES {
+        for len in [0, 13, 14, fixture.len() / 2, fixture.len() - 1] {
+            let err = decode_split(&fixture[..len], &[]).unwrap_err();
+            assert!(
+                matches!(err, DecodeError::NotEnoughData { .. }),
+                "{} truncated to {}: {:?}",
+                name,
+                len,
+                err
+            );
+        }
+    }
+}
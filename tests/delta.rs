@@ -0,0 +1,105 @@
+//! Encoding differences between images and applying them back.
+
+mod common;
+
+use common::{gradient, photo, Rng};
+use rapid_qoi::{Colors, DecodeError, EncodeError, Qoi};
+
+/// Returns copy of `base` with random pixels changed in all channels including alpha.
+fn modified(base: &[u8], channels: usize, rng: &mut Rng) -> Vec<u8> {
+    let mut new = base.to_vec();
+    for px in new.chunks_exact_mut(channels) {
+        if rng.next().is_multiple_of(4) {
+            for v in px {
+                *v = v.wrapping_add(rng.next() as u8);
+            }
+        }
+    }
+    new
+}
+
+fn encode_delta(qoi: &Qoi, base: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode_delta(base, new, &mut output).unwrap();
+    output.truncate(size);
+    output
+}
+
+#[test]
+fn delta_applies_to_new_image() {
+    let mut rng = Rng(83);
+    for colors in [Colors::Srgb, Colors::SrgbLinA, Colors::Rgba, Colors::Rgb] {
+        let channels = colors.channels();
+        for _ in 0..32 {
+            let (width, height) = (1 + rng.next() % 40, 1 + rng.next() % 40);
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let base = match rng.next() % 2 {
+                0 => photo(width, height, channels),
+                _ => gradient(width, height, channels),
+            };
+            let new = match rng.next() % 3 {
+                0 => (0..base.len()).map(|_| rng.next() as u8).collect(),
+                _ => modified(&base, channels, &mut rng),
+            };
+
+            let delta = encode_delta(&qoi, &base, &new);
+            let mut output = vec![0; new.len()];
+            let decoded = Qoi::apply_delta(&base, &delta, &mut output).unwrap();
+            assert_eq!((decoded.width, decoded.height), (width, height));
+            assert_eq!(decoded.colors.channels(), channels);
+            assert!(output == new, "{}x{} {:?}", width, height, colors);
+        }
+    }
+}
+
+#[test]
+fn unchanged_image_encodes_to_runs() {
+    let qoi = Qoi {
+        width: 64,
+        height: 64,
+        colors: Colors::Rgba,
+    };
+    let pixels = photo(64, 64, 4);
+    let delta = encode_delta(&qoi, &pixels, &pixels);
+    assert!(delta.len() < qoi.encoded_size(&pixels).unwrap() / 10);
+
+    let mut output = vec![0; pixels.len()];
+    Qoi::apply_delta(&pixels, &delta, &mut output).unwrap();
+    assert!(output == pixels);
+}
+
+#[test]
+fn dimensions_mismatch_fails() {
+    let qoi = Qoi {
+        width: 13,
+        height: 7,
+        colors: Colors::Rgba,
+    };
+    let base = gradient(13, 7, 4);
+    let new = photo(13, 7, 4);
+    let delta = encode_delta(&qoi, &base, &new);
+    let mut output = vec![0; new.len()];
+
+    assert_eq!(
+        Qoi::apply_delta(&base[..base.len() - 1], &delta, &mut output).err(),
+        Some(DecodeError::DimensionsMismatch)
+    );
+    let smaller = Qoi { width: 12, ..qoi };
+    let other = encode_delta(&smaller, &base, &new);
+    assert!(Qoi::apply_delta(&base[..smaller.decoded_size()], &other, &mut output).is_ok());
+
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    for (base, new) in [
+        (&base[..base.len() - 1], &new[..]),
+        (&base[..], &new[..new.len() - 1]),
+    ] {
+        assert!(matches!(
+            qoi.encode_delta(base, new, &mut output),
+            Err(EncodeError::InputSizeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,265 @@
+//! Decoding from `embedded_io` readers and encoding into `embedded_io` writers compared with slice API.
+
+#![cfg(feature = "embedded-io")]
+
+mod common;
+
+use core::fmt;
+
+use common::{photo, runs, FIXTURES};
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+use rapid_qoi::{Colors, DecodeError, EncodeError, IoDecodeError, IoEncodeError, Qoi};
+
+#[derive(Debug, PartialEq)]
+struct LinkError;
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("link is down")
+    }
+}
+
+impl core::error::Error for LinkError {}
+
+impl embedded_io::Error for LinkError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Reader that returns at most `chunk` bytes per call and fails after `data` is exhausted if `fail` is set.
+struct Link<'a> {
+    data: &'a [u8],
+    pos: usize,
+    chunk: usize,
+    fail: bool,
+}
+
+impl<'a> Link<'a> {
+    fn new(data: &'a [u8], chunk: usize) -> Self {
+        Link {
+            data,
+            pos: 0,
+            chunk,
+            fail: false,
+        }
+    }
+}
+
+impl ErrorType for Link<'_> {
+    type Error = LinkError;
+}
+
+impl Read for Link<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, LinkError> {
+        let rest = &self.data[self.pos..];
+        if rest.is_empty() && self.fail {
+            return Err(LinkError);
+        }
+        let len = rest.len().min(buf.len()).min(self.chunk);
+        buf[..len].copy_from_slice(&rest[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Writer that accepts at most `chunk` bytes per call and fails after `limit` bytes.
+struct Sink {
+    data: Vec<u8>,
+    chunk: usize,
+    limit: usize,
+}
+
+impl ErrorType for Sink {
+    type Error = LinkError;
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, LinkError> {
+        if self.data.len() >= self.limit {
+            return Err(LinkError);
+        }
+        let len = buf.len().min(self.chunk).min(self.limit - self.data.len());
+        self.data.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), LinkError> {
+        Ok(())
+    }
+}
+
+fn images() -> Vec<(Qoi, Vec<u8>)> {
+    let mut images = Vec::new();
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        for (width, height) in [(37, 23), (1, 1), (64, 3)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            images.push((qoi, photo(width, height, colors.channels())));
+            images.push((qoi, runs(width, height, colors.channels())));
+        }
+    }
+    images
+}
+
+fn encode(qoi: &Qoi, pixels: &[u8]) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode(pixels, &mut output).unwrap();
+    output.truncate(size);
+    output
+}
+
+#[test]
+fn decode_from_reader_as_from_slice() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(bytes, &mut expected).unwrap();
+
+        let mut data = bytes[..size].to_vec();
+        data.extend_from_slice(b"trailing");
+
+        for chunk in [1, 2, 3, 7, 13, 64, 1000] {
+            for scratch_len in [1, 5, 64, 4096] {
+                let mut link = Link::new(&data, chunk);
+                let mut scratch = vec![0; scratch_len];
+                let mut output = vec![0xcd; qoi.decoded_size() + 3];
+                let decoded =
+                    Qoi::decode_from_embedded_reader(&mut link, &mut scratch, &mut output).unwrap();
+
+                assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+                assert!(
+                    output[..expected.len()] == expected[..],
+                    "{} chunk {}",
+                    name,
+                    chunk
+                );
+                assert!(output[expected.len()..] == [0xcd; 3], "{}", name);
+                assert_eq!(link.pos, size, "{} chunk {}", name, chunk);
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_into_writer_as_into_slice() {
+    for (qoi, pixels) in images() {
+        let expected = encode(&qoi, &pixels);
+
+        for chunk in [1, 3, 7, 64, 1000] {
+            for scratch_len in [27, 31, 100, 4096] {
+                let mut sink = Sink {
+                    data: Vec::new(),
+                    chunk,
+                    limit: usize::MAX,
+                };
+                let mut scratch = vec![0; scratch_len];
+                let size = qoi
+                    .encode_to_embedded_writer(&pixels, &mut scratch, &mut sink)
+                    .unwrap();
+
+                assert_eq!(size, expected.len());
+                assert!(
+                    sink.data == expected,
+                    "{}x{} chunk {} scratch {}",
+                    qoi.width,
+                    qoi.height,
+                    chunk,
+                    scratch_len
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn reader_errors() {
+    let (qoi, pixels) = images().swap_remove(0);
+    let bytes = encode(&qoi, &pixels);
+    let mut output = vec![0; pixels.len()];
+    let mut scratch = [0; 16];
+
+    for len in 0..bytes.len() {
+        let mut link = Link::new(&bytes[..len], 5);
+        assert!(
+            matches!(
+                Qoi::decode_from_embedded_reader(&mut link, &mut scratch, &mut output),
+                Err(IoDecodeError::Decode(DecodeError::NotEnoughData { .. }))
+            ),
+            "truncated at {}",
+            len
+        );
+
+        link = Link::new(&bytes[..len], 5);
+        link.fail = true;
+        assert_eq!(
+            Qoi::decode_from_embedded_reader(&mut link, &mut scratch, &mut output).err(),
+            Some(IoDecodeError::Io(LinkError)),
+            "failed at {}",
+            len
+        );
+    }
+
+    let mut link = Link::new(&bytes, 5);
+    assert_eq!(
+        Qoi::decode_from_embedded_reader(&mut link, &mut scratch, &mut output[1..]).err(),
+        Some(IoDecodeError::Decode(DecodeError::OutputIsTooSmall))
+    );
+}
+
+#[test]
+fn writer_errors() {
+    let (qoi, pixels) = images().swap_remove(0);
+    let size = encode(&qoi, &pixels).len();
+    let mut scratch = [0; 64];
+
+    for limit in (0..size).step_by(7) {
+        let mut sink = Sink {
+            data: Vec::new(),
+            chunk: 5,
+            limit,
+        };
+        assert_eq!(
+            qoi.encode_to_embedded_writer(&pixels, &mut scratch, &mut sink)
+                .err(),
+            Some(IoEncodeError::Io(LinkError)),
+            "limit {}",
+            limit
+        );
+    }
+
+    let mut sink = Sink {
+        data: Vec::new(),
+        chunk: 5,
+        limit: usize::MAX,
+    };
+    assert!(matches!(
+        qoi.encode_to_embedded_writer(&pixels[1..], &mut scratch, &mut sink),
+        Err(IoEncodeError::Encode(EncodeError::InputSizeMismatch { .. }))
+    ));
+    assert!(matches!(
+        qoi.encode_to_embedded_writer(&pixels, &mut scratch[..20], &mut sink),
+        Err(IoEncodeError::Encode(EncodeError::OutputIsTooSmall { .. }))
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn transport_error_is_source() {
+    use std::error::Error;
+
+    let err = IoDecodeError::Io(LinkError);
+    assert_eq!(err.to_string(), "Failed to read encoded image");
+    assert_eq!(err.source().unwrap().to_string(), "link is down");
+
+    let err = IoEncodeError::Io(LinkError);
+    assert_eq!(err.to_string(), "Failed to write encoded image");
+    assert_eq!(err.source().unwrap().to_string(), "link is down");
+
+    let err = IoDecodeError::<LinkError>::Decode(DecodeError::InvalidMagic);
+    assert_eq!(err.to_string(), DecodeError::InvalidMagic.to_string());
+    assert!(err.source().is_none());
+}
@@ -0,0 +1,83 @@
+//! Push-based encoder fed with pixels in arbitrary chunks.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{EncodeError, Encoder, Qoi};
+
+/// Encodes `pixels` pushed in chunks of random number of pixels.
+fn encode_chunked(qoi: Qoi, pixels: &[u8], rng: &mut Rng, max_chunk: usize) -> Vec<u8> {
+    let channels = qoi.colors.channels();
+    let mut encoder = Encoder::new(qoi);
+    let mut encoded = Vec::new();
+
+    let mut rest = pixels;
+    while !rest.is_empty() {
+        let count = (rng.next() as usize % (max_chunk + 1)).min(rest.len() / channels);
+        let (chunk, tail) = rest.split_at(count * channels);
+        rest = tail;
+
+        let mut output = vec![0; 32 + count * (channels + 1)];
+        let size = encoder.push_pixels(chunk, &mut output).unwrap();
+        encoded.extend_from_slice(&output[..size]);
+    }
+
+    let mut output = [0; 32];
+    let size = encoder.finish(&mut output).unwrap();
+    encoded.extend_from_slice(&output[..size]);
+    assert!(encoder.is_finished());
+    encoded
+}
+
+#[test]
+fn random_chunks_match_encode() {
+    let mut rng = Rng(50);
+    for &(name, fixture) in FIXTURES {
+        let (qoi, pixels) = Qoi::decode_alloc(fixture).unwrap();
+        let expected = qoi.encode_alloc(&pixels).unwrap();
+        for max_chunk in [1, 3, 64, 1000] {
+            for _ in 0..8 {
+                let encoded = encode_chunked(qoi, &pixels, &mut rng, max_chunk);
+                assert!(encoded == expected, "{} chunks up to {}", name, max_chunk);
+            }
+        }
+    }
+}
+
+#[test]
+fn too_many_pixels_are_rejected() {
+    let (qoi, mut pixels) = Qoi::decode_alloc(FIXTURES[0].1).unwrap();
+    let channels = qoi.colors.channels();
+    pixels.extend_from_slice(&[1, 2, 3, 4][..channels]);
+
+    let mut encoder = Encoder::new(qoi);
+    let mut output = vec![0; qoi.encoded_size_limit() + 16];
+    assert!(matches!(
+        encoder.push_pixels(&pixels, &mut output),
+        Err(EncodeError::InputSizeMismatch { .. })
+    ));
+
+    let mut encoder = Encoder::new(qoi);
+    let (head, tail) = pixels.split_at(pixels.len() - channels);
+    encoder.push_pixels(head, &mut output).unwrap();
+    assert!(matches!(
+        encoder.push_pixels(tail, &mut output),
+        Err(EncodeError::InputSizeMismatch { .. })
+    ));
+}
+
+#[test]
+fn finish_before_last_pixel_fails() {
+    let (qoi, pixels) = Qoi::decode_alloc(FIXTURES[0].1).unwrap();
+    let mut encoder = Encoder::new(qoi);
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    encoder
+        .push_pixels(&pixels[qoi.colors.channels()..], &mut output)
+        .unwrap();
+    assert!(matches!(
+        encoder.finish(&mut output),
+        Err(EncodeError::InputSizeMismatch { .. })
+    ));
+}
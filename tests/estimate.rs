@@ -0,0 +1,120 @@
+//! Encoded size computation and estimates compared with actual encoded size.
+
+mod common;
+
+use common::{gradient, photo, random_runs, Rng, FIXTURES};
+use rapid_qoi::{Colors, EncodeError, Qoi};
+
+/// Fixtures of single pixel rows with varying content, documented to be estimated less accurately.
+const VARYING: &[&str] = &["column_rgb"];
+
+fn decode(bytes: &[u8]) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    (qoi, pixels)
+}
+
+fn assert_within_15_percent(qoi: &Qoi, pixels: &[u8], name: &str) {
+    let actual = qoi.encoded_size(pixels).unwrap();
+    let estimate = qoi.estimate_encoded_size(pixels, 16).unwrap();
+
+    assert!(
+        estimate.low <= estimate.size && estimate.size <= estimate.high,
+        "{} {:?}",
+        name,
+        estimate
+    );
+    let error = (estimate.size as f64 - actual as f64).abs() / actual as f64;
+    assert!(
+        error <= 0.15,
+        "{} estimated {} actual {}",
+        name,
+        estimate.size,
+        actual
+    );
+}
+
+#[test]
+fn fixtures_estimated_within_15_percent() {
+    for &(name, bytes) in FIXTURES {
+        if !VARYING.contains(&name) {
+            let (qoi, pixels) = decode(bytes);
+            assert_within_15_percent(&qoi, &pixels, name);
+        }
+    }
+}
+
+#[test]
+fn photos_estimated_within_15_percent() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        for (width, height) in [(640, 480), (97, 301)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let pixels = photo(width, height, colors.channels());
+            assert_within_15_percent(&qoi, &pixels, "photo");
+            let pixels = gradient(width, height, colors.channels());
+            assert_within_15_percent(&qoi, &pixels, "gradient");
+        }
+    }
+}
+
+#[test]
+fn all_rows_sampled_estimate_is_exact() {
+    for &(name, bytes) in FIXTURES {
+        let (qoi, pixels) = decode(bytes);
+        let actual = qoi.encoded_size(&pixels).unwrap();
+        for sample_rows in [qoi.height as usize, usize::MAX] {
+            let estimate = qoi.estimate_encoded_size(&pixels, sample_rows).unwrap();
+            assert!(estimate.is_exact(), "{}", name);
+            assert_eq!(
+                (estimate.size, estimate.low, estimate.high),
+                (actual, actual, actual),
+                "{}",
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn encoded_size_as_encode() {
+    let mut rng = Rng(36);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let channels = colors.channels();
+        for _ in 0..200 {
+            let (width, height) = (rng.next() % 48, rng.next() % 48);
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let pixels = match rng.next() % 4 {
+                0 => (0..qoi.decoded_size()).map(|_| rng.next() as u8).collect(),
+                1 => photo(width, height, channels),
+                2 => gradient(width, height, channels),
+                _ => random_runs(width, height, channels, 100, &mut rng),
+            };
+
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode(&pixels, &mut output).unwrap();
+            assert_eq!(
+                qoi.encoded_size(&pixels).unwrap(),
+                size,
+                "{}x{}",
+                width,
+                height
+            );
+
+            if !pixels.is_empty() {
+                assert!(matches!(
+                    qoi.encoded_size(&pixels[1..]),
+                    Err(EncodeError::InputSizeMismatch { .. })
+                ));
+            }
+        }
+    }
+}
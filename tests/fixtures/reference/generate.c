@@ -0,0 +1,109 @@
+// Generates fixtures encoded with the reference encoder.
+//
+// cc -O2 -I <path to qoi.h> generate.c -o generate && ./generate
+//
+// qoi.h is taken from https://github.com/phoboslab/qoi.
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define QOI_IMPLEMENTATION
+#include "qoi.h"
+
+static unsigned int seed = 0x9e3779b9u;
+
+static unsigned int next(void) {
+    seed ^= seed << 13;
+    seed ^= seed >> 17;
+    seed ^= seed << 5;
+    return seed;
+}
+
+typedef void (*generator)(unsigned char *px, int x, int y, int w, int h);
+
+// Smooth gradients hitting QOI_OP_DIFF and QOI_OP_LUMA.
+static void gradient(unsigned char *px, int x, int y, int w, int h) {
+    px[0] = x * 255 / w;
+    px[1] = y * 255 / h;
+    px[2] = (x + y) * 2;
+    px[3] = 255 - x / 4;
+}
+
+// Random pixels producing QOI_OP_RGB and QOI_OP_RGBA literals.
+static void noise(unsigned char *px, int x, int y, int w, int h) {
+    unsigned int v = next();
+    memcpy(px, &v, 4);
+}
+
+// Few colors repeated in random order producing QOI_OP_INDEX.
+static void palette(unsigned char *px, int x, int y, int w, int h) {
+    static const unsigned char colors[8][4] = {
+        {255, 0, 0, 255}, {0, 255, 0, 255}, {0, 0, 255, 128}, {17, 34, 51, 0},
+        {200, 200, 200, 255}, {0, 0, 0, 0}, {90, 60, 30, 255}, {1, 2, 3, 4},
+    };
+    memcpy(px, colors[next() % 8], 4);
+}
+
+// Long runs crossing 62 pixels, runs at the start and at the end of the image.
+static void runs(unsigned char *px, int x, int y, int w, int h) {
+    unsigned char v = (x / 75 + y / 3) % 4 == 0 ? 0 : (y * 40) & 0xff;
+    px[0] = v;
+    px[1] = v;
+    px[2] = y == h - 1 ? 7 : v;
+    px[3] = 255;
+}
+
+// Small changes of alpha channel alone.
+static void alpha(unsigned char *px, int x, int y, int w, int h) {
+    px[0] = 10;
+    px[1] = 20;
+    px[2] = 30 + (x & 1);
+    px[3] = (x * 9 + y) & 0xff;
+}
+
+// Pixels similar to the previous one with random small deltas.
+static void walk(unsigned char *px, int x, int y, int w, int h) {
+    static unsigned char prev[4] = {0, 0, 0, 255};
+    unsigned int v = next();
+    int range = (v >> 24) % 3 == 0 ? 64 : 5;
+    for (int c = 0; c < 3; c++) {
+        prev[c] += (int)((v >> (c * 8)) % range) - range / 2;
+    }
+    memcpy(px, prev, 4);
+}
+
+static void write(const char *name, generator gen, int w, int h, int channels) {
+    unsigned char *pixels = malloc(w * h * channels);
+    unsigned char px[4];
+    for (int y = 0; y < h; y++) {
+        for (int x = 0; x < w; x++) {
+            gen(px, x, y, w, h);
+            memcpy(pixels + (y * w + x) * channels, px, channels);
+        }
+    }
+
+    qoi_desc desc = {w, h, channels, channels == 4 ? QOI_LINEAR : QOI_SRGB};
+    if (!qoi_write(name, pixels, &desc)) {
+        fprintf(stderr, "failed to write %s\n", name);
+        exit(1);
+    }
+    free(pixels);
+}
+
+int main(void) {
+    write("gradient_rgb.qoi", gradient, 64, 64, 3);
+    write("gradient_rgba.qoi", gradient, 64, 64, 4);
+    write("noise_rgb.qoi", noise, 32, 32, 3);
+    write("noise_rgba.qoi", noise, 32, 32, 4);
+    write("palette_rgb.qoi", palette, 48, 48, 3);
+    write("palette_rgba.qoi", palette, 48, 48, 4);
+    write("runs_rgb.qoi", runs, 200, 20, 3);
+    write("runs_rgba.qoi", runs, 200, 20, 4);
+    write("alpha_rgba.qoi", alpha, 40, 30, 4);
+    write("walk_rgb.qoi", walk, 100, 60, 3);
+    write("walk_rgba.qoi", walk, 100, 60, 4);
+    write("single_rgba.qoi", noise, 1, 1, 4);
+    write("column_rgb.qoi", walk, 1, 257, 3);
+    return 0;
+}
@@ -0,0 +1,267 @@
+//! Images with attached ICC profile decoded as is and converted into sRGB.
+
+#![cfg(feature = "icc")]
+
+mod common;
+
+use common::{photo, FIXTURES};
+use rapid_qoi::{
+    ChannelOrder, Colors, DecodeError, DecodeOptions, EncodeOptions, PixelLayout, Qoi,
+};
+
+/// D50 adapted XYZ of sRGB primaries.
+const SRGB_PRIMARIES: [[f64; 3]; 3] = [
+    [0.4360747, 0.2225045, 0.0139322],
+    [0.3850649, 0.7168786, 0.0971045],
+    [0.1430804, 0.0606169, 0.7141733],
+];
+
+/// D50 adapted XYZ of Display P3 primaries.
+const P3_PRIMARIES: [[f64; 3]; 3] = [
+    [0.5151, 0.2412, -0.0011],
+    [0.2920, 0.6922, 0.0419],
+    [0.1571, 0.0666, 0.7841],
+];
+
+/// Tone response curve of the profile.
+#[derive(Clone, Copy)]
+enum Curve {
+    /// `curveType` with single gamma value.
+    Gamma(f64),
+    /// `parametricCurveType` of sRGB transfer function.
+    Srgb,
+}
+
+fn fixed(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+/// Builds RGB matrix/TRC profile with the same curve for every channel.
+fn profile(primaries: [[f64; 3]; 3], curve: Curve) -> Vec<u8> {
+    let mut tags: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+    for (signature, xyz) in [b"rXYZ", b"gXYZ", b"bXYZ"].iter().zip(primaries) {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in xyz {
+            tag.extend_from_slice(&fixed(value));
+        }
+        tags.push((*signature, tag));
+    }
+    let trc = match curve {
+        Curve::Gamma(gamma) => {
+            let mut tag = b"curv\0\0\0\0\0\0\0\x01".to_vec();
+            tag.extend_from_slice(&((gamma * 256.0).round() as u16).to_be_bytes());
+            tag
+        }
+        Curve::Srgb => {
+            let mut tag = b"para\0\0\0\0\0\x03\0\0".to_vec();
+            for value in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+                tag.extend_from_slice(&fixed(value));
+            }
+            tag
+        }
+    };
+    for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+        tags.push((signature, trc.clone()));
+    }
+
+    let mut bytes = vec![0; 128];
+    bytes[12..16].copy_from_slice(b"mntr");
+    bytes[16..24].copy_from_slice(b"RGB XYZ ");
+    bytes.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    let mut offset = bytes.len() + tags.len() * 12;
+    for (signature, tag) in &tags {
+        bytes.extend_from_slice(*signature);
+        bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+        bytes.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        offset += tag.len();
+    }
+    for (_, tag) in &tags {
+        bytes.extend_from_slice(tag);
+    }
+    let size = bytes.len() as u32;
+    bytes[..4].copy_from_slice(&size.to_be_bytes());
+    bytes
+}
+
+fn encode(qoi: &Qoi, pixels: &[u8], options: &EncodeOptions) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit() + 4096];
+    let size = qoi.encode_with(pixels, &mut output, options).unwrap();
+    output.truncate(size);
+    output
+}
+
+fn decode(bytes: &[u8], options: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let mut output = vec![0; qoi.decoded_size()];
+    Qoi::decode_with(bytes, &mut output, options)?;
+    Ok(output)
+}
+
+fn to_srgb(linear: f64) -> f64 {
+    match linear <= 0.0031308 {
+        true => linear * 12.92,
+        false => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+#[test]
+fn profile_round_trip() {
+    let icc = profile(P3_PRIMARIES, Curve::Srgb);
+    for colors in [Colors::Rgb, Colors::Rgba, Colors::Srgb, Colors::SrgbLinA] {
+        let qoi = Qoi {
+            width: 37,
+            height: 23,
+            colors,
+        };
+        let pixels = photo(37, 23, colors.channels());
+        let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+
+        // Header tells standard decoders to assume sRGB.
+        assert_eq!(bytes[13], 0);
+        assert_eq!(Qoi::icc_profile(&bytes), Some(&icc[..]));
+
+        let plain = qoi.encode_alloc(&pixels).unwrap();
+        assert!(bytes[..13] == plain[..13] && bytes[14..plain.len()] == plain[14..]);
+
+        let (_, size) = Qoi::decode_counted(&bytes, &mut vec![0; pixels.len()]).unwrap();
+        assert_eq!(size, plain.len());
+        assert!(decode(&bytes, &DecodeOptions::new()).unwrap() == pixels);
+        assert_eq!(Qoi::icc_profile(&plain), None);
+    }
+}
+
+#[test]
+fn images_without_profile_pass_through() {
+    let options = DecodeOptions::new().to_srgb(true);
+    for &(name, bytes) in FIXTURES {
+        assert_eq!(Qoi::icc_profile(bytes), None, "{}", name);
+        assert!(
+            decode(bytes, &options).unwrap() == decode(bytes, &DecodeOptions::new()).unwrap(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn srgb_profile_is_identity() {
+    // All 256 values of every channel.
+    let qoi = Qoi {
+        width: 256,
+        height: 3,
+        colors: Colors::SrgbLinA,
+    };
+    let mut pixels = Vec::new();
+    for c in 0..3 {
+        for v in 0..=255u8 {
+            let mut px = [0, 0, 0, v];
+            px[c] = v;
+            pixels.extend_from_slice(&px);
+        }
+    }
+
+    let icc = profile(SRGB_PRIMARIES, Curve::Srgb);
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+    let output = decode(&bytes, &DecodeOptions::new().to_srgb(true)).unwrap();
+    for (px, expected) in output.chunks_exact(4).zip(pixels.chunks_exact(4)) {
+        for c in 0..3 {
+            assert!(px[c].abs_diff(expected[c]) <= 1, "{:?} {:?}", px, expected);
+        }
+        assert_eq!(px[3], expected[3]);
+    }
+}
+
+#[test]
+fn linear_profile_is_converted() {
+    let qoi = Qoi {
+        width: 256,
+        height: 1,
+        colors: Colors::Srgb,
+    };
+    let pixels: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v]).collect();
+
+    let icc = profile(SRGB_PRIMARIES, Curve::Gamma(1.0));
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+    let output = decode(&bytes, &DecodeOptions::new().to_srgb(true)).unwrap();
+    for (v, px) in output.chunks_exact(3).enumerate() {
+        let expected = (to_srgb(v as f64 / 255.0) * 255.0).round() as u8;
+        for &c in px {
+            assert!(c.abs_diff(expected) <= 1, "{} {:?} {}", v, px, expected);
+        }
+    }
+}
+
+#[test]
+fn wide_gamut_profile_is_converted() {
+    let qoi = Qoi {
+        width: 4,
+        height: 1,
+        colors: Colors::Srgb,
+    };
+    let pixels = [128, 128, 128, 200, 60, 40, 40, 180, 60, 255, 255, 255];
+
+    let icc = profile(P3_PRIMARIES, Curve::Srgb);
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+    let output = decode(&bytes, &DecodeOptions::new().to_srgb(true)).unwrap();
+
+    // Grays stay gray while colors become more saturated in smaller gamut.
+    for i in [0, 3] {
+        for c in 0..3 {
+            assert!(
+                output[i * 3 + c].abs_diff(pixels[i * 3 + c]) <= 1,
+                "{:?}",
+                output
+            );
+        }
+    }
+    assert!(
+        output[3] > 200 && output[4] < 60 && output[5] < 40,
+        "{:?}",
+        output
+    );
+    assert!(
+        output[6] < 40 && output[7] > 180 && output[8] < 60,
+        "{:?}",
+        output
+    );
+
+    // Conversion follows channel order of the output.
+    let options = DecodeOptions::new()
+        .to_srgb(true)
+        .channel_order(ChannelOrder::Bgra)
+        .layout(PixelLayout::Rgba);
+    let mut bgra = [0; 16];
+    Qoi::decode_with(&bytes, &mut bgra, &options).unwrap();
+    for (px, rgb) in bgra.chunks_exact(4).zip(output.chunks_exact(3)) {
+        assert_eq!([px[2], px[1], px[0], px[3]], [rgb[0], rgb[1], rgb[2], 255]);
+    }
+}
+
+#[test]
+fn invalid_profile_and_options_fail() {
+    let qoi = Qoi {
+        width: 5,
+        height: 3,
+        colors: Colors::SrgbLinA,
+    };
+    let pixels = photo(5, 3, 4);
+
+    let mut icc = profile(SRGB_PRIMARIES, Curve::Srgb);
+    icc[16..20].copy_from_slice(b"CMYK");
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+    assert_eq!(
+        decode(&bytes, &DecodeOptions::new().to_srgb(true)),
+        Err(DecodeError::UnsupportedProfile)
+    );
+    assert!(decode(&bytes, &DecodeOptions::new()).unwrap() == pixels);
+
+    let icc = profile(SRGB_PRIMARIES, Curve::Srgb);
+    let bytes = encode(&qoi, &pixels, &EncodeOptions::new().icc_profile(&icc));
+    assert_eq!(
+        decode(
+            &bytes,
+            &DecodeOptions::new().to_srgb(true).premultiplied(true)
+        ),
+        Err(DecodeError::InvalidOptions)
+    );
+}
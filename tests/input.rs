@@ -0,0 +1,505 @@
+//! Encoding pixels in alternative input layouts compared with encoding equivalent raw pixels.
+
+mod common;
+
+use common::{gradient, photo, random_runs, Rng};
+use rapid_qoi::{ChannelOrder, Colors, EncodeError, PixelLayout, Qoi, U32Layout};
+
+/// RGBA pixels of generated images with varying alpha.
+fn images() -> Vec<(&'static str, u32, u32, Vec<u8>)> {
+    let mut rng = Rng(27);
+    let mut images = Vec::new();
+    for (width, height) in [(31, 19), (1, 7), (64, 1), (0, 3)] {
+        images.push(("photo", width, height, photo(width, height, 4)));
+        images.push(("gradient", width, height, gradient(width, height, 4)));
+        images.push((
+            "runs",
+            width,
+            height,
+            random_runs(width, height, 4, 100, &mut rng),
+        ));
+    }
+    images
+}
+
+fn encode(qoi: &Qoi, pixels: &[u8]) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode(pixels, &mut output).unwrap();
+    output.truncate(size);
+    output
+}
+
+/// Drops alpha channel of RGBA pixels.
+fn to_rgb(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|px| px[..3].to_vec())
+        .collect()
+}
+
+#[test]
+fn channel_order_as_swizzled_input() {
+    let orders = [
+        (ChannelOrder::Rgba, [0, 1, 2, 3]),
+        (ChannelOrder::Bgra, [2, 1, 0, 3]),
+        (ChannelOrder::Argb, [1, 2, 3, 0]),
+        (ChannelOrder::Abgr, [3, 2, 1, 0]),
+    ];
+
+    for (name, width, height, rgba) in images() {
+        for &(order, pos) in &orders {
+            let mut swizzled = vec![0; rgba.len()];
+            for (out, px) in swizzled.chunks_exact_mut(4).zip(rgba.chunks_exact(4)) {
+                for c in 0..4 {
+                    out[pos[c]] = px[c];
+                }
+            }
+
+            let qoi = Qoi {
+                width,
+                height,
+                colors: Colors::Rgba,
+            };
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi
+                .encode_with_order(&swizzled, order, &mut output)
+                .unwrap();
+            assert!(
+                output[..size] == encode(&qoi, &rgba)[..],
+                "{} {:?}",
+                name,
+                order
+            );
+
+            // Fourth byte of BGRX pixels is ignored for images without alpha channel.
+            let qoi = Qoi {
+                width,
+                height,
+                colors: Colors::Srgb,
+            };
+            for (i, px) in swizzled.chunks_exact_mut(4).enumerate() {
+                px[pos[3]] = (i * 31) as u8;
+            }
+            let size = qoi
+                .encode_with_order(&swizzled, order, &mut output)
+                .unwrap();
+            assert!(
+                output[..size] == encode(&qoi, &to_rgb(&rgba))[..],
+                "{} {:?}",
+                name,
+                order
+            );
+
+            if !swizzled.is_empty() {
+                assert!(matches!(
+                    qoi.encode_with_order(&swizzled[1..], order, &mut output),
+                    Err(EncodeError::InputSizeMismatch { .. })
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn typed_pixels_as_raw_pixels() {
+    for (name, width, height, rgba) in images() {
+        let rgb = to_rgb(&rgba);
+        let typed4: Vec<[u8; 4]> = rgba
+            .chunks_exact(4)
+            .map(|px| [px[0], px[1], px[2], px[3]])
+            .collect();
+        let typed3: Vec<[u8; 3]> = rgb
+            .chunks_exact(3)
+            .map(|px| [px[0], px[1], px[2]])
+            .collect();
+
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let expected = match colors.has_alpha() {
+                true => encode(&qoi, &rgba),
+                false => encode(&qoi, &rgb),
+            };
+            // Opaque alpha is set for three channel pixels.
+            let opaque: Vec<u8> = typed3
+                .iter()
+                .flat_map(|px| [px[0], px[1], px[2], 255])
+                .collect();
+            let expected3 = match colors.has_alpha() {
+                true => encode(&qoi, &opaque),
+                false => expected.clone(),
+            };
+
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode_pixels(&typed4, &mut output).unwrap();
+            assert!(output[..size] == expected[..], "{} {:?}", name, colors);
+            let size = qoi.encode_pixels(&typed3, &mut output).unwrap();
+            assert!(output[..size] == expected3[..], "{} {:?}", name, colors);
+
+            #[cfg(feature = "alloc")]
+            {
+                assert!(qoi.encode_pixels_alloc(&typed4).unwrap() == expected);
+                assert!(qoi.encode_pixels_alloc(&typed3).unwrap() == expected3);
+            }
+
+            if !typed4.is_empty() {
+                assert_eq!(
+                    qoi.encode_pixels(&typed4[1..], &mut output),
+                    Err(EncodeError::InputSizeMismatch {
+                        expected: typed4.len(),
+                        got: typed4.len() - 1
+                    })
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn packed_u32_as_unpacked_bytes() {
+    for (name, width, height, rgba) in images() {
+        let argb: Vec<u32> = rgba
+            .chunks_exact(4)
+            .map(|px| u32::from_be_bytes([px[3], px[0], px[1], px[2]]))
+            .collect();
+        let rgba32: Vec<u32> = rgba
+            .chunks_exact(4)
+            .map(|px| u32::from_be_bytes([px[0], px[1], px[2], px[3]]))
+            .collect();
+
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let expected = match colors.has_alpha() {
+                true => encode(&qoi, &rgba),
+                false => encode(&qoi, &to_rgb(&rgba)),
+            };
+
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            for (layout, pixels) in [(U32Layout::Argb8888, &argb), (U32Layout::Rgba8888, &rgba32)] {
+                let size = qoi.encode_u32(pixels, layout, &mut output).unwrap();
+                assert!(
+                    output[..size] == expected[..],
+                    "{} {:?} {:?}",
+                    name,
+                    colors,
+                    layout
+                );
+
+                if !pixels.is_empty() {
+                    assert!(matches!(
+                        qoi.encode_u32(&pixels[1..], layout, &mut output),
+                        Err(EncodeError::InputSizeMismatch { .. })
+                    ));
+                }
+            }
+        }
+    }
+
+    // Packing is defined on values, so it is the same on targets with any endianness.
+    let qoi = Qoi {
+        width: 1,
+        height: 1,
+        colors: Colors::Rgba,
+    };
+    let expected = encode(&qoi, &[0x11, 0x22, 0x33, 0x44]);
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi
+        .encode_u32(&[0x4411_2233], U32Layout::Argb8888, &mut output)
+        .unwrap();
+    assert!(output[..size] == expected[..]);
+    let size = qoi
+        .encode_u32(&[0x1122_3344], U32Layout::Rgba8888, &mut output)
+        .unwrap();
+    assert!(output[..size] == expected[..]);
+}
+
+#[test]
+fn layout_independent_of_channels() {
+    for (name, width, height, rgba) in images() {
+        let rgb = to_rgb(&rgba);
+        let opaque: Vec<u8> = rgb
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect();
+
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let mut output = vec![0; qoi.encoded_size_limit()];
+
+            for (layout, pixels) in [(PixelLayout::Rgb, &rgb), (PixelLayout::Rgba, &rgba)] {
+                let expected = match (colors.has_alpha(), layout) {
+                    (false, _) => encode(&qoi, &rgb),
+                    (true, PixelLayout::Rgb) => encode(&qoi, &opaque),
+                    (true, PixelLayout::Rgba) => encode(&qoi, &rgba),
+                };
+                let size = qoi.encode_with_layout(pixels, layout, &mut output).unwrap();
+                assert!(
+                    output[..size] == expected[..],
+                    "{} {:?} {:?}",
+                    name,
+                    colors,
+                    layout
+                );
+
+                // Input length is validated with stride of the layout.
+                if !pixels.is_empty() {
+                    assert_eq!(
+                        qoi.encode_with_layout(&pixels[1..], layout, &mut output),
+                        Err(EncodeError::InputSizeMismatch {
+                            expected: pixels.len(),
+                            got: pixels.len() - 1
+                        }),
+                        "{} {:?} {:?}",
+                        name,
+                        colors,
+                        layout
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn region_as_cropped_input() {
+    let mut rng = Rng(41);
+    for (name, width, height, rgba) in images() {
+        let rgb = to_rgb(&rgba);
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let channels = colors.channels();
+            let pixels = match colors.has_alpha() {
+                true => &rgba,
+                false => &rgb,
+            };
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+
+            for _ in 0..16 {
+                let x = rng.next() % (width + 1);
+                let y = rng.next() % (height + 1);
+                let w = rng.next() % (width - x + 1);
+                let h = rng.next() % (height - y + 1);
+
+                let mut crop = Vec::new();
+                for row in y..y + h {
+                    let start = (row * width + x) as usize * channels;
+                    crop.extend_from_slice(&pixels[start..start + w as usize * channels]);
+                }
+                let region = Qoi {
+                    width: w,
+                    height: h,
+                    colors,
+                };
+                let expected = encode(&region, &crop);
+
+                let mut output = vec![0; region.encoded_size_limit()];
+                let size = qoi.encode_region(pixels, x, y, w, h, &mut output).unwrap();
+                assert!(
+                    output[..size] == expected[..],
+                    "{} {}x{} at {},{}",
+                    name,
+                    w,
+                    h,
+                    x,
+                    y
+                );
+            }
+
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            for (x, y, w, h) in [
+                (1, 0, width, height),
+                (0, 1, width, height),
+                (width, 0, 1, 1),
+                (u32::MAX, 0, 2, 1),
+                (0, u32::MAX, 1, 2),
+            ] {
+                assert_eq!(
+                    qoi.encode_region(pixels, x, y, w, h, &mut output),
+                    Err(EncodeError::InvalidRegion),
+                    "{} {}x{} at {},{}",
+                    name,
+                    w,
+                    h,
+                    x,
+                    y
+                );
+            }
+            if !pixels.is_empty() {
+                assert!(matches!(
+                    qoi.encode_region(&pixels[1..], 0, 0, 1, 1, &mut output),
+                    Err(EncodeError::InputSizeMismatch { .. })
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn unpremultiplied_round_trip() {
+    // Every color value not exceeding alpha, in every color channel.
+    let mut premultiplied = Vec::new();
+    for a in 0..=255u32 {
+        for c in 0..=a {
+            premultiplied.extend_from_slice(&[c as u8, (a - c) as u8, (c / 2) as u8, a as u8]);
+        }
+    }
+    let qoi = Qoi {
+        width: (premultiplied.len() / 4) as u32,
+        height: 1,
+        colors: Colors::Rgba,
+    };
+
+    let straight: Vec<u8> = premultiplied
+        .chunks_exact(4)
+        .flat_map(|px| {
+            let a = px[3] as u32;
+            match a {
+                0 => [0; 4],
+                _ => {
+                    let c = |c: u8| ((c as u32 * 255 + a / 2) / a).min(255) as u8;
+                    [c(px[0]), c(px[1]), c(px[2]), px[3]]
+                }
+            }
+        })
+        .collect();
+
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi
+        .encode_unpremultiplied(&premultiplied, &mut output)
+        .unwrap();
+    assert!(output[..size] == encode(&qoi, &straight)[..]);
+
+    let mut decoded = vec![0; qoi.decoded_size()];
+    Qoi::decode(&output[..size], &mut decoded).unwrap();
+    for (px, orig) in decoded.chunks_exact(4).zip(premultiplied.chunks_exact(4)) {
+        let a = px[3] as u32;
+        assert_eq!(a, orig[3] as u32);
+        for c in 0..3 {
+            let again = ((px[c] as u32 * a + 127) / 255) as i32;
+            assert!(
+                (again - orig[c] as i32).abs() <= 1,
+                "{:?} decoded as {:?}",
+                orig,
+                px
+            );
+        }
+    }
+
+    // Images without alpha channel are encoded unchanged.
+    for (name, width, height, rgba) in images() {
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Srgb,
+        };
+        let rgb = to_rgb(&rgba);
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        let size = qoi.encode_unpremultiplied(&rgb, &mut output).unwrap();
+        assert!(output[..size] == encode(&qoi, &rgb)[..], "{}", name);
+    }
+}
+
+#[test]
+fn luma_as_expanded_input() {
+    for (name, width, height, rgba) in images() {
+        let luma: Vec<u8> = rgba.chunks_exact(4).map(|px| px[1]).collect();
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let expanded: Vec<u8> = luma
+                .iter()
+                .flat_map(|&v| [v, v, v, 255][..colors.channels()].to_vec())
+                .collect();
+
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode_luma(&luma, &mut output).unwrap();
+            assert!(
+                output[..size] == encode(&qoi, &expanded)[..],
+                "{} {:?}",
+                name,
+                colors
+            );
+
+            if !luma.is_empty() {
+                assert_eq!(
+                    qoi.encode_luma(&luma[1..], &mut output),
+                    Err(EncodeError::InputSizeMismatch {
+                        expected: luma.len(),
+                        got: luma.len() - 1
+                    })
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn color_keyed_as_pre_keyed_input() {
+    const KEY: [u8; 3] = [255, 0, 255];
+
+    for (name, width, height, mut rgba) in images() {
+        // Sprite sheet with key color in every third pixel and in runs.
+        for (i, px) in rgba.chunks_exact_mut(4).enumerate() {
+            if i % 3 == 0 || (i / 7) % 5 == 0 {
+                px[..3].copy_from_slice(&KEY);
+            }
+        }
+        let rgb = to_rgb(&rgba);
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Rgba,
+        };
+
+        for (layout, pixels) in [(PixelLayout::Rgb, &rgb), (PixelLayout::Rgba, &rgba)] {
+            for keep_rgb in [false, true] {
+                let keyed: Vec<u8> = pixels
+                    .chunks_exact(layout.channels())
+                    .flat_map(|px| match px[..3] == KEY {
+                        true if keep_rgb => [KEY[0], KEY[1], KEY[2], 0],
+                        true => [0; 4],
+                        false => [px[0], px[1], px[2], px.get(3).copied().unwrap_or(255)],
+                    })
+                    .collect();
+
+                let mut output = vec![0; qoi.encoded_size_limit()];
+                let size = qoi
+                    .encode_color_keyed(pixels, layout, KEY, keep_rgb, &mut output)
+                    .unwrap();
+                assert!(
+                    output[..size] == encode(&qoi, &keyed)[..],
+                    "{} {:?} {}",
+                    name,
+                    layout,
+                    keep_rgb
+                );
+
+                let mut decoded = vec![0; qoi.decoded_size()];
+                Qoi::decode(&output[..size], &mut decoded).unwrap();
+                for (px, orig) in decoded.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+                    match orig[..3] == KEY {
+                        true => assert_eq!(px[3], 0, "{}", name),
+                        false if layout == PixelLayout::Rgba => assert_eq!(px, orig, "{}", name),
+                        false => assert_eq!(px[..3], orig[..3], "{}", name),
+                    }
+                }
+            }
+        }
+    }
+}
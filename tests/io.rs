@@ -0,0 +1,394 @@
+//! Decoding from readers and encoding into writers.
+
+#![cfg(feature = "io")]
+
+mod common;
+
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+
+use common::{photo, runs, FIXTURES};
+use rapid_qoi::{
+    Colors, DecodeError, EncodeError, Qoi, QoiReader, QoiWriter, ReadDecodeError, WriteEncodeError,
+};
+
+/// Reader that returns one byte per call, interrupted before every other byte.
+struct OneByte<R> {
+    reader: R,
+    interrupt: bool,
+}
+
+impl<R> OneByte<R> {
+    fn new(reader: R) -> Self {
+        OneByte {
+            reader,
+            interrupt: false,
+        }
+    }
+}
+
+impl<R: Read> Read for OneByte<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(ErrorKind::Interrupted.into());
+        }
+        let len = buf.len().min(1);
+        self.reader.read(&mut buf[..len])
+    }
+}
+
+/// Reader that fails after `left` bytes.
+struct Failing<'a> {
+    bytes: &'a [u8],
+    left: usize,
+}
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::Error::other("reader failed"));
+        }
+        let n = buf.len().min(self.left).min(self.bytes.len());
+        buf[..n].copy_from_slice(&self.bytes[..n]);
+        self.bytes = &self.bytes[n..];
+        self.left -= n;
+        Ok(n)
+    }
+}
+
+/// Writer that fails after `left` bytes.
+struct FailingWriter {
+    left: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::Error::other("writer failed"));
+        }
+        let n = buf.len().min(self.left);
+        self.left -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns images larger than encoder buffers along with decoded fixtures.
+fn images() -> Vec<(Qoi, Vec<u8>)> {
+    let mut images = Vec::new();
+    for &(_, bytes) in FIXTURES {
+        images.push((Qoi::decode_header(bytes).unwrap(), decode(bytes)));
+    }
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 300,
+            height: 200,
+            colors,
+        };
+        images.push((qoi, photo(300, 200, colors.channels())));
+        images.push((qoi, runs(300, 200, colors.channels())));
+    }
+    images
+}
+
+fn decode(bytes: &[u8]) -> Vec<u8> {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    pixels
+}
+
+#[test]
+fn decode_from_reader_as_decode() {
+    for &(name, bytes) in FIXTURES {
+        let pixels = decode(bytes);
+        let mut output = vec![0; pixels.len()];
+
+        Qoi::decode_from_reader(bytes, &mut output).unwrap();
+        assert!(output == pixels, "{}", name);
+
+        output.fill(0);
+        Qoi::decode_from_reader(OneByte::new(bytes), &mut output).unwrap();
+        assert!(output == pixels, "{} one byte at a time", name);
+    }
+}
+
+#[test]
+fn decode_from_reader_leaves_following_bytes() {
+    for &(name, bytes) in FIXTURES {
+        let mut concatenated = bytes.to_vec();
+        concatenated.extend_from_slice(bytes);
+        concatenated.extend_from_slice(b"tail");
+        let pixels = decode(bytes);
+        let mut output = vec![0; pixels.len()];
+
+        for one_byte in [false, true] {
+            let mut cursor = Cursor::new(&concatenated);
+            for _ in 0..2 {
+                output.fill(0);
+                match one_byte {
+                    false => Qoi::decode_from_reader(&mut cursor, &mut output),
+                    true => Qoi::decode_from_reader(OneByte::new(&mut cursor), &mut output),
+                }
+                .unwrap();
+                assert!(output == pixels, "{}", name);
+            }
+
+            let mut tail = Vec::new();
+            cursor.read_to_end(&mut tail).unwrap();
+            assert_eq!(tail, b"tail", "{}", name);
+        }
+    }
+}
+
+#[test]
+fn decode_from_reader_errors() {
+    let (_, bytes) = FIXTURES[2];
+    let mut output = vec![0; decode(bytes).len()];
+
+    for len in [0, 5, 14, bytes.len() / 2, bytes.len() - 8] {
+        // Number of bytes needed is only a lower bound, so it is not compared.
+        let position = |err: Option<DecodeError>| match err {
+            Some(DecodeError::NotEnoughData {
+                offset,
+                pixels_decoded,
+                ..
+            }) => Some((offset, pixels_decoded)),
+            _ => None,
+        };
+        let expected = position(Qoi::decode_counted(&bytes[..len], &mut output).err());
+        match Qoi::decode_from_reader(OneByte::new(&bytes[..len]), &mut output) {
+            Err(ReadDecodeError::Decode(err)) => {
+                assert!(expected.is_some());
+                assert_eq!(position(Some(err)), expected, "{}", len);
+            }
+            other => panic!("truncated at {}: {:?}", len, other.err()),
+        }
+
+        let failing = Failing { bytes, left: len };
+        match Qoi::decode_from_reader(failing, &mut output) {
+            Err(ReadDecodeError::Io(err)) => assert_eq!(err.kind(), ErrorKind::Other),
+            other => panic!("failed at {}: {:?}", len, other.err()),
+        }
+    }
+
+    assert!(matches!(
+        Qoi::decode_from_reader(bytes, &mut output[1..]),
+        Err(ReadDecodeError::Decode(DecodeError::OutputIsTooSmall))
+    ));
+}
+
+#[test]
+fn encode_to_writer_as_encode() {
+    for (qoi, pixels) in images() {
+        let expected = qoi.encode_alloc(&pixels).unwrap();
+
+        let mut output = Vec::new();
+        let size = qoi.encode_to_writer(&pixels, &mut output).unwrap();
+        assert_eq!(size, output.len() as u64);
+        assert!(output == expected, "{}x{}", qoi.width, qoi.height);
+
+        // Pixels beyond the image are ignored as by slice encoder.
+        let mut longer = pixels.clone();
+        longer.extend_from_slice(&[1, 2, 3, 4]);
+        output.clear();
+        qoi.encode_to_writer(&longer, &mut output).unwrap();
+        assert!(output == expected, "{}x{}", qoi.width, qoi.height);
+    }
+}
+
+#[test]
+fn encode_to_writer_errors() {
+    for (qoi, pixels) in images() {
+        let size = qoi.encode_alloc(&pixels).unwrap().len();
+
+        match qoi.encode_to_writer(&pixels[..pixels.len() - 1], Vec::new()) {
+            Err(WriteEncodeError::Encode(EncodeError::InputSizeMismatch { expected, got })) => {
+                assert_eq!((expected, got), (pixels.len(), pixels.len() - 1))
+            }
+            other => panic!("short input: {:?}", other.err()),
+        }
+
+        for left in [0, 13, size / 2, size - 1] {
+            match qoi.encode_to_writer(&pixels, FailingWriter { left }) {
+                Err(WriteEncodeError::Io(err)) => assert_eq!(err.kind(), ErrorKind::Other),
+                other => panic!("failed at {}: {:?}", left, other.err()),
+            }
+        }
+        assert!(qoi
+            .encode_to_writer(&pixels, FailingWriter { left: size })
+            .is_ok());
+
+        let mut output = [0; 8];
+        match qoi.encode_to_writer(&pixels, &mut output[..]) {
+            Err(WriteEncodeError::Io(err)) => assert_eq!(err.kind(), ErrorKind::WriteZero),
+            other => panic!("full writer: {:?}", other.err()),
+        }
+    }
+}
+
+#[test]
+fn qoi_reader_reads_as_decode_alloc() {
+    for (qoi, pixels) in images() {
+        let bytes = qoi.encode_alloc(&pixels).unwrap();
+        let (_, expected) = Qoi::decode_alloc(&bytes).unwrap();
+
+        let mut reader = QoiReader::new(&bytes[..]).unwrap();
+        assert_eq!(
+            (reader.qoi().width, reader.qoi().height),
+            (qoi.width, qoi.height)
+        );
+        assert_eq!(reader.decoded_size(), expected.len());
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert!(output == expected, "{}x{}", qoi.width, qoi.height);
+
+        let mut reader = QoiReader::new(OneByte::new(&bytes[..])).unwrap();
+        output.clear();
+        reader.read_to_end(&mut output).unwrap();
+        assert!(
+            output == expected,
+            "{}x{} one byte at a time",
+            qoi.width,
+            qoi.height
+        );
+    }
+}
+
+#[test]
+fn qoi_reader_reads_shorter_than_pixel() {
+    for (qoi, pixels) in images() {
+        let bytes = qoi.encode_alloc(&pixels).unwrap();
+        for len in [1, 2, 3, 5, 7] {
+            let mut reader = QoiReader::new(&bytes[..]).unwrap();
+            let mut output = Vec::new();
+            let mut buf = [0; 7];
+            loop {
+                let n = reader.read(&mut buf[..len]).unwrap();
+                if n == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buf[..n]);
+            }
+            assert!(
+                output == pixels,
+                "{}x{} reads of {}",
+                qoi.width,
+                qoi.height,
+                len
+            );
+            assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        }
+    }
+}
+
+#[test]
+fn qoi_reader_leaves_following_bytes() {
+    let (qoi, pixels) = images().pop().unwrap();
+    let mut bytes = qoi.encode_alloc(&pixels).unwrap();
+    bytes.extend_from_slice(b"tail");
+
+    let mut reader = QoiReader::new(Cursor::new(&bytes)).unwrap();
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    assert!(output == pixels);
+
+    let mut tail = Vec::new();
+    reader.into_inner().read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, b"tail");
+}
+
+#[test]
+fn qoi_reader_errors() {
+    let (qoi, pixels) = images().pop().unwrap();
+    let bytes = qoi.encode_alloc(&pixels).unwrap();
+
+    assert!(matches!(
+        QoiReader::new(&bytes[..10]),
+        Err(ReadDecodeError::Decode(DecodeError::NotEnoughData { .. }))
+    ));
+    assert!(matches!(
+        QoiReader::new(&b"qoix\0\0\0\x01\0\0\0\x01\x04\0"[..]),
+        Err(ReadDecodeError::Decode(DecodeError::InvalidMagic))
+    ));
+
+    for len in [14, bytes.len() / 2, bytes.len() - 1] {
+        let mut reader = QoiReader::new(&bytes[..len]).unwrap();
+        let mut output = Vec::new();
+        let err = reader.read_to_end(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof, "{}", len);
+        assert!(output.len() <= pixels.len());
+        assert!(output[..] == pixels[..output.len()]);
+    }
+}
+
+#[test]
+fn qoi_writer_writes_as_encode() {
+    for (qoi, pixels) in images() {
+        let expected = qoi.encode_alloc(&pixels).unwrap();
+
+        // Writes of these sizes end in the middle of pixels.
+        for len in [1, 2, 5, 7, 4099, pixels.len()] {
+            let mut writer = QoiWriter::new(qoi, Vec::new());
+            for part in pixels.chunks(len) {
+                writer.write_all(part).unwrap();
+            }
+            assert_eq!(writer.remaining(), 0);
+            let output = writer.finish().unwrap();
+            assert!(
+                output == expected,
+                "{}x{} writes of {}",
+                qoi.width,
+                qoi.height,
+                len
+            );
+        }
+    }
+}
+
+#[test]
+fn qoi_writer_rejects_extra_bytes() {
+    for (qoi, pixels) in images() {
+        let mut writer = QoiWriter::new(qoi, Vec::new());
+        let mut longer = pixels.clone();
+        longer.push(0);
+        let err = writer.write(&longer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(writer.remaining(), pixels.len());
+
+        let (head, tail) = pixels.split_at(pixels.len() / 2 + 1);
+        writer.write_all(head).unwrap();
+        assert_eq!(writer.remaining(), tail.len());
+        let err = writer.write(&longer[head.len()..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        writer.write_all(tail).unwrap();
+        assert_eq!(
+            writer.write(&[0]).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+
+        let output = writer.finish().unwrap();
+        assert!(output == qoi.encode_alloc(&pixels).unwrap());
+    }
+}
+
+#[test]
+fn qoi_writer_errors() {
+    let (qoi, pixels) = images().pop().unwrap();
+
+    let mut writer = QoiWriter::new(qoi, Vec::new());
+    writer.write_all(&pixels[..pixels.len() - 1]).unwrap();
+    assert!(matches!(
+        writer.finish(),
+        Err(WriteEncodeError::Encode(
+            EncodeError::InputSizeMismatch { .. }
+        ))
+    ));
+
+    let mut writer = QoiWriter::new(qoi, FailingWriter { left: 100 });
+    let err = writer.write_all(&pixels).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
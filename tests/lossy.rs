@@ -0,0 +1,78 @@
+//! Quality and size trade-off of lossy encoding.
+
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::photo;
+use rapid_qoi::{Colors, Qoi};
+
+fn encode_lossy(qoi: &Qoi, pixels: &[u8], quality: u8) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode_lossy(pixels, &mut output, quality).unwrap();
+    output.truncate(size);
+    output
+}
+
+#[test]
+fn lossless_range() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 64,
+            height: 48,
+            colors,
+        };
+        let pixels = photo(64, 48, colors.channels());
+        let lossless = qoi.encode_alloc(&pixels).unwrap();
+        for quality in [93, 99, 100, 255] {
+            assert_eq!(encode_lossy(&qoi, &pixels, quality), lossless);
+        }
+        assert_ne!(encode_lossy(&qoi, &pixels, 92), lossless);
+    }
+}
+
+#[test]
+fn quality_is_monotonic() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 160,
+            height: 120,
+            colors,
+        };
+        let pixels = photo(160, 120, colors.channels());
+        let lossless = qoi.encode_alloc(&pixels).unwrap();
+
+        let mut prev_psnr = f64::INFINITY;
+        let mut prev_size = lossless.len();
+        for tolerance in 0..=12u8 {
+            let quality = 100 - tolerance * 8;
+            let encoded = encode_lossy(&qoi, &pixels, quality);
+
+            let (_, decoded) = Qoi::decode_alloc(&encoded).unwrap();
+            assert_eq!(decoded.len(), pixels.len());
+
+            let result = Qoi::psnr(&lossless, &encoded).unwrap();
+            assert!(result.max_error[..3].iter().all(|&e| e <= tolerance));
+            assert_eq!(result.max_error[3], 0);
+
+            let psnr = result.psnr_total(false);
+            assert!(
+                psnr <= prev_psnr,
+                "quality {}: {} > {}",
+                quality,
+                psnr,
+                prev_psnr
+            );
+            assert!(
+                encoded.len() <= prev_size,
+                "quality {}: {} > {}",
+                quality,
+                encoded.len(),
+                prev_size
+            );
+            prev_psnr = psnr;
+            prev_size = encoded.len();
+        }
+        assert!(prev_size < lossless.len() / 2);
+    }
+}
@@ -0,0 +1,43 @@
+//! Metadata trailer after the end marker.
+
+#![cfg(all(feature = "metadata", feature = "alloc"))]
+
+mod common;
+
+use common::FIXTURES;
+use rapid_qoi::{metadata, Qoi};
+
+#[test]
+fn trailer_round_trip_and_plain_decode() {
+    for &(name, fixture) in FIXTURES {
+        assert!(
+            metadata::read_trailer(fixture).unwrap().is_empty(),
+            "{}",
+            name
+        );
+
+        let mut bytes = fixture.to_vec();
+        metadata::write_trailer(
+            &mut bytes,
+            &[("source", b"scan-0042.tif"), ("software", b"rapid-qoi")],
+        );
+
+        let entries = metadata::read_trailer(&bytes).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            ("source".to_string(), b"scan-0042.tif".to_vec())
+        );
+        assert_eq!(entries[1], ("software".to_string(), b"rapid-qoi".to_vec()));
+
+        let (_, expected) = Qoi::decode_alloc(fixture).unwrap();
+        let (_, decoded) = Qoi::decode_alloc(&bytes).unwrap();
+        assert!(decoded == expected, "{}", name);
+        assert_eq!(
+            Qoi::decode_counted(&bytes, &mut vec![0; expected.len()])
+                .unwrap()
+                .1,
+            fixture.len()
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Encoding with alpha channel dropped from opaque images.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{photo, FIXTURES};
+use rapid_qoi::{Colors, Qoi};
+
+fn decode(bytes: &[u8]) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    (qoi, pixels)
+}
+
+fn same(a: Colors, b: Colors) -> bool {
+    a.channels() == b.channels() && a.is_srgb() == b.is_srgb()
+}
+
+fn encode_optimized(qoi: &Qoi, pixels: &[u8]) -> (Qoi, Vec<u8>) {
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let (qoi, size) = qoi.encode_optimized(pixels, &mut output).unwrap();
+    output.truncate(size);
+    (qoi, output)
+}
+
+#[test]
+fn opaque_image_is_encoded_with_three_channels() {
+    let qoi = Qoi {
+        width: 97,
+        height: 61,
+        colors: Colors::SrgbLinA,
+    };
+    let mut pixels = photo(97, 61, 4);
+    for px in pixels.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+    let rgb: Vec<u8> = pixels
+        .chunks_exact(4)
+        .flat_map(|px| px[..3].to_vec())
+        .collect();
+
+    let (optimized, bytes) = encode_optimized(&qoi, &pixels);
+    assert!(same(optimized.colors, Colors::Srgb));
+    assert_eq!((optimized.width, optimized.height), (97, 61));
+    assert_eq!(bytes[12], 3);
+
+    // Alpha never changes, so only channels number in the header differs,
+    // while decoded pixels take three bytes instead of four.
+    let full = qoi.encode_alloc(&pixels).unwrap();
+    assert!(bytes[..12] == full[..12] && bytes[13..] == full[13..]);
+    assert_eq!(optimized.decoded_size() * 4, qoi.decoded_size() * 3);
+
+    let (decoded, output) = decode(&bytes);
+    assert!(same(decoded.colors, Colors::Srgb));
+    assert!(output == rgb);
+
+    let linear = Qoi {
+        colors: Colors::Rgba,
+        ..qoi
+    };
+    assert!(same(
+        encode_optimized(&linear, &pixels).0.colors,
+        Colors::Rgb
+    ));
+}
+
+#[test]
+fn translucent_and_three_channel_images_are_unchanged() {
+    for &(name, bytes) in FIXTURES {
+        let (qoi, pixels) = decode(bytes);
+        let opaque = pixels.chunks_exact(4).all(|px| px[3] == 255);
+
+        let (optimized, encoded) = encode_optimized(&qoi, &pixels);
+        let mut expected = vec![0; qoi.encoded_size_limit()];
+        let size = match qoi.colors.has_alpha() && opaque {
+            true => {
+                assert_eq!(optimized.colors.channels(), 3, "{}", name);
+                let rgb: Vec<u8> = pixels
+                    .chunks_exact(4)
+                    .flat_map(|px| px[..3].to_vec())
+                    .collect();
+                optimized.encode(&rgb, &mut expected).unwrap()
+            }
+            false => {
+                assert!(same(optimized.colors, qoi.colors), "{}", name);
+                qoi.encode(&pixels, &mut expected).unwrap()
+            }
+        };
+        assert!(encoded == expected[..size], "{}", name);
+        assert!(same(
+            Qoi::decode_header(&encoded).unwrap().colors,
+            optimized.colors
+        ));
+    }
+}
@@ -0,0 +1,579 @@
+//! Decoding into alternative output layouts compared with plain decoding followed by conversion.
+
+mod common;
+
+use common::{gradient, photo, runs, FIXTURES};
+use rapid_qoi::{ChannelOrder, Colors, DecodeError, Qoi, Rotation};
+
+/// Fixtures followed by images with runs crossing rows, encoded with both channels numbers.
+fn images() -> Vec<(String, Vec<u8>)> {
+    let mut images: Vec<_> = FIXTURES
+        .iter()
+        .map(|&(name, bytes)| (name.to_string(), bytes.to_vec()))
+        .collect();
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 29,
+            height: 17,
+            colors,
+        };
+        for (name, pixels) in [
+            ("photo", photo(29, 17, colors.channels())),
+            ("runs", runs(29, 17, colors.channels())),
+        ] {
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode(&pixels, &mut output).unwrap();
+            output.truncate(size);
+            images.push((format!("{} {}", name, colors.channels()), output));
+        }
+    }
+    images
+}
+
+fn decode(bytes: &[u8]) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    (qoi, pixels)
+}
+
+/// Converts decoded pixels into RGBA pixels with opaque alpha for images without alpha channel.
+fn to_rgba(qoi: &Qoi, pixels: &[u8]) -> Vec<[u8; 4]> {
+    pixels
+        .chunks_exact(qoi.colors.channels())
+        .map(|px| [px[0], px[1], px[2], px.get(3).copied().unwrap_or(255)])
+        .collect()
+}
+
+/// Positions of RGBA channels in pixels with `order`.
+fn positions(order: ChannelOrder) -> [usize; 4] {
+    match order {
+        ChannelOrder::Rgba => [0, 1, 2, 3],
+        ChannelOrder::Bgra => [2, 1, 0, 3],
+        ChannelOrder::Argb => [1, 2, 3, 0],
+        ChannelOrder::Abgr => [3, 2, 1, 0],
+    }
+}
+
+#[test]
+fn channel_order_as_swizzled_rgba() {
+    let orders = [
+        ChannelOrder::Rgba,
+        ChannelOrder::Bgra,
+        ChannelOrder::Argb,
+        ChannelOrder::Abgr,
+    ];
+
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let rgba = to_rgba(&qoi, &pixels);
+
+        for order in orders {
+            let pos = positions(order);
+            let expected: Vec<u8> = rgba
+                .iter()
+                .flat_map(|px| {
+                    let mut out = [0; 4];
+                    for c in 0..4 {
+                        out[pos[c]] = px[c];
+                    }
+                    out
+                })
+                .collect();
+
+            let mut output = vec![0; qoi.decoded_size_rgba()];
+            Qoi::decode_with_order(&bytes, &mut output, order).unwrap();
+            assert!(output == expected, "{} {:?}", name, order);
+
+            // Swizzling back restores RGBA pixels.
+            let restored: Vec<[u8; 4]> = output
+                .chunks_exact(4)
+                .map(|px| [px[pos[0]], px[pos[1]], px[pos[2]], px[pos[3]]])
+                .collect();
+            assert!(restored == rgba, "{} {:?}", name, order);
+        }
+    }
+}
+
+#[test]
+fn typed_pixels_as_bytes() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let rgba = to_rgba(&qoi, &pixels);
+        let len = rgba.len();
+
+        // Pixels have byte alignment, so output may start at any element of the buffer.
+        let mut output = vec![[0xcd; 4]; len + 2];
+        let decoded = Qoi::decode_pixels(&bytes, &mut output[1..]).unwrap();
+        assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+        assert!(output[1..=len] == rgba[..], "{}", name);
+        assert_eq!(output[len + 1], [0xcd; 4]);
+        assert_eq!(output[0], [0xcd; 4]);
+
+        let rgb: Vec<[u8; 3]> = rgba.iter().map(|px| [px[0], px[1], px[2]]).collect();
+        let mut output = vec![[0; 3]; len];
+        Qoi::decode_pixels(&bytes, &mut output).unwrap();
+        assert!(output == rgb, "{}", name);
+
+        if len > 0 {
+            assert_eq!(
+                Qoi::decode_pixels(&bytes, &mut output[1..]).err(),
+                Some(DecodeError::OutputIsTooSmall)
+            );
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            let (_, output) = Qoi::decode_pixels_alloc::<4>(&bytes).unwrap();
+            assert!(output == rgba, "{}", name);
+
+            // Opaque pixels of images without alpha channel round trip through encoding.
+            let flat: Vec<u8> = output.iter().flatten().copied().collect();
+            let encoded = Qoi {
+                colors: Colors::Rgba,
+                ..qoi
+            }
+            .encode_alloc(&flat)
+            .unwrap();
+            let (_, again) = Qoi::decode_pixels_alloc::<4>(&encoded).unwrap();
+            assert!(again == rgba, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn typed_pixels_of_empty_images() {
+    for (width, height) in [(0, 0), (0, 5), (5, 0)] {
+        for colors in [Colors::Srgb, Colors::Rgba] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let mut bytes = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode(&[], &mut bytes).unwrap();
+
+            let mut output: [[u8; 4]; 0] = [];
+            let decoded = Qoi::decode_pixels(&bytes[..size], &mut output).unwrap();
+            assert_eq!((decoded.width, decoded.height), (width, height));
+
+            #[cfg(feature = "alloc")]
+            assert!(Qoi::decode_pixels_alloc::<3>(&bytes[..size])
+                .unwrap()
+                .1
+                .is_empty());
+        }
+    }
+}
+
+#[test]
+fn flipped_as_reversed_rows() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let row = qoi.width as usize * qoi.colors.channels();
+
+        let mut expected = Vec::with_capacity(pixels.len());
+        if row > 0 {
+            for line in pixels.chunks_exact(row).rev() {
+                expected.extend_from_slice(line);
+            }
+        }
+
+        let mut output = vec![0; pixels.len()];
+        let decoded = Qoi::decode_flipped(&bytes, &mut output).unwrap();
+        assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+        assert!(output == expected, "{}", name);
+    }
+
+    // Every row of the gradient is different.
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 13,
+            height: 7,
+            colors,
+        };
+        let pixels = gradient(13, 7, colors.channels());
+        let mut bytes = vec![0; qoi.encoded_size_limit()];
+        let size = qoi.encode(&pixels, &mut bytes).unwrap();
+
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode_flipped(&bytes[..size], &mut output).unwrap();
+        let row = 13 * colors.channels();
+        for y in 0..7 {
+            assert!(output[y * row..][..row] == pixels[(6 - y) * row..][..row]);
+        }
+    }
+}
+
+#[test]
+fn strided_rows_as_tight_rows() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let row = qoi.width as usize * qoi.colors.channels();
+        if row == 0 {
+            continue;
+        }
+        let height = qoi.height as usize;
+
+        for stride in [row, row + 1, row + 3, 256.max(row)] {
+            let size = stride * (height - 1) + row;
+            for len in [size, stride * height] {
+                let mut output = vec![0xcd; len];
+                Qoi::decode_with_stride(&bytes, &mut output, stride).unwrap();
+                for (y, line) in pixels.chunks_exact(row).enumerate() {
+                    assert!(
+                        output[y * stride..][..row] == *line,
+                        "{} stride {} row {}",
+                        name,
+                        stride,
+                        y
+                    );
+                    let padding = output[y * stride + row..].iter().take(stride - row);
+                    assert!(padding.copied().all(|b| b == 0xcd), "{}", name);
+                }
+            }
+
+            let mut output = vec![0; size - 1];
+            assert_eq!(
+                Qoi::decode_with_stride(&bytes, &mut output, stride).err(),
+                Some(DecodeError::OutputIsTooSmall),
+                "{}",
+                name
+            );
+        }
+
+        let mut output = vec![0; pixels.len()];
+        assert_eq!(
+            Qoi::decode_with_stride(&bytes, &mut output, row - 1).err(),
+            Some(DecodeError::InvalidStride),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn subsampled_as_nearest_pixels() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let channels = qoi.colors.channels();
+        let (width, height) = (qoi.width as usize, qoi.height as usize);
+
+        for factor in [1, 2, 3, 4, 8, 100] {
+            let f = factor as usize;
+            let (w, h) = (width.div_ceil(f), height.div_ceil(f));
+            assert_eq!(qoi.decoded_size_subsampled(factor), w * h * channels);
+
+            let mut expected = Vec::new();
+            for y in (0..height).step_by(f) {
+                for x in (0..width).step_by(f) {
+                    expected.extend_from_slice(&pixels[(y * width + x) * channels..][..channels]);
+                }
+            }
+            assert_eq!(expected.len(), w * h * channels);
+
+            let mut output = vec![0; expected.len()];
+            let decoded = Qoi::decode_subsampled(&bytes, factor, &mut output).unwrap();
+            assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+            assert!(output == expected, "{} factor {}", name, factor);
+
+            if !expected.is_empty() {
+                assert_eq!(
+                    Qoi::decode_subsampled(&bytes, factor, &mut output[1..]).err(),
+                    Some(DecodeError::OutputIsTooSmall)
+                );
+            }
+        }
+
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode_subsampled(&bytes, 1, &mut output).unwrap();
+        assert!(output == pixels, "{}", name);
+    }
+}
+
+#[test]
+#[should_panic]
+fn subsampled_by_zero_panics() {
+    let (_, bytes) = FIXTURES[0];
+    let _ = Qoi::decode_subsampled(bytes, 0, &mut []);
+}
+
+#[test]
+fn luma_as_converted_pixels() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let expected: Vec<u8> = pixels
+            .chunks_exact(qoi.colors.channels())
+            .map(|px| {
+                let (r, g, b) = (px[0] as u32, px[1] as u32, px[2] as u32);
+                ((77 * r + 150 * g + 29 * b + 128) >> 8) as u8
+            })
+            .collect();
+        assert_eq!(qoi.decoded_size_luma(), expected.len());
+
+        let mut output = vec![0; expected.len()];
+        let decoded = Qoi::decode_luma(&bytes, &mut output).unwrap();
+        assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+        assert!(output == expected, "{}", name);
+
+        if !expected.is_empty() {
+            assert_eq!(
+                Qoi::decode_luma(&bytes, &mut output[1..]).err(),
+                Some(DecodeError::OutputIsTooSmall)
+            );
+        }
+    }
+}
+
+/// Encodes single row image with every channel value in every channel.
+fn all_values(colors: Colors) -> Vec<u8> {
+    let channels = colors.channels();
+    let qoi = Qoi {
+        width: 256,
+        height: 1,
+        colors,
+    };
+    let pixels: Vec<u8> = (0..=255u8)
+        .flat_map(|v| [v, 255 - v, v.wrapping_mul(7), v.wrapping_add(128)][..channels].to_vec())
+        .collect();
+    let mut output = vec![0; qoi.encoded_size_limit()];
+    let size = qoi.encode(&pixels, &mut output).unwrap();
+    output.truncate(size);
+    output
+}
+
+/// sRGB transfer function computed in double precision.
+fn srgb_to_linear(v: u8) -> f32 {
+    let v = v as f64 / 255.0;
+    let l = match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    };
+    l as f32
+}
+
+fn ulps(a: f32, b: f32) -> u32 {
+    (a.to_bits() as i64 - b.to_bits() as i64).unsigned_abs() as u32
+}
+
+#[test]
+fn f32_as_normalized_pixels() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        assert_eq!(qoi.decoded_size_f32(), pixels.len());
+
+        let mut output = vec![-1.0; pixels.len() + 1];
+        Qoi::decode_f32(&bytes, &mut output, false).unwrap();
+        for (out, &v) in output.iter().zip(&pixels) {
+            assert_eq!(*out, v as f32 / 255.0, "{}", name);
+        }
+        assert_eq!(output[pixels.len()], -1.0);
+
+        if !pixels.is_empty() {
+            assert_eq!(
+                Qoi::decode_f32(&bytes, &mut output[..pixels.len() - 1], false).err(),
+                Some(DecodeError::OutputIsTooSmall)
+            );
+        }
+    }
+
+    let bytes = all_values(Colors::Rgba);
+    let mut output = vec![0.0; 256 * 4];
+    Qoi::decode_f32(&bytes, &mut output, false).unwrap();
+    assert_eq!(output[0], 0.0);
+    assert_eq!(output[128 * 4], 128.0 / 255.0);
+    assert_eq!(output[255 * 4], 1.0);
+    assert_eq!(output[1], 1.0);
+    assert_eq!(output[255 * 4 + 1], 0.0);
+}
+
+#[test]
+fn f32_linear_as_srgb_curve() {
+    for colors in [Colors::Srgb, Colors::SrgbLinA, Colors::Rgb, Colors::Rgba] {
+        let channels = colors.channels();
+        let bytes = all_values(colors);
+        let (_, pixels) = decode(&bytes);
+
+        let mut output = vec![0.0; pixels.len()];
+        Qoi::decode_f32(&bytes, &mut output, true).unwrap();
+        for (px, out) in pixels
+            .chunks_exact(channels)
+            .zip(output.chunks_exact(channels))
+        {
+            for c in 0..channels {
+                let expected = match c < 3 && matches!(colors, Colors::Srgb | Colors::SrgbLinA) {
+                    true => srgb_to_linear(px[c]),
+                    false => px[c] as f32 / 255.0,
+                };
+                assert!(
+                    ulps(out[c], expected) <= 1,
+                    "{:?} value {} channel {}: {} is not {}",
+                    colors,
+                    px[c],
+                    c,
+                    out[c],
+                    expected
+                );
+            }
+        }
+    }
+}
+
+/// Rotates pixels clockwise by 90 degrees, returning rotated pixels with swapped dimensions.
+fn rotate90(qoi: &Qoi, pixels: &[u8]) -> (Qoi, Vec<u8>) {
+    let channels = qoi.colors.channels();
+    let (width, height) = (qoi.width as usize, qoi.height as usize);
+    let mut output = vec![0; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let to = (x * height + height - 1 - y) * channels;
+            output[to..][..channels]
+                .copy_from_slice(&pixels[(y * width + x) * channels..][..channels]);
+        }
+    }
+    let rotated = Qoi {
+        width: qoi.height,
+        height: qoi.width,
+        colors: qoi.colors,
+    };
+    (rotated, output)
+}
+
+#[test]
+fn rotated_as_naive_rotation() {
+    let rotations = [
+        Rotation::None,
+        Rotation::Rot90,
+        Rotation::Rot180,
+        Rotation::Rot270,
+    ];
+
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+
+        let mut expected = (qoi, pixels.clone());
+        for rotation in rotations {
+            let mut output = vec![0; pixels.len()];
+            let decoded = Qoi::decode_rotated(&bytes, &mut output, rotation).unwrap();
+            assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+            assert!(output == expected.1, "{} {:?}", name, rotation);
+
+            if !pixels.is_empty() {
+                assert_eq!(
+                    Qoi::decode_rotated(&bytes, &mut output[1..], rotation).err(),
+                    Some(DecodeError::OutputIsTooSmall)
+                );
+            }
+            expected = rotate90(&expected.0, &expected.1);
+        }
+        assert!(expected.1 == pixels, "{}", name);
+    }
+}
+
+#[test]
+fn rotated_four_times_is_identity() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+
+        let mut current = (qoi, bytes.clone());
+        for _ in 0..4 {
+            let mut output = vec![0; pixels.len()];
+            Qoi::decode_rotated(&current.1, &mut output, Rotation::Rot90).unwrap();
+            let rotated = Qoi {
+                width: current.0.height,
+                height: current.0.width,
+                colors: qoi.colors,
+            };
+            let mut encoded = vec![0; rotated.encoded_size_limit()];
+            let size = rotated.encode(&output, &mut encoded).unwrap();
+            encoded.truncate(size);
+            current = (rotated, encoded);
+        }
+        assert_eq!((current.0.width, current.0.height), (qoi.width, qoi.height));
+        assert!(decode(&current.1).1 == pixels, "{}", name);
+    }
+}
+
+#[test]
+fn linear_table_values() {
+    // Linear segment of the curve ends between 10 and 11.
+    let golden = [
+        (0, 0),
+        (1, 0),
+        (10, 1),
+        (11, 1),
+        (12, 1),
+        (64, 13),
+        (128, 55),
+        (188, 128),
+        (254, 253),
+        (255, 255),
+    ];
+
+    for colors in [Colors::Srgb, Colors::SrgbLinA] {
+        let channels = colors.channels();
+        let bytes = all_values(colors);
+        let (_, pixels) = decode(&bytes);
+
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode_linear(&bytes, &mut output).unwrap();
+        for (px, out) in pixels
+            .chunks_exact(channels)
+            .zip(output.chunks_exact(channels))
+        {
+            for c in 0..3 {
+                let expected = (srgb_to_linear(px[c]) as f64 * 255.0).round() as u8;
+                assert_eq!(out[c], expected, "{:?} value {}", colors, px[c]);
+            }
+            assert_eq!(out[3..], px[3..], "{:?}", colors);
+        }
+
+        for &(v, l) in &golden {
+            assert_eq!(output[v * channels], l, "{:?} value {}", colors, v);
+        }
+    }
+
+    for colors in [Colors::Rgb, Colors::Rgba] {
+        let bytes = all_values(colors);
+        let (_, pixels) = decode(&bytes);
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode_linear(&bytes, &mut output).unwrap();
+        assert!(output == pixels, "{:?}", colors);
+    }
+}
+
+#[test]
+fn u16_as_replicated_bytes() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        assert_eq!(qoi.decoded_size_u16(), pixels.len());
+
+        let mut output = vec![1; pixels.len() + 1];
+        Qoi::decode_u16(&bytes, &mut output).unwrap();
+        for (&out, &v) in output.iter().zip(&pixels) {
+            assert_eq!(out, v as u16 * 257, "{}", name);
+        }
+        assert_eq!(output[pixels.len()], 1);
+
+        let narrowed: Vec<u8> = output[..pixels.len()]
+            .iter()
+            .map(|&v| (v >> 8) as u8)
+            .collect();
+        assert!(narrowed == pixels, "{}", name);
+
+        if !pixels.is_empty() {
+            assert_eq!(
+                Qoi::decode_u16(&bytes, &mut output[..pixels.len() - 1]).err(),
+                Some(DecodeError::OutputIsTooSmall)
+            );
+        }
+    }
+
+    let bytes = all_values(Colors::Rgba);
+    let mut output = vec![0; 256 * 4];
+    Qoi::decode_u16(&bytes, &mut output).unwrap();
+    assert_eq!(output[0], 0);
+    assert_eq!(output[255 * 4], 65535);
+    assert_eq!(output[1], 65535);
+    assert_eq!(output[128 * 4], 0x8080);
+}
@@ -0,0 +1,139 @@
+//! Parallel encoding and decoding compared with serial encoder and decoder.
+
+#![cfg(feature = "rayon")]
+
+mod common;
+
+use common::{random_runs, Rng};
+use rapid_qoi::{Colors, Qoi};
+
+/// Thread counts including pool size, serial fallback and more threads than rows.
+const THREADS: [usize; 7] = [0, 1, 2, 3, 7, 16, 5000];
+
+/// Returns images at and below the size encoded in parallel, with runs crossing band boundaries.
+fn images() -> Vec<(&'static str, Qoi, Vec<u8>)> {
+    let mut rng = Rng(98);
+    let mut images = Vec::new();
+    let mut image = |name: &'static str, width: u32, height: u32, colors: Colors, rng: &mut Rng| {
+        let pixels = match name {
+            "solid" => [0, 0, 0, 255][..colors.channels()].repeat(width as usize * height as usize),
+            _ => random_runs(width, height, colors.channels(), 4000, rng),
+        };
+        let qoi = Qoi {
+            width,
+            height,
+            colors,
+        };
+        images.push((name, qoi, pixels));
+    };
+
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        image("solid", 1024, 1024, colors, &mut rng);
+        image("square", 1024, 1024, colors, &mut rng);
+        image("row", 1 << 20, 1, colors, &mut rng);
+        image("column", 1, 1 << 20, colors, &mut rng);
+        image("odd", 1500, 777, colors, &mut rng);
+        image("below_threshold", 1023, 1024, colors, &mut rng);
+        image("small", 5, 3, colors, &mut rng);
+    }
+    images
+}
+
+#[test]
+fn encode_parallel_as_serial() {
+    for (name, qoi, pixels) in images() {
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        for threads in THREADS {
+            let size = qoi.encode_parallel(&pixels, &mut output, threads).unwrap();
+            assert!(
+                output[..size] == encoded[..],
+                "{} threads {}",
+                name,
+                threads
+            );
+        }
+
+        let short = &mut output[..encoded.len() - 1];
+        assert_eq!(
+            qoi.encode_parallel(&pixels, short, 3),
+            qoi.encode(&pixels, short),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn decode_parallel_as_serial() {
+    for (name, qoi, pixels) in images() {
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+
+        let mut output = vec![0; pixels.len()];
+        for threads in THREADS {
+            output.fill(0);
+            let decoded = Qoi::decode_parallel(&encoded, &mut output, threads).unwrap();
+            assert_eq!((decoded.width, decoded.height), (qoi.width, qoi.height));
+            assert!(output == pixels, "{} threads {}", name, threads);
+        }
+
+        let short = &mut output[..pixels.len() - 1];
+        assert_eq!(
+            Qoi::decode_parallel(&encoded, short, 3).err(),
+            Qoi::decode(&encoded, short).err(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn decode_parallel_errors_as_serial() {
+    let mut rng = Rng(99);
+    for (name, qoi, pixels) in images() {
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+        let mut output = vec![0; pixels.len()];
+        let mut serial = vec![0; pixels.len()];
+
+        // Truncated in every band and right before the end marker.
+        let mut ends = (1..8).map(|i| encoded.len() * i / 8).collect::<Vec<_>>();
+        ends.push(encoded.len() - 8);
+        for end in ends {
+            for threads in [2, 7] {
+                assert_eq!(
+                    Qoi::decode_parallel(&encoded[..end], &mut output, threads).err(),
+                    Qoi::decode(&encoded[..end], &mut serial).err(),
+                    "{} truncated at {} threads {}",
+                    name,
+                    end,
+                    threads
+                );
+            }
+        }
+
+        // Corrupted chunks change number of pixels covered by the stream.
+        for _ in 0..4 {
+            let mut corrupted = encoded.clone();
+            let at = 14 + rng.next() as usize % (encoded.len() - 22);
+            corrupted[at] = match rng.next() % 2 {
+                0 => 0xfd,
+                _ => 0x00,
+            };
+            let expected = Qoi::decode(&corrupted, &mut serial).err();
+            for threads in [2, 7] {
+                assert_eq!(
+                    Qoi::decode_parallel(&corrupted, &mut output, threads).err(),
+                    expected,
+                    "{} corrupted at {} threads {}",
+                    name,
+                    at,
+                    threads
+                );
+                if expected.is_none() {
+                    assert!(output == serial, "{} corrupted at {}", name, at);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Extended format with 16 bits per channel.
+
+#![cfg(all(feature = "qoi16", feature = "alloc"))]
+
+mod common;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{Colors, DecodeError, Qoi, Qoi16};
+
+fn round_trip(qoi: Qoi16, pixels: &[u16]) {
+    let encoded = qoi.encode_alloc(pixels).unwrap();
+    assert!(encoded.len() <= qoi.encoded_size_limit());
+
+    let (decoded_qoi, decoded) = Qoi16::decode_alloc(&encoded).unwrap();
+    assert_eq!(
+        (decoded_qoi.width, decoded_qoi.height),
+        (qoi.width, qoi.height)
+    );
+    assert!(decoded == pixels);
+
+    assert_eq!(
+        Qoi::decode_header(&encoded).unwrap_err(),
+        DecodeError::InvalidMagic
+    );
+}
+
+#[test]
+fn noise_round_trip() {
+    let mut rng = Rng(89);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi16 {
+            width: 37,
+            height: 23,
+            colors,
+        };
+        let pixels = (0..qoi.decoded_size())
+            .map(|_| rng.next() as u16)
+            .collect::<Vec<_>>();
+        round_trip(qoi, &pixels);
+    }
+}
+
+#[test]
+fn gradient_round_trip() {
+    let mut rng = Rng(90);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi16 {
+            width: 300,
+            height: 40,
+            colors,
+        };
+        let channels = colors.channels();
+        let mut pixels = Vec::with_capacity(qoi.decoded_size());
+        for y in 0..40u32 {
+            for x in 0..300u32 {
+                let px = [x * 211, y * 1601, (x + y) * 97 + rng.next() % 3, 65535 - x];
+                pixels.extend(px[..channels].iter().map(|&v| v as u16));
+            }
+        }
+        round_trip(qoi, &pixels);
+    }
+}
+
+#[test]
+fn conversion_from_qoi_and_back() {
+    for &(name, fixture) in FIXTURES {
+        let extended = Qoi16::from_qoi(fixture).unwrap();
+        let qoi = Qoi16::decode_header(&extended).unwrap();
+        let (_, pixels) = Qoi::decode_alloc(fixture).unwrap();
+        assert_eq!(qoi.decoded_size(), pixels.len());
+
+        let converted = Qoi::decode_alloc(&Qoi16::to_qoi(&extended).unwrap())
+            .unwrap()
+            .1;
+        assert!(converted == pixels, "{}", name);
+    }
+}
@@ -0,0 +1,33 @@
+//! Byte-exact compatibility with the reference encoder.\
+//! Fixtures are produced by `tests/fixtures/reference/generate.c` with reference `qoi.h`.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::FIXTURES;
+use rapid_qoi::Qoi;
+
+#[test]
+fn reference_compatible_matches_reference_encoder() {
+    for &(name, fixture) in FIXTURES {
+        let (qoi, pixels) = Qoi::decode_alloc(fixture).unwrap();
+
+        let mut output = vec![0; qoi.encoded_size_limit()];
+        let size = qoi
+            .encode_reference_compatible(&pixels, &mut output)
+            .unwrap();
+        assert!(&output[..size] == fixture, "{} differs", name);
+    }
+}
+
+#[test]
+fn default_encoder_is_lossless_on_fixtures() {
+    for &(name, fixture) in FIXTURES {
+        let (qoi, pixels) = Qoi::decode_alloc(fixture).unwrap();
+
+        let encoded = qoi.encode_alloc(&pixels).unwrap();
+        let (_, decoded) = Qoi::decode_alloc(&encoded).unwrap();
+        assert!(decoded == pixels, "{} differs", name);
+    }
+}
@@ -0,0 +1,105 @@
+//! Decoding and encoding in parts with `DecoderState` and `EncoderState` carried between them.
+
+mod common;
+
+use common::{Rng, FIXTURES};
+use rapid_qoi::{DecodeError, DecoderState, EncoderState, Qoi};
+
+#[test]
+fn random_parts_with_serialized_state() {
+    let mut rng = Rng(51);
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+        let channels = qoi.colors.channels();
+        let mut expected = vec![0; qoi.decoded_size()];
+        Qoi::decode(fixture, &mut expected).unwrap();
+
+        for _ in 0..16 {
+            let mut output = vec![0; qoi.decoded_size()];
+            let mut state = DecoderState::new();
+            let mut decoded = 0;
+            while !state.is_finished() {
+                let max = rng.next() as usize % 150;
+                decoded += qoi
+                    .decode_resume(fixture, &mut state, &mut output[decoded * channels..], max)
+                    .unwrap();
+                assert_eq!(state.pixels_decoded(), decoded);
+
+                let bytes = state.to_bytes();
+                let restored = DecoderState::from_bytes(&bytes).unwrap();
+                assert_eq!(restored, state);
+                state = restored;
+            }
+            assert!(output == expected, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn serialized_state_is_versioned() {
+    let bytes = DecoderState::new().to_bytes();
+    assert_eq!(bytes.len(), DecoderState::SERIALIZED_SIZE);
+
+    let mut other = bytes;
+    other[0] = other[0].wrapping_add(1);
+    assert!(matches!(
+        DecoderState::from_bytes(&other),
+        Err(DecodeError::InvalidState)
+    ));
+    assert!(matches!(
+        DecoderState::from_bytes(&bytes[1..]),
+        Err(DecodeError::InvalidState)
+    ));
+}
+
+/// Encodes rows of the image in bands of random heights, each band on its own thread with state passed from the previous one.
+fn encode_bands(qoi: &Qoi, pixels: &[u8], rng: &mut Rng) -> Vec<u8> {
+    let row = qoi.width as usize * qoi.colors.channels();
+    let mut state = EncoderState::new();
+    let mut encoded = Vec::new();
+    let mut y = 0;
+    loop {
+        let rows = (rng.next() % 5) as usize;
+        let start = (y * row).min(pixels.len());
+        let end = ((y + rows) * row).min(pixels.len());
+        let band = pixels[start..end].to_vec();
+        let qoi = *qoi;
+        let (bytes, next) = std::thread::spawn(move || {
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi
+                .encode_with_state(&band, &mut output, &mut state)
+                .unwrap();
+            output.truncate(size);
+            (output, state)
+        })
+        .join()
+        .unwrap();
+        encoded.extend_from_slice(&bytes);
+        state = next;
+        y += rows;
+        if state.is_finished() {
+            return encoded;
+        }
+        assert!(y <= qoi.height as usize);
+    }
+}
+
+#[test]
+fn bands_with_state_encode_as_whole_image() {
+    let mut rng = Rng(38);
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+        let mut pixels = vec![0; qoi.decoded_size()];
+        Qoi::decode(fixture, &mut pixels).unwrap();
+        let mut expected = vec![0; qoi.encoded_size_limit()];
+        let size = qoi.encode(&pixels, &mut expected).unwrap();
+
+        for _ in 0..8 {
+            assert!(
+                encode_bands(&qoi, &pixels, &mut rng) == expected[..size],
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Rewriting color space in the header of encoded images.
+
+mod common;
+
+use common::FIXTURES;
+use rapid_qoi::{Colors, DecodeError, Qoi, RetagError};
+
+/// Returns color spaces with the same channels number as `colors`.
+fn retag_targets(colors: Colors) -> [Colors; 2] {
+    match colors.channels() {
+        3 => [Colors::Srgb, Colors::Rgb],
+        _ => [Colors::SrgbLinA, Colors::Rgba],
+    }
+}
+
+fn is_linear(colors: Colors) -> bool {
+    matches!(colors, Colors::Rgb | Colors::Rgba)
+}
+
+fn decode(bytes: &[u8]) -> Vec<u8> {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    pixels
+}
+
+#[test]
+fn retag_changes_only_color_space() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let pixels = decode(bytes);
+
+        for colors in retag_targets(qoi.colors) {
+            for validated in [false, true] {
+                let mut retagged = bytes.to_vec();
+                let result = match validated {
+                    false => Qoi::retag_colorspace(&mut retagged, colors),
+                    true => Qoi::retag_colorspace_validated(&mut retagged, colors),
+                };
+                let result = result.unwrap();
+                assert_eq!(is_linear(result.colors), is_linear(colors));
+
+                let header = Qoi::decode_header(&retagged).unwrap();
+                assert_eq!((header.width, header.height), (qoi.width, qoi.height));
+                assert_eq!(header.colors.channels(), qoi.colors.channels());
+                assert_eq!(is_linear(header.colors), is_linear(colors), "{}", name);
+                assert_eq!(retagged[13], is_linear(colors) as u8, "{}", name);
+
+                assert!(retagged[..13] == bytes[..13], "{}", name);
+                assert!(retagged[14..] == bytes[14..], "{}", name);
+                assert!(decode(&retagged) == pixels, "{}", name);
+            }
+        }
+    }
+}
+
+#[test]
+fn channels_mismatch_leaves_bytes_untouched() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let other = match qoi.colors.channels() {
+            3 => [Colors::SrgbLinA, Colors::Rgba],
+            _ => [Colors::Srgb, Colors::Rgb],
+        };
+
+        for colors in other {
+            let mut retagged = bytes.to_vec();
+            assert_eq!(
+                Qoi::retag_colorspace(&mut retagged, colors).err(),
+                Some(RetagError::ChannelsMismatch {
+                    channels: qoi.colors.channels()
+                }),
+                "{}",
+                name
+            );
+            assert!(retagged == bytes, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn invalid_image_leaves_bytes_untouched() {
+    let (_, bytes) = FIXTURES[0];
+
+    let mut invalid = bytes.to_vec();
+    invalid[0] = b'x';
+    let copy = invalid.clone();
+    assert_eq!(
+        Qoi::retag_colorspace(&mut invalid, Colors::Rgba).err(),
+        Some(RetagError::Decode(DecodeError::InvalidMagic))
+    );
+    assert!(invalid == copy);
+
+    // Only the header is verified unless validated retagging is used.
+    let mut truncated = bytes[..bytes.len() - 1].to_vec();
+    let copy = truncated.clone();
+    assert!(Qoi::retag_colorspace_validated(&mut truncated, Colors::SrgbLinA).is_err());
+    assert!(truncated == copy);
+    assert!(Qoi::retag_colorspace(&mut truncated, Colors::SrgbLinA).is_ok());
+    assert_eq!(truncated[13], 0);
+}
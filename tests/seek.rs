@@ -0,0 +1,153 @@
+//! Decoding rows of images with seek table compared with full decoding.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{photo, runs, Rng};
+use rapid_qoi::{Colors, DecodeError, EncodeError, Qoi};
+
+fn encode_with_seek_table(qoi: &Qoi, pixels: &[u8], row_interval: u32) -> Vec<u8> {
+    let mut output = vec![0; qoi.encoded_size_limit() + 64 * 1024];
+    let size = qoi
+        .encode_with_seek_table(pixels, &mut output, row_interval)
+        .unwrap();
+    output.truncate(size);
+    output
+}
+
+fn decode_rows(bytes: &[u8], start: u32, end: u32) -> Result<Vec<u8>, DecodeError> {
+    let qoi = Qoi::decode_header(bytes)?;
+    let mut output = vec![0; (end - start) as usize * qoi.width as usize * qoi.colors.channels()];
+    Qoi::decode_rows_seek(bytes, start..end, &mut output)?;
+    Ok(output)
+}
+
+#[test]
+fn rows_decode_as_full_image() {
+    let mut rng = Rng(84);
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let channels = colors.channels();
+        for (width, height) in [(37, 53), (1, 40), (64, 1)] {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            for pixels in [
+                photo(width, height, channels),
+                runs(width, height, channels),
+            ] {
+                let row = width as usize * channels;
+                let plain = qoi.encode_alloc(&pixels).unwrap();
+
+                for row_interval in [1, 3, 16, height, height + 5] {
+                    let bytes = encode_with_seek_table(&qoi, &pixels, row_interval);
+                    for _ in 0..32 {
+                        let start = rng.next() % (height + 1);
+                        let end = start + rng.next() % (height - start + 1);
+                        let expected = &pixels[start as usize * row..end as usize * row];
+                        for bytes in [&bytes, &plain] {
+                            assert!(
+                                decode_rows(bytes, start, end).unwrap() == expected,
+                                "{}x{} interval {} rows {}..{}",
+                                width,
+                                height,
+                                row_interval,
+                                start,
+                                end
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn image_with_seek_table_decodes_as_plain() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 37,
+            height: 53,
+            colors,
+        };
+        let pixels = runs(37, 53, colors.channels());
+        let plain = qoi.encode_alloc(&pixels).unwrap();
+        let bytes = encode_with_seek_table(&qoi, &pixels, 4);
+        assert!(bytes[..plain.len()] == plain[..]);
+
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode(&bytes, &mut output).unwrap();
+        assert!(output == pixels);
+        let (_, size) = Qoi::decode_counted(&bytes, &mut output).unwrap();
+        assert_eq!(size, plain.len());
+    }
+}
+
+#[test]
+fn corrupted_seek_table_fails() {
+    let qoi = Qoi {
+        width: 37,
+        height: 53,
+        colors: Colors::Rgba,
+    };
+    let pixels = photo(37, 53, 4);
+    let bytes = encode_with_seek_table(&qoi, &pixels, 4);
+    let plain = qoi.encode_alloc(&pixels).unwrap().len();
+    let footer = bytes.len() - 17;
+
+    // Snapshot, row interval, number of snapshots, checksum and version.
+    for at in [
+        plain,
+        plain + 20,
+        footer - 1,
+        footer + 3,
+        footer + 7,
+        footer + 8,
+        footer + 12,
+    ] {
+        let mut corrupted = bytes.clone();
+        corrupted[at] ^= 0x10;
+        for (start, end) in [(0, 1), (21, 30), (52, 53)] {
+            assert_eq!(
+                decode_rows(&corrupted, start, end).err(),
+                Some(DecodeError::InvalidSeekTable),
+                "corrupted at {} rows {}..{}",
+                at,
+                start,
+                end
+            );
+        }
+
+        let mut output = vec![0; pixels.len()];
+        Qoi::decode(&corrupted, &mut output).unwrap();
+        assert!(output == pixels);
+    }
+}
+
+#[test]
+fn invalid_rows_fail() {
+    let qoi = Qoi {
+        width: 37,
+        height: 53,
+        colors: Colors::Srgb,
+    };
+    let pixels = photo(37, 53, 3);
+    let bytes = encode_with_seek_table(&qoi, &pixels, 4);
+
+    let mut output = vec![0; pixels.len()];
+    assert_eq!(
+        Qoi::decode_rows_seek(&bytes, 50..54, &mut output).err(),
+        Some(DecodeError::InvalidRegion)
+    );
+    assert_eq!(
+        Qoi::decode_rows_seek(&bytes, 0..2, &mut output[..37 * 3 * 2 - 1]).err(),
+        Some(DecodeError::OutputIsTooSmall)
+    );
+    assert_eq!(
+        qoi.encode_with_seek_table(&pixels, &mut output, 0),
+        Err(EncodeError::InvalidOptions)
+    );
+}
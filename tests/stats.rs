@@ -0,0 +1,146 @@
+//! Statistics computed while walking chunks compared with statistics of decoded pixels.
+
+mod common;
+
+use common::{photo, runs, FIXTURES};
+use rapid_qoi::{Colors, Qoi};
+
+/// Fixtures followed by generated images of both channels numbers.
+fn images() -> Vec<(String, Vec<u8>)> {
+    let mut images: Vec<_> = FIXTURES
+        .iter()
+        .map(|&(name, bytes)| (name.to_string(), bytes.to_vec()))
+        .collect();
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let qoi = Qoi {
+            width: 57,
+            height: 31,
+            colors,
+        };
+        for (name, pixels) in [
+            ("photo", photo(57, 31, colors.channels())),
+            ("runs", runs(57, 31, colors.channels())),
+        ] {
+            let mut output = vec![0; qoi.encoded_size_limit()];
+            let size = qoi.encode(&pixels, &mut output).unwrap();
+            output.truncate(size);
+            images.push((format!("{} {}", name, colors.channels()), output));
+        }
+    }
+    images
+}
+
+/// Decodes the image into RGBA pixels.
+fn decode_rgba(bytes: &[u8]) -> Vec<[u8; 4]> {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![[0; 4]; qoi.width as usize * qoi.height as usize];
+    Qoi::decode_pixels(bytes, &mut pixels).unwrap();
+    pixels
+}
+
+fn average(pixels: &[[u8; 4]]) -> [u8; 4] {
+    let count = pixels.len() as u64;
+    let mut px = [0; 4];
+    for (c, out) in px.iter_mut().enumerate() {
+        let sum: u64 = pixels.iter().map(|px| px[c] as u64).sum();
+        *out = ((sum + count / 2) / count) as u8;
+    }
+    px
+}
+
+/// Encodes image of `width` by `height` pixels filled with `first` and then `second` color after `split` pixels.\
+/// Chunks are written directly, so huge images do not need pixels buffer.
+fn two_colors(width: u32, height: u32, split: u64, first: [u8; 3], second: [u8; 3]) -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[3, 0]);
+
+    let count = width as u64 * height as u64;
+    for (color, mut left) in [(first, split), (second, count - split)] {
+        if left == 0 {
+            continue;
+        }
+        bytes.push(0xfe);
+        bytes.extend_from_slice(&color);
+        left -= 1;
+        while left > 0 {
+            let run = left.min(62);
+            bytes.push(0xc0 | (run - 1) as u8);
+            left -= run;
+        }
+    }
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    bytes
+}
+
+#[test]
+fn average_as_decoded_average() {
+    for (name, bytes) in images() {
+        let pixels = decode_rgba(&bytes);
+        let expected = match pixels.is_empty() {
+            true => [0, 0, 0, 255],
+            false => average(&pixels),
+        };
+        assert_eq!(Qoi::average_color(&bytes).unwrap(), expected, "{}", name);
+    }
+}
+
+#[test]
+fn average_of_image_overflowing_u32_sums() {
+    // 4400 * 4000 * 255 exceeds `u32::MAX`.
+    let (width, height) = (4400, 4000);
+    let count = width as u64 * height as u64;
+    let split = count / 3;
+    let bytes = two_colors(width, height, split, [255, 254, 0], [1, 128, 255]);
+
+    let mean = |a: u64, b: u64| ((a * split + b * (count - split) + count / 2) / count) as u8;
+    assert_eq!(
+        Qoi::average_color(&bytes).unwrap(),
+        [mean(255, 1), mean(254, 128), mean(0, 255), 255]
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn dominant_as_most_populated_cell() {
+    for (name, bytes) in images() {
+        let pixels = decode_rgba(&bytes);
+        if pixels.is_empty() {
+            assert_eq!(Qoi::dominant_color(&bytes).unwrap(), [0, 0, 0, 255]);
+            continue;
+        }
+
+        let cell = |px: &[u8; 4]| (px[0] >> 4, px[1] >> 4, px[2] >> 4);
+        let mut cells: Vec<_> = pixels.iter().map(cell).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        let counts: Vec<_> = cells
+            .iter()
+            .map(|&c| pixels.iter().filter(|px| cell(px) == c).count())
+            .collect();
+        let most = *counts.iter().max().unwrap();
+
+        // Ties are allowed to be resolved either way.
+        let dominant = Qoi::dominant_color(&bytes).unwrap();
+        let candidates: Vec<_> = cells
+            .iter()
+            .zip(&counts)
+            .filter(|&(_, &n)| n == most)
+            .map(|(&c, _)| {
+                let members: Vec<_> = pixels.iter().copied().filter(|px| cell(px) == c).collect();
+                average(&members)
+            })
+            .collect();
+        assert!(
+            candidates.contains(&dominant),
+            "{} {:?} {:?}",
+            name,
+            dominant,
+            candidates
+        );
+    }
+
+    let bytes = two_colors(300, 200, 20000, [255, 254, 0], [1, 128, 255]);
+    assert_eq!(Qoi::dominant_color(&bytes).unwrap(), [1, 128, 255, 255]);
+}
@@ -0,0 +1,171 @@
+//! Images with chunks not matching pixel count in the header, with invalid end marker or with trailing data.
+
+mod common;
+
+use common::FIXTURES;
+use rapid_qoi::{chunk::ChunkIter, DecodeError, DecodeOptions, Decoder, Qoi};
+
+/// 4x1 image with red pixel followed by run of 5 pixels, exceeding the image by two pixels.
+const TOO_MANY_PIXELS: &[u8] = include_bytes!("fixtures/invalid/too_many_pixels.qoi");
+
+/// 4x1 image with red pixel followed by run of 2 pixels, the end marker follows the third pixel.
+const NOT_ENOUGH_PIXELS: &[u8] = include_bytes!("fixtures/invalid/not_enough_pixels.qoi");
+
+/// Pixels decoded from both images by reference decoder.
+const REFERENCE: [u8; 16] = [
+    255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0, 255,
+];
+
+fn decode(bytes: &[u8], options: &DecodeOptions) -> Result<[u8; 16], DecodeError> {
+    let mut output = [0; 16];
+    Qoi::decode_with(bytes, &mut output, options)?;
+    Ok(output)
+}
+
+#[test]
+fn too_many_pixels() {
+    let strict = DecodeOptions::new().strict(true);
+    assert_eq!(
+        decode(TOO_MANY_PIXELS, &strict),
+        Err(DecodeError::TooManyPixels { at_byte: 18 })
+    );
+    assert_eq!(
+        Qoi::validate_strict(TOO_MANY_PIXELS).unwrap_err(),
+        DecodeError::TooManyPixels { at_byte: 18 }
+    );
+}
+
+#[test]
+fn too_many_pixels_clamped_as_reference_decoder() {
+    let clamped = DecodeOptions::new().strict(true).clamp_runs(true);
+    assert_eq!(decode(TOO_MANY_PIXELS, &clamped), Ok(REFERENCE));
+    assert_eq!(
+        decode(TOO_MANY_PIXELS, &DecodeOptions::new()),
+        Ok(REFERENCE)
+    );
+    assert!(Qoi::validate(TOO_MANY_PIXELS).is_ok());
+}
+
+#[test]
+fn not_enough_pixels() {
+    for options in [
+        DecodeOptions::new().strict(true),
+        DecodeOptions::new().strict(true).clamp_runs(true),
+    ] {
+        assert_eq!(
+            decode(NOT_ENOUGH_PIXELS, &options),
+            Err(DecodeError::NotEnoughPixels { decoded: 3 })
+        );
+    }
+    assert_eq!(
+        Qoi::validate_strict(NOT_ENOUGH_PIXELS).unwrap_err(),
+        DecodeError::NotEnoughPixels { decoded: 3 }
+    );
+}
+
+#[test]
+fn valid_image_is_accepted_in_strict_mode() {
+    let mut bytes = TOO_MANY_PIXELS.to_vec();
+    bytes[18] = 0xc2;
+    assert_eq!(
+        decode(&bytes, &DecodeOptions::new().strict(true)),
+        Ok(REFERENCE)
+    );
+    assert!(Qoi::validate_strict(&bytes).is_ok());
+}
+
+/// Error reported by every decoder verifying the end marker, checking that they agree.
+fn end_marker_error(bytes: &[u8]) -> Option<DecodeError> {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut output = vec![0; qoi.decoded_size()];
+
+    let strict = Qoi::decode_with(bytes, &mut output, &DecodeOptions::new().strict(true)).err();
+    assert_eq!(Qoi::decode_counted(bytes, &mut output).err(), strict);
+    assert_eq!(Qoi::validate_strict(bytes).err(), strict);
+    let mut decoder = Decoder::new();
+    let pushed = decoder
+        .push(bytes, &mut output)
+        .and_then(|_| decoder.finish());
+    assert_eq!(pushed.err(), strict);
+    assert_eq!(ChunkIter::new(bytes).unwrap().find_map(Result::err), strict);
+
+    let mut row = vec![0; qoi.width as usize * qoi.colors.channels()];
+    let mut rows = Qoi::rows(bytes, &mut row).unwrap();
+    let mut error = None;
+    while let Some(result) = rows.next_row() {
+        error = result.err();
+    }
+    assert_eq!(error, strict);
+
+    strict
+}
+
+#[test]
+fn end_marker_is_verified() {
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        Qoi::decode(bytes, &mut expected).unwrap();
+        let end = bytes.len() - 8;
+
+        assert_eq!(end_marker_error(bytes), None, "{}", name);
+
+        for at in end..bytes.len() {
+            let mut flipped = bytes.to_vec();
+            flipped[at] ^= 0x01;
+            assert_eq!(
+                end_marker_error(&flipped),
+                Some(DecodeError::InvalidPadding { offset: end }),
+                "{} flipped at {}",
+                name,
+                at
+            );
+
+            // Lenient decoding still stops after the last pixel.
+            let mut output = vec![0; qoi.decoded_size()];
+            Qoi::decode(&flipped, &mut output).unwrap();
+            assert!(output == expected, "{}", name);
+        }
+
+        assert!(
+            matches!(
+                end_marker_error(&bytes[..bytes.len() - 1]),
+                Some(DecodeError::NotEnoughData { needed: 1, .. })
+            ),
+            "{} missing last byte",
+            name
+        );
+    }
+}
+
+#[test]
+fn trailing_data_is_accepted_unless_strict() {
+    let strict = DecodeOptions::new().strict(true);
+    for &(name, bytes) in FIXTURES {
+        let qoi = Qoi::decode_header(bytes).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        Qoi::decode_with(bytes, &mut expected, &strict).unwrap();
+
+        let mut trailed = bytes.to_vec();
+        trailed.extend((0..1024).map(|i| (i * 7) as u8));
+
+        let mut output = vec![0; qoi.decoded_size()];
+        let (_, size) = Qoi::decode_counted(&trailed, &mut output).unwrap();
+        assert_eq!(size, bytes.len(), "{}", name);
+        assert!(output == expected, "{}", name);
+
+        let mut output = vec![0; qoi.decoded_size()];
+        Qoi::decode(&trailed, &mut output).unwrap();
+        assert!(output == expected, "{}", name);
+        assert!(Qoi::validate(&trailed).is_ok());
+
+        let trailing = Err(DecodeError::TrailingData {
+            offset: bytes.len(),
+        });
+        assert_eq!(
+            Qoi::decode_with(&trailed, &mut output, &strict).map(|_| ()),
+            trailing
+        );
+        assert_eq!(Qoi::validate_strict(&trailed).map(|_| ()), trailing);
+    }
+}
@@ -0,0 +1,260 @@
+//! Tiled container encoding and decoding of tiles and regions.
+
+#![cfg(feature = "tiled")]
+
+mod common;
+
+use common::{photo, Rng};
+use rapid_qoi::{
+    tiled::{TiledDecoder, TiledEncoder},
+    Colors, DecodeError, EncodeError, Qoi,
+};
+
+/// Image sizes and tile sizes that divide them evenly or leave smaller last column and row.
+const SIZES: [(u32, u32, u32); 8] = [
+    (64, 64, 16),
+    (100, 37, 16),
+    (37, 100, 7),
+    (5, 300, 64),
+    (1, 1, 1),
+    (33, 17, 1),
+    (40, 30, 1000),
+    (65, 65, 64),
+];
+
+/// Returns pixels of region of `pixels` with `width` and `channels`.
+fn crop(pixels: &[u8], width: u32, channels: usize, x: u32, y: u32, w: u32, h: u32) -> Vec<u8> {
+    let row = width as usize * channels;
+    (y..y + h)
+        .flat_map(|y| {
+            let start = y as usize * row + x as usize * channels;
+            pixels[start..start + w as usize * channels].to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn tiles_decode_as_cropped_image() {
+    for colors in [Colors::Srgb, Colors::Rgba] {
+        let channels = colors.channels();
+        for (width, height, tile_size) in SIZES {
+            let qoi = Qoi {
+                width,
+                height,
+                colors,
+            };
+            let pixels = photo(width, height, channels);
+            let mut bytes = Vec::new();
+            let encoder = TiledEncoder::new(qoi);
+            let size = encoder.encode(&pixels, tile_size, &mut bytes).unwrap();
+            assert_eq!(size, bytes.len());
+
+            let decoder = TiledDecoder::new(&bytes).unwrap();
+            let tiles = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+            assert_eq!(decoder.tiles(), tiles);
+            assert_eq!(encoder.tiles(tile_size), tiles);
+            assert_eq!(decoder.tile_size(), tile_size);
+
+            for ty in 0..tiles.1 {
+                for tx in 0..tiles.0 {
+                    let (x, y) = (tx * tile_size, ty * tile_size);
+                    let (w, h) = (tile_size.min(width - x), tile_size.min(height - y));
+                    let tile = decoder.tile_qoi(tx, ty);
+                    assert_eq!((tile.width, tile.height), (w, h));
+
+                    let mut output = vec![0; tile.decoded_size()];
+                    decoder.decode_tile(tx, ty, &mut output).unwrap();
+                    assert!(
+                        output == crop(&pixels, width, channels, x, y, w, h),
+                        "{}x{} tile {} {}",
+                        width,
+                        height,
+                        tx,
+                        ty
+                    );
+                }
+            }
+
+            let mut output = vec![0; pixels.len()];
+            decoder
+                .decode_region(0, 0, width, height, &mut output)
+                .unwrap();
+            assert!(output == pixels, "{}x{}", width, height);
+        }
+    }
+}
+
+#[test]
+fn regions_decode_as_cropped_image() {
+    let mut rng = Rng(90);
+    for (width, height, tile_size) in SIZES {
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Rgba,
+        };
+        let pixels = photo(width, height, 4);
+        let mut bytes = Vec::new();
+        TiledEncoder::new(qoi)
+            .encode(&pixels, tile_size, &mut bytes)
+            .unwrap();
+        let decoder = TiledDecoder::new(&bytes).unwrap();
+
+        for _ in 0..16 {
+            let (x, y) = (rng.next() % width, rng.next() % height);
+            let (w, h) = (rng.next() % (width - x + 1), rng.next() % (height - y + 1));
+            let mut output = vec![0; w as usize * h as usize * 4];
+            decoder.decode_region(x, y, w, h, &mut output).unwrap();
+            assert!(
+                output == crop(&pixels, width, 4, x, y, w, h),
+                "{}x{} region {} {} {} {}",
+                width,
+                height,
+                x,
+                y,
+                w,
+                h
+            );
+        }
+
+        let mut output = vec![0; 4];
+        assert_eq!(
+            decoder.decode_region(width, 0, 1, 1, &mut output),
+            Err(DecodeError::InvalidRegion)
+        );
+        assert_eq!(
+            decoder.decode_region(0, height - 1, 1, 2, &mut output),
+            Err(DecodeError::InvalidRegion)
+        );
+    }
+}
+
+#[test]
+fn separately_encoded_tiles_assemble_as_encoded() {
+    let qoi = Qoi {
+        width: 100,
+        height: 37,
+        colors: Colors::Srgb,
+    };
+    let pixels = photo(100, 37, 3);
+    let encoder = TiledEncoder::new(qoi);
+    let mut expected = Vec::new();
+    encoder.encode(&pixels, 16, &mut expected).unwrap();
+
+    let (tiles_x, tiles_y) = encoder.tiles(16);
+    let mut tiles = Vec::new();
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let mut tile = Vec::new();
+            encoder.encode_tile(&pixels, 16, tx, ty, &mut tile).unwrap();
+            tiles.push(tile);
+        }
+    }
+    let mut bytes = Vec::new();
+    encoder.assemble(16, &tiles, &mut bytes).unwrap();
+    assert!(bytes == expected);
+
+    let mut out = Vec::new();
+    assert_eq!(
+        encoder.encode_tile(&pixels, 16, tiles_x, 0, &mut out),
+        Err(EncodeError::InvalidRegion)
+    );
+    assert_eq!(
+        encoder.encode(&pixels, 0, &mut out),
+        Err(EncodeError::InvalidOptions)
+    );
+    assert_eq!(
+        encoder.assemble(16, &tiles[1..], &mut out),
+        Err(EncodeError::InputSizeMismatch {
+            expected: tiles.len(),
+            got: tiles.len() - 1
+        })
+    );
+    tiles.swap(0, tiles_x as usize - 1);
+    assert_eq!(
+        encoder.assemble(16, &tiles, &mut out),
+        Err(EncodeError::InvalidRegion)
+    );
+}
+
+#[test]
+fn other_containers_fail() {
+    let qoi = Qoi {
+        width: 16,
+        height: 16,
+        colors: Colors::Rgba,
+    };
+    let pixels = photo(16, 16, 4);
+    let image = qoi.encode_alloc(&pixels).unwrap();
+    assert_eq!(
+        TiledDecoder::new(&image).err(),
+        Some(DecodeError::InvalidMagic)
+    );
+
+    let mut bytes = Vec::new();
+    TiledEncoder::new(qoi)
+        .encode(&pixels, 5, &mut bytes)
+        .unwrap();
+    for len in 0..bytes.len() {
+        if let Ok(decoder) = TiledDecoder::new(&bytes[..len]) {
+            let mut output = vec![0; pixels.len()];
+            assert!(decoder.decode_region(0, 0, 16, 16, &mut output).is_err());
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn tiles_encode_and_decode_in_parallel() {
+    use rayon::prelude::*;
+
+    for (width, height, tile_size) in [(1000, 700, 64), (333, 1025, 100)] {
+        let qoi = Qoi {
+            width,
+            height,
+            colors: Colors::Rgba,
+        };
+        let pixels = photo(width, height, 4);
+        let encoder = TiledEncoder::new(qoi);
+        let mut expected = Vec::new();
+        encoder.encode(&pixels, tile_size, &mut expected).unwrap();
+
+        let (tiles_x, tiles_y) = encoder.tiles(tile_size);
+        let tiles = (0..tiles_x * tiles_y)
+            .into_par_iter()
+            .map(|i| {
+                let mut tile = Vec::new();
+                encoder
+                    .encode_tile(&pixels, tile_size, i % tiles_x, i / tiles_x, &mut tile)
+                    .unwrap();
+                tile
+            })
+            .collect::<Vec<_>>();
+        let mut bytes = Vec::new();
+        encoder.assemble(tile_size, &tiles, &mut bytes).unwrap();
+        assert!(bytes == expected);
+
+        let decoder = TiledDecoder::new(&bytes).unwrap();
+        let decoded = (0..tiles_x * tiles_y)
+            .into_par_iter()
+            .map(|i| {
+                let (tx, ty) = (i % tiles_x, i / tiles_x);
+                let mut output = vec![0; decoder.tile_qoi(tx, ty).decoded_size()];
+                decoder.decode_tile(tx, ty, &mut output).unwrap();
+                (tx, ty, output)
+            })
+            .collect::<Vec<_>>();
+
+        let mut output = vec![0; pixels.len()];
+        let row = width as usize * 4;
+        for (tx, ty, tile) in decoded {
+            let tile_row = decoder.tile_qoi(tx, ty).width as usize * 4;
+            for (i, part) in tile.chunks_exact(tile_row).enumerate() {
+                let start =
+                    (ty * tile_size) as usize * row + i * row + (tx * tile_size) as usize * 4;
+                output[start..start + tile_row].copy_from_slice(part);
+            }
+        }
+        assert!(output == pixels, "{}x{}", width, height);
+    }
+}
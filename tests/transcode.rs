@@ -0,0 +1,225 @@
+//! Transcoding and re-encoding encoded images compared with decoding and encoding them again.
+
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{photo, FIXTURES};
+use rapid_qoi::{chunk::Op, Colors, DecodeError, Qoi, TranscodeError};
+
+const TARGETS: [Colors; 4] = [Colors::Srgb, Colors::SrgbLinA, Colors::Rgb, Colors::Rgba];
+
+fn decode(bytes: &[u8]) -> (Qoi, Vec<u8>) {
+    let qoi = Qoi::decode_header(bytes).unwrap();
+    let mut pixels = vec![0; qoi.decoded_size()];
+    Qoi::decode(bytes, &mut pixels).unwrap();
+    (qoi, pixels)
+}
+
+/// Converts pixels to `channels` dropping alpha or setting it to `255`.
+fn convert(pixels: &[u8], from: usize, channels: usize) -> Vec<u8> {
+    pixels
+        .chunks_exact(from)
+        .flat_map(|px| {
+            let mut rgba = [px[0], px[1], px[2], 255];
+            rgba[..from].copy_from_slice(px);
+            rgba[..channels].to_vec()
+        })
+        .collect()
+}
+
+/// Fixtures along with opaque image with four channels.
+fn images() -> Vec<(&'static str, Vec<u8>)> {
+    let mut images: Vec<_> = FIXTURES
+        .iter()
+        .map(|&(name, bytes)| (name, bytes.to_vec()))
+        .collect();
+
+    let qoi = Qoi {
+        width: 53,
+        height: 29,
+        colors: Colors::Rgba,
+    };
+    let opaque = convert(&photo(53, 29, 3), 3, 4);
+    images.push(("opaque", qoi.encode_alloc(&opaque).unwrap()));
+    images
+}
+
+#[test]
+fn transcode_as_decode_and_encode() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        for target in TARGETS {
+            let expected = Qoi {
+                colors: target,
+                ..qoi
+            };
+            let converted = convert(&pixels, qoi.colors.channels(), target.channels());
+
+            let mut out = b"prefix".to_vec();
+            let transcoded = Qoi::transcode(&bytes, target, &mut out).unwrap();
+            assert_eq!(&out[..6], b"prefix");
+            assert_eq!(
+                (transcoded.width, transcoded.height),
+                (qoi.width, qoi.height)
+            );
+            assert_eq!(transcoded.colors.channels(), target.channels());
+            assert_eq!(transcoded.colors.is_srgb(), target.is_srgb());
+
+            let (decoded, output) = decode(&out[6..]);
+            assert_eq!(decoded.colors.channels(), target.channels());
+            assert_eq!(decoded.colors.is_srgb(), target.is_srgb());
+            assert!(output == converted, "{} to {:?}", name, target);
+
+            let naive = expected.encode_alloc(&converted).unwrap();
+            match qoi.colors.channels() == target.channels() {
+                // Chunks are copied, only color space byte may differ.
+                true => {
+                    assert!(
+                        out[6..19] == bytes[..13] && out[20..] == bytes[14..],
+                        "{}",
+                        name
+                    )
+                }
+                false => assert!(out[6..] == naive[..], "{} to {:?}", name, target),
+            }
+        }
+    }
+}
+
+#[test]
+fn transcode_opaque_rejects_translucent_pixels() {
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let translucent = match qoi.colors.has_alpha() {
+            true => pixels.chunks_exact(4).position(|px| px[3] != 255),
+            false => None,
+        };
+
+        for target in TARGETS {
+            let mut out = Vec::new();
+            let result = Qoi::transcode_opaque(&bytes, target, &mut out);
+            match (translucent, target.has_alpha()) {
+                (Some(pixel), false) => {
+                    assert_eq!(
+                        result.err(),
+                        Some(TranscodeError::NotOpaque { pixel }),
+                        "{}",
+                        name
+                    )
+                }
+                _ => {
+                    result.unwrap();
+                    let mut expected = Vec::new();
+                    Qoi::transcode(&bytes, target, &mut expected).unwrap();
+                    assert!(out == expected, "{} to {:?}", name, target);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn transcode_reports_decode_errors() {
+    let (_, bytes) = FIXTURES[0];
+    let mut out = Vec::new();
+    for target in TARGETS {
+        assert!(matches!(
+            Qoi::transcode(&bytes[..bytes.len() - 1], target, &mut out),
+            Err(TranscodeError::Decode(DecodeError::NotEnoughData { .. }))
+        ));
+        assert!(matches!(
+            Qoi::transcode(&bytes[..10], target, &mut out),
+            Err(TranscodeError::Decode(DecodeError::NotEnoughData { .. }))
+        ));
+    }
+}
+
+/// Encodes every pixel with literal chunk as suboptimal encoder may do.
+fn encode_literals(qoi: &Qoi, pixels: &[u8]) -> Vec<u8> {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend_from_slice(&qoi.width.to_be_bytes());
+    bytes.extend_from_slice(&qoi.height.to_be_bytes());
+    bytes.extend_from_slice(&[qoi.colors.channels() as u8, !qoi.colors.is_srgb() as u8]);
+
+    let mut chunk = [0; 5];
+    for px in pixels.chunks_exact(qoi.colors.channels()) {
+        let op = match *px {
+            [r, g, b] => Op::Rgb { r, g, b },
+            [r, g, b, a] => Op::Rgba { r, g, b, a },
+            _ => unreachable!(),
+        };
+        let size = op.write(&mut chunk);
+        bytes.extend_from_slice(&chunk[..size]);
+    }
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    bytes
+}
+
+#[test]
+fn reencode_preserves_header_and_pixels() {
+    let mut images = images();
+    for (name, bytes) in images.clone() {
+        let (qoi, pixels) = decode(&bytes);
+        images.push((name, encode_literals(&qoi, &pixels)));
+    }
+
+    for (name, bytes) in images {
+        let (qoi, pixels) = decode(&bytes);
+
+        let mut out = b"prefix".to_vec();
+        let size = Qoi::reencode(&bytes, &mut out).unwrap();
+        assert_eq!(size, out.len() - 6);
+        assert!(out[6..20] == bytes[..14], "{}", name);
+
+        let (_, output) = decode(&out[6..]);
+        assert!(output == pixels, "{}", name);
+        assert!(
+            out[6..] == qoi.encode_alloc(&pixels).unwrap()[..],
+            "{}",
+            name
+        );
+
+        // Other encoders may choose different chunks, but not much shorter ones.
+        assert!(size <= bytes.len() + 8, "{}", name);
+
+        let mut verified = b"prefix".to_vec();
+        assert_eq!(Qoi::reencode_verified(&bytes, &mut verified), Ok(size));
+        assert!(verified == out, "{}", name);
+
+        let mut again = Vec::new();
+        Qoi::reencode(&out[6..], &mut again).unwrap();
+        assert!(again[..] == out[6..], "{}", name);
+    }
+}
+
+#[test]
+fn reencode_shrinks_literal_only_images() {
+    let mut total = (0, 0);
+    for (name, bytes) in images() {
+        let (qoi, pixels) = decode(&bytes);
+        let literals = encode_literals(&qoi, &pixels);
+
+        let mut out = Vec::new();
+        let size = Qoi::reencode(&literals, &mut out).unwrap();
+        // Noise with translucent pixels takes literals anyway.
+        assert!(size <= literals.len(), "{}", name);
+        total = (total.0 + size, total.1 + literals.len());
+        assert!(out == qoi.encode_alloc(&pixels).unwrap(), "{}", name);
+    }
+    assert!(total.0 * 3 < total.1 * 2, "{:?}", total);
+}
+
+#[test]
+fn reencode_reports_decode_errors() {
+    let (_, bytes) = FIXTURES[0];
+    let mut out = Vec::new();
+    assert!(matches!(
+        Qoi::reencode(&bytes[..bytes.len() - 1], &mut out),
+        Err(TranscodeError::Decode(DecodeError::NotEnoughData { .. }))
+    ));
+    assert!(matches!(
+        Qoi::reencode_verified(&bytes[..20], &mut out),
+        Err(TranscodeError::Decode(DecodeError::NotEnoughData { .. }))
+    ));
+}
@@ -0,0 +1,45 @@
+//! Decoding into uninitialized memory.
+
+mod common;
+
+use core::mem::MaybeUninit;
+
+use common::FIXTURES;
+use rapid_qoi::{DecodeError, Qoi};
+
+#[test]
+fn decode_uninit_as_decode() {
+    for &(name, fixture) in FIXTURES {
+        let qoi = Qoi::decode_header(fixture).unwrap();
+        let mut expected = vec![0; qoi.decoded_size()];
+        Qoi::decode(fixture, &mut expected).unwrap();
+
+        // Spare bytes after the image are not part of the returned pixels.
+        let mut output = vec![MaybeUninit::uninit(); qoi.decoded_size() + 5];
+        let pixels = Qoi::decode_uninit(fixture, &mut output).unwrap();
+        assert!(pixels == &expected[..], "{}", name);
+    }
+}
+
+#[test]
+fn decode_uninit_fails_as_decode() {
+    let (_, fixture) = FIXTURES[0];
+    let qoi = Qoi::decode_header(fixture).unwrap();
+
+    let mut output = vec![MaybeUninit::uninit(); qoi.decoded_size() - 1];
+    assert_eq!(
+        Qoi::decode_uninit(fixture, &mut output),
+        Err(DecodeError::OutputIsTooSmall)
+    );
+
+    let mut output = vec![MaybeUninit::uninit(); qoi.decoded_size()];
+    let mut expected = vec![0; qoi.decoded_size()];
+    for end in [0, 13, 14, fixture.len() / 2, fixture.len() - 9] {
+        assert_eq!(
+            Qoi::decode_uninit(&fixture[..end], &mut output).err(),
+            Qoi::decode(&fixture[..end], &mut expected).err(),
+            "truncated at {}",
+            end
+        );
+    }
+}